@@ -0,0 +1,157 @@
+use revm::bytecode::{OpCode, opcode};
+
+/// Broad functional grouping of an EVM opcode, used for statistics and reporting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OpcodeCategory {
+    Stack,
+    Arithmetic,
+    Memory,
+    Storage,
+    ControlFlow,
+    Calls,
+    Logging,
+    Environment,
+    System,
+}
+
+impl OpcodeCategory {
+    /// All categories, in display order.
+    pub const ALL: [OpcodeCategory; 9] = [
+        OpcodeCategory::Stack,
+        OpcodeCategory::Arithmetic,
+        OpcodeCategory::Memory,
+        OpcodeCategory::Storage,
+        OpcodeCategory::ControlFlow,
+        OpcodeCategory::Calls,
+        OpcodeCategory::Logging,
+        OpcodeCategory::Environment,
+        OpcodeCategory::System,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OpcodeCategory::Stack => "stack",
+            OpcodeCategory::Arithmetic => "arithmetic",
+            OpcodeCategory::Memory => "memory",
+            OpcodeCategory::Storage => "storage",
+            OpcodeCategory::ControlFlow => "control flow",
+            OpcodeCategory::Calls => "calls",
+            OpcodeCategory::Logging => "logging",
+            OpcodeCategory::Environment => "environment",
+            OpcodeCategory::System => "system",
+        }
+    }
+}
+
+impl std::fmt::Display for OpcodeCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returns the category an opcode belongs to.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{OpcodeCategory, categorize};
+/// use revm::bytecode::OpCode;
+///
+/// assert_eq!(categorize(OpCode::SSTORE), OpcodeCategory::Storage);
+/// assert_eq!(categorize(OpCode::PUSH1), OpcodeCategory::Stack);
+/// ```
+pub fn categorize(op: OpCode) -> OpcodeCategory {
+    match op.get() {
+        // Stack manipulation
+        opcode::POP | opcode::PUSH0..=opcode::SWAP16 => OpcodeCategory::Stack,
+        opcode::DUPN | opcode::SWAPN | opcode::EXCHANGE => OpcodeCategory::Stack,
+
+        // Arithmetic, comparison, bitwise and hashing
+        opcode::ADD..=opcode::SIGNEXTEND | opcode::LT..=opcode::SAR | opcode::KECCAK256 => {
+            OpcodeCategory::Arithmetic
+        }
+
+        // Memory
+        opcode::MLOAD | opcode::MSTORE | opcode::MSTORE8 | opcode::MSIZE | opcode::MCOPY => {
+            OpcodeCategory::Memory
+        }
+
+        // Storage, persistent and transient
+        opcode::SLOAD | opcode::SSTORE | opcode::TLOAD | opcode::TSTORE => OpcodeCategory::Storage,
+
+        // Control flow and termination
+        opcode::STOP
+        | opcode::JUMP
+        | opcode::JUMPI
+        | opcode::PC
+        | opcode::JUMPDEST
+        | opcode::RETURN
+        | opcode::REVERT
+        | opcode::INVALID
+        | opcode::RJUMP..=opcode::JUMPF => OpcodeCategory::ControlFlow,
+
+        // Message calls
+        opcode::CALL
+        | opcode::CALLCODE
+        | opcode::DELEGATECALL
+        | opcode::STATICCALL
+        | opcode::EXTCALL
+        | opcode::EXTDELEGATECALL
+        | opcode::EXTSTATICCALL => OpcodeCategory::Calls,
+
+        // Event logs
+        opcode::LOG0..=opcode::LOG4 => OpcodeCategory::Logging,
+
+        // Contract creation and destruction
+        opcode::CREATE
+        | opcode::CREATE2
+        | opcode::SELFDESTRUCT
+        | opcode::EOFCREATE
+        | opcode::TXCREATE
+        | opcode::RETURNCONTRACT => OpcodeCategory::System,
+
+        // Transaction, block and account context
+        _ => OpcodeCategory::Environment,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stack_opcodes() {
+        assert_eq!(categorize(OpCode::PUSH0), OpcodeCategory::Stack);
+        assert_eq!(categorize(OpCode::PUSH32), OpcodeCategory::Stack);
+        assert_eq!(categorize(OpCode::POP), OpcodeCategory::Stack);
+        assert_eq!(categorize(OpCode::DUP16), OpcodeCategory::Stack);
+        assert_eq!(categorize(OpCode::SWAP1), OpcodeCategory::Stack);
+    }
+
+    #[test]
+    fn test_arithmetic_opcodes() {
+        assert_eq!(categorize(OpCode::ADD), OpcodeCategory::Arithmetic);
+        assert_eq!(categorize(OpCode::ISZERO), OpcodeCategory::Arithmetic);
+        assert_eq!(categorize(OpCode::SAR), OpcodeCategory::Arithmetic);
+        assert_eq!(categorize(OpCode::KECCAK256), OpcodeCategory::Arithmetic);
+    }
+
+    #[test]
+    fn test_other_categories() {
+        assert_eq!(categorize(OpCode::MSTORE), OpcodeCategory::Memory);
+        assert_eq!(categorize(OpCode::TLOAD), OpcodeCategory::Storage);
+        assert_eq!(categorize(OpCode::JUMPDEST), OpcodeCategory::ControlFlow);
+        assert_eq!(categorize(OpCode::REVERT), OpcodeCategory::ControlFlow);
+        assert_eq!(categorize(OpCode::DELEGATECALL), OpcodeCategory::Calls);
+        assert_eq!(categorize(OpCode::LOG2), OpcodeCategory::Logging);
+        assert_eq!(
+            categorize(OpCode::CALLDATALOAD),
+            OpcodeCategory::Environment
+        );
+        assert_eq!(categorize(OpCode::TIMESTAMP), OpcodeCategory::Environment);
+        assert_eq!(categorize(OpCode::CREATE2), OpcodeCategory::System);
+        assert_eq!(categorize(OpCode::SELFDESTRUCT), OpcodeCategory::System);
+    }
+}
@@ -0,0 +1,550 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use revm::{bytecode::opcode, primitives::U256};
+
+use crate::instruction::{Instruction, decode_instructions};
+
+/// Maximum number of distinct entry stacks explored per block.
+///
+/// Internal functions are entered with a different return address on the stack from every call
+/// site, so a block can legitimately be reached with many stacks. The cap keeps exploration
+/// bounded on code with loops that keep pushing values.
+pub const MAX_CONTEXTS_PER_BLOCK: usize = 64;
+
+/// EVM stack limit; paths that grow the stack beyond it are abandoned.
+const MAX_STACK_DEPTH: usize = 1024;
+
+/// Largest `KECCAK256` input, in words, handed to [`AbstractValue::keccak`].
+const MAX_HASHED_WORDS: usize = 4;
+
+/// How control leaves a basic block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockExit {
+    /// Unconditional `JUMP`.
+    Jump,
+    /// Conditional `JUMPI`; the fall-through block is also a successor.
+    JumpI,
+    /// The next instruction is a `JUMPDEST` that starts a new block.
+    FallThrough,
+    /// `STOP`, `RETURN`, `REVERT`, `INVALID`, `SELFDESTRUCT`, an unknown opcode, or the end of
+    /// the code.
+    Halt,
+}
+
+/// A straight-line run of instructions with a single entry and a single exit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock<'a> {
+    /// Position of the first instruction.
+    pub start: usize,
+    pub instructions: Vec<Instruction<'a>>,
+    pub exit: BlockExit,
+    /// Start positions of the blocks control can flow to.
+    pub successors: BTreeSet<usize>,
+    /// Whether the block was reached from the entry point.
+    pub reachable: bool,
+    /// Set when the block ends in a jump whose target could not be determined on some path.
+    pub unresolved_jump: bool,
+}
+
+impl BasicBlock<'_> {
+    /// Position just past the last instruction.
+    pub fn end(&self) -> usize {
+        self.instructions
+            .last()
+            .map_or(self.start, |instruction| instruction.next_pc())
+    }
+
+    /// Position of the block that follows this one in the code.
+    pub fn fall_through(&self) -> usize {
+        self.end()
+    }
+}
+
+/// Control flow graph of legacy bytecode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg<'a> {
+    /// Blocks keyed by their start position.
+    pub blocks: BTreeMap<usize, BasicBlock<'a>>,
+}
+
+impl<'a> Cfg<'a> {
+    /// Builds the control flow graph, resolving jump targets by tracking constants on the stack.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use evm_lens_core::Cfg;
+    ///
+    /// // PUSH1 0x03, JUMP, JUMPDEST, STOP
+    /// let bytes = hex::decode("6003565b00").unwrap();
+    /// let cfg = Cfg::new(&bytes);
+    /// assert_eq!(cfg.blocks.len(), 2);
+    /// assert!(cfg.blocks[&0].successors.contains(&3));
+    /// ```
+    pub fn new(bytes: &'a [u8]) -> Self {
+        explore::<Option<U256>>(bytes, |_, _| {})
+    }
+
+    pub fn block(&self, start: usize) -> Option<&BasicBlock<'a>> {
+        self.blocks.get(&start)
+    }
+
+    /// Returns the block containing the instruction at `pc`.
+    pub fn block_containing(&self, pc: usize) -> Option<&BasicBlock<'a>> {
+        self.blocks
+            .range(..=pc)
+            .next_back()
+            .map(|(_, block)| block)
+            .filter(|block| pc < block.end())
+    }
+
+    /// Iterates over the blocks reached from the entry point.
+    pub fn reachable_blocks(&self) -> impl Iterator<Item = &BasicBlock<'a>> {
+        self.blocks.values().filter(|block| block.reachable)
+    }
+
+    /// Maps every block start to the starts of the blocks that flow into it.
+    pub fn predecessors(&self) -> BTreeMap<usize, BTreeSet<usize>> {
+        let mut predecessors: BTreeMap<usize, BTreeSet<usize>> = self
+            .blocks
+            .keys()
+            .map(|start| (*start, BTreeSet::new()))
+            .collect();
+        for block in self.blocks.values() {
+            for successor in &block.successors {
+                predecessors
+                    .entry(*successor)
+                    .or_default()
+                    .insert(block.start);
+            }
+        }
+        predecessors
+    }
+}
+
+/// A value tracked on the abstract stack while exploring the control flow graph.
+///
+/// Exploration is path-sensitive up to [`MAX_CONTEXTS_PER_BLOCK`]: a block is revisited for
+/// every distinct stack it is entered with, so values should carry only as much detail as the
+/// analysis needs.
+pub trait AbstractValue: Clone + Eq + Hash + Debug {
+    /// A value nothing is known about, used for stack slots below the entry stack.
+    fn unknown() -> Self;
+
+    /// The value pushed by a `PUSH` instruction.
+    fn constant(value: U256) -> Self;
+
+    /// The concrete value, if known. Used to resolve jump targets.
+    fn as_constant(&self) -> Option<U256>;
+
+    /// The output of an instruction other than `PUSH`, `DUP` and `SWAP`.
+    ///
+    /// `inputs[0]` is the top of the stack, i.e. the first operand.
+    fn apply(instruction: &Instruction, inputs: &[Self]) -> Self;
+
+    /// The output of `KECCAK256` over whole words at constant offsets.
+    ///
+    /// Memory is only tracked within a basic block, for words written with `MSTORE` at constant
+    /// offsets; `words[i]` is `None` when the word at that position is not known. Compilers
+    /// derive mapping and array slots this way. Other hashes go through [`AbstractValue::apply`].
+    fn keccak(words: &[Option<Self>]) -> Self {
+        let _ = words;
+        Self::unknown()
+    }
+}
+
+/// Plain constant propagation: the value is known only if it was pushed or folded from
+/// constants.
+impl AbstractValue for Option<U256> {
+    fn unknown() -> Self {
+        None
+    }
+
+    fn constant(value: U256) -> Self {
+        Some(value)
+    }
+
+    fn as_constant(&self) -> Option<U256> {
+        *self
+    }
+
+    fn apply(instruction: &Instruction, inputs: &[Self]) -> Self {
+        let constants = inputs.iter().copied().collect::<Option<Vec<_>>>()?;
+        fold_constants(instruction.opcode, &constants)
+    }
+}
+
+/// Evaluates pure arithmetic and bitwise opcodes on constant operands.
+///
+/// `operands[0]` is the top of the stack. Returns `None` for opcodes that depend on the
+/// environment or for which folding is not implemented.
+pub fn fold_constants(op: u8, operands: &[U256]) -> Option<U256> {
+    let a = operands.first().copied();
+    let b = operands.get(1).copied();
+    let value = match op {
+        opcode::ADD => a?.wrapping_add(b?),
+        opcode::SUB => a?.wrapping_sub(b?),
+        opcode::MUL => a?.wrapping_mul(b?),
+        opcode::DIV => a?.checked_div(b?).unwrap_or(U256::ZERO),
+        opcode::MOD => a?.checked_rem(b?).unwrap_or(U256::ZERO),
+        opcode::EXP => a?.pow(b?),
+        opcode::LT => U256::from(a? < b?),
+        opcode::GT => U256::from(a? > b?),
+        opcode::EQ => U256::from(a? == b?),
+        opcode::ISZERO => U256::from(a?.is_zero()),
+        opcode::AND => a? & b?,
+        opcode::OR => a? | b?,
+        opcode::XOR => a? ^ b?,
+        opcode::NOT => !a?,
+        opcode::SHL => shift(a?, b?, |value, shift| value << shift),
+        opcode::SHR => shift(a?, b?, |value, shift| value >> shift),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn shift(amount: U256, value: U256, apply: impl Fn(U256, usize) -> U256) -> U256 {
+    match usize::try_from(amount) {
+        Ok(amount) if amount < 256 => apply(value, amount),
+        _ => U256::ZERO,
+    }
+}
+
+/// Returns true for opcodes that end execution.
+pub fn is_halting(op: u8) -> bool {
+    matches!(
+        op,
+        opcode::STOP | opcode::RETURN | opcode::REVERT | opcode::INVALID | opcode::SELFDESTRUCT
+    ) || crate::OpCode::new(op).is_none()
+}
+
+/// Explores the control flow graph from the entry point with a custom abstract value.
+///
+/// `visit` is called for every instruction on every explored path, before the instruction is
+/// applied, with its stack inputs (`inputs[0]` is the top of the stack). The same instruction can
+/// be visited several times with different inputs.
+pub fn explore<'a, V: AbstractValue>(
+    bytes: &'a [u8],
+    mut visit: impl FnMut(&Instruction<'a>, &[V]),
+) -> Cfg<'a> {
+    let mut blocks = split_blocks(bytes);
+    let jumpdests: HashSet<usize> = blocks
+        .values()
+        .filter(|block| {
+            block
+                .instructions
+                .first()
+                .is_some_and(|instruction| instruction.opcode == opcode::JUMPDEST)
+        })
+        .map(|block| block.start)
+        .collect();
+
+    let mut contexts: HashMap<usize, HashSet<Vec<V>>> = HashMap::new();
+    let mut worklist: Vec<(usize, Vec<V>)> = Vec::new();
+    if blocks.contains_key(&0) {
+        worklist.push((0, Vec::new()));
+    }
+
+    while let Some((start, mut stack)) = worklist.pop() {
+        let seen = contexts.entry(start).or_default();
+        if seen.len() >= MAX_CONTEXTS_PER_BLOCK || !seen.insert(stack.clone()) {
+            continue;
+        }
+
+        let block = blocks
+            .get_mut(&start)
+            .expect("worklist only holds block starts");
+        block.reachable = true;
+
+        let mut jump_target = None;
+        let mut memory = BTreeMap::new();
+        let mut overflowed = false;
+        for instruction in &block.instructions {
+            if !step(
+                instruction,
+                &mut stack,
+                &mut memory,
+                &mut visit,
+                &mut jump_target,
+            ) {
+                overflowed = true;
+                break;
+            }
+        }
+        if overflowed {
+            continue;
+        }
+
+        let mut targets = Vec::new();
+        match block.exit {
+            BlockExit::Jump | BlockExit::JumpI => {
+                match jump_target.and_then(|target| usize::try_from(target).ok()) {
+                    Some(target) if jumpdests.contains(&target) => targets.push(target),
+                    Some(_) => {}
+                    None => block.unresolved_jump = true,
+                }
+                if block.exit == BlockExit::JumpI {
+                    targets.push(block.fall_through());
+                }
+            }
+            BlockExit::FallThrough => targets.push(block.fall_through()),
+            BlockExit::Halt => {}
+        }
+
+        for target in targets {
+            if blocks.contains_key(&target) {
+                blocks
+                    .get_mut(&start)
+                    .expect("block exists")
+                    .successors
+                    .insert(target);
+                worklist.push((target, stack.clone()));
+            }
+        }
+    }
+
+    Cfg { blocks }
+}
+
+/// Applies one instruction to the abstract stack. Returns false if the stack overflows.
+fn step<'a, V: AbstractValue>(
+    instruction: &Instruction<'a>,
+    stack: &mut Vec<V>,
+    memory: &mut BTreeMap<usize, V>,
+    visit: &mut impl FnMut(&Instruction<'a>, &[V]),
+    jump_target: &mut Option<U256>,
+) -> bool {
+    let op = instruction.opcode;
+    let (inputs, outputs) = match crate::OpCode::new(op) {
+        Some(op) => (op.inputs() as usize, op.outputs() as usize),
+        None => (0, 0),
+    };
+
+    // Pad with unknown values so code that reads below the entry stack can still be analysed
+    while stack.len() < inputs {
+        stack.insert(0, V::unknown());
+    }
+    let operands: Vec<V> = stack.iter().rev().take(inputs).cloned().collect();
+    visit(instruction, &operands);
+
+    match op {
+        opcode::PUSH0..=opcode::PUSH32 => {
+            stack.push(V::constant(instruction.push_value().unwrap_or_default()));
+        }
+        opcode::DUP1..=opcode::DUP16 => {
+            let depth = (op - opcode::DUP1) as usize;
+            stack.push(stack[stack.len() - 1 - depth].clone());
+        }
+        opcode::SWAP1..=opcode::SWAP16 => {
+            let depth = (op - opcode::SWAP1 + 1) as usize;
+            let top = stack.len() - 1;
+            stack.swap(top, top - depth);
+        }
+        _ => {
+            if matches!(op, opcode::JUMP | opcode::JUMPI) {
+                *jump_target = operands[0].as_constant();
+            }
+            stack.truncate(stack.len() - inputs);
+            let output = match op {
+                opcode::KECCAK256 => hash_memory(memory, &operands),
+                _ => None,
+            };
+            update_memory(memory, op, &operands);
+            if outputs > 0 {
+                stack.push(output.unwrap_or_else(|| V::apply(instruction, &operands)));
+            }
+        }
+    }
+
+    stack.len() <= MAX_STACK_DEPTH
+}
+
+/// Records `MSTORE`s at constant offsets and forgets memory on any other write.
+fn update_memory<V: AbstractValue>(memory: &mut BTreeMap<usize, V>, op: u8, operands: &[V]) {
+    let writes_memory =
+        op != opcode::MLOAD && crate::OpCode::new(op).is_some_and(|op| op.modifies_memory());
+    if !writes_memory {
+        return;
+    }
+
+    let offset = operands[0]
+        .as_constant()
+        .and_then(|offset| usize::try_from(offset).ok());
+    match (op, offset) {
+        (opcode::MSTORE, Some(offset)) => {
+            // Drop words the store partially overwrites
+            let overlapping: Vec<usize> = memory
+                .range(offset.saturating_sub(31)..offset.saturating_add(32))
+                .map(|(known, _)| *known)
+                .collect();
+            for known in overlapping {
+                memory.remove(&known);
+            }
+            memory.insert(offset, operands[1].clone());
+        }
+        _ => memory.clear(),
+    }
+}
+
+fn hash_memory<V: AbstractValue>(memory: &BTreeMap<usize, V>, operands: &[V]) -> Option<V> {
+    let offset = usize::try_from(operands[0].as_constant()?).ok()?;
+    let size = usize::try_from(operands[1].as_constant()?).ok()?;
+    if size == 0 || !size.is_multiple_of(32) || size / 32 > MAX_HASHED_WORDS {
+        return None;
+    }
+
+    // A range running past the address space is as unknown as any other
+    let words = (0..size / 32)
+        .map(|word| {
+            let address = word.checked_mul(32).and_then(|w| offset.checked_add(w))?;
+            Some(memory.get(&address).cloned())
+        })
+        .collect::<Option<Vec<Option<V>>>>()?;
+    Some(V::keccak(&words))
+}
+
+/// Splits the code into basic blocks without resolving any edges.
+fn split_blocks(bytes: &[u8]) -> BTreeMap<usize, BasicBlock<'_>> {
+    let mut blocks = BTreeMap::new();
+    let mut current = Vec::new();
+
+    for instruction in decode_instructions(bytes) {
+        if instruction.opcode == opcode::JUMPDEST && !current.is_empty() {
+            finish_block(&mut blocks, &mut current, BlockExit::FallThrough);
+        }
+
+        let op = instruction.opcode;
+        current.push(instruction);
+        match op {
+            opcode::JUMP => finish_block(&mut blocks, &mut current, BlockExit::Jump),
+            opcode::JUMPI => finish_block(&mut blocks, &mut current, BlockExit::JumpI),
+            _ if is_halting(op) => finish_block(&mut blocks, &mut current, BlockExit::Halt),
+            _ => {}
+        }
+    }
+    finish_block(&mut blocks, &mut current, BlockExit::Halt);
+
+    blocks
+}
+
+fn finish_block<'a>(
+    blocks: &mut BTreeMap<usize, BasicBlock<'a>>,
+    instructions: &mut Vec<Instruction<'a>>,
+    exit: BlockExit,
+) {
+    let Some(first) = instructions.first() else {
+        return;
+    };
+    let start = first.pc;
+    blocks.insert(
+        start,
+        BasicBlock {
+            start,
+            instructions: std::mem::take(instructions),
+            exit,
+            successors: BTreeSet::new(),
+            reachable: false,
+            unresolved_jump: false,
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_blocks() {
+        // PUSH1 0x01, PUSH1 0x06, JUMPI, STOP, JUMPDEST, PUSH1 0x00, STOP
+        let bytes = hex::decode("6001600657005b600000").unwrap();
+        let cfg = Cfg::new(&bytes);
+
+        let starts: Vec<usize> = cfg.blocks.keys().copied().collect();
+        assert_eq!(starts, vec![0, 5, 6]);
+        assert_eq!(cfg.blocks[&0].exit, BlockExit::JumpI);
+        assert_eq!(
+            cfg.blocks[&0].successors,
+            BTreeSet::from([5, 6]),
+            "jump target and fall-through"
+        );
+        assert!(cfg.blocks.values().all(|block| block.reachable));
+    }
+
+    #[test]
+    fn test_return_address_resolved_through_stack() {
+        // 0x00: PUSH1 0x07 (return address), PUSH1 0x0a, JUMP
+        // 0x05: STOP, STOP
+        // 0x07: JUMPDEST, STOP, STOP
+        // 0x0a: JUMPDEST, JUMP (returns to 0x07)
+        let bytes = hex::decode("6007600a5600005b00005b56").unwrap();
+        let cfg = Cfg::new(&bytes);
+
+        assert_eq!(cfg.blocks[&0x0a].successors, BTreeSet::from([0x07]));
+        assert!(!cfg.blocks[&0x0a].unresolved_jump);
+        assert!(cfg.blocks[&0x07].reachable);
+        assert!(!cfg.blocks[&0x05].reachable);
+    }
+
+    #[test]
+    fn test_unresolved_jump() {
+        // CALLDATASIZE, JUMP, JUMPDEST, STOP
+        let bytes = hex::decode("36565b00").unwrap();
+        let cfg = Cfg::new(&bytes);
+
+        assert!(cfg.blocks[&0].unresolved_jump);
+        assert!(cfg.blocks[&0].successors.is_empty());
+        assert!(!cfg.blocks[&2].reachable);
+    }
+
+    #[test]
+    fn test_folded_jump_target() {
+        // PUSH1 0x03, PUSH1 0x04, ADD, JUMP, STOP, JUMPDEST, STOP
+        let bytes = hex::decode("600360040156005b00").unwrap();
+        let cfg = Cfg::new(&bytes);
+
+        assert_eq!(cfg.blocks[&0].successors, BTreeSet::from([7]));
+    }
+
+    #[test]
+    fn test_block_containing() {
+        let bytes = hex::decode("6001600657005b600000").unwrap();
+        let cfg = Cfg::new(&bytes);
+
+        assert_eq!(cfg.block_containing(2).unwrap().start, 0);
+        assert_eq!(cfg.block_containing(7).unwrap().start, 6);
+        assert!(cfg.block_containing(100).is_none());
+        assert_eq!(cfg.predecessors()[&6], BTreeSet::from([0]));
+    }
+
+    #[test]
+    fn test_hash_past_address_space_is_unknown() {
+        // PUSH1 0x40, PUSH8 0xffffffffffffffff, KECCAK256, STOP
+        let bytes = hex::decode("604067ffffffffffffffff2000").unwrap();
+        let mut hashed = Vec::new();
+        explore::<Option<U256>>(&bytes, |instruction, operands| {
+            if instruction.opcode == opcode::KECCAK256 {
+                hashed.extend_from_slice(operands);
+            }
+        });
+        assert_eq!(hashed, [Some(U256::from(u64::MAX)), Some(U256::from(0x40))]);
+
+        let offset = Some(U256::from(usize::MAX - 31));
+        let memory = BTreeMap::new();
+        assert_eq!(hash_memory(&memory, &[offset, Some(U256::from(64))]), None);
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        let one = U256::from(1);
+        let two = U256::from(2);
+        assert_eq!(fold_constants(opcode::SUB, &[two, one]), Some(one));
+        assert_eq!(
+            fold_constants(opcode::DIV, &[one, U256::ZERO]),
+            Some(U256::ZERO)
+        );
+        assert_eq!(fold_constants(opcode::SHL, &[one, one]), Some(two));
+        assert_eq!(fold_constants(opcode::CALLER, &[]), None);
+    }
+}
@@ -0,0 +1,445 @@
+use crate::instruction::{Instruction, decode_instructions};
+use crate::metadata::parse_metadata;
+
+/// How two bytecodes are compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CodeDiffOptions {
+    /// Match `PUSH32` instructions by opcode alone. solc writes immutable variables into
+    /// `PUSH32` placeholders at deployment, so their values differ between deployments of the
+    /// same code.
+    pub ignore_immutables: bool,
+}
+
+impl CodeDiffOptions {
+    pub fn new(ignore_immutables: bool) -> Self {
+        Self { ignore_immutables }
+    }
+}
+
+/// One line of an instruction-level diff. Instructions are rendered as the mnemonic followed
+/// by the immediate in hex, e.g. `PUSH1 0x80`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffEntry {
+    Same {
+        old_pc: usize,
+        new_pc: usize,
+        instruction: String,
+    },
+    /// A `PUSH32` matched by opcode alone under [`CodeDiffOptions::ignore_immutables`].
+    ImmutableDiffers {
+        old_pc: usize,
+        new_pc: usize,
+        old: String,
+        new: String,
+    },
+    Removed {
+        old_pc: usize,
+        instruction: String,
+    },
+    Added {
+        new_pc: usize,
+        instruction: String,
+    },
+}
+
+impl DiffEntry {
+    /// Whether the entry is an actual difference.
+    pub fn is_change(&self) -> bool {
+        matches!(self, DiffEntry::Removed { .. } | DiffEntry::Added { .. })
+    }
+}
+
+/// The aligned instruction streams of two bytecodes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeDiff {
+    pub entries: Vec<DiffEntry>,
+    /// Matched instructions relative to the instructions on both sides, from 0.0 to 1.0.
+    pub similarity: f64,
+    /// Set when the metadata trailers, which are left out of the comparison, differ.
+    pub metadata_differs: bool,
+}
+
+impl CodeDiff {
+    /// Whether the instruction streams match, ignoring metadata and, when asked, immutables.
+    pub fn is_identical(&self) -> bool {
+        !self.entries.iter().any(DiffEntry::is_change)
+    }
+
+    /// Number of removed and added instructions.
+    pub fn change_counts(&self) -> (usize, usize) {
+        self.entries
+            .iter()
+            .fold((0, 0), |(removed, added), entry| match entry {
+                DiffEntry::Removed { .. } => (removed + 1, added),
+                DiffEntry::Added { .. } => (removed, added + 1),
+                _ => (removed, added),
+            })
+    }
+}
+
+/// Aligns the instructions of two bytecodes by their longest common subsequence.
+///
+/// Instructions match when their opcode and immediate are equal, so an inserted instruction
+/// shows up as one added line instead of shifting every later byte. Metadata trailers are
+/// stripped before comparing.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{CodeDiffOptions, DiffEntry, diff_bytecode};
+///
+/// // PUSH1 0x01, PUSH1 0x02, ADD, STOP vs. the same with a POP inserted after ADD
+/// let old = hex::decode("600160020100").unwrap();
+/// let new = hex::decode("60016002015000").unwrap();
+/// let diff = diff_bytecode(&old, &new, &CodeDiffOptions::default());
+/// assert_eq!(diff.change_counts(), (0, 1));
+/// assert_eq!(
+///     diff.entries[3],
+///     DiffEntry::Added { new_pc: 5, instruction: "POP".to_string() }
+/// );
+/// assert!((diff.similarity - 8.0 / 9.0).abs() < 1e-9);
+/// ```
+pub fn diff_bytecode(old: &[u8], new: &[u8], options: &CodeDiffOptions) -> CodeDiff {
+    let old_metadata = parse_metadata(old);
+    let new_metadata = parse_metadata(new);
+    let old_code = old_metadata
+        .as_ref()
+        .map_or(old, |metadata| metadata.strip(old));
+    let new_code = new_metadata
+        .as_ref()
+        .map_or(new, |metadata| metadata.strip(new));
+    let metadata_differs = old[old_code.len()..] != new[new_code.len()..];
+
+    let old_instructions = decode_instructions(old_code);
+    let new_instructions = decode_instructions(new_code);
+    let key = |instruction: &Instruction<'_>| -> (u8, Vec<u8>) {
+        let immutable = options.ignore_immutables && instruction.immediate.len() == 32;
+        let immediate = if immutable {
+            Vec::new()
+        } else {
+            instruction.immediate.to_vec()
+        };
+        (instruction.opcode, immediate)
+    };
+    let old_keys: Vec<_> = old_instructions.iter().map(key).collect();
+    let new_keys: Vec<_> = new_instructions.iter().map(key).collect();
+
+    let mut matches = Vec::new();
+    common_subsequence(&old_keys, &new_keys, 0, 0, &mut matches);
+
+    let mut entries = Vec::with_capacity(old_keys.len().max(new_keys.len()));
+    let (mut old_index, mut new_index) = (0, 0);
+    for (old_match, new_match) in matches
+        .iter()
+        .copied()
+        .chain([(old_keys.len(), new_keys.len())])
+    {
+        for instruction in &old_instructions[old_index..old_match] {
+            entries.push(DiffEntry::Removed {
+                old_pc: instruction.pc,
+                instruction: render(instruction),
+            });
+        }
+        for instruction in &new_instructions[new_index..new_match] {
+            entries.push(DiffEntry::Added {
+                new_pc: instruction.pc,
+                instruction: render(instruction),
+            });
+        }
+        if let (Some(old_instruction), Some(new_instruction)) = (
+            old_instructions.get(old_match),
+            new_instructions.get(new_match),
+        ) {
+            let (old_text, new_text) = (render(old_instruction), render(new_instruction));
+            entries.push(if old_text == new_text {
+                DiffEntry::Same {
+                    old_pc: old_instruction.pc,
+                    new_pc: new_instruction.pc,
+                    instruction: new_text,
+                }
+            } else {
+                DiffEntry::ImmutableDiffers {
+                    old_pc: old_instruction.pc,
+                    new_pc: new_instruction.pc,
+                    old: old_text,
+                    new: new_text,
+                }
+            });
+        }
+        old_index = old_match + 1;
+        new_index = new_match + 1;
+    }
+
+    let total = old_keys.len() + new_keys.len();
+    let similarity = if total == 0 {
+        1.0
+    } else {
+        2.0 * matches.len() as f64 / total as f64
+    };
+    CodeDiff {
+        entries,
+        similarity,
+        metadata_differs,
+    }
+}
+
+fn render(instruction: &Instruction<'_>) -> String {
+    if instruction.immediate.is_empty() {
+        instruction.name()
+    } else {
+        format!(
+            "{} 0x{}",
+            instruction.name(),
+            hex::encode(instruction.immediate)
+        )
+    }
+}
+
+/// Appends the index pairs of a longest common subsequence of `a` and `b`, offset by
+/// `a_offset` and `b_offset`, in order.
+///
+/// Myers' linear-space algorithm: find the middle snake of a shortest edit script, then solve
+/// the halves before and after it. Runs in O((n + m) d) time for d differences.
+fn common_subsequence<T: PartialEq>(
+    a: &[T],
+    b: &[T],
+    a_offset: usize,
+    b_offset: usize,
+    matches: &mut Vec<(usize, usize)>,
+) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    matches.extend((0..prefix).map(|i| (a_offset + i, b_offset + i)));
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let (a_offset, b_offset) = (a_offset + prefix, b_offset + prefix);
+
+    let suffix = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    if !a.is_empty() && !b.is_empty() {
+        let (x0, y0, x1, y1) = middle_snake(a, b);
+        common_subsequence(&a[..x0], &b[..y0], a_offset, b_offset, matches);
+        matches.extend((0..x1 - x0).map(|i| (a_offset + x0 + i, b_offset + y0 + i)));
+        common_subsequence(&a[x1..], &b[y1..], a_offset + x1, b_offset + y1, matches);
+    }
+
+    matches.extend((0..suffix).map(|i| (a_offset + a.len() + i, b_offset + b.len() + i)));
+}
+
+/// Returns the start and end points `(x0, y0, x1, y1)` of the snake in the middle of a
+/// shortest edit script from `a` to `b`.
+fn middle_snake<T: PartialEq>(a: &[T], b: &[T]) -> (usize, usize, usize, usize) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    // Furthest x reached on each diagonal k = x - y, forwards and (on the reversed sequences)
+    // backwards
+    let mut forward = vec![0isize; (2 * offset + 1) as usize];
+    let mut backward = vec![0isize; (2 * offset + 1) as usize];
+    let index = |k: isize| (k + offset) as usize;
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[index(k - 1)] < forward[index(k + 1)]) {
+                forward[index(k + 1)]
+            } else {
+                forward[index(k - 1)] + 1
+            };
+            let (x0, y0) = (x, x - k);
+            let mut y = y0;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[index(k)] = x;
+
+            let c = delta - k;
+            if odd && (-(d - 1)..=d - 1).contains(&c) && x + backward[index(c)] >= n {
+                return (x0 as usize, y0 as usize, x as usize, y as usize);
+            }
+        }
+
+        for c in (-d..=d).step_by(2) {
+            let mut x = if c == -d || (c != d && backward[index(c - 1)] < backward[index(c + 1)]) {
+                backward[index(c + 1)]
+            } else {
+                backward[index(c - 1)] + 1
+            };
+            let (x0, y0) = (x, x - c);
+            let mut y = y0;
+            while x < n && y < m && a[(n - 1 - x) as usize] == b[(m - 1 - y) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[index(c)] = x;
+
+            let k = delta - c;
+            if !odd && (-d..=d).contains(&k) && x + forward[index(k)] >= n {
+                return (
+                    (n - x) as usize,
+                    (m - y) as usize,
+                    (n - x0) as usize,
+                    (m - y0) as usize,
+                );
+            }
+        }
+    }
+    unreachable!("a shortest edit script has at most n + m edits")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lcs(a: &str, b: &str) -> Vec<(usize, usize)> {
+        let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+        let mut matches = Vec::new();
+        common_subsequence(&a, &b, 0, 0, &mut matches);
+        matches
+    }
+
+    /// Length of a longest common subsequence by dynamic programming.
+    fn lcs_length(a: &[u8], b: &[u8]) -> usize {
+        let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+        for i in 0..a.len() {
+            for j in 0..b.len() {
+                table[i + 1][j + 1] = if a[i] == b[j] {
+                    table[i][j] + 1
+                } else {
+                    table[i][j + 1].max(table[i + 1][j])
+                };
+            }
+        }
+        table[a.len()][b.len()]
+    }
+
+    #[test]
+    fn test_common_subsequence() {
+        assert_eq!(lcs("abc", "abc"), [(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(lcs("", "abc"), []);
+        assert_eq!(lcs("abcabba", "cbabac").len(), 4);
+        assert_eq!(lcs("xaby", "ab"), [(1, 0), (2, 1)]);
+    }
+
+    #[test]
+    fn test_common_subsequence_is_longest() {
+        let mut seed: u64 = 0x2545f4914f6cdd1d;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for _ in 0..300 {
+            let a: Vec<u8> = (0..next() % 40).map(|_| (next() % 4) as u8).collect();
+            let b: Vec<u8> = (0..next() % 40).map(|_| (next() % 4) as u8).collect();
+            let mut matches = Vec::new();
+            common_subsequence(&a, &b, 0, 0, &mut matches);
+
+            assert_eq!(matches.len(), lcs_length(&a, &b));
+            assert!(matches.iter().all(|&(i, j)| a[i] == b[j]));
+            assert!(
+                matches
+                    .windows(2)
+                    .all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1)
+            );
+        }
+    }
+
+    #[test]
+    fn test_diff_entries_carry_both_pcs() {
+        // PUSH1 0x80, PUSH1 0x40, MSTORE, STOP vs. the same with CALLVALUE, POP inserted
+        let old = hex::decode("608060405200").unwrap();
+        let new = hex::decode("6080345060405200").unwrap();
+        let diff = diff_bytecode(&old, &new, &CodeDiffOptions::default());
+
+        assert_eq!(
+            diff.entries[..4],
+            [
+                DiffEntry::Same {
+                    old_pc: 0,
+                    new_pc: 0,
+                    instruction: "PUSH1 0x80".to_string()
+                },
+                DiffEntry::Added {
+                    new_pc: 2,
+                    instruction: "CALLVALUE".to_string()
+                },
+                DiffEntry::Added {
+                    new_pc: 3,
+                    instruction: "POP".to_string()
+                },
+                DiffEntry::Same {
+                    old_pc: 2,
+                    new_pc: 4,
+                    instruction: "PUSH1 0x40".to_string()
+                },
+            ]
+        );
+        assert_eq!(diff.entries.len(), 6);
+        assert_eq!(diff.change_counts(), (0, 2));
+        assert!((diff.similarity - 0.8).abs() < 1e-9);
+        assert!(!diff.is_identical());
+    }
+
+    #[test]
+    fn test_ignore_immutables() {
+        // PUSH32 <word>, STOP with different words
+        let old = hex::decode(format!("7f{}00", "11".repeat(32))).unwrap();
+        let new = hex::decode(format!("7f{}00", "22".repeat(32))).unwrap();
+
+        let diff = diff_bytecode(&old, &new, &CodeDiffOptions::default());
+        assert_eq!(diff.change_counts(), (1, 1));
+        assert!((diff.similarity - 0.5).abs() < 1e-9);
+
+        let diff = diff_bytecode(&old, &new, &CodeDiffOptions::new(true));
+        assert!(diff.is_identical());
+        assert_eq!(diff.similarity, 1.0);
+        assert!(matches!(
+            diff.entries[0],
+            DiffEntry::ImmutableDiffers {
+                old_pc: 0,
+                new_pc: 0,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_metadata_is_ignored() {
+        let code = "6080604052348015600f57600080fd5b50";
+        let old = hex::decode(format!(
+            "{code}fea264697066735822{}64736f6c63430008130033",
+            "11".repeat(34)
+        ))
+        .unwrap();
+        let new = hex::decode(format!(
+            "{code}fea264697066735822{}64736f6c63430008130033",
+            "22".repeat(34)
+        ))
+        .unwrap();
+
+        let diff = diff_bytecode(&old, &new, &CodeDiffOptions::default());
+        assert!(diff.is_identical());
+        assert!(diff.metadata_differs);
+        assert!(!diff_bytecode(&old, &old, &CodeDiffOptions::default()).metadata_differs);
+    }
+
+    #[test]
+    fn test_empty_sides() {
+        let code = hex::decode("600100").unwrap();
+        let diff = diff_bytecode(&[], &code, &CodeDiffOptions::default());
+        assert_eq!(diff.change_counts(), (0, 2));
+        assert_eq!(diff.similarity, 0.0);
+        assert_eq!(
+            diff_bytecode(&[], &[], &CodeDiffOptions::default()).similarity,
+            1.0
+        );
+    }
+}
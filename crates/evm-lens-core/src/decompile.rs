@@ -0,0 +1,1302 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::rc::Rc;
+
+use revm::{bytecode::opcode, primitives::U256};
+
+use crate::cfg::{BasicBlock, BlockExit, Cfg, fold_constants, is_halting};
+use crate::dispatch::function_entries;
+use crate::instruction::Instruction;
+
+/// Duplicated expressions with more nodes than this are bound to a variable instead of being
+/// repeated at every use.
+const MAX_INLINE_NODES: usize = 8;
+
+/// Pseudo-code for one public function, or for the whole contract when there is no dispatcher.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecompiledFunction {
+    /// Selector the dispatcher routes to this function; `None` when the code is decompiled from
+    /// the entry point.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::option_selector"))]
+    pub selector: Option<[u8; 4]>,
+    /// Position of the first block of the function.
+    pub entry: usize,
+    /// Statements of the body, indented by four spaces per nesting level.
+    pub lines: Vec<String>,
+}
+
+impl DecompiledFunction {
+    /// Returns the function header, e.g. `function 0xa9059cbb()`, or `function main()` when
+    /// there is no selector.
+    pub fn signature(&self) -> String {
+        match self.selector {
+            Some(selector) => format!("function 0x{}()", hex::encode(selector)),
+            None => "function main()".to_string(),
+        }
+    }
+}
+
+/// A value on the symbolic stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Const(U256),
+    Var(String),
+    /// An environment value such as `msg.sender`.
+    Env(&'static str),
+    Not(Rc<Expr>),
+    IsZero(Rc<Expr>),
+    Binary(&'static str, Rc<Expr>, Rc<Expr>),
+    Call {
+        name: String,
+        args: Vec<Rc<Expr>>,
+        /// Whether the result depends on memory, storage or other state a later write can
+        /// change.
+        reads_state: bool,
+    },
+}
+
+impl Expr {
+    fn is_atomic(&self) -> bool {
+        matches!(self, Expr::Const(_) | Expr::Var(_) | Expr::Env(_))
+    }
+
+    fn nodes(&self) -> usize {
+        match self {
+            Expr::Const(_) | Expr::Var(_) | Expr::Env(_) => 1,
+            Expr::Not(inner) | Expr::IsZero(inner) => 1 + inner.nodes(),
+            Expr::Binary(_, lhs, rhs) => 1 + lhs.nodes() + rhs.nodes(),
+            Expr::Call { args, .. } => 1 + args.iter().map(|arg| arg.nodes()).sum::<usize>(),
+        }
+    }
+
+    fn reads_state(&self) -> bool {
+        match self {
+            Expr::Const(_) | Expr::Var(_) | Expr::Env(_) => false,
+            Expr::Not(inner) | Expr::IsZero(inner) => inner.reads_state(),
+            Expr::Binary(_, lhs, rhs) => lhs.reads_state() || rhs.reads_state(),
+            Expr::Call {
+                args, reads_state, ..
+            } => *reads_state || args.iter().any(|arg| arg.reads_state()),
+        }
+    }
+
+    fn is_boolean(&self) -> bool {
+        match self {
+            Expr::IsZero(_) => true,
+            Expr::Binary(op, _, _) => matches!(*op, "<" | ">" | "=="),
+            Expr::Call { name, .. } => matches!(name.as_str(), "slt" | "sgt"),
+            _ => false,
+        }
+    }
+}
+
+/// A structured statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Stmt {
+    /// Start of a block; printed only when a `goto` targets it.
+    Label(usize),
+    Assign(String, Rc<Expr>),
+    Call(String, Vec<Rc<Expr>>),
+    /// Runs `then` when `cond` is non-zero, `otherwise` when it is zero.
+    If {
+        cond: Rc<Expr>,
+        then: Vec<Stmt>,
+        otherwise: Vec<Stmt>,
+    },
+    /// `while (true)` when there is no condition.
+    While {
+        cond: Option<Rc<Expr>>,
+        body: Vec<Stmt>,
+    },
+    Goto(usize),
+    /// A jump whose target is computed at runtime.
+    DynamicJump(Rc<Expr>),
+    Break,
+    Continue,
+}
+
+/// How control leaves a lifted block.
+enum Flow {
+    Halt,
+    Goto(usize),
+    Dynamic(Rc<Expr>),
+    Branch {
+        cond: Rc<Expr>,
+        taken: Result<usize, Rc<Expr>>,
+        fall: usize,
+    },
+}
+
+/// The loop whose body is being emitted.
+struct LoopScope {
+    header: usize,
+    /// Block the loop condition exits to; jumps there become `break`.
+    exit: Option<usize>,
+    blocks: BTreeSet<usize>,
+    /// Stack at the loop header, with loop-carried positions replaced by variables.
+    template: Vec<Rc<Expr>>,
+    /// Positions of `template` reassigned before some `continue`.
+    reassigned: BTreeSet<usize>,
+    /// Stack at the first `break`.
+    exit_stack: Option<Vec<Rc<Expr>>>,
+}
+
+/// Per-contract control flow facts shared by every function.
+struct Structure {
+    postdominators: HashMap<usize, usize>,
+    loops: HashMap<usize, BTreeSet<usize>>,
+}
+
+/// Decompiles bytecode into Solidity-like pseudo-code, one function per dispatcher entry.
+///
+/// Each basic block is lifted into expression trees by running its instructions on a symbolic
+/// stack. Values are carried across jumps along the order blocks are emitted in, so constants
+/// such as return addresses still resolve internal calls. Branches are structured into `if`
+/// statements that rejoin at the immediate postdominator, and natural loops into `while`;
+/// anything else falls back to labels and `goto`. Results of calls and values read from state
+/// that a later write could change are bound to variables first.
+///
+/// Every block is emitted at most once per function, so internal functions shared by several
+/// call sites are inlined at the first one and reached with `goto` from the others.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::decompile;
+///
+/// // CALLVALUE, DUP1, ISZERO, PUSH1 0x09, JUMPI, PUSH0, DUP1, REVERT, JUMPDEST, POP,
+/// // PUSH1 0x04, CALLDATALOAD, PUSH0, SSTORE, STOP
+/// let bytes = hex::decode("3480156009575f80fd5b5060043560005500").unwrap();
+/// let functions = decompile(&bytes);
+/// assert_eq!(
+///     functions[0].lines,
+///     vec!["if (msg.value != 0) revert(0, 0);", "sstore(0, calldataload(4));", "stop();"]
+/// );
+/// ```
+pub fn decompile(bytes: &[u8]) -> Vec<DecompiledFunction> {
+    let cfg = Cfg::new(bytes);
+    let structure = Structure::new(&cfg);
+
+    let entries: Vec<(Option<[u8; 4]>, usize)> = {
+        let functions = function_entries(bytes);
+        if functions.is_empty() {
+            vec![(None, 0)]
+        } else {
+            functions
+                .iter()
+                .map(|function| (Some(function.selector), function.entry))
+                .collect()
+        }
+    };
+
+    entries
+        .into_iter()
+        .filter(|(_, entry)| cfg.block(*entry).is_some())
+        .map(|(selector, entry)| {
+            let mut lifter = Lifter::new(&cfg, &structure);
+            let (body, _) = lifter.emit(entry, Vec::new(), None, None);
+            DecompiledFunction {
+                selector,
+                entry,
+                lines: render_body(&body),
+            }
+        })
+        .collect()
+}
+
+impl Structure {
+    fn new(cfg: &Cfg) -> Self {
+        Structure {
+            postdominators: immediate_postdominators(cfg),
+            loops: natural_loops(cfg),
+        }
+    }
+}
+
+/// Lifts and structures the blocks of one function.
+struct Lifter<'c, 'a> {
+    cfg: &'c Cfg<'a>,
+    structure: &'c Structure,
+    emitted: HashSet<usize>,
+    next_var: usize,
+    next_arg: usize,
+    /// Loop-carried stack positions, by loop header and stack depth at entry.
+    carried: HashMap<(usize, usize), BTreeSet<usize>>,
+}
+
+impl<'c, 'a> Lifter<'c, 'a> {
+    fn new(cfg: &'c Cfg<'a>, structure: &'c Structure) -> Self {
+        Lifter {
+            cfg,
+            structure,
+            emitted: HashSet::new(),
+            next_var: 0,
+            next_arg: 0,
+            carried: HashMap::new(),
+        }
+    }
+
+    /// Emits statements from `start` until control reaches `stop`, halts, or leaves the
+    /// enclosing loop. Returns the stack at `stop` when it is reached.
+    fn emit(
+        &mut self,
+        start: usize,
+        mut stack: Vec<Rc<Expr>>,
+        stop: Option<usize>,
+        mut scope: Option<&mut LoopScope>,
+    ) -> (Vec<Stmt>, Option<Vec<Rc<Expr>>>) {
+        let mut out = Vec::new();
+        let mut current = start;
+        let mut first = true;
+
+        loop {
+            if Some(current) == stop {
+                return (out, Some(stack));
+            }
+            if let Some(scope) = scope.as_deref_mut() {
+                if current == scope.header && !first {
+                    continue_loop(scope, &stack, &mut out);
+                    return (out, None);
+                }
+                if Some(current) == scope.exit {
+                    scope.exit_stack.get_or_insert(stack);
+                    out.push(Stmt::Break);
+                    return (out, None);
+                }
+            }
+            first = false;
+
+            if self.emitted.contains(&current) {
+                out.push(Stmt::Goto(current));
+                return (out, None);
+            }
+            let Some(block) = self.cfg.block(current) else {
+                out.push(Stmt::Call("invalid".to_string(), Vec::new()));
+                return (out, None);
+            };
+
+            let in_loop = scope
+                .as_deref()
+                .is_some_and(|scope| scope.header == current);
+            if !in_loop && self.structure.loops.contains_key(&current) {
+                match self.emit_loop(current, &mut stack, &mut out) {
+                    Some(next) => {
+                        current = next;
+                        continue;
+                    }
+                    None => return (out, None),
+                }
+            }
+
+            self.emitted.insert(current);
+            out.push(Stmt::Label(current));
+            match self.lift_block(block, &mut stack, &mut out) {
+                Flow::Halt => return (out, None),
+                Flow::Goto(target) => current = target,
+                Flow::Dynamic(target) => {
+                    out.push(Stmt::DynamicJump(target));
+                    return (out, None);
+                }
+                Flow::Branch {
+                    cond,
+                    taken: Err(target),
+                    fall,
+                } => {
+                    out.push(Stmt::If {
+                        cond,
+                        then: vec![Stmt::DynamicJump(target)],
+                        otherwise: Vec::new(),
+                    });
+                    current = fall;
+                }
+                Flow::Branch {
+                    cond,
+                    taken: Ok(taken),
+                    fall,
+                } => {
+                    let loop_blocks = scope.as_deref().map(|scope| &scope.blocks);
+                    let merge = self
+                        .structure
+                        .postdominators
+                        .get(&current)
+                        .copied()
+                        .filter(|merge| loop_blocks.is_none_or(|blocks| blocks.contains(merge)))
+                        .filter(|merge| Some(*merge) != stop);
+
+                    match merge {
+                        Some(merge) => {
+                            let (mut then, then_stack) =
+                                self.emit(taken, stack.clone(), Some(merge), scope.as_deref_mut());
+                            let (mut otherwise, else_stack) =
+                                self.emit(fall, stack.clone(), Some(merge), scope.as_deref_mut());
+                            let merged = self.merge_stacks(
+                                then_stack,
+                                else_stack,
+                                &mut then,
+                                &mut otherwise,
+                            );
+                            out.push(structured_if(cond, then, otherwise));
+                            match merged {
+                                Some(merged) => {
+                                    stack = merged;
+                                    current = merge;
+                                }
+                                None => return (out, None),
+                            }
+                        }
+                        None => {
+                            // Emit the side that leaves first as the `if` body and continue
+                            // with the other one inline
+                            let (exit_side, cond, rest) =
+                                if self.exit_rank(fall, &scope) <= self.exit_rank(taken, &scope) {
+                                    (fall, negate(cond), taken)
+                                } else {
+                                    (taken, cond, fall)
+                                };
+                            let (then, _) =
+                                self.emit(exit_side, stack.clone(), stop, scope.as_deref_mut());
+                            out.push(structured_if(cond, then, Vec::new()));
+                            current = rest;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Ranks how directly entering `start` leaves the current code path: leaving the loop or
+    /// jumping to emitted code first, then reverting, then other halts, shorter blocks first.
+    /// The side that ranks lower becomes the `if` body, so `require` checks read as
+    /// `if (...) revert(...)` followed by the rest of the function.
+    fn exit_rank(&self, start: usize, scope: &Option<&mut LoopScope>) -> (u8, usize) {
+        let leaves_loop = scope
+            .as_ref()
+            .is_some_and(|scope| start == scope.header || Some(start) == scope.exit);
+        if leaves_loop || self.emitted.contains(&start) {
+            return (0, 0);
+        }
+        match self.cfg.block(start) {
+            Some(block) if block.exit == BlockExit::Halt => {
+                let reverts = block.instructions.last().is_some_and(|instruction| {
+                    matches!(instruction.opcode, opcode::REVERT | opcode::INVALID)
+                });
+                (if reverts { 1 } else { 2 }, block.instructions.len())
+            }
+            Some(_) => (3, 0),
+            None => (0, 0),
+        }
+    }
+
+    /// Emits the loop headed by `header` and returns the block it exits to.
+    ///
+    /// The first time a loop is seen, its body is emitted with every stack position as a
+    /// variable to find the positions the loop changes, and the result is cached so a loop
+    /// nested in another is probed only once. The body is then emitted with only those
+    /// positions as variables.
+    fn emit_loop(
+        &mut self,
+        header: usize,
+        stack: &mut Vec<Rc<Expr>>,
+        out: &mut Vec<Stmt>,
+    ) -> Option<usize> {
+        // Values read before the loop must not be re-read inside it
+        self.flush(stack, out);
+
+        let key = (header, stack.len());
+        let carried = match self.carried.get(&key) {
+            Some(carried) => carried.clone(),
+            None => {
+                let saved = (self.emitted.clone(), self.next_var);
+                let all: BTreeSet<usize> = (0..stack.len()).collect();
+                let (_, probe) = self.loop_body(header, stack, &all);
+                (self.emitted, self.next_var) = saved;
+                self.carried.insert(key, probe.reassigned.clone());
+                probe.reassigned
+            }
+        };
+
+        let (statements, scope) = self.loop_body(header, stack, &carried);
+        out.extend(statements);
+        let exit = scope.exit?;
+        *stack = scope.exit_stack.unwrap_or(scope.template);
+        Some(exit)
+    }
+
+    fn loop_body(
+        &mut self,
+        header: usize,
+        stack: &[Rc<Expr>],
+        carried: &BTreeSet<usize>,
+    ) -> (Vec<Stmt>, LoopScope) {
+        let mut statements = Vec::new();
+        let template: Vec<Rc<Expr>> = stack
+            .iter()
+            .enumerate()
+            .map(|(position, value)| {
+                if carried.contains(&position) {
+                    let name = self.fresh_var();
+                    statements.push(Stmt::Assign(name.clone(), value.clone()));
+                    Rc::new(Expr::Var(name))
+                } else {
+                    value.clone()
+                }
+            })
+            .collect();
+
+        let blocks = self.structure.loops[&header].clone();
+        let exit = self.cfg.block(header).and_then(|block| {
+            let outside: Vec<usize> = block
+                .successors
+                .iter()
+                .copied()
+                .filter(|successor| !blocks.contains(successor))
+                .collect();
+            (block.exit == BlockExit::JumpI && outside.len() == 1).then(|| outside[0])
+        });
+
+        let mut scope = LoopScope {
+            header,
+            exit,
+            blocks,
+            template: template.clone(),
+            reassigned: BTreeSet::new(),
+            exit_stack: None,
+        };
+        let (mut body, _) = self.emit(header, template, None, Some(&mut scope));
+
+        // `Label, if (c) break; ...` is `while (!c) { ... }`
+        let cond = match body.get(1) {
+            Some(Stmt::If {
+                cond,
+                then,
+                otherwise,
+            }) if then[..] == [Stmt::Break] && otherwise.is_empty() => Some(negate(cond.clone())),
+            _ => None,
+        };
+        if cond.is_some() {
+            let label = body.remove(0);
+            body.remove(0);
+            statements.push(label);
+        }
+        if body.last() == Some(&Stmt::Continue) {
+            body.pop();
+        }
+        statements.push(Stmt::While { cond, body });
+        (statements, scope)
+    }
+
+    /// Combines the stacks two branches leave at their merge point, assigning positions that
+    /// differ to a variable in each branch.
+    fn merge_stacks(
+        &mut self,
+        then_stack: Option<Vec<Rc<Expr>>>,
+        else_stack: Option<Vec<Rc<Expr>>>,
+        then: &mut Vec<Stmt>,
+        otherwise: &mut Vec<Stmt>,
+    ) -> Option<Vec<Rc<Expr>>> {
+        match (then_stack, else_stack) {
+            (Some(then_stack), Some(else_stack)) if then_stack.len() == else_stack.len() => Some(
+                then_stack
+                    .into_iter()
+                    .zip(else_stack)
+                    .map(|(then_value, else_value)| {
+                        if then_value == else_value {
+                            return then_value;
+                        }
+                        let name = self.fresh_var();
+                        then.push(Stmt::Assign(name.clone(), then_value));
+                        otherwise.push(Stmt::Assign(name.clone(), else_value));
+                        Rc::new(Expr::Var(name))
+                    })
+                    .collect(),
+            ),
+            (Some(stack), _) | (None, Some(stack)) => Some(stack),
+            (None, None) => None,
+        }
+    }
+
+    /// Lifts the instructions of a block onto the symbolic stack and resolves its exit.
+    fn lift_block(
+        &mut self,
+        block: &BasicBlock,
+        stack: &mut Vec<Rc<Expr>>,
+        out: &mut Vec<Stmt>,
+    ) -> Flow {
+        let body = match block.exit {
+            BlockExit::Jump | BlockExit::JumpI => {
+                &block.instructions[..block.instructions.len() - 1]
+            }
+            _ => &block.instructions[..],
+        };
+        for instruction in body {
+            if self.lift(instruction, stack, out) {
+                return Flow::Halt;
+            }
+        }
+
+        match block.exit {
+            BlockExit::Halt => {
+                // Running off the end of the code
+                out.push(Stmt::Call("stop".to_string(), Vec::new()));
+                Flow::Halt
+            }
+            BlockExit::FallThrough => Flow::Goto(block.fall_through()),
+            BlockExit::Jump => {
+                let target = self.pop(stack);
+                match self.resolve(block, &target) {
+                    Ok(target) => Flow::Goto(target),
+                    Err(target) => Flow::Dynamic(target),
+                }
+            }
+            BlockExit::JumpI => {
+                let target = self.pop(stack);
+                let cond = self.pop(stack);
+                let taken = self.resolve(block, &target);
+                Flow::Branch {
+                    cond,
+                    taken,
+                    fall: block.fall_through(),
+                }
+            }
+        }
+    }
+
+    /// Resolves a jump target from the symbolic stack, or from the graph when the block has a
+    /// single jump successor.
+    fn resolve(&self, block: &BasicBlock, target: &Rc<Expr>) -> Result<usize, Rc<Expr>> {
+        let is_jumpdest = |pc: usize| {
+            self.cfg
+                .block(pc)
+                .is_some_and(|block| block.instructions[0].opcode == opcode::JUMPDEST)
+        };
+        if let Expr::Const(value) = target.as_ref()
+            && let Ok(pc) = usize::try_from(*value)
+            && is_jumpdest(pc)
+        {
+            return Ok(pc);
+        }
+
+        let jump_successors: Vec<usize> = block
+            .successors
+            .iter()
+            .copied()
+            .filter(|successor| block.exit == BlockExit::Jump || *successor != block.fall_through())
+            .collect();
+        match jump_successors[..] {
+            [successor] => Ok(successor),
+            _ => Err(target.clone()),
+        }
+    }
+
+    /// Applies one instruction to the symbolic stack. Returns true if it halts.
+    fn lift(
+        &mut self,
+        instruction: &Instruction,
+        stack: &mut Vec<Rc<Expr>>,
+        out: &mut Vec<Stmt>,
+    ) -> bool {
+        let op = instruction.opcode;
+        match op {
+            opcode::PUSH0..=opcode::PUSH32 => {
+                let value = instruction.push_value().unwrap_or_default();
+                stack.push(Rc::new(Expr::Const(value)));
+                return false;
+            }
+            opcode::DUP1..=opcode::DUP16 => {
+                let depth = (op - opcode::DUP1) as usize;
+                self.pad(stack, depth + 1);
+                let index = stack.len() - 1 - depth;
+                if stack[index].nodes() > MAX_INLINE_NODES {
+                    self.bind(stack, index, out);
+                }
+                stack.push(stack[index].clone());
+                return false;
+            }
+            opcode::SWAP1..=opcode::SWAP16 => {
+                let depth = (op - opcode::SWAP1 + 1) as usize;
+                self.pad(stack, depth + 1);
+                let top = stack.len() - 1;
+                stack.swap(top, top - depth);
+                return false;
+            }
+            opcode::POP => {
+                self.pop(stack);
+                return false;
+            }
+            opcode::JUMPDEST => return false,
+            opcode::PC => {
+                stack.push(Rc::new(Expr::Const(U256::from(instruction.pc))));
+                return false;
+            }
+            _ => {}
+        }
+
+        let Some(info) = instruction.op() else {
+            out.push(Stmt::Call("invalid".to_string(), Vec::new()));
+            return true;
+        };
+        let operands: Vec<Rc<Expr>> = (0..info.inputs()).map(|_| self.pop(stack)).collect();
+        let name = match op {
+            opcode::GAS => "gasleft".to_string(),
+            _ => info.as_str().to_lowercase(),
+        };
+
+        if is_halting(op) {
+            out.push(Stmt::Call(name, operands));
+            return true;
+        }
+
+        let constants: Option<Vec<U256>> = operands
+            .iter()
+            .map(|operand| match operand.as_ref() {
+                Expr::Const(value) => Some(*value),
+                _ => None,
+            })
+            .collect();
+        if let Some(value) = constants.and_then(|constants| fold_constants(op, &constants)) {
+            stack.push(Rc::new(Expr::Const(value)));
+            return false;
+        }
+
+        let value = match op {
+            opcode::ISZERO => Expr::IsZero(operands[0].clone()),
+            opcode::NOT => Expr::Not(operands[0].clone()),
+            // The shift amount is the first operand
+            opcode::SHL => Expr::Binary("<<", operands[1].clone(), operands[0].clone()),
+            opcode::SHR => Expr::Binary(">>", operands[1].clone(), operands[0].clone()),
+            _ if let Some(operator) = binary_operator(op) => {
+                let (mut lhs, mut rhs) = (operands[0].clone(), operands[1].clone());
+                // Write `x + 1` rather than `1 + x`
+                let commutative = matches!(operator, "+" | "*" | "&" | "|" | "^" | "==");
+                if commutative && matches!(lhs.as_ref(), Expr::Const(_)) {
+                    std::mem::swap(&mut lhs, &mut rhs);
+                }
+                Expr::Binary(operator, lhs, rhs)
+            }
+            _ if let Some(name) = environment(op) => Expr::Env(name),
+            opcode::CALL
+            | opcode::CALLCODE
+            | opcode::DELEGATECALL
+            | opcode::STATICCALL
+            | opcode::CREATE
+            | opcode::CREATE2 => {
+                self.flush(stack, out);
+                let var = self.fresh_var();
+                out.push(Stmt::Assign(
+                    var.clone(),
+                    Rc::new(Expr::Call {
+                        name,
+                        args: operands,
+                        reads_state: true,
+                    }),
+                ));
+                Expr::Var(var)
+            }
+            _ if info.outputs() == 0 => {
+                self.flush(stack, out);
+                out.push(Stmt::Call(name, operands));
+                return false;
+            }
+            _ => Expr::Call {
+                name,
+                args: operands,
+                reads_state: reads_state(op),
+            },
+        };
+        stack.push(Rc::new(value));
+        false
+    }
+
+    fn pop(&mut self, stack: &mut Vec<Rc<Expr>>) -> Rc<Expr> {
+        self.pad(stack, 1);
+        stack.pop().expect("padded")
+    }
+
+    /// Fills the stack from below with the function's unnamed arguments.
+    fn pad(&mut self, stack: &mut Vec<Rc<Expr>>, depth: usize) {
+        while stack.len() < depth {
+            stack.insert(0, Rc::new(Expr::Var(format!("arg{}", self.next_arg))));
+            self.next_arg += 1;
+        }
+    }
+
+    /// Binds every stack value that reads state to a variable, so a following write does not
+    /// change what it refers to.
+    fn flush(&mut self, stack: &mut [Rc<Expr>], out: &mut Vec<Stmt>) {
+        for index in 0..stack.len() {
+            if !stack[index].is_atomic() && stack[index].reads_state() {
+                self.bind(stack, index, out);
+            }
+        }
+    }
+
+    /// Assigns `stack[index]` to a variable and replaces every copy of it on the stack.
+    fn bind(&mut self, stack: &mut [Rc<Expr>], index: usize, out: &mut Vec<Stmt>) {
+        let value = stack[index].clone();
+        let name = self.fresh_var();
+        out.push(Stmt::Assign(name.clone(), value.clone()));
+
+        let var = Rc::new(Expr::Var(name));
+        for slot in stack.iter_mut() {
+            if Rc::ptr_eq(slot, &value) {
+                *slot = var.clone();
+            }
+        }
+    }
+
+    fn fresh_var(&mut self) -> String {
+        let name = format!("v{}", self.next_var);
+        self.next_var += 1;
+        name
+    }
+}
+
+/// Assigns the loop-carried variables their next values before jumping back to the header.
+fn continue_loop(scope: &mut LoopScope, stack: &[Rc<Expr>], out: &mut Vec<Stmt>) {
+    if stack.len() == scope.template.len() {
+        for (position, (value, template)) in stack.iter().zip(&scope.template).enumerate() {
+            if let Expr::Var(name) = template.as_ref()
+                && value != template
+            {
+                out.push(Stmt::Assign(name.clone(), value.clone()));
+                scope.reassigned.insert(position);
+            }
+        }
+    }
+    out.push(Stmt::Continue);
+}
+
+/// Builds an `if`, flipping the condition when only the `else` branch has statements.
+fn structured_if(cond: Rc<Expr>, then: Vec<Stmt>, otherwise: Vec<Stmt>) -> Stmt {
+    let is_empty = |statements: &[Stmt]| {
+        statements
+            .iter()
+            .all(|statement| matches!(statement, Stmt::Label(_)))
+    };
+    if is_empty(&then) && !is_empty(&otherwise) {
+        Stmt::If {
+            cond: negate(cond),
+            then: otherwise,
+            otherwise: then,
+        }
+    } else {
+        Stmt::If {
+            cond,
+            then,
+            otherwise,
+        }
+    }
+}
+
+fn negate(cond: Rc<Expr>) -> Rc<Expr> {
+    match cond.as_ref() {
+        Expr::IsZero(inner) => inner.clone(),
+        _ => Rc::new(Expr::IsZero(cond)),
+    }
+}
+
+fn binary_operator(op: u8) -> Option<&'static str> {
+    Some(match op {
+        opcode::ADD => "+",
+        opcode::SUB => "-",
+        opcode::MUL => "*",
+        opcode::DIV => "/",
+        opcode::MOD => "%",
+        opcode::EXP => "**",
+        opcode::LT => "<",
+        opcode::GT => ">",
+        opcode::EQ => "==",
+        opcode::AND => "&",
+        opcode::OR => "|",
+        opcode::XOR => "^",
+        _ => return None,
+    })
+}
+
+fn environment(op: u8) -> Option<&'static str> {
+    Some(match op {
+        opcode::ADDRESS => "address(this)",
+        opcode::ORIGIN => "tx.origin",
+        opcode::CALLER => "msg.sender",
+        opcode::CALLVALUE => "msg.value",
+        opcode::CALLDATASIZE => "msg.data.length",
+        opcode::CODESIZE => "codesize()",
+        opcode::GASPRICE => "tx.gasprice",
+        opcode::COINBASE => "block.coinbase",
+        opcode::TIMESTAMP => "block.timestamp",
+        opcode::NUMBER => "block.number",
+        opcode::DIFFICULTY => "block.prevrandao",
+        opcode::GASLIMIT => "block.gaslimit",
+        opcode::CHAINID => "block.chainid",
+        opcode::BASEFEE => "block.basefee",
+        opcode::BLOBBASEFEE => "block.blobbasefee",
+        _ => return None,
+    })
+}
+
+/// Whether the opcode's result can change when memory, storage or balances are written.
+fn reads_state(op: u8) -> bool {
+    matches!(
+        op,
+        opcode::SLOAD
+            | opcode::TLOAD
+            | opcode::MLOAD
+            | opcode::KECCAK256
+            | opcode::BALANCE
+            | opcode::SELFBALANCE
+            | opcode::EXTCODESIZE
+            | opcode::EXTCODEHASH
+            | opcode::RETURNDATASIZE
+            | opcode::MSIZE
+            | opcode::GAS
+    )
+}
+
+/// Immediate postdominator of every reachable block, computed on the reversed graph with the
+/// Cooper-Harvey-Kennedy algorithm. Blocks whose paths reach several exits have none.
+fn immediate_postdominators(cfg: &Cfg) -> HashMap<usize, usize> {
+    // Node 0 is a virtual exit that every halting block flows into
+    let starts: Vec<usize> = cfg.reachable_blocks().map(|block| block.start).collect();
+    let index: HashMap<usize, usize> = starts
+        .iter()
+        .enumerate()
+        .map(|(position, start)| (*start, position + 1))
+        .collect();
+    let node_count = starts.len() + 1;
+
+    // Edges of the reversed graph: from each node to the blocks that flow into it
+    let mut reversed: Vec<Vec<usize>> = vec![Vec::new(); node_count];
+    let mut forward: Vec<Vec<usize>> = vec![Vec::new(); node_count];
+    for (position, start) in starts.iter().enumerate() {
+        let node = position + 1;
+        let block = &cfg.blocks[start];
+        let successors: Vec<usize> = block
+            .successors
+            .iter()
+            .filter_map(|successor| index.get(successor).copied())
+            .collect();
+        if successors.is_empty() {
+            reversed[0].push(node);
+            forward[node].push(0);
+        }
+        for successor in successors {
+            reversed[successor].push(node);
+            forward[node].push(successor);
+        }
+    }
+
+    // Postorder of the reversed graph from the exit
+    let mut order = vec![usize::MAX; node_count];
+    let mut postorder = Vec::with_capacity(node_count);
+    let mut visited = vec![false; node_count];
+    let mut work = vec![(0, 0)];
+    visited[0] = true;
+    while let Some((node, next)) = work.pop() {
+        if let Some(&child) = reversed[node].get(next) {
+            work.push((node, next + 1));
+            if !visited[child] {
+                visited[child] = true;
+                work.push((child, 0));
+            }
+        } else {
+            order[node] = postorder.len();
+            postorder.push(node);
+        }
+    }
+
+    let mut dominator = vec![usize::MAX; node_count];
+    dominator[0] = 0;
+    let intersect = |dominator: &[usize], mut a: usize, mut b: usize| {
+        while a != b {
+            while order[a] < order[b] {
+                a = dominator[a];
+            }
+            while order[b] < order[a] {
+                b = dominator[b];
+            }
+        }
+        a
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &node in postorder.iter().rev().skip(1) {
+            let mut new_dominator = usize::MAX;
+            for &predecessor in &forward[node] {
+                if dominator[predecessor] == usize::MAX {
+                    continue;
+                }
+                new_dominator = if new_dominator == usize::MAX {
+                    predecessor
+                } else {
+                    intersect(&dominator, predecessor, new_dominator)
+                };
+            }
+            if new_dominator != usize::MAX && dominator[node] != new_dominator {
+                dominator[node] = new_dominator;
+                changed = true;
+            }
+        }
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .filter_map(|(position, start)| match dominator[position + 1] {
+            0 | usize::MAX => None,
+            node => Some((*start, starts[node - 1])),
+        })
+        .collect()
+}
+
+/// Maps each loop header to the blocks of its natural loops, found from the back edges of a
+/// depth-first search from the entry point.
+fn natural_loops(cfg: &Cfg) -> HashMap<usize, BTreeSet<usize>> {
+    let mut back_edges = Vec::new();
+    let mut on_path = HashSet::new();
+    let mut visited = HashSet::new();
+    if cfg.block(0).is_some() {
+        let mut work: Vec<(usize, Vec<usize>)> = vec![(0, successors(cfg, 0))];
+        visited.insert(0);
+        on_path.insert(0);
+        while let Some((node, mut pending)) = work.pop() {
+            match pending.pop() {
+                Some(successor) => {
+                    work.push((node, pending));
+                    if on_path.contains(&successor) {
+                        back_edges.push((node, successor));
+                    } else if visited.insert(successor) {
+                        on_path.insert(successor);
+                        work.push((successor, successors(cfg, successor)));
+                    }
+                }
+                None => {
+                    on_path.remove(&node);
+                }
+            }
+        }
+    }
+
+    let predecessors = cfg.predecessors();
+    let mut loops: HashMap<usize, BTreeSet<usize>> = HashMap::new();
+    for (latch, header) in back_edges {
+        let body = loops
+            .entry(header)
+            .or_insert_with(|| BTreeSet::from([header]));
+        let mut work = vec![latch];
+        while let Some(node) = work.pop() {
+            if body.insert(node) {
+                work.extend(predecessors[&node].iter().copied());
+            }
+        }
+    }
+    loops
+}
+
+fn successors(cfg: &Cfg, start: usize) -> Vec<usize> {
+    cfg.block(start)
+        .map(|block| block.successors.iter().rev().copied().collect())
+        .unwrap_or_default()
+}
+
+fn render_body(body: &[Stmt]) -> Vec<String> {
+    let mut targets = HashSet::new();
+    collect_goto_targets(body, &mut targets);
+    let mut lines = Vec::new();
+    render_statements(body, 0, &targets, &mut lines);
+    lines
+}
+
+fn collect_goto_targets(statements: &[Stmt], targets: &mut HashSet<usize>) {
+    for statement in statements {
+        match statement {
+            Stmt::Goto(target) => {
+                targets.insert(*target);
+            }
+            Stmt::If {
+                then, otherwise, ..
+            } => {
+                collect_goto_targets(then, targets);
+                collect_goto_targets(otherwise, targets);
+            }
+            Stmt::While { body, .. } => collect_goto_targets(body, targets),
+            _ => {}
+        }
+    }
+}
+
+fn render_statements(
+    statements: &[Stmt],
+    depth: usize,
+    targets: &HashSet<usize>,
+    lines: &mut Vec<String>,
+) {
+    let indent = "    ".repeat(depth);
+    let visible = |statements: &[Stmt]| -> Vec<Stmt> {
+        statements
+            .iter()
+            .filter(|statement| !matches!(statement, Stmt::Label(pc) if !targets.contains(pc)))
+            .cloned()
+            .collect()
+    };
+
+    for statement in statements {
+        match statement {
+            Stmt::Label(pc) => {
+                if targets.contains(pc) {
+                    lines.push(format!("{indent}label_{pc:04x}:"));
+                }
+            }
+            Stmt::If {
+                cond,
+                then,
+                otherwise,
+            } => {
+                let then = visible(then);
+                let otherwise = visible(otherwise);
+                let cond = render_condition(cond);
+                if otherwise.is_empty()
+                    && let [single] = &then[..]
+                    && let Some(line) = render_simple(single)
+                {
+                    lines.push(format!("{indent}if ({cond}) {line}"));
+                    continue;
+                }
+
+                lines.push(format!("{indent}if ({cond}) {{"));
+                render_statements(&then, depth + 1, targets, lines);
+                if !otherwise.is_empty() {
+                    lines.push(format!("{indent}}} else {{"));
+                    render_statements(&otherwise, depth + 1, targets, lines);
+                }
+                lines.push(format!("{indent}}}"));
+            }
+            Stmt::While { cond, body } => {
+                let cond = cond
+                    .as_ref()
+                    .map_or("true".to_string(), |cond| render_condition(cond));
+                lines.push(format!("{indent}while ({cond}) {{"));
+                render_statements(body, depth + 1, targets, lines);
+                lines.push(format!("{indent}}}"));
+            }
+            _ => {
+                let line = render_simple(statement).expect("compound statements handled above");
+                lines.push(format!("{indent}{line}"));
+            }
+        }
+    }
+}
+
+/// Renders a statement that fits on one line.
+fn render_simple(statement: &Stmt) -> Option<String> {
+    Some(match statement {
+        Stmt::Assign(name, value) => format!("{name} = {};", render(value)),
+        Stmt::Call(name, args) => format!("{name}({});", render_args(args)),
+        Stmt::Goto(target) => format!("goto label_{target:04x};"),
+        Stmt::DynamicJump(target) => format!("goto *({});", render(target)),
+        Stmt::Break => "break;".to_string(),
+        Stmt::Continue => "continue;".to_string(),
+        Stmt::Label(_) | Stmt::If { .. } | Stmt::While { .. } => return None,
+    })
+}
+
+fn render(expr: &Expr) -> String {
+    match expr {
+        Expr::Const(value) if *value < U256::from(256) => value.to_string(),
+        Expr::Const(value) => format!("{value:#x}"),
+        Expr::Var(name) => name.clone(),
+        Expr::Env(name) => name.to_string(),
+        Expr::Not(inner) => format!("~{}", render_operand(inner)),
+        Expr::IsZero(inner) => render_negated(inner),
+        Expr::Binary(op, lhs, rhs) => {
+            format!("{} {op} {}", render_operand(lhs), render_operand(rhs))
+        }
+        Expr::Call { name, args, .. } => format!("{name}({})", render_args(args)),
+    }
+}
+
+fn render_args(args: &[Rc<Expr>]) -> String {
+    args.iter()
+        .map(|arg| render(arg))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Renders an operand of a binary or unary operator, parenthesised when needed.
+fn render_operand(expr: &Expr) -> String {
+    match expr {
+        Expr::Binary(..) | Expr::IsZero(_) => format!("({})", render(expr)),
+        _ => render(expr),
+    }
+}
+
+/// Renders `expr` as the condition of an `if` or `while`, which holds when it is non-zero.
+fn render_condition(expr: &Expr) -> String {
+    match expr {
+        Expr::IsZero(inner) => render_negated(inner),
+        _ if expr.is_boolean() => render(expr),
+        _ => format!("{} != 0", render_operand(expr)),
+    }
+}
+
+/// Renders a condition that holds when `expr` is zero.
+fn render_negated(expr: &Expr) -> String {
+    match expr {
+        Expr::IsZero(inner) => render_condition(inner),
+        Expr::Binary("==", lhs, rhs) => {
+            format!("{} != {}", render_operand(lhs), render_operand(rhs))
+        }
+        _ if expr.is_boolean() => format!("!({})", render(expr)),
+        _ => format!("{} == 0", render_operand(expr)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(code: &str) -> Vec<String> {
+        let functions = decompile(&hex::decode(code).unwrap());
+        assert_eq!(functions.len(), 1);
+        functions[0].lines.clone()
+    }
+
+    #[test]
+    fn test_expressions_are_lifted() {
+        // PUSH1 0x01, PUSH0, CALLDATALOAD, ADD, CALLER, SSTORE, STOP
+        assert_eq!(
+            lines("60015f35013355"),
+            vec!["sstore(msg.sender, calldataload(0) + 1);", "stop();"]
+        );
+    }
+
+    #[test]
+    fn test_constants_are_folded() {
+        // PUSH1 0x02, PUSH1 0x03, MUL, PUSH0, MSTORE, PUSH1 0x20, PUSH0, RETURN
+        assert_eq!(
+            lines("600260030260005260205ff3"),
+            vec!["mstore(0, 6);", "return(0, 32);"]
+        );
+    }
+
+    #[test]
+    fn test_if_else_rejoins() {
+        // 0x00: CALLDATASIZE, PUSH1 0x0c, JUMPI
+        // 0x04: PUSH1 0x01, PUSH0, SSTORE, PUSH1 0x11, JUMP, STOP
+        // 0x0c: JUMPDEST, PUSH1 0x02, PUSH0, SSTORE
+        // 0x11: JUMPDEST, STOP
+        assert_eq!(
+            lines("36600c5760015f55601156005b60025f555b00"),
+            vec![
+                "if (msg.data.length != 0) {",
+                "    sstore(0, 2);",
+                "} else {",
+                "    sstore(0, 1);",
+                "}",
+                "stop();",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_while_loop() {
+        // 0x00: PUSH0 (i)
+        // 0x01: JUMPDEST, DUP1, PUSH1 0x0a, GT, ISZERO, PUSH1 0x13, JUMPI
+        // 0x0a: DUP1, DUP1, SSTORE, PUSH1 0x01, ADD, PUSH1 0x01, JUMP
+        // 0x13: JUMPDEST, POP, STOP
+        assert_eq!(
+            lines("5f5b80600a11156013578080556001016001565b5000"),
+            vec![
+                "v0 = 0;",
+                "while (10 > v0) {",
+                "    sstore(v0, v0);",
+                "    v0 = v0 + 1;",
+                "}",
+                "stop();",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nested_loops_are_emitted_once() {
+        // Each level: JUMPDEST, PUSH1 <level>, CALLDATALOAD, ISZERO, PUSH2 <exit>, JUMPI,
+        // <inner level>, PUSH2 <header>, JUMP, JUMPDEST (exit)
+        fn nest(code: &mut Vec<u8>, level: u8, depth: u8) {
+            let header = code.len() as u16;
+            code.extend([0x5b, 0x60, level, 0x35, 0x15, 0x61, 0, 0, 0x57]);
+            let patch = code.len() - 3;
+            if level + 1 < depth {
+                nest(code, level + 1, depth);
+            }
+            code.push(0x61);
+            code.extend(header.to_be_bytes());
+            code.push(0x56);
+            let exit = code.len() as u16;
+            code[patch..patch + 2].copy_from_slice(&exit.to_be_bytes());
+            code.push(0x5b);
+        }
+
+        let depth = 24;
+        let mut code = Vec::new();
+        nest(&mut code, 0, depth);
+        code.push(0x00);
+
+        let functions = decompile(&code);
+        let loops = functions[0]
+            .lines
+            .iter()
+            .filter(|line| line.trim_start().starts_with("while"))
+            .count();
+        assert_eq!(loops, depth as usize);
+    }
+
+    #[test]
+    fn test_state_reads_are_bound_before_writes() {
+        // PUSH0, SLOAD, PUSH1 0x01, PUSH0, SSTORE, PUSH0, MSTORE, STOP
+        assert_eq!(
+            lines("5f5460015f555f5200"),
+            vec![
+                "v0 = sload(0);",
+                "sstore(0, 1);",
+                "mstore(0, v0);",
+                "stop();"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_call_results_are_variables() {
+        // PUSH0 x5, CALLER, GAS, CALL, ISZERO, PUSH1 0x0d, JUMPI, STOP, JUMPDEST, PUSH0, DUP1,
+        // REVERT
+        assert_eq!(
+            lines("5f5f5f5f5f335af115600d57005b5f80fd"),
+            vec![
+                "v0 = call(gasleft(), msg.sender, 0, 0, 0, 0, 0);",
+                "if (v0 == 0) revert(0, 0);",
+                "stop();",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_one_function_per_selector() {
+        // DUP1, PUSH4 0xaabbccdd, EQ, PUSH1 0x0b, JUMPI, STOP, JUMPDEST, CALLER, PUSH0, SSTORE,
+        // STOP
+        let functions = decompile(&hex::decode("8063aabbccdd14600b57005b335f5500").unwrap());
+
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0].signature(), "function 0xaabbccdd()");
+        assert_eq!(functions[0].entry, 0x0b);
+        assert_eq!(
+            functions[0].lines,
+            vec!["sstore(0, msg.sender);", "stop();"]
+        );
+    }
+
+    #[test]
+    fn test_dynamic_jump() {
+        // PUSH0, CALLDATALOAD, JUMP
+        assert_eq!(lines("5f3556"), vec!["goto *(calldataload(0));"]);
+    }
+}
@@ -0,0 +1,141 @@
+use revm::{bytecode::opcode, primitives::keccak256};
+
+use crate::instruction::decode_instructions;
+
+/// A public function found in the selector dispatcher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FunctionEntry {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::selector"))]
+    pub selector: [u8; 4],
+    /// Position of the `JUMPDEST` the dispatcher jumps to for this selector.
+    pub entry: usize,
+}
+
+impl FunctionEntry {
+    /// Returns the selector as `0x`-prefixed hex.
+    pub fn selector_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.selector))
+    }
+}
+
+/// Computes the selector of a canonical function signature such as `transfer(address,uint256)`.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::function_selector;
+///
+/// assert_eq!(function_selector("transfer(address,uint256)"), [0xa9, 0x05, 0x9c, 0xbb]);
+/// ```
+pub fn function_selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Finds the function selectors in a Solidity, Vyper or Huff style dispatcher.
+///
+/// Matches `PUSH4 <selector> [DUPn] EQ PUSHn <entry> JUMPI`, which covers both the legacy and
+/// via-IR solc dispatchers. Entries that do not point at a `JUMPDEST` are skipped. The result is
+/// sorted by entry position and contains each selector once.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::function_entries;
+///
+/// // DUP1, PUSH4 0xa9059cbb, EQ, PUSH1 0x0b, JUMPI, STOP, JUMPDEST, STOP
+/// let bytes = hex::decode("8063a9059cbb14600b57005b00").unwrap();
+/// let entries = function_entries(&bytes);
+/// assert_eq!(entries[0].selector_hex(), "0xa9059cbb");
+/// assert_eq!(entries[0].entry, 0x0b);
+/// ```
+pub fn function_entries(bytes: &[u8]) -> Vec<FunctionEntry> {
+    let instructions = decode_instructions(bytes);
+    let is_jumpdest = |pc: usize| bytes.get(pc) == Some(&opcode::JUMPDEST);
+
+    let mut entries: Vec<FunctionEntry> = Vec::new();
+    for (index, instruction) in instructions.iter().enumerate() {
+        if instruction.opcode != opcode::PUSH4 || instruction.immediate.len() != 4 {
+            continue;
+        }
+
+        let mut rest = instructions[index + 1..].iter();
+        let mut next = rest.next();
+        if next.is_some_and(|next| (opcode::DUP1..=opcode::DUP16).contains(&next.opcode)) {
+            next = rest.next();
+        }
+        if next.is_none_or(|next| next.opcode != opcode::EQ) {
+            continue;
+        }
+        let (Some(push), Some(jumpi)) = (rest.next(), rest.next()) else {
+            continue;
+        };
+        if !push.is_push() || jumpi.opcode != opcode::JUMPI {
+            continue;
+        }
+
+        let Some(entry) = push
+            .push_value()
+            .and_then(|value| usize::try_from(value).ok())
+        else {
+            continue;
+        };
+        let selector = [
+            instruction.immediate[0],
+            instruction.immediate[1],
+            instruction.immediate[2],
+            instruction.immediate[3],
+        ];
+        if is_jumpdest(entry) && entries.iter().all(|known| known.selector != selector) {
+            entries.push(FunctionEntry { selector, entry });
+        }
+    }
+
+    entries.sort_by_key(|entry| (entry.entry, entry.selector));
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_legacy_and_via_ir_dispatch() {
+        // 0x00: DUP1, PUSH4 0x11111111, EQ, PUSH1 0x17, JUMPI
+        // 0x0a: PUSH4 0x22222222, DUP2, EQ, PUSH1 0x19, JUMPI
+        // 0x14: STOP, STOP, STOP
+        // 0x17: JUMPDEST, STOP
+        // 0x19: JUMPDEST, STOP
+        let bytes = hex::decode("80631111111114601757632222222281146019570000005b005b00").unwrap();
+        let entries = function_entries(&bytes);
+
+        assert_eq!(
+            entries,
+            vec![
+                FunctionEntry {
+                    selector: [0x11; 4],
+                    entry: 0x17
+                },
+                FunctionEntry {
+                    selector: [0x22; 4],
+                    entry: 0x19
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_entry_must_be_jumpdest() {
+        // DUP1, PUSH4 0x11111111, EQ, PUSH1 0x09, JUMPI, STOP, STOP
+        let bytes = hex::decode("806311111111146009570000").unwrap();
+        assert!(function_entries(&bytes).is_empty());
+    }
+
+    #[test]
+    fn test_binary_search_split_is_ignored() {
+        // DUP1, PUSH4 0x11111111, GT, PUSH1 0x0b, JUMPI, STOP, JUMPDEST, STOP
+        let bytes = hex::decode("80631111111111600b57005b00").unwrap();
+        assert!(function_entries(&bytes).is_empty());
+    }
+}
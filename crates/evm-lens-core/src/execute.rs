@@ -0,0 +1,411 @@
+use std::convert::Infallible;
+use std::fmt;
+
+use revm::{
+    Context, ExecuteEvm, MainBuilder, MainContext,
+    bytecode::Bytecode,
+    context::{
+        TxEnv,
+        result::{EVMError, ExecutionResult, InvalidTransaction, Output},
+    },
+    database::{CacheDB, EmptyDB},
+    primitives::{Address, B256, Bytes, TxKind, U256, address},
+    state::{AccountInfo, EvmState},
+};
+
+/// Address the code under test is installed at (the one Foundry deploys test contracts to).
+pub const CONTRACT_ADDRESS: Address = address!("0x7FA9385bE102ac3EAc297483Dd6233D62b3e1496");
+
+/// Default caller (Foundry's default sender).
+pub const DEFAULT_CALLER: Address = address!("0x1804c8AB1F12E6bbf3894d4083f33e07309d1f38");
+
+/// Default transaction gas limit.
+pub const DEFAULT_GAS_LIMIT: u64 = 30_000_000;
+
+/// Selector of Solidity's `Error(string)` revert payload.
+pub const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Selector of Solidity's `Panic(uint256)` revert payload.
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// The transaction that calls the code under test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallParams {
+    pub calldata: Vec<u8>,
+    /// Wei sent with the call; the caller is funded to cover it.
+    pub value: U256,
+    pub caller: Address,
+    pub gas_limit: u64,
+}
+
+impl CallParams {
+    pub fn new(calldata: Vec<u8>, value: U256, caller: Address, gas_limit: u64) -> Self {
+        Self {
+            calldata,
+            value,
+            caller,
+            gas_limit,
+        }
+    }
+}
+
+impl Default for CallParams {
+    fn default() -> Self {
+        Self::new(Vec::new(), U256::ZERO, DEFAULT_CALLER, DEFAULT_GAS_LIMIT)
+    }
+}
+
+/// How a call ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// `STOP`, `RETURN` or `SELFDESTRUCT`.
+    Success,
+    Revert,
+    /// An exceptional halt such as running out of gas or an invalid jump, as named by revm.
+    Halt(String),
+}
+
+/// A log emitted during execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub address: Address,
+    pub topics: Vec<B256>,
+    pub data: Vec<u8>,
+}
+
+/// A storage slot whose value changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageWrite {
+    pub address: Address,
+    pub slot: U256,
+    pub original: U256,
+    pub present: U256,
+}
+
+/// The result of running code against an empty in-memory state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Execution {
+    pub status: ExitStatus,
+    /// Returned or reverted data.
+    pub output: Vec<u8>,
+    /// Gas used by the whole transaction, including the intrinsic cost.
+    pub gas_used: u64,
+    pub logs: Vec<LogEntry>,
+    /// Changed slots in address and slot order; empty unless the call succeeded.
+    pub storage_writes: Vec<StorageWrite>,
+}
+
+impl Execution {
+    pub fn is_success(&self) -> bool {
+        self.status == ExitStatus::Success
+    }
+
+    /// Decodes the revert data, if the call reverted.
+    pub fn revert_reason(&self) -> Option<RevertReason> {
+        (self.status == ExitStatus::Revert).then(|| decode_revert_reason(&self.output))
+    }
+}
+
+/// Decoded revert data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertReason {
+    /// `revert("...")` and failed `require`s with a message.
+    Error(String),
+    /// Compiler-inserted checks such as arithmetic overflow.
+    Panic(U256),
+    Empty,
+    /// Custom errors and anything else.
+    Raw(Vec<u8>),
+}
+
+impl fmt::Display for RevertReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevertReason::Error(message) => write!(f, "Error({message:?})"),
+            RevertReason::Panic(code) => match panic_description(*code) {
+                Some(description) => write!(f, "Panic({code:#x}): {description}"),
+                None => write!(f, "Panic({code:#x})"),
+            },
+            RevertReason::Empty => f.write_str("no data"),
+            RevertReason::Raw(data) => write!(f, "0x{}", hex::encode(data)),
+        }
+    }
+}
+
+/// Decodes `Error(string)` and `Panic(uint256)` revert payloads.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{RevertReason, decode_revert_reason};
+///
+/// let data = hex::decode(
+///     "4e487b710000000000000000000000000000000000000000000000000000000000000011",
+/// )
+/// .unwrap();
+/// let reason = decode_revert_reason(&data);
+/// assert_eq!(reason.to_string(), "Panic(0x11): arithmetic overflow or underflow");
+/// ```
+pub fn decode_revert_reason(data: &[u8]) -> RevertReason {
+    if data.is_empty() {
+        return RevertReason::Empty;
+    }
+    let decoded = match data.split_first_chunk::<4>() {
+        Some((&ERROR_SELECTOR, args)) => decode_string(args).map(RevertReason::Error),
+        Some((&PANIC_SELECTOR, args)) if args.len() == 32 => {
+            Some(RevertReason::Panic(U256::from_be_slice(args)))
+        }
+        _ => None,
+    };
+    decoded.unwrap_or_else(|| RevertReason::Raw(data.to_vec()))
+}
+
+/// Decodes an ABI-encoded `string` that is the only argument.
+fn decode_string(args: &[u8]) -> Option<String> {
+    let word = |at: usize| -> Option<usize> {
+        let word = args.get(at..at.checked_add(32)?)?;
+        usize::try_from(U256::from_be_slice(word)).ok()
+    };
+    let offset = word(0)?;
+    let length = word(offset)?;
+    let start = offset.checked_add(32)?;
+    let bytes = args.get(start..start.checked_add(length)?)?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+/// Solidity's description of a panic code.
+pub fn panic_description(code: U256) -> Option<&'static str> {
+    let description = match u8::try_from(code).ok()? {
+        0x00 => "generic compiler panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "invalid storage byte array encoding",
+        0x31 => "pop on an empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "too much memory allocated",
+        0x51 => "call to an uninitialized internal function",
+        _ => return None,
+    };
+    Some(description)
+}
+
+/// Execution could not start, e.g. because the gas limit is below the intrinsic cost.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionError(String);
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Execution failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for ExecutionError {}
+
+impl From<EVMError<Infallible, InvalidTransaction>> for ExecutionError {
+    fn from(error: EVMError<Infallible, InvalidTransaction>) -> Self {
+        ExecutionError(error.to_string())
+    }
+}
+
+/// Installs `bytes` as runtime code at [`CONTRACT_ADDRESS`] in an empty in-memory state and
+/// calls it, without any network access.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{CallParams, execute};
+///
+/// // PUSH1 0x2a, PUSH0, SSTORE, PUSH1 0x2a, PUSH0, MSTORE, PUSH1 0x20, PUSH0, RETURN
+/// let bytes = hex::decode("602a5f55602a5f5260205ff3").unwrap();
+/// let execution = execute(&bytes, &CallParams::default()).unwrap();
+/// assert!(execution.is_success());
+/// assert_eq!(execution.output[31], 0x2a);
+/// assert_eq!(execution.storage_writes.len(), 1);
+/// ```
+pub fn execute(bytes: &[u8], params: &CallParams) -> Result<Execution, ExecutionError> {
+    let mut evm = Context::mainnet()
+        .with_db(database(bytes, params))
+        .build_mainnet();
+    let result = evm.transact(transaction(params))?;
+    let state = evm.finalize();
+    Ok(execution_from(result, &state))
+}
+
+pub(crate) fn database(bytes: &[u8], params: &CallParams) -> CacheDB<EmptyDB> {
+    let mut db = CacheDB::<EmptyDB>::default();
+    let code = Bytecode::new_legacy(Bytes::copy_from_slice(bytes));
+    db.insert_account_info(
+        CONTRACT_ADDRESS,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..AccountInfo::default()
+        },
+    );
+    // Gas is free, so the caller only needs the value it sends
+    db.insert_account_info(
+        params.caller,
+        AccountInfo {
+            balance: params.value,
+            ..AccountInfo::default()
+        },
+    );
+    db
+}
+
+pub(crate) fn transaction(params: &CallParams) -> TxEnv {
+    TxEnv {
+        caller: params.caller,
+        gas_limit: params.gas_limit,
+        kind: TxKind::Call(CONTRACT_ADDRESS),
+        value: params.value,
+        data: Bytes::copy_from_slice(&params.calldata),
+        ..TxEnv::default()
+    }
+}
+
+pub(crate) fn execution_from(result: ExecutionResult, state: &EvmState) -> Execution {
+    let (status, output, gas_used, logs) = match result {
+        ExecutionResult::Success {
+            gas_used,
+            logs,
+            output,
+            ..
+        } => {
+            let output = match output {
+                Output::Call(data) => data.to_vec(),
+                Output::Create(data, _) => data.to_vec(),
+            };
+            (ExitStatus::Success, output, gas_used, logs)
+        }
+        ExecutionResult::Revert { gas_used, output } => {
+            (ExitStatus::Revert, output.to_vec(), gas_used, Vec::new())
+        }
+        ExecutionResult::Halt { reason, gas_used } => (
+            ExitStatus::Halt(format!("{reason:?}")),
+            Vec::new(),
+            gas_used,
+            Vec::new(),
+        ),
+    };
+
+    let logs = logs
+        .into_iter()
+        .map(|log| LogEntry {
+            address: log.address,
+            topics: log.data.topics().to_vec(),
+            data: log.data.data.to_vec(),
+        })
+        .collect();
+
+    let mut storage_writes: Vec<StorageWrite> = state
+        .iter()
+        .flat_map(|(address, account)| {
+            account
+                .changed_storage_slots()
+                .map(|(slot, value)| StorageWrite {
+                    address: *address,
+                    slot: *slot,
+                    original: value.original_value,
+                    present: value.present_value,
+                })
+        })
+        .collect();
+    storage_writes.sort_by_key(|write| (write.address, write.slot));
+
+    Execution {
+        status,
+        output,
+        gas_used,
+        logs,
+        storage_writes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(code: &str, params: &CallParams) -> Execution {
+        execute(&hex::decode(code).unwrap(), params).unwrap()
+    }
+
+    #[test]
+    fn test_return_and_storage() {
+        // CALLVALUE, PUSH1 0x01, SSTORE, PUSH0, CALLDATALOAD, PUSH0, MSTORE, PUSH1 0x20, PUSH0,
+        // RETURN
+        let mut calldata = vec![0; 32];
+        calldata[31] = 7;
+        let params = CallParams::new(calldata, U256::from(5), DEFAULT_CALLER, DEFAULT_GAS_LIMIT);
+        let execution = run("346001555f355f5260205ff3", &params);
+
+        assert_eq!(execution.status, ExitStatus::Success);
+        assert_eq!(execution.output.len(), 32);
+        assert_eq!(execution.output[31], 7);
+        assert_eq!(
+            execution.storage_writes,
+            [StorageWrite {
+                address: CONTRACT_ADDRESS,
+                slot: U256::from(1),
+                original: U256::ZERO,
+                present: U256::from(5),
+            }]
+        );
+        assert!(execution.gas_used > 21_000);
+        assert_eq!(execution.revert_reason(), None);
+    }
+
+    #[test]
+    fn test_logs() {
+        // CALLER, PUSH1 0x2a, PUSH0, MSTORE, PUSH1 0x20, PUSH0, LOG1, STOP
+        let execution = run("33602a5f5260205fa100", &CallParams::default());
+        assert_eq!(execution.logs.len(), 1);
+        let log = &execution.logs[0];
+        assert_eq!(log.address, CONTRACT_ADDRESS);
+        assert_eq!(log.topics, [DEFAULT_CALLER.into_word()]);
+        assert_eq!(log.data[31], 0x2a);
+    }
+
+    #[test]
+    fn test_revert_discards_writes() {
+        // PUSH1 0x01, PUSH0, SSTORE, PUSH0, DUP1, REVERT
+        let execution = run("60015f555f80fd", &CallParams::default());
+        assert_eq!(execution.status, ExitStatus::Revert);
+        assert!(execution.storage_writes.is_empty());
+        assert_eq!(execution.revert_reason(), Some(RevertReason::Empty));
+    }
+
+    #[test]
+    fn test_halt() {
+        let execution = run("fe", &CallParams::default());
+        assert!(matches!(execution.status, ExitStatus::Halt(_)));
+        assert_eq!(execution.gas_used, DEFAULT_GAS_LIMIT);
+
+        let params = CallParams::new(Vec::new(), U256::ZERO, DEFAULT_CALLER, 1_000);
+        assert!(execute(&[0x00], &params).is_err());
+    }
+
+    #[test]
+    fn test_decode_error_string() {
+        let data = hex::decode(concat!(
+            "08c379a0",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "000000000000000000000000000000000000000000000000000000000000000d",
+            "4e6f7420746865206f776e6572000000000000000000000000000000000000"
+        ))
+        .unwrap();
+        let reason = decode_revert_reason(&data);
+        assert_eq!(reason, RevertReason::Error("Not the owner".to_string()));
+        assert_eq!(reason.to_string(), "Error(\"Not the owner\")");
+
+        // A length running past the data is not decoded
+        let truncated = &data[..data.len() - 32];
+        assert!(matches!(
+            decode_revert_reason(truncated),
+            RevertReason::Raw(_)
+        ));
+        assert_eq!(decode_revert_reason(&[0xde, 0xad]).to_string(), "0xdead");
+    }
+}
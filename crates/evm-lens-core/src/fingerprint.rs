@@ -0,0 +1,428 @@
+use std::collections::HashSet;
+
+use revm::bytecode::opcode;
+
+use crate::instruction::{Instruction, decode_instructions};
+use crate::metadata::{Version, parse_metadata};
+
+/// Free memory pointer initialisation emitted by solc 0.4.22 and later:
+/// `PUSH1 0x80 PUSH1 0x40 MSTORE`.
+const SOLC_PROLOGUE: [u8; 5] = [0x60, 0x80, 0x60, 0x40, 0x52];
+
+/// Free memory pointer initialisation emitted by solc before 0.4.22:
+/// `PUSH1 0x60 PUSH1 0x40 MSTORE`.
+const OLD_SOLC_PROLOGUE: [u8; 5] = [0x60, 0x60, 0x60, 0x40, 0x52];
+
+/// Vyper 0.3.x entry: `PUSH1 0x03 CALLDATASIZE GT PUSH2`.
+const VYPER_PROLOGUE: [u8; 5] = [0x60, 0x03, 0x36, 0x11, 0x61];
+
+/// Vyper 0.1/0.2 entry: `PUSH1 0x04 CALLDATASIZE LT ISZERO PUSH2`.
+const OLD_VYPER_PROLOGUE: [u8; 6] = [0x60, 0x04, 0x36, 0x10, 0x15, 0x61];
+
+/// Legacy solc non-payable check: `CALLVALUE DUP1 ISZERO`.
+const LEGACY_CALLVALUE_CHECK: [u8; 3] = [0x34, 0x80, 0x15];
+
+/// Code up to this size without a dispatcher is most likely written by hand.
+const HAND_WRITTEN_MAX_LEN: usize = 256;
+
+/// Toolchain that most likely produced a bytecode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Toolchain {
+    Solc,
+    Vyper,
+    Huff,
+    /// Standalone Yul compiled with `solc --strict-assembly`.
+    Yul,
+    HandWritten,
+}
+
+impl Toolchain {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Toolchain::Solc => "solc",
+            Toolchain::Vyper => "Vyper",
+            Toolchain::Huff => "Huff",
+            Toolchain::Yul => "Yul",
+            Toolchain::HandWritten => "hand-written",
+        }
+    }
+}
+
+impl std::fmt::Display for Toolchain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// solc code generation pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SolcPipeline {
+    Legacy,
+    ViaIr,
+}
+
+impl std::fmt::Display for SolcPipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SolcPipeline::Legacy => write!(f, "legacy"),
+            SolcPipeline::ViaIr => write!(f, "via-IR"),
+        }
+    }
+}
+
+/// What is known about the compiler version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "version", rename_all = "snake_case")
+)]
+pub enum VersionHint {
+    Exact(Version),
+    AtLeast(Version),
+    Below(Version),
+}
+
+impl std::fmt::Display for VersionHint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VersionHint::Exact(version) => write!(f, "{}", version),
+            VersionHint::AtLeast(version) => write!(f, ">={}", version),
+            VersionHint::Below(version) => write!(f, "<{}", version),
+        }
+    }
+}
+
+/// Best guess at the toolchain behind a bytecode.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fingerprint {
+    pub toolchain: Toolchain,
+    pub version: Option<VersionHint>,
+    /// Code generation pipeline; only set for solc when it can be told apart.
+    pub pipeline: Option<SolcPipeline>,
+    /// Confidence in the guess, between 0.0 and 1.0.
+    pub confidence: f64,
+    /// Short descriptions of the signals the guess is based on.
+    pub evidence: Vec<String>,
+}
+
+/// Guesses which toolchain produced `bytes`.
+///
+/// The CBOR metadata trailer is the strongest signal and yields an exact version. Without it,
+/// codegen idioms are used instead: solc's free memory pointer prologue, Vyper's calldata size
+/// check, and the shape of the dispatcher. Huff and standalone Yul are told apart by whether the
+/// code pushes return addresses for internal function calls, which Huff macros do not need.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{Toolchain, fingerprint};
+///
+/// // PUSH1 0x80, PUSH1 0x40, MSTORE, STOP, then {"solc": 0.8.24}
+/// let bytes = hex::decode("608060405200a164736f6c6343000818000a").unwrap();
+/// let fingerprint = fingerprint(&bytes);
+/// assert_eq!(fingerprint.toolchain, Toolchain::Solc);
+/// assert_eq!(fingerprint.version.unwrap().to_string(), "0.8.24");
+/// ```
+pub fn fingerprint(bytes: &[u8]) -> Fingerprint {
+    let metadata = parse_metadata(bytes);
+    let code = metadata
+        .as_ref()
+        .map_or(bytes, |metadata| metadata.strip(bytes));
+    let instructions = decode_instructions(code);
+
+    let mut evidence = Vec::new();
+
+    if let Some(metadata) = &metadata {
+        if let Some(version) = metadata.vyper {
+            evidence.push(format!("metadata records vyper {}", version));
+            return Fingerprint {
+                toolchain: Toolchain::Vyper,
+                version: Some(VersionHint::Exact(version)),
+                pipeline: None,
+                confidence: 0.95,
+                evidence,
+            };
+        }
+
+        let (version, confidence) = match metadata.solc {
+            Some(version) => {
+                evidence.push(format!("metadata records solc {}", version));
+                (Some(VersionHint::Exact(version)), 0.95)
+            }
+            // The solc key was added to the metadata in 0.5.9
+            None if metadata.bzzr0.is_some() => {
+                evidence.push("bzzr0 metadata hash without compiler version".to_string());
+                (Some(VersionHint::Below(Version::new(0, 5, 9))), 0.85)
+            }
+            None => {
+                evidence.push("metadata hash without compiler version".to_string());
+                (None, 0.8)
+            }
+        };
+
+        return Fingerprint {
+            toolchain: Toolchain::Solc,
+            version,
+            pipeline: detect_pipeline(code, &instructions, &mut evidence),
+            confidence,
+            evidence,
+        };
+    }
+
+    let vyper_version = if code.starts_with(&VYPER_PROLOGUE) {
+        Some(VersionHint::AtLeast(Version::new(0, 3, 0)))
+    } else if code.starts_with(&OLD_VYPER_PROLOGUE) {
+        Some(VersionHint::Below(Version::new(0, 3, 0)))
+    } else {
+        None
+    };
+    if let Some(version) = vyper_version {
+        evidence.push("Vyper calldata size prologue".to_string());
+        return Fingerprint {
+            toolchain: Toolchain::Vyper,
+            version: Some(version),
+            pipeline: None,
+            confidence: 0.7,
+            evidence,
+        };
+    }
+
+    if code.starts_with(&SOLC_PROLOGUE) || code.starts_with(&OLD_SOLC_PROLOGUE) {
+        evidence.push("free memory pointer prologue without metadata".to_string());
+        let version = if code.starts_with(&OLD_SOLC_PROLOGUE) {
+            evidence.push("free memory pointer starts at 0x60".to_string());
+            Some(VersionHint::Below(Version::new(0, 4, 22)))
+        } else if uses_push0(&instructions) {
+            evidence.push("uses PUSH0".to_string());
+            Some(VersionHint::AtLeast(Version::new(0, 8, 20)))
+        } else {
+            None
+        };
+
+        return Fingerprint {
+            toolchain: Toolchain::Solc,
+            version,
+            pipeline: detect_pipeline(code, &instructions, &mut evidence),
+            confidence: 0.7,
+            evidence,
+        };
+    }
+
+    if has_selector_dispatch(&instructions) {
+        evidence.push("selector dispatcher without solc prologue or metadata".to_string());
+        let internal_calls = count_return_address_pushes(&instructions);
+        let toolchain = if internal_calls > 0 {
+            evidence.push(format!("{} internal call return addresses", internal_calls));
+            Toolchain::Yul
+        } else {
+            evidence.push("no internal function calls".to_string());
+            Toolchain::Huff
+        };
+
+        return Fingerprint {
+            toolchain,
+            version: None,
+            pipeline: None,
+            confidence: 0.45,
+            evidence,
+        };
+    }
+
+    let confidence = if code.len() <= HAND_WRITTEN_MAX_LEN {
+        evidence.push(format!("{} bytes without a dispatcher", code.len()));
+        0.5
+    } else {
+        evidence.push("no recognisable compiler idioms".to_string());
+        0.3
+    };
+
+    Fingerprint {
+        toolchain: Toolchain::HandWritten,
+        version: None,
+        pipeline: None,
+        confidence,
+        evidence,
+    }
+}
+
+/// Tells legacy and via-IR output apart by how non-payable functions check `CALLVALUE`.
+///
+/// The legacy pipeline emits `CALLVALUE DUP1 ISZERO PUSH2 <continue> JUMPI`, while via-IR jumps
+/// straight to a revert helper with `CALLVALUE PUSH <revert> JUMPI`.
+fn detect_pipeline(
+    code: &[u8],
+    instructions: &[Instruction],
+    evidence: &mut Vec<String>,
+) -> Option<SolcPipeline> {
+    if code
+        .windows(LEGACY_CALLVALUE_CHECK.len())
+        .any(|window| window == LEGACY_CALLVALUE_CHECK)
+    {
+        evidence.push("legacy CALLVALUE DUP1 ISZERO check".to_string());
+        return Some(SolcPipeline::Legacy);
+    }
+
+    let via_ir = instructions.windows(3).any(|window| {
+        window[0].opcode == opcode::CALLVALUE
+            && matches!(window[1].opcode, opcode::PUSH1..=opcode::PUSH3)
+            && window[2].opcode == opcode::JUMPI
+    });
+    if via_ir {
+        evidence.push("via-IR CALLVALUE PUSH JUMPI check".to_string());
+        return Some(SolcPipeline::ViaIr);
+    }
+
+    None
+}
+
+fn uses_push0(instructions: &[Instruction]) -> bool {
+    instructions
+        .iter()
+        .any(|instruction| instruction.opcode == opcode::PUSH0)
+}
+
+/// Looks for `PUSH4 <selector> EQ` or `PUSH4 <selector> DUPn EQ` comparisons.
+fn has_selector_dispatch(instructions: &[Instruction]) -> bool {
+    instructions.windows(3).any(|window| {
+        window[0].opcode == opcode::PUSH4
+            && (window[1].opcode == opcode::EQ
+                || ((opcode::DUP1..=opcode::DUP16).contains(&window[1].opcode)
+                    && window[2].opcode == opcode::EQ))
+    })
+}
+
+/// Counts pushes of a `JUMPDEST` position that are not consumed by the next jump, which is how
+/// compiled code passes return addresses to internal functions.
+fn count_return_address_pushes(instructions: &[Instruction]) -> usize {
+    let jumpdests: HashSet<usize> = instructions
+        .iter()
+        .filter(|instruction| instruction.opcode == opcode::JUMPDEST)
+        .map(|instruction| instruction.pc)
+        .collect();
+
+    instructions
+        .windows(2)
+        .filter(|window| {
+            let target = match window[0].opcode {
+                opcode::PUSH1 | opcode::PUSH2 => window[0]
+                    .push_value()
+                    .and_then(|value| usize::try_from(value).ok()),
+                _ => None,
+            };
+            target.is_some_and(|target| jumpdests.contains(&target))
+                && !matches!(window[1].opcode, opcode::JUMP | opcode::JUMPI)
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solc_with_metadata_legacy() {
+        // PUSH1 0x80, PUSH1 0x40, MSTORE, CALLVALUE, DUP1, ISZERO, ..., {"solc": 0.8.19}
+        let bytes =
+            hex::decode("6080604052348015600f57600080fd5b5000a164736f6c6343000813000a").unwrap();
+        let fingerprint = fingerprint(&bytes);
+
+        assert_eq!(fingerprint.toolchain, Toolchain::Solc);
+        assert_eq!(
+            fingerprint.version,
+            Some(VersionHint::Exact(Version::new(0, 8, 19)))
+        );
+        assert_eq!(fingerprint.pipeline, Some(SolcPipeline::Legacy));
+        assert!(fingerprint.confidence > 0.9);
+    }
+
+    #[test]
+    fn test_solc_via_ir_without_metadata() {
+        // PUSH1 0x80, PUSH1 0x40, MSTORE, CALLVALUE, PUSH1 0x09, JUMPI, PUSH0, DUP1, REVERT,
+        // JUMPDEST, STOP
+        let bytes = hex::decode("6080604052346009575f80fd5b00").unwrap();
+        let fingerprint = fingerprint(&bytes);
+
+        assert_eq!(fingerprint.toolchain, Toolchain::Solc);
+        assert_eq!(fingerprint.pipeline, Some(SolcPipeline::ViaIr));
+        assert_eq!(
+            fingerprint.version,
+            Some(VersionHint::AtLeast(Version::new(0, 8, 20)))
+        );
+    }
+
+    #[test]
+    fn test_old_solc_prologue() {
+        let bytes = hex::decode("606060405200").unwrap();
+        let fingerprint = fingerprint(&bytes);
+
+        assert_eq!(fingerprint.toolchain, Toolchain::Solc);
+        assert_eq!(fingerprint.version.unwrap().to_string(), "<0.4.22");
+    }
+
+    #[test]
+    fn test_vyper_metadata() {
+        let bytes = hex::decode("5f3560e01c00a165767970657283000307000b").unwrap();
+        let fingerprint = fingerprint(&bytes);
+
+        assert_eq!(fingerprint.toolchain, Toolchain::Vyper);
+        assert_eq!(fingerprint.version.unwrap().to_string(), "0.3.7");
+    }
+
+    #[test]
+    fn test_vyper_prologue() {
+        // PUSH1 0x03, CALLDATASIZE, GT, PUSH2 0x000c, JUMPI, ...
+        let bytes = hex::decode("6003361161000c57005b00").unwrap();
+        let fingerprint = fingerprint(&bytes);
+
+        assert_eq!(fingerprint.toolchain, Toolchain::Vyper);
+        assert_eq!(fingerprint.version.unwrap().to_string(), ">=0.3.0");
+    }
+
+    #[test]
+    fn test_old_vyper_prologue() {
+        // PUSH1 0x04, CALLDATASIZE, LT, ISZERO, PUSH2 0x000d, JUMPI, ...
+        let bytes = hex::decode("600436101561000d57005b00").unwrap();
+        let fingerprint = fingerprint(&bytes);
+
+        assert_eq!(fingerprint.toolchain, Toolchain::Vyper);
+        assert_eq!(fingerprint.version.unwrap().to_string(), "<0.3.0");
+    }
+
+    #[test]
+    fn test_huff_dispatcher() {
+        // PUSH0, CALLDATALOAD, PUSH1 0xe0, SHR, DUP1, PUSH4 <selector>, EQ, PUSH1 0x13, JUMPI,
+        // PUSH0, PUSH0, REVERT, JUMPDEST, STOP
+        let bytes = hex::decode("5f3560e01c8063aabbccdd14601357005f5ffd5b00").unwrap();
+        let fingerprint = fingerprint(&bytes);
+
+        assert_eq!(fingerprint.toolchain, Toolchain::Huff);
+    }
+
+    #[test]
+    fn test_yul_dispatcher_with_internal_calls() {
+        // PUSH0, CALLDATALOAD, PUSH1 0xe0, SHR, PUSH4 <selector>, DUP2, EQ, PUSH1 0x13, JUMPI,
+        // STOP, ..., JUMPDEST, PUSH1 0x1a (return address), PUSH1 0x1c, JUMP, JUMPDEST, STOP,
+        // JUMPDEST, JUMP
+        let bytes =
+            hex::decode("5f3560e01c63aabbccdd811460145700000000005b601a601c565b005b56").unwrap();
+        let fingerprint = fingerprint(&bytes);
+
+        assert_eq!(fingerprint.toolchain, Toolchain::Yul);
+    }
+
+    #[test]
+    fn test_hand_written() {
+        // PUSH1 0x2a, PUSH0, MSTORE, PUSH1 0x20, PUSH0, RETURN
+        let bytes = hex::decode("602a5f5260205ff3").unwrap();
+        let fingerprint = fingerprint(&bytes);
+
+        assert_eq!(fingerprint.toolchain, Toolchain::HandWritten);
+        assert_eq!(fingerprint.confidence, 0.5);
+    }
+}
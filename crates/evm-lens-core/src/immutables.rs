@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::code_diff::{CodeDiffOptions, DiffEntry, diff_bytecode};
+
+/// An immutable variable and the byte ranges of runtime code holding its value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Immutable {
+    /// AST id of the variable, the key of solc's `immutableReferences`; `None` when detected.
+    pub ast_id: Option<u64>,
+    /// `start..start + length`, as listed in `immutableReferences`.
+    pub ranges: Vec<Range<usize>>,
+}
+
+impl Immutable {
+    pub fn new(ast_id: Option<u64>, ranges: Vec<Range<usize>>) -> Self {
+        Self { ast_id, ranges }
+    }
+}
+
+/// Finds likely immutables by comparing two deployments of the same code.
+///
+/// Instructions are aligned as in [`diff_bytecode`] with `PUSH32` matched by opcode alone; a
+/// matched `PUSH32` whose value differs is taken to hold an immutable. References whose values
+/// differ in the same way on both sides are grouped into one variable. Ranges are positions in
+/// `bytes`.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::detect_immutables;
+///
+/// // PUSH32 <owner>, SLOAD, PUSH32 <owner>, STOP in two deployments
+/// let deploy = |owner: &str| {
+///     hex::decode(format!("7f{0}547f{0}00", owner.repeat(32))).unwrap()
+/// };
+/// let immutables = detect_immutables(&deploy("11"), &deploy("22"));
+/// assert_eq!(immutables.len(), 1);
+/// assert_eq!(immutables[0].ranges, [1..33, 35..67]);
+/// ```
+pub fn detect_immutables(bytes: &[u8], other: &[u8]) -> Vec<Immutable> {
+    let diff = diff_bytecode(bytes, other, &CodeDiffOptions::new(true));
+    let mut immutables: Vec<Immutable> = Vec::new();
+    let mut by_values: BTreeMap<(Vec<u8>, Vec<u8>), usize> = BTreeMap::new();
+
+    for entry in &diff.entries {
+        let DiffEntry::ImmutableDiffers { old_pc, new_pc, .. } = entry else {
+            continue;
+        };
+        let value = |code: &[u8], pc: usize| code[pc + 1..pc + 33].to_vec();
+        // Truncated pushes at the end of the code hold no full value
+        if old_pc + 33 > bytes.len() || new_pc + 33 > other.len() {
+            continue;
+        }
+        let key = (value(bytes, *old_pc), value(other, *new_pc));
+        let range = old_pc + 1..old_pc + 33;
+        match by_values.get(&key) {
+            Some(&index) => immutables[index].ranges.push(range),
+            None => {
+                by_values.insert(key, immutables.len());
+                immutables.push(Immutable::new(None, vec![range]));
+            }
+        }
+    }
+    immutables
+}
+
+/// Maps the position of each `PUSH` instruction whose immediate holds an immutable to the
+/// immutable's 1-based number in `immutables`, for labelling listings.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{Immutable, immutable_pushes};
+///
+/// let immutables = [Immutable::new(Some(7), vec![1..33, 40..72])];
+/// let pushes = immutable_pushes(&immutables);
+/// assert_eq!(pushes.get(&0), Some(&1));
+/// assert_eq!(pushes.get(&39), Some(&1));
+/// ```
+pub fn immutable_pushes(immutables: &[Immutable]) -> BTreeMap<usize, usize> {
+    immutables
+        .iter()
+        .enumerate()
+        .flat_map(|(index, immutable)| {
+            immutable
+                .ranges
+                .iter()
+                .filter(|range| range.start > 0)
+                .map(move |range| (range.start - 1, index + 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push32(fill: &str) -> String {
+        format!("7f{}", fill.repeat(32))
+    }
+
+    /// Ranges of each immutable as (start, end) pairs.
+    fn spans(immutables: &[Immutable]) -> Vec<Vec<(usize, usize)>> {
+        immutables
+            .iter()
+            .map(|immutable| {
+                immutable
+                    .ranges
+                    .iter()
+                    .map(|range| (range.start, range.end))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_groups_references_by_values() {
+        // Two immutables, the first referenced twice, and an equal PUSH32 constant
+        let a = format!(
+            "{}{}50{}{}00",
+            push32("11"),
+            push32("aa"),
+            push32("11"),
+            push32("ff")
+        );
+        let b = format!(
+            "{}{}50{}{}00",
+            push32("22"),
+            push32("bb"),
+            push32("22"),
+            push32("ff")
+        );
+        let immutables = detect_immutables(&hex::decode(a).unwrap(), &hex::decode(b).unwrap());
+
+        assert_eq!(
+            spans(&immutables),
+            [vec![(1, 33), (68, 100)], vec![(34, 66)]]
+        );
+        assert!(
+            immutables
+                .iter()
+                .all(|immutable| immutable.ast_id.is_none())
+        );
+    }
+
+    #[test]
+    fn test_aligns_around_changed_code() {
+        // The second deployment has an extra POP before the PUSH32
+        let a = format!("6001{}00", push32("11"));
+        let b = format!("600150{}00", push32("22"));
+        let immutables = detect_immutables(&hex::decode(a).unwrap(), &hex::decode(b).unwrap());
+        assert_eq!(spans(&immutables), [[(3, 35)]]);
+    }
+
+    #[test]
+    fn test_identical_code_has_none() {
+        let code = hex::decode(format!("{}00", push32("11"))).unwrap();
+        assert!(detect_immutables(&code, &code).is_empty());
+    }
+
+    #[test]
+    fn test_immutable_pushes_numbers_variables() {
+        let immutables = [
+            Immutable::new(Some(3), vec![1..33, 200..232]),
+            Immutable::new(Some(9), vec![50..82, 100..132]),
+        ];
+        let pushes: Vec<(usize, usize)> = immutable_pushes(&immutables).into_iter().collect();
+        assert_eq!(pushes, [(0, 1), (49, 2), (99, 2), (199, 1)]);
+    }
+}
@@ -0,0 +1,155 @@
+use revm::{
+    bytecode::{OpCode, opcode},
+    primitives::U256,
+};
+
+/// A single decoded instruction together with its immediate data.
+///
+/// Unlike [`crate::disassemble`], decoding never stops at bytes that are not valid opcodes, so
+/// metadata trailers and data sections show up as unknown instructions instead of truncating
+/// the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction<'a> {
+    /// Position of the opcode in the bytecode.
+    pub pc: usize,
+    /// Raw opcode byte.
+    pub opcode: u8,
+    /// Immediate bytes following the opcode; shorter than expected if the code is truncated.
+    pub immediate: &'a [u8],
+}
+
+/// Written as `{"pc", "opcode", "immediate", "size"}` with the mnemonic as `opcode` and the
+/// immediate as `0x`-prefixed hex, or `null` for instructions without one. Instructions borrow
+/// the code, so they serialize but do not deserialize.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Instruction<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("Instruction", 4)?;
+        state.serialize_field("pc", &self.pc)?;
+        state.serialize_field("opcode", &self.name())?;
+        let immediate =
+            (!self.immediate.is_empty()).then(|| format!("0x{}", hex::encode(self.immediate)));
+        state.serialize_field("immediate", &immediate)?;
+        state.serialize_field("size", &self.size())?;
+        state.end()
+    }
+}
+
+impl Instruction<'_> {
+    /// Returns the opcode, or `None` if the byte is not a known opcode.
+    pub fn op(&self) -> Option<OpCode> {
+        OpCode::new(self.opcode)
+    }
+
+    /// Returns the mnemonic, or `UNKNOWN(0x..)` for bytes that are not opcodes.
+    pub fn name(&self) -> String {
+        match self.op() {
+            Some(op) => op.as_str().to_string(),
+            None => format!("UNKNOWN(0x{:02x})", self.opcode),
+        }
+    }
+
+    pub fn is_push(&self) -> bool {
+        (opcode::PUSH0..=opcode::PUSH32).contains(&self.opcode)
+    }
+
+    /// Returns the value pushed by a `PUSH0`..`PUSH32` instruction.
+    pub fn push_value(&self) -> Option<U256> {
+        if !self.is_push() {
+            return None;
+        }
+        Some(U256::from_be_slice(self.immediate))
+    }
+
+    /// Number of bytes the instruction occupies, opcode included.
+    pub fn size(&self) -> usize {
+        1 + self.immediate.len()
+    }
+
+    /// Position of the next instruction.
+    pub fn next_pc(&self) -> usize {
+        self.pc + self.size()
+    }
+}
+
+/// Decodes legacy bytecode into instructions, keeping PUSH immediates.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::decode_instructions;
+/// use revm::primitives::U256;
+///
+/// let bytes = hex::decode("61ABCD00").unwrap(); // PUSH2 0xABCD, STOP
+/// let instructions = decode_instructions(&bytes);
+/// assert_eq!(instructions.len(), 2);
+/// assert_eq!(instructions[0].push_value(), Some(U256::from(0xABCD)));
+/// assert_eq!(instructions[1].pc, 3);
+/// ```
+pub fn decode_instructions(bytes: &[u8]) -> Vec<Instruction<'_>> {
+    let mut instructions = Vec::new();
+    let mut pc = 0;
+
+    while pc < bytes.len() {
+        let op = bytes[pc];
+        let immediate_len = if (opcode::PUSH1..=opcode::PUSH32).contains(&op) {
+            (op - opcode::PUSH0) as usize
+        } else {
+            0
+        };
+        let end = (pc + 1 + immediate_len).min(bytes.len());
+
+        instructions.push(Instruction {
+            pc,
+            opcode: op,
+            immediate: &bytes[pc + 1..end],
+        });
+        pc = end;
+    }
+
+    instructions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_push_immediates() {
+        // PUSH1 0xFF, PUSH2 0xABCD, PUSH0, STOP
+        let bytes = hex::decode("60FF61ABCD5F00").unwrap();
+        let instructions = decode_instructions(&bytes);
+
+        assert_eq!(instructions.len(), 4);
+        assert_eq!(instructions[0].immediate, &[0xFF]);
+        assert_eq!(instructions[1].pc, 2);
+        assert_eq!(instructions[1].push_value(), Some(U256::from(0xABCD)));
+        assert_eq!(instructions[2].push_value(), Some(U256::ZERO));
+        assert_eq!(instructions[3].push_value(), None);
+        assert_eq!(instructions[3].op(), Some(OpCode::STOP));
+    }
+
+    #[test]
+    fn test_decode_unknown_opcode_continues() {
+        // STOP, 0x0C (unassigned), STOP
+        let bytes = hex::decode("000C00").unwrap();
+        let instructions = decode_instructions(&bytes);
+
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[1].op(), None);
+        assert_eq!(instructions[1].name(), "UNKNOWN(0x0c)");
+    }
+
+    #[test]
+    fn test_decode_truncated_push() {
+        // PUSH4 with only two bytes of data
+        let bytes = hex::decode("63ABCD").unwrap();
+        let instructions = decode_instructions(&bytes);
+
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0].immediate, &[0xAB, 0xCD]);
+        assert_eq!(instructions[0].next_pc(), 3);
+    }
+}
@@ -1,10 +1,79 @@
-use revm::{
-    bytecode::{Bytecode, OpCode},
-    primitives::Bytes,
+use revm::{bytecode::Bytecode, primitives::Bytes};
+
+pub use revm::{
+    bytecode::OpCode,
+    primitives::{Address, B256, U256},
 };
 
+pub mod category;
+pub mod cfg;
+pub mod code_diff;
+pub mod decompile;
+pub mod dispatch;
+pub mod execute;
+pub mod fingerprint;
+pub mod immutables;
+pub mod instruction;
+pub mod limits;
+pub mod link;
+pub mod lint;
+pub mod metadata;
+pub mod normalize;
+pub mod proxy;
+pub mod reentrancy;
+#[cfg(feature = "serde")]
+mod serde_hex;
+pub mod similarity;
+#[cfg(feature = "smt")]
+pub mod smt;
+pub mod source_map;
+pub mod ssa;
 pub mod stats;
-pub use stats::{Stats, StatsError, compute_stats};
+pub mod storage;
+pub mod storage_diff;
+pub mod symbolic;
+pub mod trace;
+pub use category::{OpcodeCategory, categorize};
+pub use cfg::{AbstractValue, BasicBlock, BlockExit, Cfg};
+pub use code_diff::{CodeDiff, CodeDiffOptions, DiffEntry, diff_bytecode};
+pub use decompile::{DecompiledFunction, decompile};
+pub use dispatch::{FunctionEntry, function_entries, function_selector};
+pub use execute::{
+    CONTRACT_ADDRESS, CallParams, DEFAULT_CALLER, DEFAULT_GAS_LIMIT, Execution, ExecutionError,
+    ExitStatus, LogEntry, RevertReason, StorageWrite, decode_revert_reason, execute,
+    panic_description,
+};
+pub use fingerprint::{Fingerprint, SolcPipeline, Toolchain, VersionHint, fingerprint};
+pub use immutables::{Immutable, detect_immutables, immutable_pushes};
+pub use instruction::{Instruction, decode_instructions};
+pub use limits::{
+    CodeKind, EIP170_MAX_CODE_SIZE, EIP3860_MAX_INITCODE_SIZE, LimitUsage, SizeLimits,
+    detect_code_kind,
+};
+pub use link::{
+    LibraryPlaceholder, LinkError, find_library_placeholders, library_hash, link_libraries,
+};
+pub use lint::{Finding, LintRule, Severity, lint};
+pub use metadata::{Metadata, Version, parse_metadata};
+pub use normalize::{NormalizeOptions, Normalized, normalize};
+pub use proxy::{
+    EIP1822_PROXIABLE_SLOT, EIP1967_ADMIN_SLOT, EIP1967_BEACON_SLOT, EIP1967_IMPLEMENTATION_SLOT,
+    IMPLEMENTATION_SELECTOR, MinimalProxy, MinimalProxyKind, ProxyKind, UpgradeableProxy,
+    ZEPPELINOS_IMPLEMENTATION_SLOT, address_from_word, detect_minimal_proxy,
+    detect_upgradeable_proxy,
+};
+pub use reentrancy::{CALL_STIPEND, ReentrancyRisk, find_reentrancy};
+pub use similarity::{Cluster, MINHASH_SIZE, MinHash, cluster, similarity};
+pub use source_map::{JumpKind, SourceMapEntry, parse_source_map};
+pub use ssa::{Location, Operand, Phi, Ssa, SsaBlock, SsaInstruction, ValueId};
+pub use stats::{Stats, StatsError, compute_stats, compute_stats_with_limits};
+pub use storage::{PackedField, SlotKind, StorageLayout, StorageVariable, infer_storage_layout};
+pub use storage_diff::{StorageChange, StorageDiff, diff_storage_layouts};
+pub use symbolic::{
+    ConstantFolding, Exploration, Feasibility, PathCondition, PathEnd, Solver, SymbolicLimits,
+    SymbolicPath, Term, explore_paths, explore_paths_with,
+};
+pub use trace::{Trace, TraceOptions, TraceStep, trace, trace_with};
 
 #[derive(Debug)]
 pub enum DisassemblyError {
@@ -89,6 +158,7 @@ pub fn disassemble(bytes: &[u8]) -> Result<Vec<(usize, OpCode)>, DisassemblyErro
 ///
 /// This function takes raw bytecode bytes and returns comprehensive statistics
 /// including byte length, opcode count, maximum stack depth, and other metrics.
+/// Size limit usage is measured against the Ethereum mainnet limits.
 ///
 /// # Arguments
 ///
@@ -110,6 +180,25 @@ pub fn disassemble(bytes: &[u8]) -> Result<Vec<(usize, OpCode)>, DisassemblyErro
 /// println!("Number of opcodes: {}", stats.opcode_count);
 /// ```
 pub fn get_stats(bytes: &[u8]) -> Result<Stats, DisassemblyError> {
+    get_stats_with_limits(bytes, &SizeLimits::default())
+}
+
+/// Computes statistics for the given bytecode, measuring size against custom limits.
+///
+/// Same as [`get_stats`], but lets chains with non-standard contract size limits be described
+/// through [`SizeLimits`].
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{SizeLimits, get_stats_with_limits};
+///
+/// let bytecode = hex::decode("60FF600101").unwrap();
+/// let limits = SizeLimits::new(4, 8);
+/// let stats = get_stats_with_limits(&bytecode, &limits).unwrap();
+/// assert!(stats.code_size_limit.is_exceeded());
+/// ```
+pub fn get_stats_with_limits(bytes: &[u8], limits: &SizeLimits) -> Result<Stats, DisassemblyError> {
     if bytes.is_empty() {
         return Err(DisassemblyError::EmptyBytecode);
     }
@@ -119,7 +208,7 @@ pub fn get_stats(bytes: &[u8]) -> Result<Stats, DisassemblyError> {
         Err(e) => return Err(DisassemblyError::InvalidBytecode(e.to_string())),
     };
 
-    compute_stats(&bytecode).map_err(|e| match e {
+    compute_stats_with_limits(&bytecode, limits).map_err(|e| match e {
         StatsError::UnknownOpcode(opcode) => DisassemblyError::MalformedInstruction {
             position: 0, // We don't have position info from stats error
             byte: opcode,
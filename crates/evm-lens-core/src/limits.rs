@@ -0,0 +1,187 @@
+use revm::bytecode::opcode;
+
+use crate::instruction::{Instruction, decode_instructions};
+
+/// Maximum runtime code size introduced by EIP-170.
+pub const EIP170_MAX_CODE_SIZE: usize = 24_576;
+
+/// Maximum initcode size introduced by EIP-3860.
+pub const EIP3860_MAX_INITCODE_SIZE: usize = 2 * EIP170_MAX_CODE_SIZE;
+
+/// Contract size limits enforced by a chain.
+///
+/// Defaults to the Ethereum mainnet limits; L2s and app-chains that raise or lower them can be
+/// described with [`SizeLimits::new`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SizeLimits {
+    pub max_code_size: usize,
+    pub max_initcode_size: usize,
+}
+
+impl SizeLimits {
+    pub fn new(max_code_size: usize, max_initcode_size: usize) -> Self {
+        Self {
+            max_code_size,
+            max_initcode_size,
+        }
+    }
+}
+
+impl Default for SizeLimits {
+    fn default() -> Self {
+        Self::new(EIP170_MAX_CODE_SIZE, EIP3860_MAX_INITCODE_SIZE)
+    }
+}
+
+/// How much of a size limit a bytecode uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LimitUsage {
+    pub size: usize,
+    pub limit: usize,
+}
+
+impl LimitUsage {
+    pub fn new(size: usize, limit: usize) -> Self {
+        Self { size, limit }
+    }
+
+    /// Percentage of the limit used; above 100.0 when the limit is exceeded.
+    pub fn percent_used(&self) -> f64 {
+        if self.limit == 0 {
+            return 0.0;
+        }
+        self.size as f64 * 100.0 / self.limit as f64
+    }
+
+    /// Bytes left before hitting the limit, zero if already over it.
+    pub fn remaining(&self) -> usize {
+        self.limit.saturating_sub(self.size)
+    }
+
+    /// Bytes over the limit, zero if within it.
+    pub fn excess(&self) -> usize {
+        self.size.saturating_sub(self.limit)
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        self.size > self.limit
+    }
+}
+
+/// Whether a bytecode is deployed runtime code or creation (init) code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CodeKind {
+    Runtime,
+    Creation,
+}
+
+impl std::fmt::Display for CodeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodeKind::Runtime => write!(f, "runtime"),
+            CodeKind::Creation => write!(f, "creation"),
+        }
+    }
+}
+
+/// Guesses whether `bytes` is creation code.
+///
+/// Compilers end the constructor by copying the runtime code into memory and returning it,
+/// so the presence of `CODECOPY` followed by `PUSH1 0x00`/`PUSH0` and `RETURN` is taken as
+/// the mark of creation code. Anything else is assumed to be runtime code. The code is read
+/// with [`decode_instructions`], so push data is never mistaken for that sequence.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{CodeKind, detect_code_kind};
+///
+/// // PUSH1 0x0a, DUP1, PUSH1 0x0c, PUSH1 0x00, CODECOPY, PUSH1 0x00, RETURN
+/// let initcode = hex::decode("600a80600c6000396000f3").unwrap();
+/// assert_eq!(detect_code_kind(&initcode), CodeKind::Creation);
+/// ```
+pub fn detect_code_kind(bytes: &[u8]) -> CodeKind {
+    let instructions = decode_instructions(bytes);
+
+    let is_zero = |instruction: &Instruction| {
+        instruction.opcode == opcode::PUSH0
+            || (instruction.opcode == opcode::PUSH1 && instruction.immediate == [0x00])
+    };
+
+    let found = instructions.windows(3).any(|window| {
+        window[0].opcode == opcode::CODECOPY
+            && is_zero(&window[1])
+            && window[2].opcode == opcode::RETURN
+    });
+
+    if found {
+        CodeKind::Creation
+    } else {
+        CodeKind::Runtime
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_usage_within_limit() {
+        let usage = LimitUsage::new(6_144, EIP170_MAX_CODE_SIZE);
+        assert_eq!(usage.percent_used(), 25.0);
+        assert_eq!(usage.remaining(), 18_432);
+        assert_eq!(usage.excess(), 0);
+        assert!(!usage.is_exceeded());
+    }
+
+    #[test]
+    fn test_limit_usage_exceeded() {
+        let usage = LimitUsage::new(EIP170_MAX_CODE_SIZE + 10, EIP170_MAX_CODE_SIZE);
+        assert!(usage.is_exceeded());
+        assert_eq!(usage.remaining(), 0);
+        assert_eq!(usage.excess(), 10);
+        assert!(usage.percent_used() > 100.0);
+    }
+
+    #[test]
+    fn test_default_limits() {
+        let limits = SizeLimits::default();
+        assert_eq!(limits.max_code_size, 24_576);
+        assert_eq!(limits.max_initcode_size, 49_152);
+    }
+
+    #[test]
+    fn test_detect_runtime_code() {
+        // PUSH1 0x80, PUSH1 0x40, MSTORE, STOP
+        let bytes = hex::decode("608060405200").unwrap();
+        assert_eq!(detect_code_kind(&bytes), CodeKind::Runtime);
+    }
+
+    #[test]
+    fn test_detect_creation_code_with_push0() {
+        // PUSH1 0x0a, DUP1, PUSH1 0x0b, PUSH0, CODECOPY, PUSH0, RETURN
+        let bytes = hex::decode("600a80600b5f395ff3").unwrap();
+        assert_eq!(detect_code_kind(&bytes), CodeKind::Creation);
+    }
+
+    #[test]
+    fn test_codecopy_in_push_data_is_ignored() {
+        // PUSH4 0x396000f3, STOP
+        let bytes = hex::decode("63396000f300").unwrap();
+        assert_eq!(detect_code_kind(&bytes), CodeKind::Runtime);
+    }
+
+    #[test]
+    fn test_truncated_push_ends_detection() {
+        // CODECOPY, PUSH0, RETURN, then a PUSH32 cut short by the end of the code
+        let bytes = hex::decode("395ff37f3960").unwrap();
+        assert_eq!(detect_code_kind(&bytes), CodeKind::Creation);
+        // PUSH3 0x396000, RETURN: the CODECOPY and PUSH1 0x00 are push data
+        let bytes = hex::decode("62396000f3").unwrap();
+        assert_eq!(detect_code_kind(&bytes), CodeKind::Runtime);
+    }
+}
@@ -0,0 +1,223 @@
+use revm::primitives::{Address, keccak256};
+
+/// Length of a placeholder in hex characters, the size of an address.
+const PLACEHOLDER_LEN: usize = 40;
+
+/// A `__$<34 hex chars>$__` placeholder that solc leaves for a library address in unlinked
+/// bytecode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryPlaceholder {
+    /// Byte position of the 20-byte address the placeholder stands for.
+    pub offset: usize,
+    /// The 34 hex characters between `__$` and `$__`: the start of the keccak256 hash of the
+    /// library's fully qualified name.
+    pub hash: String,
+}
+
+impl LibraryPlaceholder {
+    /// Whether the placeholder stands for `library`, given as a fully qualified name such as
+    /// `contracts/Math.sol:SafeMath` or as the placeholder hash itself.
+    pub fn matches(&self, library: &str) -> bool {
+        let library = library
+            .strip_prefix("__$")
+            .and_then(|name| name.strip_suffix("$__"))
+            .unwrap_or(library);
+        library.eq_ignore_ascii_case(&self.hash) || library_hash(library) == self.hash
+    }
+}
+
+impl std::fmt::Display for LibraryPlaceholder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "__${}$__", self.hash)
+    }
+}
+
+/// The placeholder hash solc uses for a library's fully qualified name.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::library_hash;
+///
+/// assert_eq!(library_hash("lib.sol:L").len(), 34);
+/// ```
+pub fn library_hash(fully_qualified_name: &str) -> String {
+    hex::encode(keccak256(fully_qualified_name.as_bytes()))[..34].to_string()
+}
+
+/// Finds library placeholders in hex bytecode, which may start with `0x`, and returns the hex
+/// with each placeholder replaced by zeros, ready to decode.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::find_library_placeholders;
+///
+/// let hash = "a".repeat(34);
+/// let (hex, placeholders) = find_library_placeholders(&format!("73__${hash}$__3b"));
+/// assert_eq!(hex, format!("73{}3b", "0".repeat(40)));
+/// assert_eq!(placeholders[0].offset, 1);
+/// assert_eq!(placeholders[0].hash, hash);
+/// ```
+pub fn find_library_placeholders(hex: &str) -> (String, Vec<LibraryPlaceholder>) {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    let mut cleaned = String::with_capacity(hex.len());
+    let mut placeholders = Vec::new();
+    let mut rest = hex;
+
+    while let Some(start) = rest.find("__$") {
+        let candidate = rest.get(start..start + PLACEHOLDER_LEN);
+        let hash = candidate.and_then(|placeholder| {
+            let hash = placeholder.strip_prefix("__$")?.strip_suffix("$__")?;
+            hash.chars().all(|c| c.is_ascii_hexdigit()).then_some(hash)
+        });
+        let Some(hash) = hash else {
+            // Not a placeholder; leave it for hex decoding to reject
+            cleaned.push_str(&rest[..start + 3]);
+            rest = &rest[start + 3..];
+            continue;
+        };
+
+        cleaned.push_str(&rest[..start]);
+        placeholders.push(LibraryPlaceholder {
+            offset: cleaned.len() / 2,
+            hash: hash.to_lowercase(),
+        });
+        cleaned.push_str(&"0".repeat(PLACEHOLDER_LEN));
+        rest = &rest[start + PLACEHOLDER_LEN..];
+    }
+    cleaned.push_str(rest);
+
+    (cleaned, placeholders)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// No library address was given for the placeholder.
+    MissingLibrary(LibraryPlaceholder),
+    /// The placeholder lies outside the bytecode.
+    OutOfBounds(LibraryPlaceholder),
+}
+
+impl std::fmt::Display for LinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkError::MissingLibrary(placeholder) => write!(
+                f,
+                "No library address for placeholder {} at byte {}",
+                placeholder, placeholder.offset
+            ),
+            LinkError::OutOfBounds(placeholder) => write!(
+                f,
+                "Placeholder {} at byte {} is outside the bytecode",
+                placeholder, placeholder.offset
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LinkError {}
+
+/// Writes library addresses over their placeholders, producing the final bytecode.
+///
+/// Libraries are named as for [`LibraryPlaceholder::matches`]. Every placeholder must be
+/// resolved.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{Address, find_library_placeholders, library_hash, link_libraries};
+///
+/// let unlinked = format!("73__${}$__3b", library_hash("lib.sol:L"));
+/// let (hex, placeholders) = find_library_placeholders(&unlinked);
+/// let bytes = hex::decode(hex).unwrap();
+///
+/// let address = Address::repeat_byte(0x11);
+/// let linked =
+///     link_libraries(&bytes, &placeholders, &[("lib.sol:L".to_string(), address)]).unwrap();
+/// assert_eq!(&linked[1..21], address.as_slice());
+/// ```
+pub fn link_libraries(
+    bytes: &[u8],
+    placeholders: &[LibraryPlaceholder],
+    libraries: &[(String, Address)],
+) -> Result<Vec<u8>, LinkError> {
+    let mut linked = bytes.to_vec();
+    for placeholder in placeholders {
+        let (_, address) = libraries
+            .iter()
+            .find(|(name, _)| placeholder.matches(name))
+            .ok_or_else(|| LinkError::MissingLibrary(placeholder.clone()))?;
+        linked
+            .get_mut(placeholder.offset..placeholder.offset + 20)
+            .ok_or_else(|| LinkError::OutOfBounds(placeholder.clone()))?
+            .copy_from_slice(address.as_slice());
+    }
+    Ok(linked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_placeholders() {
+        let a = library_hash("contracts/Math.sol:SafeMath");
+        let b = library_hash("contracts/Strings.sol:Strings");
+        let hex = format!("0x6001__${a}$__50__${b}$__00");
+        let (cleaned, placeholders) = find_library_placeholders(&hex);
+
+        assert_eq!(cleaned, format!("6001{0}50{0}00", "0".repeat(40)));
+        assert_eq!(
+            placeholders,
+            [
+                LibraryPlaceholder { offset: 2, hash: a },
+                LibraryPlaceholder {
+                    offset: 23,
+                    hash: b
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_malformed_placeholder_is_left_in_place() {
+        let (cleaned, placeholders) = find_library_placeholders("60__$zz$__00");
+        assert_eq!(cleaned, "60__$zz$__00");
+        assert!(placeholders.is_empty());
+    }
+
+    #[test]
+    fn test_matches_name_or_hash() {
+        let placeholder = LibraryPlaceholder {
+            offset: 0,
+            hash: library_hash("lib.sol:L"),
+        };
+        assert!(placeholder.matches("lib.sol:L"));
+        assert!(placeholder.matches(&placeholder.hash.to_uppercase()));
+        assert!(placeholder.matches(&placeholder.to_string()));
+        assert!(!placeholder.matches("L"));
+    }
+
+    #[test]
+    fn test_link_reports_missing_library() {
+        let (hex, placeholders) =
+            find_library_placeholders(&format!("73__${}$__", library_hash("lib.sol:L")));
+        let bytes = hex::decode(hex).unwrap();
+
+        let error = link_libraries(&bytes, &placeholders, &[]).unwrap_err();
+        assert_eq!(error, LinkError::MissingLibrary(placeholders[0].clone()));
+        assert!(
+            error
+                .to_string()
+                .starts_with("No library address for placeholder __$")
+        );
+
+        let error = link_libraries(
+            &[0x73],
+            &placeholders,
+            &[("lib.sol:L".into(), Address::ZERO)],
+        );
+        assert!(matches!(error, Err(LinkError::OutOfBounds(_))));
+    }
+}
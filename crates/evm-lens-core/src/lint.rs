@@ -0,0 +1,366 @@
+use std::collections::BTreeMap;
+
+use revm::{bytecode::opcode, primitives::U256};
+
+use crate::cfg::{AbstractValue, explore, fold_constants};
+use crate::instruction::Instruction;
+
+/// How serious a lint finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A lint rule. Rule IDs are stable and can be used to filter or suppress findings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LintRule {
+    /// `SELFDESTRUCT` reachable from the entry point.
+    #[cfg_attr(feature = "serde", serde(rename = "EL001"))]
+    ReachableSelfdestruct,
+    /// `DELEGATECALL` or `CALLCODE` to an address that is not a constant.
+    #[cfg_attr(feature = "serde", serde(rename = "EL002"))]
+    DynamicDelegatecall,
+    /// `ORIGIN` used in a comparison, typically `tx.origin` authentication.
+    #[cfg_attr(feature = "serde", serde(rename = "EL003"))]
+    TxOriginComparison,
+    /// Success flag of an external call discarded with `POP`.
+    #[cfg_attr(feature = "serde", serde(rename = "EL004"))]
+    UncheckedCall,
+    /// `TIMESTAMP`, `PREVRANDAO` or `BLOCKHASH` deciding a branch.
+    #[cfg_attr(feature = "serde", serde(rename = "EL005"))]
+    BlockValueBranch,
+    /// `SSTORE` to a slot taken directly from calldata.
+    #[cfg_attr(feature = "serde", serde(rename = "EL006"))]
+    ArbitraryStorageWrite,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 6] = [
+        LintRule::ReachableSelfdestruct,
+        LintRule::DynamicDelegatecall,
+        LintRule::TxOriginComparison,
+        LintRule::UncheckedCall,
+        LintRule::BlockValueBranch,
+        LintRule::ArbitraryStorageWrite,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            LintRule::ReachableSelfdestruct => "EL001",
+            LintRule::DynamicDelegatecall => "EL002",
+            LintRule::TxOriginComparison => "EL003",
+            LintRule::UncheckedCall => "EL004",
+            LintRule::BlockValueBranch => "EL005",
+            LintRule::ArbitraryStorageWrite => "EL006",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::ReachableSelfdestruct => "reachable-selfdestruct",
+            LintRule::DynamicDelegatecall => "dynamic-delegatecall",
+            LintRule::TxOriginComparison => "tx-origin-comparison",
+            LintRule::UncheckedCall => "unchecked-call",
+            LintRule::BlockValueBranch => "block-value-branch",
+            LintRule::ArbitraryStorageWrite => "arbitrary-storage-write",
+        }
+    }
+}
+
+impl std::fmt::Display for LintRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.id(), self.name())
+    }
+}
+
+/// A risky pattern found at a specific instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Finding {
+    pub rule: LintRule,
+    pub pc: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Where a stack value may have come from.
+type Sources = u8;
+
+const FROM_CALLDATA: Sources = 1 << 0;
+const FROM_ORIGIN: Sources = 1 << 1;
+const FROM_BLOCK: Sources = 1 << 2;
+const FROM_STORAGE: Sources = 1 << 3;
+
+/// Stack value for the lint pass: a constant when known, plus the sources it depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Tracked {
+    constant: Option<U256>,
+    sources: Sources,
+}
+
+impl AbstractValue for Tracked {
+    fn unknown() -> Self {
+        Tracked {
+            constant: None,
+            sources: 0,
+        }
+    }
+
+    fn constant(value: U256) -> Self {
+        Tracked {
+            constant: Some(value),
+            sources: 0,
+        }
+    }
+
+    fn as_constant(&self) -> Option<U256> {
+        self.constant
+    }
+
+    fn apply(instruction: &Instruction, inputs: &[Self]) -> Self {
+        let sources = match instruction.opcode {
+            opcode::CALLDATALOAD => FROM_CALLDATA,
+            opcode::ORIGIN => FROM_ORIGIN,
+            opcode::TIMESTAMP | opcode::DIFFICULTY | opcode::BLOCKHASH => FROM_BLOCK,
+            opcode::SLOAD => FROM_STORAGE,
+            // Hashing is how mappings and arrays derive slots, so the result is not attacker-chosen
+            opcode::KECCAK256 => 0,
+            _ => inputs
+                .iter()
+                .fold(0, |sources, input| sources | input.sources),
+        };
+        let constant = inputs
+            .iter()
+            .map(|input| input.constant)
+            .collect::<Option<Vec<_>>>()
+            .and_then(|constants| fold_constants(instruction.opcode, &constants));
+
+        Tracked { constant, sources }
+    }
+}
+
+/// Scans reachable code for risky patterns.
+///
+/// Values are tracked along control flow paths from the entry point, so a finding is only
+/// reported for instructions that are reachable with resolved jumps. Findings are sorted by
+/// position.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{LintRule, lint};
+///
+/// // CALLER, SELFDESTRUCT
+/// let bytes = hex::decode("33ff").unwrap();
+/// let findings = lint(&bytes);
+/// assert_eq!(findings[0].rule, LintRule::ReachableSelfdestruct);
+/// ```
+pub fn lint(bytes: &[u8]) -> Vec<Finding> {
+    let mut findings: BTreeMap<(usize, LintRule), Finding> = BTreeMap::new();
+    let mut report = |instruction: &Instruction, rule: LintRule, severity, message: &str| {
+        let finding = Finding {
+            rule,
+            pc: instruction.pc,
+            severity,
+            message: message.to_string(),
+        };
+        // Keep the most severe variant when a path-dependent rule fires more than once
+        findings
+            .entry((instruction.pc, rule))
+            .and_modify(|existing| {
+                if finding.severity > existing.severity {
+                    *existing = finding.clone();
+                }
+            })
+            .or_insert(finding);
+    };
+
+    let cfg = explore::<Tracked>(bytes, |instruction, inputs| match instruction.opcode {
+        opcode::SELFDESTRUCT => report(
+            instruction,
+            LintRule::ReachableSelfdestruct,
+            Severity::High,
+            "SELFDESTRUCT is reachable; check that it is restricted to a trusted caller",
+        ),
+        opcode::DELEGATECALL | opcode::CALLCODE if inputs[1].constant.is_none() => {
+            if inputs[1].sources & FROM_CALLDATA != 0 {
+                report(
+                    instruction,
+                    LintRule::DynamicDelegatecall,
+                    Severity::High,
+                    "delegated call target is taken from calldata",
+                )
+            } else {
+                report(
+                    instruction,
+                    LintRule::DynamicDelegatecall,
+                    Severity::Low,
+                    "delegated call target is not a constant; expected for proxies",
+                )
+            }
+        }
+        opcode::EQ | opcode::LT | opcode::GT | opcode::SLT | opcode::SGT
+            if inputs.iter().any(|input| input.sources & FROM_ORIGIN != 0) =>
+        {
+            report(
+                instruction,
+                LintRule::TxOriginComparison,
+                Severity::Medium,
+                "tx.origin is compared; authorising with it lets intermediate contracts act for users",
+            )
+        }
+        opcode::JUMPI if inputs[1].sources & FROM_BLOCK != 0 => report(
+            instruction,
+            LintRule::BlockValueBranch,
+            Severity::Medium,
+            "branch depends on TIMESTAMP, PREVRANDAO or BLOCKHASH, which block producers can influence",
+        ),
+        opcode::SSTORE if inputs[0].sources & FROM_CALLDATA != 0 => report(
+            instruction,
+            LintRule::ArbitraryStorageWrite,
+            Severity::High,
+            "storage slot is taken from calldata without hashing; callers can overwrite any slot",
+        ),
+        _ => {}
+    });
+
+    for block in cfg.reachable_blocks() {
+        for pair in block.instructions.windows(2) {
+            let is_call = matches!(
+                pair[0].opcode,
+                opcode::CALL | opcode::CALLCODE | opcode::DELEGATECALL | opcode::STATICCALL
+            );
+            if is_call && pair[1].opcode == opcode::POP {
+                report(
+                    &pair[0],
+                    LintRule::UncheckedCall,
+                    Severity::Medium,
+                    "call success flag is discarded; a failed call goes unnoticed",
+                );
+            }
+        }
+    }
+
+    findings.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    fn rules(bytes: &str) -> Vec<(LintRule, usize, Severity)> {
+        lint(&hex::decode(bytes).unwrap())
+            .into_iter()
+            .map(|finding| (finding.rule, finding.pc, finding.severity))
+            .collect()
+    }
+
+    #[test]
+    fn test_rule_ids_are_unique() {
+        let ids: BTreeSet<&str> = LintRule::ALL.iter().map(|rule| rule.id()).collect();
+        assert_eq!(ids.len(), LintRule::ALL.len());
+    }
+
+    #[test]
+    fn test_unreachable_selfdestruct_is_ignored() {
+        // STOP, CALLER, SELFDESTRUCT
+        assert!(rules("0033ff").is_empty());
+        // CALLER, SELFDESTRUCT
+        assert_eq!(
+            rules("33ff"),
+            vec![(LintRule::ReachableSelfdestruct, 1, Severity::High)]
+        );
+    }
+
+    #[test]
+    fn test_delegatecall_targets() {
+        // PUSH0 x4, PUSH0 CALLDATALOAD (target), GAS, DELEGATECALL, STOP
+        assert_eq!(
+            rules("5f5f5f5f5f355af400"),
+            vec![(LintRule::DynamicDelegatecall, 7, Severity::High)]
+        );
+        // PUSH0 x4, PUSH0 SLOAD (target), GAS, DELEGATECALL, STOP
+        assert_eq!(
+            rules("5f5f5f5f5f545af400"),
+            vec![(LintRule::DynamicDelegatecall, 7, Severity::Low)]
+        );
+        // PUSH0 x4, PUSH20 <address>, GAS, DELEGATECALL, STOP
+        let constant = format!("5f5f5f5f73{}5af400", "11".repeat(20));
+        assert!(rules(&constant).is_empty());
+    }
+
+    #[test]
+    fn test_tx_origin_comparison() {
+        // CALLER, ORIGIN, EQ, STOP
+        assert_eq!(
+            rules("33321400"),
+            vec![(LintRule::TxOriginComparison, 2, Severity::Medium)]
+        );
+    }
+
+    #[test]
+    fn test_unchecked_call() {
+        // PUSH0 x5, CALLER, GAS, CALL, POP, STOP
+        assert_eq!(
+            rules("5f5f5f5f5f335af15000"),
+            vec![(LintRule::UncheckedCall, 7, Severity::Medium)]
+        );
+        // PUSH0 x5, CALLER, GAS, CALL, ISZERO, ... checked
+        assert!(rules("5f5f5f5f5f335af11500").is_empty());
+    }
+
+    #[test]
+    fn test_block_value_branch() {
+        // TIMESTAMP, PUSH1 0x05, JUMPI, STOP, JUMPDEST, STOP
+        assert_eq!(
+            rules("42600557005b00"),
+            vec![(LintRule::BlockValueBranch, 3, Severity::Medium)]
+        );
+    }
+
+    #[test]
+    fn test_arbitrary_storage_write() {
+        // CALLVALUE, PUSH0, CALLDATALOAD, SSTORE, STOP
+        assert_eq!(
+            rules("345f355500"),
+            vec![(LintRule::ArbitraryStorageWrite, 3, Severity::High)]
+        );
+        // Mapping write: CALLVALUE, PUSH0, CALLDATALOAD, PUSH0, MSTORE, PUSH1 0x20, PUSH0,
+        // KECCAK256, SSTORE, STOP
+        assert!(rules("345f355f5260205f205500").is_empty());
+    }
+
+    #[test]
+    fn test_findings_follow_resolved_jumps() {
+        // PUSH1 0x04, JUMP, STOP, JUMPDEST, CALLER, SELFDESTRUCT
+        assert_eq!(
+            rules("600456005b33ff"),
+            vec![(LintRule::ReachableSelfdestruct, 6, Severity::High)]
+        );
+    }
+}
@@ -0,0 +1,310 @@
+/// A compiler version as encoded in contract metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Version {
+    pub major: u8,
+    pub minor: u8,
+    pub patch: u8,
+}
+
+impl Version {
+    pub const fn new(major: u8, minor: u8, patch: u8) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// CBOR-encoded metadata trailer appended to the code by solc and Vyper.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata {
+    /// Position of the first byte of the trailer; everything before it is code and data.
+    pub offset: usize,
+    /// Version recorded under the `solc` key (solc 0.5.9 and later).
+    pub solc: Option<Version>,
+    /// Version recorded under the `vyper` key (Vyper 0.3.4 and later).
+    pub vyper: Option<Version>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::option_bytes"))]
+    pub ipfs: Option<Vec<u8>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::option_bytes"))]
+    pub bzzr0: Option<Vec<u8>>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::option_bytes"))]
+    pub bzzr1: Option<Vec<u8>>,
+    /// Set when the source used `pragma experimental`.
+    pub experimental: bool,
+}
+
+impl Metadata {
+    /// Returns the bytes before the metadata trailer.
+    pub fn strip<'a>(&self, bytes: &'a [u8]) -> &'a [u8] {
+        &bytes[..self.offset.min(bytes.len())]
+    }
+}
+
+/// Parses the metadata trailer at the end of `bytes`, if there is one.
+///
+/// The trailer is a CBOR item followed by its length as a big-endian `u16`. solc and Vyper before
+/// 0.3.10 encode a map and count only the CBOR bytes; Vyper 0.3.10 and later encode an array
+/// ending in a map and include the two length bytes in the count. Both layouts are accepted.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{Version, parse_metadata};
+///
+/// // STOP, then {"solc": 0x000818} and its length
+/// let bytes = hex::decode("00a164736f6c6343000818000a").unwrap();
+/// let metadata = parse_metadata(&bytes).unwrap();
+/// assert_eq!(metadata.solc, Some(Version::new(0, 8, 24)));
+/// assert_eq!(metadata.offset, 1);
+/// ```
+pub fn parse_metadata(bytes: &[u8]) -> Option<Metadata> {
+    if bytes.len() < 2 {
+        return None;
+    }
+
+    let declared = u16::from_be_bytes([bytes[bytes.len() - 2], bytes[bytes.len() - 1]]) as usize;
+    let body_end = bytes.len() - 2;
+
+    // Length of the CBOR item alone, then length including the two length bytes
+    let candidates = [Some(declared), declared.checked_sub(2)];
+    candidates
+        .into_iter()
+        .flatten()
+        .filter(|len| *len > 0 && *len <= body_end)
+        .find_map(|len| {
+            let start = body_end - len;
+            let mut decoder = Decoder::new(&bytes[start..body_end]);
+            let item = decoder.item()?;
+            if !decoder.is_done() {
+                return None;
+            }
+            metadata_from_item(item, start)
+        })
+}
+
+fn metadata_from_item(item: Cbor, offset: usize) -> Option<Metadata> {
+    let entries = match item {
+        Cbor::Map(entries) => entries,
+        // Vyper 0.3.10+: [runtime size, data sizes, immutables size, {"vyper": [...]}]
+        Cbor::Array(mut items) => match items.pop() {
+            Some(Cbor::Map(entries)) => entries,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let mut metadata = Metadata {
+        offset,
+        ..Default::default()
+    };
+    let mut recognised = false;
+
+    for (key, value) in entries {
+        let Cbor::Text(key) = key else {
+            return None;
+        };
+
+        match (key.as_str(), value) {
+            ("solc", Cbor::Bytes(version)) if version.len() == 3 => {
+                metadata.solc = Some(Version::new(version[0], version[1], version[2]));
+            }
+            ("vyper", Cbor::Array(parts)) if parts.len() == 3 => {
+                let mut numbers = parts.iter().filter_map(|part| match part {
+                    Cbor::Uint(n) => u8::try_from(*n).ok(),
+                    _ => None,
+                });
+                let (Some(major), Some(minor), Some(patch)) =
+                    (numbers.next(), numbers.next(), numbers.next())
+                else {
+                    return None;
+                };
+                metadata.vyper = Some(Version::new(major, minor, patch));
+            }
+            ("ipfs", Cbor::Bytes(hash)) => metadata.ipfs = Some(hash),
+            ("bzzr0", Cbor::Bytes(hash)) => metadata.bzzr0 = Some(hash),
+            ("bzzr1", Cbor::Bytes(hash)) => metadata.bzzr1 = Some(hash),
+            ("experimental", Cbor::Bool(flag)) => metadata.experimental = flag,
+            // Unknown keys are tolerated as long as a known one is present
+            _ => continue,
+        }
+        recognised = true;
+    }
+
+    recognised.then_some(metadata)
+}
+
+/// The subset of CBOR used by compiler metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Cbor {
+    Uint(u64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Cbor>),
+    Map(Vec<(Cbor, Cbor)>),
+    Bool(bool),
+    Null,
+}
+
+/// Nesting limit, so malformed input cannot recurse deeply.
+const MAX_CBOR_DEPTH: usize = 8;
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(slice)
+    }
+
+    /// Reads a collection length, rejecting lengths the remaining input cannot hold.
+    fn length(&mut self, info: u8) -> Option<usize> {
+        let len = usize::try_from(self.argument(info)?).ok()?;
+        (len <= self.bytes.len() - self.pos).then_some(len)
+    }
+
+    fn argument(&mut self, info: u8) -> Option<u64> {
+        match info {
+            0..=23 => Some(info as u64),
+            24 => self.take(1).map(|b| b[0] as u64),
+            25 => self
+                .take(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as u64),
+            26 => self
+                .take(4)
+                .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as u64),
+            27 => self
+                .take(8)
+                .map(|b| u64::from_be_bytes(b.try_into().unwrap_or_default())),
+            _ => None,
+        }
+    }
+
+    fn item(&mut self) -> Option<Cbor> {
+        if self.depth > MAX_CBOR_DEPTH {
+            return None;
+        }
+
+        let initial = *self.take(1)?.first()?;
+        let major = initial >> 5;
+        let info = initial & 0x1f;
+
+        let item = match major {
+            0 => Cbor::Uint(self.argument(info)?),
+            2 => {
+                let len = self.length(info)?;
+                Cbor::Bytes(self.take(len)?.to_vec())
+            }
+            3 => {
+                let len = self.length(info)?;
+                Cbor::Text(String::from_utf8(self.take(len)?.to_vec()).ok()?)
+            }
+            4 => {
+                let len = self.length(info)?;
+                self.depth += 1;
+                let items = (0..len).map(|_| self.item()).collect::<Option<Vec<_>>>()?;
+                self.depth -= 1;
+                Cbor::Array(items)
+            }
+            5 => {
+                let len = self.length(info)?;
+                self.depth += 1;
+                let entries = (0..len)
+                    .map(|_| Some((self.item()?, self.item()?)))
+                    .collect::<Option<Vec<_>>>()?;
+                self.depth -= 1;
+                Cbor::Map(entries)
+            }
+            7 => match info {
+                20 => Cbor::Bool(false),
+                21 => Cbor::Bool(true),
+                22 => Cbor::Null,
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solc_ipfs_metadata() {
+        // {"ipfs": <34 bytes>, "solc": 0.8.19}
+        let hash = "12".repeat(34);
+        let trailer = format!("a264697066735822{}64736f6c63430008130033", hash);
+        let bytes = hex::decode(format!("6080604052fe{}", trailer)).unwrap();
+
+        let metadata = parse_metadata(&bytes).unwrap();
+        assert_eq!(metadata.offset, 6);
+        assert_eq!(metadata.solc, Some(Version::new(0, 8, 19)));
+        assert_eq!(metadata.ipfs, Some(vec![0x12; 34]));
+        assert_eq!(metadata.strip(&bytes), hex::decode("6080604052fe").unwrap());
+    }
+
+    #[test]
+    fn test_legacy_bzzr0_metadata() {
+        // {"bzzr0": <32 bytes>}
+        let trailer = format!("a165627a7a72305820{}0029", "ab".repeat(32));
+        let bytes = hex::decode(format!("00{}", trailer)).unwrap();
+
+        let metadata = parse_metadata(&bytes).unwrap();
+        assert_eq!(metadata.bzzr0, Some(vec![0xab; 32]));
+        assert_eq!(metadata.solc, None);
+    }
+
+    #[test]
+    fn test_vyper_map_metadata() {
+        // {"vyper": [0, 3, 7]}, length counts the CBOR bytes only
+        let bytes = hex::decode("00a165767970657283000307000b").unwrap();
+        let metadata = parse_metadata(&bytes).unwrap();
+        assert_eq!(metadata.vyper, Some(Version::new(0, 3, 7)));
+        assert_eq!(metadata.offset, 1);
+    }
+
+    #[test]
+    fn test_vyper_array_metadata() {
+        // [10, [], 0, {"vyper": [0, 4, 0]}], length includes the two length bytes
+        let bytes = hex::decode("00840a8000a1657679706572830004000011").unwrap();
+        let metadata = parse_metadata(&bytes).unwrap();
+        assert_eq!(metadata.vyper, Some(Version::new(0, 4, 0)));
+        assert_eq!(metadata.offset, 1);
+    }
+
+    #[test]
+    fn test_no_metadata() {
+        assert!(parse_metadata(&hex::decode("60FF61ABCD00").unwrap()).is_none());
+        assert!(parse_metadata(&[0x00]).is_none());
+        assert!(parse_metadata(&[]).is_none());
+    }
+}
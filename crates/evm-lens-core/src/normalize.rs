@@ -0,0 +1,157 @@
+use std::ops::Range;
+
+use revm::bytecode::opcode;
+use revm::primitives::{B256, keccak256};
+
+use crate::instruction::decode_instructions;
+use crate::metadata::parse_metadata;
+
+/// What [`normalize`] masks besides the metadata trailer.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NormalizeOptions {
+    /// Byte ranges holding immutable values, such as solc's `immutableReferences`
+    /// (`start..start + length`). They are zeroed.
+    pub immutables: Vec<Range<usize>>,
+    /// Zero every `PUSH20` immediate, which hides addresses set by the constructor or linker.
+    pub mask_addresses: bool,
+    /// Zero every `PUSH32` immediate.
+    pub mask_constants: bool,
+}
+
+impl NormalizeOptions {
+    pub fn new(mask_addresses: bool, mask_constants: bool) -> Self {
+        Self {
+            immutables: Vec::new(),
+            mask_addresses,
+            mask_constants,
+        }
+    }
+
+    /// Adds immutable byte ranges to zero.
+    pub fn with_immutables(mut self, immutables: impl IntoIterator<Item = Range<usize>>) -> Self {
+        self.immutables.extend(immutables);
+        self
+    }
+}
+
+/// Bytecode with the parts that vary between compilations of the same source masked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Normalized {
+    pub bytes: Vec<u8>,
+    /// `keccak256` of [`Normalized::bytes`], a stable key for code compiled from the same
+    /// source.
+    pub hash: B256,
+}
+
+/// Strips the metadata trailer and zeroes immutables and, when asked, address and 32-byte
+/// constants, so that deployments of the same source normalize to the same bytes.
+///
+/// Masked values keep their length, so positions in the normalized code match the original.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{NormalizeOptions, normalize};
+///
+/// // PUSH20 <address>, SLOAD, STOP, then a metadata trailer
+/// let a = hex::decode(format!(
+///     "73{}5400a264697066735822{}64736f6c63430008130033",
+///     "11".repeat(20),
+///     "aa".repeat(34)
+/// ))
+/// .unwrap();
+/// let b = hex::decode(format!("73{}5400", "22".repeat(20))).unwrap();
+///
+/// let options = NormalizeOptions::new(true, false);
+/// let normalized = normalize(&a, &options);
+/// assert_eq!(normalized.bytes, hex::decode(format!("73{}5400", "00".repeat(20))).unwrap());
+/// assert_eq!(normalized.hash, normalize(&b, &options).hash);
+/// ```
+pub fn normalize(bytes: &[u8], options: &NormalizeOptions) -> Normalized {
+    let code = parse_metadata(bytes).map_or(bytes, |metadata| metadata.strip(bytes));
+    let mut normalized = code.to_vec();
+
+    for range in &options.immutables {
+        let end = range.end.min(normalized.len());
+        if range.start < end {
+            normalized[range.start..end].fill(0);
+        }
+    }
+
+    if options.mask_addresses || options.mask_constants {
+        let masked: Vec<Range<usize>> = decode_instructions(code)
+            .iter()
+            .filter(|instruction| match instruction.opcode {
+                opcode::PUSH20 => options.mask_addresses,
+                opcode::PUSH32 => options.mask_constants,
+                _ => false,
+            })
+            .map(|instruction| {
+                let start = instruction.pc + 1;
+                start..start + instruction.immediate.len()
+            })
+            .collect();
+        for range in masked {
+            normalized[range].fill(0);
+        }
+    }
+
+    Normalized {
+        hash: keccak256(&normalized),
+        bytes: normalized,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize_hex(code: &str, options: &NormalizeOptions) -> String {
+        hex::encode(normalize(&hex::decode(code).unwrap(), options).bytes)
+    }
+
+    #[test]
+    fn test_metadata_only_by_default() {
+        let code = format!("7f{}73{}00", "11".repeat(32), "22".repeat(20));
+        let trailer = format!("a264697066735822{}64736f6c63430008130033", "ab".repeat(34));
+        let options = NormalizeOptions::default();
+        assert_eq!(normalize_hex(&format!("{code}{trailer}"), &options), code);
+        assert_eq!(
+            normalize(&hex::decode(&code).unwrap(), &options).hash,
+            keccak256(hex::decode(&code).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_masks_push_immediates() {
+        let code = format!("7f{}73{}6001", "11".repeat(32), "22".repeat(20));
+        assert_eq!(
+            normalize_hex(&code, &NormalizeOptions::new(false, true)),
+            format!("7f{}73{}6001", "00".repeat(32), "22".repeat(20))
+        );
+        assert_eq!(
+            normalize_hex(&code, &NormalizeOptions::new(true, true)),
+            format!("7f{}73{}6001", "00".repeat(32), "00".repeat(20))
+        );
+    }
+
+    #[test]
+    fn test_zeroes_immutable_ranges() {
+        // PUSH32 <immutable>, PUSH1 0x01, STOP
+        let code = format!("7f{}600100", "ab".repeat(32));
+        let options = NormalizeOptions::default().with_immutables([1..33, 40..50]);
+        assert_eq!(
+            normalize_hex(&code, &options),
+            format!("7f{}600100", "00".repeat(32))
+        );
+    }
+
+    #[test]
+    fn test_truncated_push_is_masked() {
+        // PUSH20 with only two immediate bytes
+        assert_eq!(
+            normalize_hex("731122", &NormalizeOptions::new(true, true)),
+            "730000"
+        );
+    }
+}
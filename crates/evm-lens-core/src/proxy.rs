@@ -0,0 +1,601 @@
+use revm::{
+    bytecode::opcode,
+    primitives::{Address, B256, b256},
+};
+
+use crate::instruction::decode_instructions;
+
+/// EIP-1967 implementation slot, `keccak256("eip1967.proxy.implementation") - 1`.
+pub const EIP1967_IMPLEMENTATION_SLOT: B256 =
+    b256!("360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+
+/// EIP-1967 beacon slot, `keccak256("eip1967.proxy.beacon") - 1`.
+pub const EIP1967_BEACON_SLOT: B256 =
+    b256!("a3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50");
+
+/// EIP-1967 admin slot, `keccak256("eip1967.proxy.admin") - 1`.
+pub const EIP1967_ADMIN_SLOT: B256 =
+    b256!("b53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103");
+
+/// EIP-1822 (UUPS) code slot, `keccak256("PROXIABLE")`.
+pub const EIP1822_PROXIABLE_SLOT: B256 =
+    b256!("c5f16f0fcc639fa48a6947836d9850f504798523bf8c9a3a87d5876cf622bcf7");
+
+/// Implementation slot of pre-EIP-1967 OpenZeppelin (ZeppelinOS) proxies,
+/// `keccak256("org.zeppelinos.proxy.implementation")`.
+pub const ZEPPELINOS_IMPLEMENTATION_SLOT: B256 =
+    b256!("7050c9e0f4ca769c69bd3a8ef740bc37934f8e2c036e5a723fd8ee048ed3f8c3");
+
+/// Selector of `implementation()`, exposed by EIP-897 proxies and beacons.
+pub const IMPLEMENTATION_SELECTOR: [u8; 4] = [0x5c, 0x60, 0xda, 0x1b];
+
+/// Selector of `proxyType()` from EIP-897.
+const PROXY_TYPE_SELECTOR: [u8; 4] = [0x45, 0x55, 0xd5, 0xc9];
+
+/// Selector of `proxiableUUID()` from EIP-1822, implemented by UUPS contracts.
+const PROXIABLE_UUID_SELECTOR: [u8; 4] = [0x52, 0xd1, 0x90, 0x2d];
+
+/// Code preceding the implementation address in an EIP-1167 minimal proxy.
+const EIP1167_PREFIX: [u8; 9] = [0x36, 0x3d, 0x3d, 0x37, 0x3d, 0x3d, 0x3d, 0x36, 0x3d];
+
+/// Code following the implementation address in an EIP-1167 minimal proxy, up to the
+/// `PUSH1 <jumpdest>` operand.
+const EIP1167_SUFFIX: [u8; 10] = [0x5a, 0xf4, 0x3d, 0x82, 0x80, 0x3e, 0x90, 0x3d, 0x91, 0x60];
+
+/// Code preceding the implementation address in an ERC-7511 (PUSH0) minimal proxy.
+const ERC7511_PREFIX: [u8; 8] = [0x36, 0x5f, 0x5f, 0x37, 0x5f, 0x5f, 0x36, 0x5f];
+
+/// Code following the implementation address in an ERC-7511 (PUSH0) minimal proxy, up to the
+/// `PUSH1 <jumpdest>` operand.
+const ERC7511_SUFFIX: [u8; 10] = [0x5a, 0xf4, 0x3d, 0x5f, 0x5f, 0x3e, 0x5f, 0x3d, 0x91, 0x60];
+
+/// Code closing both minimal proxy variants: `JUMPI REVERT JUMPDEST RETURN`.
+const PROXY_EPILOGUE: [u8; 4] = [0x57, 0xfd, 0x5b, 0xf3];
+
+/// Largest code section, in bytes, still considered a clone by the structural fallback.
+const MAX_CLONE_CODE_LEN: usize = 96;
+
+/// Flavour of minimal proxy recognised by [`detect_minimal_proxy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MinimalProxyKind {
+    /// The canonical EIP-1167 clone, including vanity variants with a shorter address push.
+    Eip1167,
+    /// The ERC-7511 clone, which uses `PUSH0` instead of `RETURNDATASIZE` for zeros.
+    Erc7511,
+    /// Any other delegating clone, such as the `ClonesWithImmutableArgs` family, that only
+    /// forwards calldata to a hardcoded implementation.
+    Clone,
+}
+
+impl MinimalProxyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MinimalProxyKind::Eip1167 => "EIP-1167",
+            MinimalProxyKind::Erc7511 => "ERC-7511 (PUSH0)",
+            MinimalProxyKind::Clone => "clone",
+        }
+    }
+}
+
+impl std::fmt::Display for MinimalProxyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A minimal proxy and the implementation it delegates to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MinimalProxy {
+    pub kind: MinimalProxyKind,
+    pub implementation: Address,
+    /// Bytes appended after the proxy code, used as immutable arguments by some clone factories.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::bytes"))]
+    pub immutable_args: Vec<u8>,
+}
+
+/// Written as the kind, followed by "with immutable args" when bytes are appended to the code.
+impl std::fmt::Display for MinimalProxy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.immutable_args.is_empty() {
+            write!(f, "{}", self.kind)
+        } else {
+            write!(f, "{} with immutable args", self.kind)
+        }
+    }
+}
+
+/// Recognises minimal proxy (clone) bytecode and extracts its implementation address.
+///
+/// The EIP-1167 and ERC-7511 templates are matched exactly, allowing for vanity addresses pushed
+/// with fewer than 20 bytes. Bytes appended after either template are returned as immutable
+/// arguments, which is how Solady's `LibClone` stores them. Other short contracts that do nothing
+/// but forward calldata to a hardcoded address with `DELEGATECALL` are reported as
+/// [`MinimalProxyKind::Clone`].
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{MinimalProxyKind, detect_minimal_proxy};
+///
+/// let bytes = hex::decode(
+///     "363d3d373d3d3d363d73bebebebebebebebebebebebebebebebebebebebe5af43d82803e903d91602b57fd5bf3",
+/// )
+/// .unwrap();
+/// let proxy = detect_minimal_proxy(&bytes).unwrap();
+/// assert_eq!(proxy.kind, MinimalProxyKind::Eip1167);
+/// assert_eq!(proxy.implementation.0[0], 0xbe);
+/// assert!(proxy.immutable_args.is_empty());
+/// ```
+pub fn detect_minimal_proxy(bytes: &[u8]) -> Option<MinimalProxy> {
+    match_template(
+        bytes,
+        &EIP1167_PREFIX,
+        &EIP1167_SUFFIX,
+        MinimalProxyKind::Eip1167,
+    )
+    .or_else(|| {
+        match_template(
+            bytes,
+            &ERC7511_PREFIX,
+            &ERC7511_SUFFIX,
+            MinimalProxyKind::Erc7511,
+        )
+    })
+    .or_else(|| match_delegating_clone(bytes))
+}
+
+/// Matches `prefix PUSHn <address> suffix <jumpdest> JUMPI REVERT JUMPDEST RETURN <args>`.
+fn match_template(
+    bytes: &[u8],
+    prefix: &[u8],
+    suffix: &[u8],
+    kind: MinimalProxyKind,
+) -> Option<MinimalProxy> {
+    let rest = bytes.strip_prefix(prefix)?;
+    let (implementation, rest) = read_address_push(rest)?;
+    let rest = rest.strip_prefix(suffix)?;
+    let (&jumpdest, rest) = rest.split_first()?;
+    let args = rest.strip_prefix(&PROXY_EPILOGUE)?;
+
+    // The JUMPI target must be the JUMPDEST two bytes before the end of the template
+    let code_len = bytes.len() - args.len();
+    if jumpdest as usize != code_len - 2 {
+        return None;
+    }
+
+    Some(MinimalProxy {
+        kind,
+        implementation,
+        immutable_args: args.to_vec(),
+    })
+}
+
+/// Reads a `PUSH1`..`PUSH20` instruction and returns the pushed value as a left-padded address.
+fn read_address_push(bytes: &[u8]) -> Option<(Address, &[u8])> {
+    let (&op, rest) = bytes.split_first()?;
+    if !(opcode::PUSH1..=opcode::PUSH20).contains(&op) {
+        return None;
+    }
+
+    let len = (op - opcode::PUSH0) as usize;
+    if rest.len() < len {
+        return None;
+    }
+
+    let mut address = [0u8; 20];
+    address[20 - len..].copy_from_slice(&rest[..len]);
+    Some((Address::from(address), &rest[len..]))
+}
+
+/// Structural fallback for clones that do not follow one of the known templates.
+///
+/// Walks the code up to the first `RETURN` behind a `JUMPDEST` and accepts it if every
+/// instruction is one a forwarding proxy needs, and the only address pushed is immediately
+/// followed by `GAS DELEGATECALL`. Whatever follows the code is treated as immutable arguments.
+fn match_delegating_clone(bytes: &[u8]) -> Option<MinimalProxy> {
+    let mut pc = 0;
+    let mut implementation = None;
+    let mut seen_jumpdest = false;
+
+    while pc < bytes.len() && pc < MAX_CLONE_CODE_LEN {
+        let op = bytes[pc];
+
+        match op {
+            opcode::PUSH1..=opcode::PUSH32 => {
+                let len = (op - opcode::PUSH0) as usize;
+                bytes.get(pc + 1..pc + 1 + len)?;
+
+                // Only a push directly followed by GAS DELEGATECALL is the implementation
+                let next = bytes.get(pc + 1 + len..pc + 3 + len);
+                if next == Some(&[opcode::GAS, opcode::DELEGATECALL][..]) {
+                    if len > 20 || implementation.is_some() {
+                        return None;
+                    }
+                    let (address, _) = read_address_push(&bytes[pc..])?;
+                    implementation = Some(address);
+                } else if len > 2 {
+                    // Anything longer than an offset or length is unexpected in a clone
+                    return None;
+                }
+                pc += 1 + len;
+            }
+            opcode::JUMPDEST => {
+                seen_jumpdest = true;
+                pc += 1;
+            }
+            opcode::RETURN if seen_jumpdest => {
+                return implementation.map(|implementation| MinimalProxy {
+                    kind: MinimalProxyKind::Clone,
+                    implementation,
+                    immutable_args: bytes[pc + 1..].to_vec(),
+                });
+            }
+            _ if is_clone_opcode(op) => pc += 1,
+            _ => return None,
+        }
+    }
+
+    None
+}
+
+/// Opcodes that may appear in a forwarding clone besides pushes.
+fn is_clone_opcode(op: u8) -> bool {
+    matches!(
+        op,
+        opcode::ADD
+            | opcode::SUB
+            | opcode::CALLDATASIZE
+            | opcode::CALLDATACOPY
+            | opcode::CODESIZE
+            | opcode::CODECOPY
+            | opcode::RETURNDATASIZE
+            | opcode::RETURNDATACOPY
+            | opcode::MLOAD
+            | opcode::MSTORE
+            | opcode::GAS
+            | opcode::DELEGATECALL
+            | opcode::JUMPI
+            | opcode::PUSH0
+            | opcode::DUP1..=opcode::SWAP16 | opcode::REVERT | opcode::RETURN
+    )
+}
+
+/// Flavour of upgradeable proxy recognised by [`detect_upgradeable_proxy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ProxyKind {
+    /// EIP-1967 proxy without an admin slot, such as OpenZeppelin's `ERC1967Proxy`.
+    Eip1967,
+    /// Transparent proxy: EIP-1967 implementation and admin slots.
+    Transparent,
+    /// UUPS: the upgrade logic lives in the implementation (EIP-1822 or EIP-1967 based).
+    Uups,
+    /// Beacon proxy: the implementation is read from a beacon contract.
+    Beacon,
+    /// EIP-897 proxy exposing `implementation()` and `proxyType()`.
+    Eip897,
+    /// Pre-EIP-1967 OpenZeppelin (ZeppelinOS) proxy.
+    ZeppelinOs,
+}
+
+impl ProxyKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProxyKind::Eip1967 => "EIP-1967",
+            ProxyKind::Transparent => "transparent (EIP-1967)",
+            ProxyKind::Uups => "UUPS",
+            ProxyKind::Beacon => "beacon (EIP-1967)",
+            ProxyKind::Eip897 => "EIP-897",
+            ProxyKind::ZeppelinOs => "ZeppelinOS",
+        }
+    }
+}
+
+impl std::fmt::Display for ProxyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An upgradeable proxy pattern found in the bytecode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UpgradeableProxy {
+    pub kind: ProxyKind,
+    /// Storage slot holding the implementation address, or the beacon address for
+    /// [`ProxyKind::Beacon`]. `None` when the implementation is only reachable through
+    /// `implementation()`.
+    pub slot: Option<B256>,
+    /// Positions of the `DELEGATECALL` instructions in the bytecode.
+    pub delegatecall_pcs: Vec<usize>,
+}
+
+/// Recognises upgradeable proxies by the well-known storage slots and selectors they push.
+///
+/// Only bytecode containing a `DELEGATECALL` is considered. Slot constants are matched against
+/// `PUSH32` operands and selectors against `PUSH4` operands, so the result is a strong hint rather
+/// than a proof: an implementation contract that references the same slots, such as a UUPS
+/// implementation, is reported as well.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{EIP1967_IMPLEMENTATION_SLOT, ProxyKind, detect_upgradeable_proxy};
+///
+/// // PUSH32 <implementation slot>, SLOAD, GAS, DELEGATECALL
+/// let bytes = hex::decode(
+///     "7f360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc545af4",
+/// )
+/// .unwrap();
+/// let proxy = detect_upgradeable_proxy(&bytes).unwrap();
+/// assert_eq!(proxy.kind, ProxyKind::Eip1967);
+/// assert_eq!(proxy.slot, Some(EIP1967_IMPLEMENTATION_SLOT));
+/// ```
+pub fn detect_upgradeable_proxy(bytes: &[u8]) -> Option<UpgradeableProxy> {
+    let instructions = decode_instructions(bytes);
+
+    let delegatecall_pcs: Vec<usize> = instructions
+        .iter()
+        .filter(|instruction| instruction.opcode == opcode::DELEGATECALL)
+        .map(|instruction| instruction.pc)
+        .collect();
+    if delegatecall_pcs.is_empty() {
+        return None;
+    }
+
+    let pushes_slot = |slot: B256| {
+        instructions.iter().any(|instruction| {
+            instruction.opcode == opcode::PUSH32 && instruction.immediate == slot.as_slice()
+        })
+    };
+    let pushes_selector = |selector: [u8; 4]| {
+        instructions.iter().any(|instruction| {
+            instruction.opcode == opcode::PUSH4 && instruction.immediate == selector
+        })
+    };
+
+    let (kind, slot) = if pushes_slot(EIP1967_BEACON_SLOT) {
+        (ProxyKind::Beacon, Some(EIP1967_BEACON_SLOT))
+    } else if pushes_slot(EIP1967_IMPLEMENTATION_SLOT) {
+        let kind = if pushes_slot(EIP1967_ADMIN_SLOT) {
+            ProxyKind::Transparent
+        } else if pushes_selector(PROXIABLE_UUID_SELECTOR) {
+            ProxyKind::Uups
+        } else {
+            ProxyKind::Eip1967
+        };
+        (kind, Some(EIP1967_IMPLEMENTATION_SLOT))
+    } else if pushes_slot(EIP1822_PROXIABLE_SLOT) {
+        (ProxyKind::Uups, Some(EIP1822_PROXIABLE_SLOT))
+    } else if pushes_slot(ZEPPELINOS_IMPLEMENTATION_SLOT) {
+        (ProxyKind::ZeppelinOs, Some(ZEPPELINOS_IMPLEMENTATION_SLOT))
+    } else if pushes_selector(IMPLEMENTATION_SELECTOR) && pushes_selector(PROXY_TYPE_SELECTOR) {
+        (ProxyKind::Eip897, None)
+    } else {
+        return None;
+    };
+
+    Some(UpgradeableProxy {
+        kind,
+        slot,
+        delegatecall_pcs,
+    })
+}
+
+/// Extracts the address stored in the low 20 bytes of a storage word or ABI-encoded return value.
+pub fn address_from_word(word: B256) -> Address {
+    Address::from_word(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMPLEMENTATION: &str = "bebebebebebebebebebebebebebebebebebebebe";
+
+    fn eip1167(address_push: &str, jumpdest: &str) -> Vec<u8> {
+        hex::decode(format!(
+            "363d3d373d3d3d363d{}5af43d82803e903d9160{}57fd5bf3",
+            address_push, jumpdest
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_eip1167_clone() {
+        let bytes = eip1167(&format!("73{}", IMPLEMENTATION), "2b");
+        assert_eq!(bytes.len(), 45);
+
+        let proxy = detect_minimal_proxy(&bytes).unwrap();
+        assert_eq!(proxy.kind, MinimalProxyKind::Eip1167);
+        assert_eq!(proxy.implementation, Address::from([0xbe; 20]));
+        assert!(proxy.immutable_args.is_empty());
+    }
+
+    #[test]
+    fn test_eip1167_vanity_address() {
+        // PUSH19 with a leading zero byte dropped shortens the proxy by one byte
+        let bytes = eip1167(&format!("72{}", &IMPLEMENTATION[2..]), "2a");
+
+        let proxy = detect_minimal_proxy(&bytes).unwrap();
+        assert_eq!(proxy.kind, MinimalProxyKind::Eip1167);
+        assert_eq!(proxy.implementation.0[0], 0x00);
+        assert_eq!(proxy.implementation.0[1], 0xbe);
+    }
+
+    #[test]
+    fn test_eip1167_with_immutable_args() {
+        let mut bytes = eip1167(&format!("73{}", IMPLEMENTATION), "2b");
+        bytes.extend_from_slice(&[0xca, 0xfe]);
+
+        let proxy = detect_minimal_proxy(&bytes).unwrap();
+        assert_eq!(proxy.kind, MinimalProxyKind::Eip1167);
+        assert_eq!(proxy.immutable_args, vec![0xca, 0xfe]);
+    }
+
+    #[test]
+    fn test_eip1167_wrong_jumpdest_is_rejected_by_template() {
+        let bytes = eip1167(&format!("73{}", IMPLEMENTATION), "2c");
+        let proxy = detect_minimal_proxy(&bytes);
+        assert_ne!(proxy.map(|p| p.kind), Some(MinimalProxyKind::Eip1167));
+    }
+
+    #[test]
+    fn test_erc7511_clone() {
+        let bytes = hex::decode(format!(
+            "365f5f375f5f365f73{}5af43d5f5f3e5f3d91602a57fd5bf3",
+            IMPLEMENTATION
+        ))
+        .unwrap();
+        assert_eq!(bytes.len(), 44);
+
+        let proxy = detect_minimal_proxy(&bytes).unwrap();
+        assert_eq!(proxy.kind, MinimalProxyKind::Erc7511);
+        assert_eq!(proxy.implementation, Address::from([0xbe; 20]));
+    }
+
+    #[test]
+    fn test_generic_delegating_clone() {
+        // CALLDATASIZE, RETURNDATASIZE, RETURNDATASIZE, CALLDATACOPY, RETURNDATASIZE,
+        // RETURNDATASIZE, CALLDATASIZE, RETURNDATASIZE, PUSH20 <impl>, GAS, DELEGATECALL,
+        // RETURNDATASIZE, PUSH0, DUP1, RETURNDATACOPY, PUSH1 0x29, JUMPI, RETURNDATASIZE,
+        // PUSH0, REVERT, JUMPDEST, RETURNDATASIZE, PUSH0, RETURN
+        let bytes = hex::decode(format!(
+            "363d3d373d3d363d73{}5af43d5f803e602957{}",
+            IMPLEMENTATION, "3d5ffd5b3d5ff3aabb"
+        ))
+        .unwrap();
+
+        let proxy = detect_minimal_proxy(&bytes).unwrap();
+        assert_eq!(proxy.kind, MinimalProxyKind::Clone);
+        assert_eq!(proxy.implementation, Address::from([0xbe; 20]));
+        assert_eq!(proxy.immutable_args, vec![0xaa, 0xbb]);
+        assert_eq!(proxy.to_string(), "clone with immutable args");
+
+        let bare = &bytes[..bytes.len() - 2];
+        let proxy = detect_minimal_proxy(bare).unwrap();
+        assert_eq!(proxy.kind, MinimalProxyKind::Clone);
+        assert_eq!(proxy.to_string(), "clone");
+    }
+
+    #[test]
+    fn test_regular_contract_is_not_a_proxy() {
+        // PUSH1 0x80, PUSH1 0x40, MSTORE, PUSH1 0x00, SLOAD, STOP
+        let bytes = hex::decode("60806040526000545b00f3").unwrap();
+        assert!(detect_minimal_proxy(&bytes).is_none());
+    }
+
+    #[test]
+    fn test_delegatecall_to_storage_is_not_a_clone() {
+        // PUSH1 0x00, SLOAD, GAS, DELEGATECALL, JUMPDEST, RETURN
+        let bytes = hex::decode("6000545af45bf3").unwrap();
+        assert!(detect_minimal_proxy(&bytes).is_none());
+    }
+
+    fn delegating_proxy(slots: &[B256], selectors: &[[u8; 4]]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for selector in selectors {
+            bytes.push(opcode::PUSH4);
+            bytes.extend_from_slice(selector);
+            bytes.push(opcode::POP);
+        }
+        for slot in slots {
+            bytes.push(opcode::PUSH32);
+            bytes.extend_from_slice(slot.as_slice());
+            bytes.push(opcode::SLOAD);
+        }
+        bytes.extend_from_slice(&[opcode::GAS, opcode::DELEGATECALL, opcode::STOP]);
+        bytes
+    }
+
+    #[test]
+    fn test_slot_constants_match_their_preimages() {
+        use revm::primitives::{U256, keccak256};
+
+        let minus_one =
+            |label: &str| B256::from(U256::from_be_bytes(keccak256(label).0) - U256::from(1));
+        assert_eq!(
+            EIP1967_IMPLEMENTATION_SLOT,
+            minus_one("eip1967.proxy.implementation")
+        );
+        assert_eq!(EIP1967_BEACON_SLOT, minus_one("eip1967.proxy.beacon"));
+        assert_eq!(EIP1967_ADMIN_SLOT, minus_one("eip1967.proxy.admin"));
+        assert_eq!(EIP1822_PROXIABLE_SLOT, keccak256("PROXIABLE"));
+        assert_eq!(
+            ZEPPELINOS_IMPLEMENTATION_SLOT,
+            keccak256("org.zeppelinos.proxy.implementation")
+        );
+        assert_eq!(keccak256("implementation()")[..4], IMPLEMENTATION_SELECTOR);
+        assert_eq!(keccak256("proxyType()")[..4], PROXY_TYPE_SELECTOR);
+        assert_eq!(keccak256("proxiableUUID()")[..4], PROXIABLE_UUID_SELECTOR);
+    }
+
+    #[test]
+    fn test_detect_eip1967_proxy() {
+        let bytes = delegating_proxy(&[EIP1967_IMPLEMENTATION_SLOT], &[]);
+        let proxy = detect_upgradeable_proxy(&bytes).unwrap();
+        assert_eq!(proxy.kind, ProxyKind::Eip1967);
+        assert_eq!(proxy.slot, Some(EIP1967_IMPLEMENTATION_SLOT));
+        assert_eq!(proxy.delegatecall_pcs, vec![bytes.len() - 2]);
+    }
+
+    #[test]
+    fn test_detect_transparent_proxy() {
+        let bytes = delegating_proxy(&[EIP1967_ADMIN_SLOT, EIP1967_IMPLEMENTATION_SLOT], &[]);
+        let proxy = detect_upgradeable_proxy(&bytes).unwrap();
+        assert_eq!(proxy.kind, ProxyKind::Transparent);
+        assert_eq!(proxy.slot, Some(EIP1967_IMPLEMENTATION_SLOT));
+    }
+
+    #[test]
+    fn test_detect_uups() {
+        let bytes = delegating_proxy(&[EIP1967_IMPLEMENTATION_SLOT], &[PROXIABLE_UUID_SELECTOR]);
+        assert_eq!(
+            detect_upgradeable_proxy(&bytes).unwrap().kind,
+            ProxyKind::Uups
+        );
+
+        let bytes = delegating_proxy(&[EIP1822_PROXIABLE_SLOT], &[]);
+        let proxy = detect_upgradeable_proxy(&bytes).unwrap();
+        assert_eq!(proxy.kind, ProxyKind::Uups);
+        assert_eq!(proxy.slot, Some(EIP1822_PROXIABLE_SLOT));
+    }
+
+    #[test]
+    fn test_detect_beacon_proxy() {
+        let bytes = delegating_proxy(&[EIP1967_BEACON_SLOT], &[IMPLEMENTATION_SELECTOR]);
+        let proxy = detect_upgradeable_proxy(&bytes).unwrap();
+        assert_eq!(proxy.kind, ProxyKind::Beacon);
+        assert_eq!(proxy.slot, Some(EIP1967_BEACON_SLOT));
+    }
+
+    #[test]
+    fn test_detect_eip897_proxy() {
+        let bytes = delegating_proxy(&[], &[IMPLEMENTATION_SELECTOR, PROXY_TYPE_SELECTOR]);
+        let proxy = detect_upgradeable_proxy(&bytes).unwrap();
+        assert_eq!(proxy.kind, ProxyKind::Eip897);
+        assert_eq!(proxy.slot, None);
+    }
+
+    #[test]
+    fn test_slot_without_delegatecall_is_not_a_proxy() {
+        let mut bytes = vec![opcode::PUSH32];
+        bytes.extend_from_slice(EIP1967_IMPLEMENTATION_SLOT.as_slice());
+        bytes.extend_from_slice(&[opcode::SLOAD, opcode::STOP]);
+        assert!(detect_upgradeable_proxy(&bytes).is_none());
+    }
+
+    #[test]
+    fn test_address_from_word() {
+        let mut word = [0u8; 32];
+        word[12..].copy_from_slice(&[0xbe; 20]);
+        assert_eq!(
+            address_from_word(B256::from(word)),
+            Address::from([0xbe; 20])
+        );
+    }
+}
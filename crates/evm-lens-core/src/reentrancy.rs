@@ -0,0 +1,411 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use revm::{bytecode::opcode, primitives::U256};
+
+use crate::cfg::{AbstractValue, BasicBlock, Cfg, explore, fold_constants};
+use crate::dispatch::function_entries;
+use crate::instruction::Instruction;
+
+/// Gas stipend passed by `transfer` and `send`; too little for the callee to write storage.
+pub const CALL_STIPEND: u64 = 2300;
+
+/// An external call that is followed on some path by a storage write.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReentrancyRisk {
+    /// Selector of the public function the path starts from; `None` when the code has no
+    /// recognisable dispatcher and paths are followed from the entry point.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::option_selector"))]
+    pub selector: Option<[u8; 4]>,
+    /// Position of the `CALL` or `CALLCODE`.
+    pub call_pc: usize,
+    /// Positions of the `SSTORE`s reachable after the call.
+    pub sstore_pcs: Vec<usize>,
+}
+
+impl ReentrancyRisk {
+    /// Returns the selector as `0x`-prefixed hex, or `-` when there is none.
+    pub fn selector_hex(&self) -> String {
+        match self.selector {
+            Some(selector) => format!("0x{}", hex::encode(selector)),
+            None => "-".to_string(),
+        }
+    }
+}
+
+/// Dataflow state at a block boundary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PathState {
+    /// Constant slots read with `SLOAD` on some path.
+    loaded: BTreeSet<U256>,
+    /// Loaded slots whose value a `JUMPI` branched on.
+    checked: BTreeSet<U256>,
+    /// Whether `TLOAD` was executed on some path.
+    transient_loaded: bool,
+    /// Slots used as a lock; writes to them after a call release the lock and are not reported.
+    lock_slots: BTreeSet<U256>,
+    /// Whether a lock was taken on every path.
+    guarded: bool,
+    /// Unguarded calls that forward gas and precede this point on some path.
+    pending_calls: BTreeSet<usize>,
+}
+
+impl PathState {
+    fn entry() -> Self {
+        PathState {
+            loaded: BTreeSet::new(),
+            checked: BTreeSet::new(),
+            transient_loaded: false,
+            lock_slots: BTreeSet::new(),
+            guarded: false,
+            pending_calls: BTreeSet::new(),
+        }
+    }
+
+    fn join(&mut self, other: &PathState) -> bool {
+        let before = self.clone();
+        self.loaded.extend(other.loaded.iter().copied());
+        self.checked.extend(other.checked.iter().copied());
+        self.transient_loaded |= other.transient_loaded;
+        self.lock_slots.extend(other.lock_slots.iter().copied());
+        self.guarded &= other.guarded;
+        self.pending_calls
+            .extend(other.pending_calls.iter().copied());
+        *self != before
+    }
+}
+
+/// A stack value: its constant, if known, and the constant slot it was loaded from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Value {
+    constant: Option<U256>,
+    /// Slot of the `SLOAD` this value was computed from, if it depends on exactly one.
+    loaded_from: Option<U256>,
+}
+
+impl Value {
+    fn merge(&mut self, other: &Value) {
+        if self.constant != other.constant {
+            self.constant = None;
+        }
+        if self.loaded_from != other.loaded_from {
+            self.loaded_from = None;
+        }
+    }
+}
+
+impl AbstractValue for Value {
+    fn unknown() -> Self {
+        Value {
+            constant: None,
+            loaded_from: None,
+        }
+    }
+
+    fn constant(value: U256) -> Self {
+        Value {
+            constant: Some(value),
+            loaded_from: None,
+        }
+    }
+
+    fn as_constant(&self) -> Option<U256> {
+        self.constant
+    }
+
+    fn apply(instruction: &Instruction, inputs: &[Self]) -> Self {
+        if instruction.opcode == opcode::SLOAD {
+            return Value {
+                constant: None,
+                loaded_from: inputs[0].constant,
+            };
+        }
+
+        let mut slots = inputs.iter().filter_map(|input| input.loaded_from);
+        let loaded_from = slots
+            .next()
+            .filter(|slot| slots.all(|other| other == *slot));
+        let constant = inputs
+            .iter()
+            .map(|input| input.constant)
+            .collect::<Option<Vec<_>>>()
+            .and_then(|constants| fold_constants(instruction.opcode, &constants));
+
+        Value {
+            constant,
+            loaded_from,
+        }
+    }
+}
+
+/// Operand values seen at an instruction, merged over every explored path.
+#[derive(Default)]
+struct Operands(HashMap<(usize, usize), Value>);
+
+impl Operands {
+    fn record(&mut self, pc: usize, index: usize, value: Value) {
+        self.0
+            .entry((pc, index))
+            .and_modify(|known| known.merge(&value))
+            .or_insert(value);
+    }
+
+    /// The `index`-th operand, if it is the same constant on every path.
+    fn constant(&self, pc: usize, index: usize) -> Option<U256> {
+        self.0.get(&(pc, index)).and_then(|value| value.constant)
+    }
+
+    /// The slot the `index`-th operand was loaded from, if it is the same on every path.
+    fn loaded_from(&self, pc: usize, index: usize) -> Option<U256> {
+        self.0.get(&(pc, index)).and_then(|value| value.loaded_from)
+    }
+}
+
+/// Reports external calls that can be followed by `SSTORE` before the function returns.
+///
+/// Paths are followed from every function in the dispatcher (or from the entry point when there
+/// is none). A call counts when it is a `CALL` or `CALLCODE` that forwards more than the
+/// [`CALL_STIPEND`]. Calls made while a reentrancy lock is held are ignored: a lock is a
+/// constant slot that was `SLOAD`ed, branched on with `JUMPI`, and then set to a constant with
+/// `SSTORE`, or a `TSTORE` after a `TLOAD`, taken on every path before the call. Writes to the
+/// lock slot after the call are the release and are not reported. A counter or balance at a
+/// fixed slot is not a lock, since the value written back is computed from the one loaded.
+///
+/// This is a heuristic: jumps shared between functions merge their paths, and checks-effects-
+/// interactions violations that write only through `DELEGATECALL` are not seen.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::find_reentrancy;
+///
+/// // PUSH0 x5, CALLER, GAS, CALL, POP, PUSH1 0x01, PUSH0, SSTORE, STOP
+/// let bytes = hex::decode("5f5f5f5f5f335af15060015f5500").unwrap();
+/// let risks = find_reentrancy(&bytes);
+/// assert_eq!(risks[0].call_pc, 7);
+/// assert_eq!(risks[0].sstore_pcs, vec![12]);
+/// ```
+pub fn find_reentrancy(bytes: &[u8]) -> Vec<ReentrancyRisk> {
+    let mut operands = Operands::default();
+    let cfg = explore::<Value>(bytes, |instruction, inputs| match instruction.opcode {
+        opcode::SLOAD | opcode::CALL | opcode::CALLCODE => {
+            operands.record(instruction.pc, 0, inputs[0]);
+        }
+        opcode::SSTORE => {
+            operands.record(instruction.pc, 0, inputs[0]);
+            operands.record(instruction.pc, 1, inputs[1]);
+        }
+        opcode::JUMPI => operands.record(instruction.pc, 1, inputs[1]),
+        _ => {}
+    });
+
+    let entries: Vec<(Option<[u8; 4]>, usize)> = {
+        let functions = function_entries(bytes);
+        if functions.is_empty() {
+            vec![(None, 0)]
+        } else {
+            functions
+                .iter()
+                .map(|function| (Some(function.selector), function.entry))
+                .collect()
+        }
+    };
+
+    let mut risks = Vec::new();
+    for (selector, entry) in entries {
+        for (call_pc, sstore_pcs) in analyse_function(&cfg, &operands, entry) {
+            risks.push(ReentrancyRisk {
+                selector,
+                call_pc,
+                sstore_pcs: sstore_pcs.into_iter().collect(),
+            });
+        }
+    }
+    risks
+}
+
+/// Runs the dataflow from `entry` and returns, per call site, the stores that follow it.
+fn analyse_function(
+    cfg: &Cfg,
+    operands: &Operands,
+    entry: usize,
+) -> BTreeMap<usize, BTreeSet<usize>> {
+    let mut findings: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+    if cfg.block(entry).is_none() {
+        return findings;
+    }
+
+    let mut states: HashMap<usize, PathState> = HashMap::from([(entry, PathState::entry())]);
+    let mut worklist = VecDeque::from([entry]);
+
+    while let Some(start) = worklist.pop_front() {
+        let Some(block) = cfg.block(start) else {
+            continue;
+        };
+        let mut state = states[&start].clone();
+        transfer(block, operands, &mut state, &mut findings);
+
+        for successor in &block.successors {
+            let changed = match states.get_mut(successor) {
+                Some(existing) => existing.join(&state),
+                None => {
+                    states.insert(*successor, state.clone());
+                    true
+                }
+            };
+            if changed && !worklist.contains(successor) {
+                worklist.push_back(*successor);
+            }
+        }
+    }
+
+    findings
+}
+
+fn transfer(
+    block: &BasicBlock,
+    operands: &Operands,
+    state: &mut PathState,
+    findings: &mut BTreeMap<usize, BTreeSet<usize>>,
+) {
+    for instruction in &block.instructions {
+        let pc = instruction.pc;
+        match instruction.opcode {
+            opcode::SLOAD => {
+                if let Some(slot) = operands.constant(pc, 0) {
+                    state.loaded.insert(slot);
+                }
+            }
+            opcode::JUMPI => {
+                if let Some(slot) = operands.loaded_from(pc, 1)
+                    && state.loaded.contains(&slot)
+                {
+                    state.checked.insert(slot);
+                }
+            }
+            opcode::TLOAD => state.transient_loaded = true,
+            opcode::SSTORE => {
+                let slot = operands.constant(pc, 0);
+                let releases_lock = slot.is_some_and(|slot| state.lock_slots.contains(&slot));
+                if !releases_lock {
+                    for call_pc in &state.pending_calls {
+                        findings.entry(*call_pc).or_default().insert(pc);
+                    }
+                }
+
+                if let Some(slot) = slot
+                    && state.pending_calls.is_empty()
+                    && state.checked.contains(&slot)
+                    && operands.constant(pc, 1).is_some()
+                {
+                    state.lock_slots.insert(slot);
+                    state.guarded = true;
+                }
+            }
+            opcode::TSTORE if state.pending_calls.is_empty() && state.transient_loaded => {
+                state.guarded = true;
+            }
+            opcode::CALL | opcode::CALLCODE => {
+                let stipend_only = operands
+                    .constant(pc, 0)
+                    .is_some_and(|gas| gas <= U256::from(CALL_STIPEND));
+                if !stipend_only && !state.guarded {
+                    state.pending_calls.insert(pc);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gas-forwarding call to `CALLER` with no arguments, success flag popped.
+    const CALL: &str = "5f5f5f5f5f335af150";
+    /// `PUSH1 0x01, PUSH0, SSTORE`: writes slot 0.
+    const WRITE_SLOT_0: &str = "60015f55";
+    /// `PUSH1 0x01, PUSH1 0x01, SSTORE`: writes slot 1.
+    const WRITE_SLOT_1: &str = "6001600155";
+
+    fn risks(code: &str) -> Vec<(usize, Vec<usize>)> {
+        find_reentrancy(&hex::decode(code).unwrap())
+            .into_iter()
+            .map(|risk| (risk.call_pc, risk.sstore_pcs))
+            .collect()
+    }
+
+    #[test]
+    fn test_write_after_call() {
+        assert_eq!(
+            risks(&format!("{CALL}{WRITE_SLOT_0}00")),
+            vec![(7, vec![12])]
+        );
+    }
+
+    #[test]
+    fn test_write_before_call_is_fine() {
+        assert!(risks(&format!("{WRITE_SLOT_0}{CALL}00")).is_empty());
+    }
+
+    #[test]
+    fn test_stipend_call_is_ignored() {
+        // PUSH0 x5, CALLER, PUSH2 0x08fc (2300), CALL, POP, then write slot 0
+        assert!(risks(&format!("5f5f5f5f5f336108fcf150{WRITE_SLOT_0}00")).is_empty());
+    }
+
+    #[test]
+    fn test_storage_lock_guards_call() {
+        // PUSH1 0x01, SLOAD, ISZERO, PUSH1 0x0a, JUMPI, PUSH0, PUSH0, REVERT, JUMPDEST,
+        // write slot 1 (lock), call, write slot 0, PUSH0, PUSH1 0x01, SSTORE (release)
+        let code = format!("60015415600a575f5ffd5b{WRITE_SLOT_1}{CALL}{WRITE_SLOT_0}5f60015500");
+        assert!(risks(&code).is_empty());
+    }
+
+    #[test]
+    fn test_unchecked_load_is_not_a_lock() {
+        // PUSH1 0x01, SLOAD, POP, write slot 1, call, write slot 0
+        let code = format!("60015450{WRITE_SLOT_1}{CALL}{WRITE_SLOT_0}00");
+        assert_eq!(risks(&code), vec![(16, vec![21])]);
+    }
+
+    #[test]
+    fn test_counter_increment_is_not_a_lock() {
+        // PUSH0, SLOAD, PUSH1 0x01, ADD, PUSH0, SSTORE (counter++), call, write slot 1
+        let code = format!("5f546001015f55{CALL}{WRITE_SLOT_1}00");
+        assert_eq!(risks(&code), vec![(14, vec![20])]);
+    }
+
+    #[test]
+    fn test_checked_counter_is_not_a_lock() {
+        // PUSH0, SLOAD, DUP1, PUSH1 0x07, JUMPI, INVALID, JUMPDEST, PUSH1 0x01, ADD, PUSH0,
+        // SSTORE (counter++ after branching on it), call, write slot 1
+        let code = format!("5f5480600757fe5b6001015f55{CALL}{WRITE_SLOT_1}00");
+        assert_eq!(risks(&code), vec![(20, vec![26])]);
+    }
+
+    #[test]
+    fn test_transient_lock_guards_call() {
+        // PUSH0, TLOAD, POP, PUSH1 0x01, PUSH0, TSTORE, call, write slot 0
+        assert!(risks(&format!("5f5c5060015f5d{CALL}{WRITE_SLOT_0}00")).is_empty());
+    }
+
+    #[test]
+    fn test_write_on_one_branch_after_call() {
+        // call, CALLDATASIZE, PUSH1 <write>, JUMPI, STOP, JUMPDEST, write slot 0, STOP
+        let code = format!("{CALL}36600e57005b{WRITE_SLOT_0}00");
+        assert_eq!(risks(&code), vec![(7, vec![18])]);
+    }
+
+    #[test]
+    fn test_risks_are_attributed_to_selectors() {
+        // DUP1, PUSH4 0xaabbccdd, EQ, PUSH1 0x0b, JUMPI, STOP, JUMPDEST, call, write slot 0
+        let code = format!("8063aabbccdd14600b57005b{CALL}{WRITE_SLOT_0}00");
+        let risks = find_reentrancy(&hex::decode(code).unwrap());
+
+        assert_eq!(risks.len(), 1);
+        assert_eq!(risks[0].selector_hex(), "0xaabbccdd");
+        assert_eq!(risks[0].call_pc, 0x0b + 8);
+    }
+}
@@ -0,0 +1,233 @@
+//! Serde helpers that write bytes as `0x`-prefixed hex strings instead of arrays of numbers.
+
+use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+fn encode(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn decode<'de, D: Deserializer<'de>>(hex: &str) -> Result<Vec<u8>, D::Error> {
+    hex::decode(hex.strip_prefix("0x").unwrap_or(hex)).map_err(D::Error::custom)
+}
+
+fn decode_selector<'de, D: Deserializer<'de>>(hex: &str) -> Result<[u8; 4], D::Error> {
+    decode::<D>(hex)?
+        .try_into()
+        .map_err(|_| D::Error::custom("a selector is 4 bytes"))
+}
+
+/// `Vec<u8>` as one hex string.
+pub(crate) mod bytes {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(bytes))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        decode::<D>(&String::deserialize(deserializer)?)
+    }
+}
+
+/// `Option<Vec<u8>>` as a hex string or `null`.
+pub(crate) mod option_bytes {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        bytes: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => serializer.serialize_some(&encode(bytes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|hex| decode::<D>(&hex))
+            .transpose()
+    }
+}
+
+/// A 4-byte function selector.
+pub(crate) mod selector {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        selector: &[u8; 4],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(selector))
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<[u8; 4], D::Error> {
+        decode_selector::<D>(&String::deserialize(deserializer)?)
+    }
+}
+
+/// An optional selector, `null` when absent.
+pub(crate) mod option_selector {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        selector: &Option<[u8; 4]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match selector {
+            Some(selector) => serializer.serialize_some(&encode(selector)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<[u8; 4]>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|hex| decode_selector::<D>(&hex))
+            .transpose()
+    }
+}
+
+/// A list of selectors.
+pub(crate) mod selectors {
+    use super::*;
+    use serde::ser::SerializeSeq;
+
+    pub(crate) fn serialize<S: Serializer>(
+        selectors: &[[u8; 4]],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(selectors.len()))?;
+        for selector in selectors {
+            seq.serialize_element(&encode(selector))?;
+        }
+        seq.end()
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<[u8; 4]>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|hex| decode_selector::<D>(hex))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use revm::primitives::U256;
+
+    use crate::{
+        DecompiledFunction, FunctionEntry, LintRule, SlotKind, StorageLayout, StorageVariable,
+        SymbolicLimits, decode_instructions, explore_paths, get_stats, lint,
+    };
+
+    #[test]
+    fn test_selectors_round_trip_as_hex() {
+        let entry = FunctionEntry {
+            selector: [0xa9, 0x05, 0x9c, 0xbb],
+            entry: 0x0b,
+        };
+        let json = serde_json::to_value(entry).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "selector": "0xa9059cbb", "entry": 11 })
+        );
+        assert_eq!(
+            serde_json::from_value::<FunctionEntry>(json).unwrap(),
+            entry
+        );
+
+        let short = serde_json::json!({ "selector": "0xa905", "entry": 11 });
+        assert!(serde_json::from_value::<FunctionEntry>(short).is_err());
+    }
+
+    #[test]
+    fn test_storage_layout_round_trip() {
+        let layout = StorageLayout {
+            variables: vec![StorageVariable {
+                slot: U256::from(3),
+                kind: SlotKind::DynamicArray,
+                fields: Vec::new(),
+                read_pcs: vec![10],
+                write_pcs: Vec::new(),
+                readers: vec![[0x12, 0x34, 0x56, 0x78]],
+                writers: Vec::new(),
+            }],
+        };
+        let json = serde_json::to_value(&layout).unwrap();
+        assert_eq!(json["variables"][0]["kind"], "dynamic_array");
+        assert_eq!(json["variables"][0]["readers"][0], "0x12345678");
+        assert_eq!(
+            serde_json::from_value::<StorageLayout>(json).unwrap(),
+            layout
+        );
+    }
+
+    #[test]
+    fn test_instructions_stats_and_findings() {
+        // PUSH1 0x80, CALLER, SELFDESTRUCT
+        let code = hex::decode("608033ff").unwrap();
+
+        let instructions = serde_json::to_value(decode_instructions(&code)).unwrap();
+        assert_eq!(
+            instructions[0],
+            serde_json::json!({ "pc": 0, "opcode": "PUSH1", "immediate": "0x80", "size": 2 })
+        );
+        assert_eq!(instructions[1]["immediate"], serde_json::Value::Null);
+
+        let stats = serde_json::to_value(get_stats(&code).unwrap()).unwrap();
+        assert_eq!(stats["opcode_histogram"]["SELFDESTRUCT"], 1);
+        assert_eq!(stats["code_size_limit"]["limit"], 24576);
+        let stats: crate::Stats = serde_json::from_value(stats).unwrap();
+        assert_eq!(stats.opcode_count, 4);
+
+        let findings = serde_json::to_value(lint(&code)).unwrap();
+        assert_eq!(findings[0]["rule"], LintRule::ReachableSelfdestruct.id());
+        assert_eq!(findings[0]["severity"], "high");
+    }
+
+    #[test]
+    fn test_decompiled_functions_and_paths() {
+        let function = DecompiledFunction {
+            selector: None,
+            entry: 0,
+            lines: vec!["stop()".to_string()],
+        };
+        let json = serde_json::to_value(&function).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "selector": null, "entry": 0, "lines": ["stop()"] })
+        );
+        assert_eq!(
+            serde_json::from_value::<DecompiledFunction>(json).unwrap(),
+            function
+        );
+
+        // CALLVALUE, PUSH1 0x05, JUMPI, STOP, JUMPDEST, PUSH0, DUP1, REVERT
+        let code = hex::decode("34600557005b5f80fd").unwrap();
+        let exploration = explore_paths(&code, 0, &SymbolicLimits::default());
+        let json = serde_json::to_value(&exploration).unwrap();
+        assert_eq!(json["path_limit_reached"], false);
+        let revert = json["paths"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|path| path["end"] == "revert")
+            .unwrap();
+        assert_eq!(revert["end_pc"], 8);
+        assert_eq!(revert["feasibility"], "unknown");
+        assert_eq!(
+            revert["conditions"],
+            serde_json::json!([{ "pc": 3, "condition": "msg.value != 0" }])
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::HashMap;
+
+use crate::instruction::decode_instructions;
+use crate::metadata::parse_metadata;
+
+/// Number of hash functions, and so of minimums, in a [`MinHash`] signature.
+pub const MINHASH_SIZE: usize = 128;
+
+/// Number of consecutive opcodes hashed together.
+const SHINGLE_LEN: usize = 4;
+
+/// Chance that [`cluster`] compares two signatures exactly as similar as its threshold.
+const BAND_RECALL: f64 = 0.999;
+
+/// A MinHash signature over the opcode 4-grams of a bytecode.
+///
+/// Push immediates and the metadata trailer are ignored, so recompiling the same source with
+/// different constants, addresses or metadata barely changes the signature. The fraction of
+/// equal minimums between two signatures estimates the Jaccard similarity of their 4-gram sets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinHash {
+    minimums: Vec<u64>,
+    /// Whether the code had no instructions at all.
+    empty: bool,
+}
+
+impl MinHash {
+    /// Builds the signature of `bytes`.
+    pub fn new(bytes: &[u8]) -> Self {
+        let code = parse_metadata(bytes).map_or(bytes, |metadata| metadata.strip(bytes));
+        let opcodes: Vec<u8> = decode_instructions(code)
+            .iter()
+            .map(|instruction| instruction.opcode)
+            .collect();
+
+        let mut minimums = vec![u64::MAX; MINHASH_SIZE];
+        // Code shorter than a shingle is one shingle of its own length
+        for shingle in opcodes.windows(SHINGLE_LEN.min(opcodes.len()).max(1)) {
+            let value = shingle
+                .iter()
+                .fold(shingle.len() as u64, |value, &op| value << 8 | op as u64);
+            for (seed, minimum) in minimums.iter_mut().enumerate() {
+                *minimum = (*minimum).min(mix(value ^ mix(seed as u64)));
+            }
+        }
+
+        Self {
+            minimums,
+            empty: opcodes.is_empty(),
+        }
+    }
+
+    /// Estimated Jaccard similarity, from 0.0 to 1.0. Two empty codes are identical.
+    pub fn similarity(&self, other: &MinHash) -> f64 {
+        if self.empty || other.empty {
+            return if self.empty == other.empty { 1.0 } else { 0.0 };
+        }
+        let equal = self
+            .minimums
+            .iter()
+            .zip(&other.minimums)
+            .filter(|(a, b)| a == b)
+            .count();
+        equal as f64 / MINHASH_SIZE as f64
+    }
+}
+
+/// Estimates how similar two bytecodes are, from 0.0 to 1.0, ignoring push immediates and
+/// metadata.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::similarity;
+///
+/// // Same instructions, different constants
+/// let a = hex::decode("6080604052600160020160005500").unwrap();
+/// let b = hex::decode("6080604052600560070160015500").unwrap();
+/// assert_eq!(similarity(&a, &b), 1.0);
+///
+/// let c = hex::decode("3360005460e01c1415").unwrap();
+/// assert!(similarity(&a, &c) < 0.2);
+/// ```
+pub fn similarity(a: &[u8], b: &[u8]) -> f64 {
+    MinHash::new(a).similarity(&MinHash::new(b))
+}
+
+/// A group of near-duplicate contracts, as indices into the clustered signatures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cluster {
+    /// Members in ascending order.
+    pub members: Vec<usize>,
+    /// The member most similar to the rest of the cluster.
+    pub representative: usize,
+}
+
+impl Cluster {
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+}
+
+/// Groups signatures into clusters of near-duplicates.
+///
+/// Two contracts are linked when their similarity is at least `threshold`, and clusters are
+/// the connected components of those links, so members of a chain can be less similar than
+/// the threshold to each other. Only contracts whose signatures agree on a whole band of
+/// minimums are compared (locality-sensitive hashing), with bands sized so that a pair at the
+/// threshold is missed about once in a thousand and more similar pairs far less often. Every contract is in exactly one cluster; clusters are ordered
+/// by size, largest first, then by first member.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{MinHash, cluster};
+///
+/// let codes = ["6001600201600055", "6007600801600155", "3360005460e01c1415"];
+/// let signatures: Vec<MinHash> = codes
+///     .iter()
+///     .map(|code| MinHash::new(&hex::decode(code).unwrap()))
+///     .collect();
+/// let clusters = cluster(&signatures, 0.8);
+/// assert_eq!(clusters.len(), 2);
+/// assert_eq!(clusters[0].members, [0, 1]);
+/// assert_eq!(clusters[1].members, [2]);
+/// ```
+pub fn cluster(signatures: &[MinHash], threshold: f64) -> Vec<Cluster> {
+    let count = signatures.len();
+    let mut parents: Vec<usize> = (0..count).collect();
+    let link = |parents: &mut Vec<usize>, i: usize, j: usize| {
+        let (root_i, root_j) = (find(parents, i), find(parents, j));
+        if root_i != root_j && signatures[i].similarity(&signatures[j]) >= threshold {
+            parents[root_i.max(root_j)] = root_i.min(root_j);
+        }
+    };
+    if threshold <= 0.0 {
+        // Every pair is similar enough
+        for index in 1..count {
+            link(&mut parents, 0, index);
+        }
+    } else {
+        // Only signatures sharing a whole band are compared
+        let rows = band_rows(threshold);
+        let mut buckets: HashMap<(usize, &[u64]), Vec<usize>> = HashMap::new();
+        for (index, signature) in signatures.iter().enumerate() {
+            for (band, minimums) in signature.minimums.chunks_exact(rows).enumerate() {
+                buckets.entry((band, minimums)).or_default().push(index);
+            }
+        }
+        for members in buckets.values() {
+            for (position, &i) in members.iter().enumerate() {
+                for &j in &members[position + 1..] {
+                    link(&mut parents, i, j);
+                }
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = vec![Vec::new(); count];
+    for index in 0..count {
+        let root = find(&mut parents, index);
+        groups[root].push(index);
+    }
+
+    let mut clusters: Vec<Cluster> = groups
+        .into_iter()
+        .filter(|members| !members.is_empty())
+        .map(|members| {
+            let totals: Vec<f64> = members
+                .iter()
+                .map(|&member| {
+                    members
+                        .iter()
+                        .map(|&other| signatures[member].similarity(&signatures[other]))
+                        .sum()
+                })
+                .collect();
+            // The first of equally central members wins
+            let (best, _) = totals.iter().enumerate().fold(
+                (0, f64::MIN),
+                |(best, best_total), (position, &total)| {
+                    if total > best_total {
+                        (position, total)
+                    } else {
+                        (best, best_total)
+                    }
+                },
+            );
+            let representative = members[best];
+            Cluster {
+                members,
+                representative,
+            }
+        })
+        .collect();
+    clusters.sort_by(|a, b| b.len().cmp(&a.len()).then(a.members[0].cmp(&b.members[0])));
+    clusters
+}
+
+/// Rows per LSH band for `threshold`: the most that still make a pair at the threshold share a
+/// band with probability at least [`BAND_RECALL`], so that unrelated pairs rarely do.
+fn band_rows(threshold: f64) -> usize {
+    (1..=MINHASH_SIZE)
+        .rev()
+        .find(|&rows| {
+            let bands = (MINHASH_SIZE / rows) as i32;
+            (1.0 - threshold.powi(rows as i32)).powi(bands) <= 1.0 - BAND_RECALL
+        })
+        .unwrap_or(1)
+}
+
+fn find(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+/// SplitMix64 finaliser.
+fn mix(mut value: u64) -> u64 {
+    value = value.wrapping_add(0x9e3779b97f4a7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signature(code: &str) -> MinHash {
+        MinHash::new(&hex::decode(code).unwrap())
+    }
+
+    #[test]
+    fn test_push_values_and_metadata_are_ignored() {
+        let a = "6080604052348015600f57600080fd5b50";
+        let b = "6080604052348015601057600180fd5b50";
+        assert_eq!(signature(a), signature(b));
+
+        let trailer = format!("a264697066735822{}64736f6c63430008130033", "ab".repeat(34));
+        assert_eq!(signature(a), signature(&format!("{a}{trailer}")));
+    }
+
+    #[test]
+    fn test_similarity_tracks_shared_code() {
+        // A run of distinct opcodes, then the same run with a small change in the middle
+        let base: String = (0x01..=0x1d).map(|op: u8| format!("{op:02x}")).collect();
+        let mut changed = base.clone();
+        changed.replace_range(28..30, "50");
+
+        let score = similarity(
+            &hex::decode(&base).unwrap(),
+            &hex::decode(&changed).unwrap(),
+        );
+        assert!(score > 0.6 && score < 1.0, "{score}");
+        assert_eq!(similarity(&[], &[]), 1.0);
+        assert_eq!(similarity(&[], &[0x00]), 0.0);
+    }
+
+    #[test]
+    fn test_short_code_has_one_shingle() {
+        assert_eq!(signature("6001").similarity(&signature("6002")), 1.0);
+        assert_eq!(signature("6001").similarity(&signature("6101")), 0.0);
+    }
+
+    #[test]
+    fn test_cluster_representative_and_order() {
+        let base: String = (0x01..=0x1d).map(|op: u8| format!("{op:02x}")).collect();
+        let mut near = base.clone();
+        near.replace_range(0..2, "50");
+        let mut far = near.clone();
+        far.replace_range(56..58, "50");
+        let unrelated = "3360005460e01c1415";
+
+        let signatures: Vec<MinHash> = [unrelated, &far, &base, &near]
+            .iter()
+            .map(|code| signature(code))
+            .collect();
+        let clusters = cluster(&signatures, 0.7);
+
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].members, [1, 2, 3]);
+        // The middle of the chain is closest to both ends
+        assert_eq!(clusters[0].representative, 3);
+        assert_eq!(clusters[1].members, [0]);
+        assert_eq!(clusters[1].representative, 0);
+        assert!(cluster(&[], 0.5).is_empty());
+    }
+
+    #[test]
+    fn test_band_rows_follow_threshold() {
+        assert_eq!(band_rows(0.7), 4);
+        assert_eq!(band_rows(0.9), 8);
+        assert_eq!(band_rows(1.0), MINHASH_SIZE);
+        assert!(band_rows(0.5) < band_rows(0.7));
+    }
+
+    #[test]
+    fn test_banded_clusters_match_all_pairs() {
+        // Variants of two unrelated runs of opcodes, each with one or two opcodes replaced
+        let runs: [String; 2] = [
+            (0x01..=0x1d).map(|op: u8| format!("{op:02x}")).collect(),
+            (0x30..=0x48).map(|op: u8| format!("{op:02x}")).collect(),
+        ];
+        let mut codes = Vec::new();
+        for run in &runs {
+            for edit in 0..8 {
+                let mut code = run.clone();
+                code.replace_range(edit * 6..edit * 6 + 2, "50");
+                if edit % 3 == 0 {
+                    code.replace_range(edit * 6 + 4..edit * 6 + 6, "80");
+                }
+                codes.push(code);
+            }
+        }
+        let signatures: Vec<MinHash> = codes.iter().map(|code| signature(code)).collect();
+
+        for threshold in [0.0, 0.4, 0.6, 0.8, 1.0] {
+            let mut parents: Vec<usize> = (0..signatures.len()).collect();
+            for i in 0..signatures.len() {
+                for j in i + 1..signatures.len() {
+                    if signatures[i].similarity(&signatures[j]) >= threshold {
+                        let (root_i, root_j) = (find(&mut parents, i), find(&mut parents, j));
+                        parents[root_i.max(root_j)] = root_i.min(root_j);
+                    }
+                }
+            }
+            let mut expected: Vec<Vec<usize>> = Vec::new();
+            for index in 0..signatures.len() {
+                let root = find(&mut parents, index);
+                match expected.iter_mut().find(|members| members[0] == root) {
+                    Some(members) => members.push(index),
+                    None => expected.push(vec![index]),
+                }
+            }
+            expected.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+
+            let actual: Vec<Vec<usize>> = cluster(&signatures, threshold)
+                .into_iter()
+                .map(|cluster| cluster.members)
+                .collect();
+            assert_eq!(actual, expected, "threshold {threshold}");
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+
+use revm::bytecode::opcode;
+
+use crate::symbolic::{Feasibility, PathCondition, Solver, Term};
+
+/// A [`Solver`] that runs an external SMT-LIB 2 solver, such as `z3` or `cvc5`, on each query.
+///
+/// Words are 256-bit bit-vectors. Calldata, initial storage and hashes are uninterpreted
+/// functions, so two reads of the same slot agree. A solver that cannot be started or gives an
+/// unexpected answer yields [`Feasibility::Unknown`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmtSolver {
+    program: String,
+    args: Vec<String>,
+}
+
+impl SmtSolver {
+    /// A solver reading SMT-LIB from standard input when run as `program args...`.
+    pub fn new(program: impl Into<String>, args: &[&str]) -> Self {
+        Self {
+            program: program.into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+impl Default for SmtSolver {
+    fn default() -> Self {
+        Self::new("z3", &["-in", "-smt2"])
+    }
+}
+
+impl Solver for SmtSolver {
+    fn check(&mut self, conditions: &[PathCondition]) -> Feasibility {
+        let query = encode(conditions);
+        let Ok(mut child) = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            return Feasibility::Unknown;
+        };
+        let written = child
+            .stdin
+            .take()
+            .is_some_and(|mut stdin| stdin.write_all(query.as_bytes()).is_ok());
+        let Ok(output) = child.wait_with_output() else {
+            return Feasibility::Unknown;
+        };
+        if !written {
+            return Feasibility::Unknown;
+        }
+        match String::from_utf8_lossy(&output.stdout).lines().next() {
+            Some("sat") => Feasibility::Feasible,
+            Some("unsat") => Feasibility::Infeasible,
+            _ => Feasibility::Unknown,
+        }
+    }
+}
+
+/// Renders path conditions as an SMT-LIB 2 script ending in `(check-sat)`.
+pub fn encode(conditions: &[PathCondition]) -> String {
+    let mut encoder = Encoder::default();
+    let assertions: Vec<String> = conditions
+        .iter()
+        .map(|condition| {
+            let term = encoder.term(&condition.term);
+            if condition.nonzero {
+                format!("(assert (not (= {term} {ZERO})))")
+            } else {
+                format!("(assert (= {term} {ZERO}))")
+            }
+        })
+        .collect();
+
+    let mut script = String::from("(set-logic QF_UFBV)\n");
+    for declaration in &encoder.declarations {
+        let _ = writeln!(script, "{declaration}");
+    }
+    for assertion in assertions {
+        let _ = writeln!(script, "{assertion}");
+    }
+    script.push_str("(check-sat)\n");
+    script
+}
+
+const WORD: &str = "(_ BitVec 256)";
+const ZERO: &str = "(_ bv0 256)";
+const ONE: &str = "(_ bv1 256)";
+
+#[derive(Default)]
+struct Encoder {
+    declarations: BTreeSet<String>,
+}
+
+impl Encoder {
+    fn declare(&mut self, name: &str, arity: usize) -> String {
+        let args = vec![WORD; arity].join(" ");
+        self.declarations
+            .insert(format!("(declare-fun {name} ({args}) {WORD})"));
+        name.to_string()
+    }
+
+    fn term(&mut self, term: &Term) -> String {
+        match term {
+            Term::Const(value) => format!("(_ bv{value} 256)"),
+            Term::CallData(offset) => {
+                let offset = self.term(offset);
+                format!("({} {offset})", self.declare("calldata", 1))
+            }
+            Term::CallDataSize => self.declare("calldatasize", 0),
+            Term::CallValue => self.declare("callvalue", 0),
+            Term::Caller => self.declare("caller", 0),
+            Term::Storage(slot) => {
+                let slot = self.term(slot);
+                format!("({} {slot})", self.declare("storage", 1))
+            }
+            Term::Input(depth) => self.declare(&format!("input{depth}"), 0),
+            Term::Opaque { op, id } => self.declare(&format!("opaque{id}_{op:02x}"), 0),
+            Term::Op(op, args) => {
+                let args: Vec<String> = args.iter().map(|arg| self.term(arg)).collect();
+                self.op(*op, &args)
+            }
+        }
+    }
+
+    fn op(&mut self, op: u8, args: &[String]) -> String {
+        let bool_word = |predicate: String| format!("(ite {predicate} {ONE} {ZERO})");
+        let unless_zero =
+            |divisor: &String, value: String| format!("(ite (= {divisor} {ZERO}) {ZERO} {value})");
+        match (op, args) {
+            (opcode::ADD, [a, b]) => format!("(bvadd {a} {b})"),
+            (opcode::MUL, [a, b]) => format!("(bvmul {a} {b})"),
+            (opcode::SUB, [a, b]) => format!("(bvsub {a} {b})"),
+            (opcode::DIV, [a, b]) => unless_zero(b, format!("(bvudiv {a} {b})")),
+            (opcode::SDIV, [a, b]) => unless_zero(b, format!("(bvsdiv {a} {b})")),
+            (opcode::MOD, [a, b]) => unless_zero(b, format!("(bvurem {a} {b})")),
+            (opcode::SMOD, [a, b]) => unless_zero(b, format!("(bvsrem {a} {b})")),
+            (opcode::LT, [a, b]) => bool_word(format!("(bvult {a} {b})")),
+            (opcode::GT, [a, b]) => bool_word(format!("(bvugt {a} {b})")),
+            (opcode::SLT, [a, b]) => bool_word(format!("(bvslt {a} {b})")),
+            (opcode::SGT, [a, b]) => bool_word(format!("(bvsgt {a} {b})")),
+            (opcode::EQ, [a, b]) => bool_word(format!("(= {a} {b})")),
+            (opcode::ISZERO, [a]) => bool_word(format!("(= {a} {ZERO})")),
+            (opcode::AND, [a, b]) => format!("(bvand {a} {b})"),
+            (opcode::OR, [a, b]) => format!("(bvor {a} {b})"),
+            (opcode::XOR, [a, b]) => format!("(bvxor {a} {b})"),
+            (opcode::NOT, [a]) => format!("(bvnot {a})"),
+            // The shift amount is on top of the stack; SMT-LIB shifts by 256 or more give zero
+            // (or the sign fill for `bvashr`), as the EVM does
+            (opcode::SHL, [shift, value]) => format!("(bvshl {value} {shift})"),
+            (opcode::SHR, [shift, value]) => format!("(bvlshr {value} {shift})"),
+            (opcode::SAR, [shift, value]) => format!("(bvashr {value} {shift})"),
+            _ => {
+                // Hashes, exponentiation, block values and the like stay uninterpreted. Hashes
+                // take a varying number of words, and SMT-LIB allows one arity per name, so the
+                // arity is part of the name.
+                let base = crate::OpCode::new(op)
+                    .map(|op| op.as_str().to_lowercase())
+                    .unwrap_or_else(|| format!("op{op:02x}"));
+                let name = if args.is_empty() {
+                    self.declare(&base, 0)
+                } else {
+                    self.declare(&format!("{base}_{}", args.len()), args.len())
+                };
+                if args.is_empty() {
+                    name
+                } else {
+                    format!("({name} {})", args.join(" "))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use revm::primitives::U256;
+
+    use super::*;
+
+    fn word(value: u64) -> Rc<Term> {
+        Rc::new(Term::Const(U256::from(value)))
+    }
+
+    #[test]
+    fn test_encode_declares_symbols_once() {
+        let selector = Rc::new(Term::Op(
+            opcode::SHR,
+            vec![word(0xe0), Rc::new(Term::CallData(word(0)))],
+        ));
+        let conditions = [
+            PathCondition::new(
+                0x10,
+                Rc::new(Term::Op(
+                    opcode::EQ,
+                    vec![word(0xaabbccdd), selector.clone()],
+                )),
+                true,
+            ),
+            PathCondition::new(0x20, Rc::new(Term::CallValue), false),
+        ];
+
+        let script = encode(&conditions);
+        assert_eq!(
+            script
+                .matches("(declare-fun calldata ((_ BitVec 256)) (_ BitVec 256))")
+                .count(),
+            1
+        );
+        assert!(script.contains("(declare-fun callvalue () (_ BitVec 256))"));
+        assert!(script.contains(
+            "(assert (not (= (ite (= (_ bv2864434397 256) (bvlshr (calldata (_ bv0 256)) (_ bv224 256))) (_ bv1 256) (_ bv0 256)) (_ bv0 256))))"
+        ));
+        assert!(script.contains("(assert (= callvalue (_ bv0 256)))"));
+        assert!(script.ends_with("(check-sat)\n"));
+    }
+
+    #[test]
+    fn test_encode_uninterpreted_ops() {
+        let hash = Rc::new(Term::Op(
+            opcode::KECCAK256,
+            vec![Rc::new(Term::Caller), word(0)],
+        ));
+        let single = Rc::new(Term::Op(opcode::KECCAK256, vec![word(1)]));
+        let conditions = [
+            PathCondition::new(0, Rc::new(Term::Storage(hash)), true),
+            PathCondition::new(1, single, true),
+        ];
+
+        let script = encode(&conditions);
+        assert!(
+            script.contains(
+                "(declare-fun keccak256_2 ((_ BitVec 256) (_ BitVec 256)) (_ BitVec 256))"
+            )
+        );
+        assert!(script.contains("(declare-fun keccak256_1 ((_ BitVec 256)) (_ BitVec 256))"));
+        assert!(script.contains("(storage (keccak256_2 caller (_ bv0 256)))"));
+        assert!(script.contains("(keccak256_1 (_ bv1 256))"));
+    }
+
+    #[test]
+    fn test_missing_solver_is_unknown() {
+        let mut solver = SmtSolver::new("evm-lens-no-such-solver", &[]);
+        let conditions = [PathCondition::new(0, Rc::new(Term::CallValue), true)];
+        assert_eq!(solver.check(&conditions), Feasibility::Unknown);
+    }
+}
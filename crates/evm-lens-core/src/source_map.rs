@@ -0,0 +1,108 @@
+/// How an instruction jumps, from the `j` field of a source map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JumpKind {
+    /// A jump into a function.
+    Into,
+    /// A return from a function.
+    Out,
+    #[default]
+    Regular,
+}
+
+/// The source range an instruction was generated from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SourceMapEntry {
+    /// Byte offset of the range in the source file.
+    pub start: usize,
+    pub length: usize,
+    /// Source file index; `None` for compiler-generated code without a source (`-1`).
+    pub file: Option<usize>,
+    pub jump: JumpKind,
+    pub modifier_depth: usize,
+}
+
+/// Decompresses a solc source map, which has one `s:l:f:j:m` entry per instruction.
+///
+/// Empty fields and missing trailing fields repeat the previous entry's values. Fields that
+/// cannot be parsed are treated as empty.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{JumpKind, parse_source_map};
+///
+/// let entries = parse_source_map("26:480:0:-:0;;;12:3::i;:::o");
+/// assert_eq!(entries.len(), 5);
+/// assert_eq!(entries[2].start, 26);
+/// assert_eq!((entries[3].start, entries[3].length, entries[3].file), (12, 3, Some(0)));
+/// assert_eq!(entries[3].jump, JumpKind::Into);
+/// assert_eq!(entries[4].jump, JumpKind::Out);
+/// ```
+pub fn parse_source_map(source_map: &str) -> Vec<SourceMapEntry> {
+    if source_map.is_empty() {
+        return Vec::new();
+    }
+
+    let mut current = SourceMapEntry::default();
+    source_map
+        .split(';')
+        .map(|entry| {
+            let mut fields = entry.split(':');
+            let mut next = || fields.next().filter(|field| !field.is_empty());
+            if let Some(start) = next().and_then(|field| field.parse().ok()) {
+                current.start = start;
+            }
+            if let Some(length) = next().and_then(|field| field.parse().ok()) {
+                current.length = length;
+            }
+            if let Some(file) = next().and_then(|field| field.parse::<i64>().ok()) {
+                current.file = usize::try_from(file).ok();
+            }
+            match next() {
+                Some("i") => current.jump = JumpKind::Into,
+                Some("o") => current.jump = JumpKind::Out,
+                Some("-") => current.jump = JumpKind::Regular,
+                _ => {}
+            }
+            if let Some(depth) = next().and_then(|field| field.parse().ok()) {
+                current.modifier_depth = depth;
+            }
+            current
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields_carry_over() {
+        let entries = parse_source_map("1:2:0:-:0;3;;:4:-1;::1:o:2");
+        assert_eq!(entries.len(), 5);
+        assert_eq!(
+            entries[1],
+            SourceMapEntry {
+                start: 3,
+                length: 2,
+                file: Some(0),
+                jump: JumpKind::Regular,
+                modifier_depth: 0
+            }
+        );
+        assert_eq!(entries[2], entries[1]);
+        assert_eq!((entries[3].length, entries[3].file), (4, None));
+        assert_eq!(entries[4].file, Some(1));
+        assert_eq!(entries[4].jump, JumpKind::Out);
+        assert_eq!(entries[4].modifier_depth, 2);
+    }
+
+    #[test]
+    fn test_empty_and_malformed() {
+        assert!(parse_source_map("").is_empty());
+        let entries = parse_source_map("5:x:0;y");
+        assert_eq!(entries[0].start, 5);
+        assert_eq!(entries[0].length, 0);
+        assert_eq!(entries[1].start, 5);
+    }
+}
@@ -0,0 +1,792 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use revm::{bytecode::opcode, primitives::U256};
+
+use crate::cfg::{BlockExit, Cfg, fold_constants};
+use crate::instruction::Instruction;
+
+/// A value defined exactly once, by a phi node, an instruction, or as a function input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ValueId(pub usize);
+
+impl fmt::Display for ValueId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+/// An instruction or phi input: a value, or a constant folded in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operand {
+    Value(ValueId),
+    Const(U256),
+}
+
+impl Operand {
+    pub fn as_value(&self) -> Option<ValueId> {
+        match self {
+            Operand::Value(value) => Some(*value),
+            Operand::Const(_) => None,
+        }
+    }
+
+    pub fn as_constant(&self) -> Option<U256> {
+        match self {
+            Operand::Value(_) => None,
+            Operand::Const(value) => Some(*value),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Value(value) => value.fmt(f),
+            Operand::Const(value) => write!(f, "{value:#x}"),
+        }
+    }
+}
+
+/// Selects between incoming values at a block with several predecessors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Phi {
+    pub output: ValueId,
+    /// Value coming from each predecessor block, keyed by its start position.
+    pub incoming: BTreeMap<usize, Operand>,
+}
+
+/// An instruction that is not pure stack manipulation. `PUSH`, `DUP`, `SWAP`, `POP` and
+/// `JUMPDEST` are resolved into operands and do not appear.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsaInstruction {
+    pub pc: usize,
+    pub opcode: u8,
+    /// Stack inputs; `inputs[0]` was the top of the stack.
+    pub inputs: Vec<Operand>,
+    pub output: Option<ValueId>,
+}
+
+impl SsaInstruction {
+    /// Returns the lower-case mnemonic, or `unknown(0x..)` for bytes that are not opcodes.
+    pub fn name(&self) -> String {
+        match crate::OpCode::new(self.opcode) {
+            Some(op) => op.as_str().to_lowercase(),
+            None => format!("unknown({:#04x})", self.opcode),
+        }
+    }
+}
+
+/// A basic block in SSA form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsaBlock {
+    pub start: usize,
+    pub phis: Vec<Phi>,
+    pub instructions: Vec<SsaInstruction>,
+    pub exit: BlockExit,
+    pub predecessors: BTreeSet<usize>,
+    pub successors: BTreeSet<usize>,
+    /// Stack left for the successors, bottom first.
+    pub exit_stack: Vec<Operand>,
+}
+
+/// Where a value is defined or used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Location {
+    /// `blocks[block].phis[index]`.
+    Phi { block: usize, index: usize },
+    /// `blocks[block].instructions[index]`.
+    Instruction { block: usize, index: usize },
+}
+
+/// Static single assignment form of the reachable code.
+///
+/// Every stack slot written by an instruction becomes a numbered [`ValueId`]; stack shuffling is
+/// resolved away. Blocks whose predecessors leave different values in a stack slot get a
+/// [`Phi`] for it, and operands that are constant on every path are folded into
+/// [`Operand::Const`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ssa {
+    /// Reachable blocks keyed by start position.
+    pub blocks: BTreeMap<usize, SsaBlock>,
+    /// Values read from below the stack a block was entered with, numbered first. Code
+    /// reached from the entry point only has them when the stack heights of merging paths
+    /// disagree.
+    pub inputs: Vec<ValueId>,
+    definitions: Vec<Option<Location>>,
+}
+
+impl Ssa {
+    /// Converts the reachable code into SSA form, using the control flow graph of
+    /// [`Cfg::new`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use evm_lens_core::Ssa;
+    ///
+    /// // PUSH1 0x04, CALLDATALOAD, DUP1, ADD, PUSH1 0x01, SSTORE, STOP
+    /// let bytes = hex::decode("6004358001600155").unwrap();
+    /// let ssa = Ssa::new(&bytes);
+    /// let block = ssa.block(0).unwrap();
+    /// assert_eq!(block.instructions[0].name(), "calldataload");
+    /// assert_eq!(block.instructions[1].inputs[0], block.instructions[1].inputs[1]);
+    /// assert_eq!(block.instructions[2].inputs[0].to_string(), "0x1");
+    /// ```
+    pub fn new(bytes: &[u8]) -> Self {
+        Builder::new(&Cfg::new(bytes)).build()
+    }
+
+    pub fn block(&self, start: usize) -> Option<&SsaBlock> {
+        self.blocks.get(&start)
+    }
+
+    /// Number of values; ids run from `0` to `value_count() - 1`.
+    pub fn value_count(&self) -> usize {
+        self.definitions.len()
+    }
+
+    /// Returns where `value` is defined, or `None` for [`Ssa::inputs`].
+    pub fn definition(&self, value: ValueId) -> Option<Location> {
+        self.definitions.get(value.0).copied().flatten()
+    }
+
+    /// Returns the instruction that defines `value`, if it is not a phi or an input.
+    pub fn defining_instruction(&self, value: ValueId) -> Option<&SsaInstruction> {
+        match self.definition(value)? {
+            Location::Instruction { block, index } => {
+                Some(&self.blocks[&block].instructions[index])
+            }
+            Location::Phi { .. } => None,
+        }
+    }
+
+    /// Returns every phi and instruction that reads `value`, in block order.
+    pub fn uses(&self, value: ValueId) -> Vec<Location> {
+        let operand = Operand::Value(value);
+        let mut uses = Vec::new();
+        for block in self.blocks.values() {
+            for (index, phi) in block.phis.iter().enumerate() {
+                if phi.incoming.values().any(|incoming| *incoming == operand) {
+                    uses.push(Location::Phi {
+                        block: block.start,
+                        index,
+                    });
+                }
+            }
+            for (index, instruction) in block.instructions.iter().enumerate() {
+                if instruction.inputs.contains(&operand) {
+                    uses.push(Location::Instruction {
+                        block: block.start,
+                        index,
+                    });
+                }
+            }
+        }
+        uses
+    }
+}
+
+/// Dumps the IR one block at a time:
+///
+/// ```text
+/// block_000e: ; preds 0000, 0019
+///     %3 = phi [0000: 0x0], [0019: %7]
+///     %4 = lt %3, 0xa
+///     jumpi 0x1c, %4
+///     ; -> 0014, 001c
+/// ```
+impl fmt::Display for Ssa {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.inputs.is_empty() {
+            let inputs: Vec<String> = self.inputs.iter().map(ValueId::to_string).collect();
+            writeln!(f, "inputs: {}", inputs.join(", "))?;
+        }
+
+        for (position, block) in self.blocks.values().enumerate() {
+            if position > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "block_{:04x}:", block.start)?;
+            if !block.predecessors.is_empty() {
+                write!(f, " ; preds {}", join_positions(&block.predecessors))?;
+            }
+            writeln!(f)?;
+
+            for phi in &block.phis {
+                let incoming: Vec<String> = phi
+                    .incoming
+                    .iter()
+                    .map(|(predecessor, operand)| format!("[{predecessor:04x}: {operand}]"))
+                    .collect();
+                writeln!(f, "    {} = phi {}", phi.output, incoming.join(", "))?;
+            }
+            for instruction in &block.instructions {
+                write!(f, "    ")?;
+                if let Some(output) = instruction.output {
+                    write!(f, "{output} = ")?;
+                }
+                write!(f, "{}", instruction.name())?;
+                let inputs: Vec<String> =
+                    instruction.inputs.iter().map(Operand::to_string).collect();
+                if !inputs.is_empty() {
+                    write!(f, " {}", inputs.join(", "))?;
+                }
+                writeln!(f)?;
+            }
+            if !block.successors.is_empty() {
+                writeln!(f, "    ; -> {}", join_positions(&block.successors))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn join_positions(positions: &BTreeSet<usize>) -> String {
+    positions
+        .iter()
+        .map(|position| format!("{position:04x}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// What a provisional value id stands for while the IR is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Provisional {
+    Input,
+    Phi,
+    Instruction,
+}
+
+struct Builder<'c, 'a> {
+    cfg: &'c Cfg<'a>,
+    predecessors: BTreeMap<usize, BTreeSet<usize>>,
+    kinds: Vec<Provisional>,
+    /// Values found to equal another operand: trivial phis and folded instructions.
+    replaced: HashMap<usize, Operand>,
+    blocks: BTreeMap<usize, SsaBlock>,
+    /// Entry stack height assumed for each block with phis.
+    entry_heights: HashMap<usize, usize>,
+}
+
+impl<'c, 'a> Builder<'c, 'a> {
+    fn new(cfg: &'c Cfg<'a>) -> Self {
+        // Only reachable blocks take part
+        let predecessors = cfg
+            .predecessors()
+            .into_iter()
+            .filter(|(start, _)| cfg.blocks[start].reachable)
+            .map(|(start, predecessors)| {
+                let reachable = predecessors
+                    .into_iter()
+                    .filter(|predecessor| cfg.blocks[predecessor].reachable)
+                    .collect();
+                (start, reachable)
+            })
+            .collect();
+
+        Builder {
+            cfg,
+            predecessors,
+            kinds: Vec::new(),
+            replaced: HashMap::new(),
+            blocks: BTreeMap::new(),
+            entry_heights: HashMap::new(),
+        }
+    }
+
+    fn build(mut self) -> Ssa {
+        for start in self.reverse_postorder() {
+            self.lift_block(start);
+        }
+        self.fill_phis();
+        self.simplify();
+        self.finish()
+    }
+
+    /// Blocks in reverse postorder from the entry point, so every block except loop headers is
+    /// visited after all its predecessors.
+    fn reverse_postorder(&self) -> Vec<usize> {
+        let mut postorder = Vec::new();
+        if self.cfg.block(0).is_none_or(|block| !block.reachable) {
+            return postorder;
+        }
+
+        let successors = |start: usize| -> Vec<usize> {
+            self.cfg.blocks[&start].successors.iter().copied().collect()
+        };
+        let mut visited = BTreeSet::from([0]);
+        let mut work = vec![(0, successors(0))];
+        while let Some((start, mut pending)) = work.pop() {
+            match pending.pop() {
+                Some(successor) => {
+                    work.push((start, pending));
+                    if visited.insert(successor) {
+                        work.push((successor, successors(successor)));
+                    }
+                }
+                None => postorder.push(start),
+            }
+        }
+        postorder.reverse();
+        postorder
+    }
+
+    fn new_value(&mut self, kind: Provisional) -> ValueId {
+        self.kinds.push(kind);
+        ValueId(self.kinds.len() - 1)
+    }
+
+    fn lift_block(&mut self, start: usize) {
+        let cfg = self.cfg;
+        let block = &cfg.blocks[&start];
+        let predecessors = self.predecessors[&start].clone();
+        let lifted: Vec<usize> = predecessors
+            .iter()
+            .copied()
+            .filter(|predecessor| self.blocks.contains_key(predecessor))
+            .collect();
+
+        let mut phis = Vec::new();
+        let mut stack: Vec<Operand> = match (predecessors.len(), &lifted[..]) {
+            (_, []) => Vec::new(),
+            (1, [predecessor]) => self.blocks[predecessor].exit_stack.clone(),
+            _ => {
+                // Slots deeper than the shortest incoming stack are not reliably there
+                let height = lifted
+                    .iter()
+                    .map(|predecessor| self.blocks[predecessor].exit_stack.len())
+                    .min()
+                    .unwrap_or_default();
+                self.entry_heights.insert(start, height);
+                (0..height)
+                    .map(|_| {
+                        let output = self.new_value(Provisional::Phi);
+                        phis.push(Phi {
+                            output,
+                            incoming: BTreeMap::new(),
+                        });
+                        Operand::Value(output)
+                    })
+                    .collect()
+            }
+        };
+
+        let mut instructions = Vec::new();
+        for instruction in &block.instructions {
+            self.lift(instruction, &mut stack, &mut instructions);
+        }
+
+        self.blocks.insert(
+            start,
+            SsaBlock {
+                start,
+                phis,
+                instructions,
+                exit: block.exit,
+                predecessors,
+                successors: block.successors.clone(),
+                exit_stack: stack,
+            },
+        );
+    }
+
+    fn lift(
+        &mut self,
+        instruction: &Instruction,
+        stack: &mut Vec<Operand>,
+        out: &mut Vec<SsaInstruction>,
+    ) {
+        let op = instruction.opcode;
+        let (inputs, outputs) = match instruction.op() {
+            Some(info) => (info.inputs() as usize, info.outputs() as usize),
+            None => (0, 0),
+        };
+        let needed = match op {
+            opcode::DUP1..=opcode::DUP16 => (op - opcode::DUP1 + 1) as usize,
+            opcode::SWAP1..=opcode::SWAP16 => (op - opcode::SWAP1 + 2) as usize,
+            _ => inputs,
+        };
+        while stack.len() < needed {
+            let input = self.new_value(Provisional::Input);
+            stack.insert(0, Operand::Value(input));
+        }
+
+        match op {
+            opcode::PUSH0..=opcode::PUSH32 => {
+                stack.push(Operand::Const(instruction.push_value().unwrap_or_default()));
+            }
+            opcode::DUP1..=opcode::DUP16 => stack.push(stack[stack.len() - needed]),
+            opcode::SWAP1..=opcode::SWAP16 => {
+                let top = stack.len() - 1;
+                stack.swap(top, top + 1 - needed);
+            }
+            opcode::POP => {
+                stack.pop();
+            }
+            opcode::JUMPDEST => {}
+            opcode::PC => stack.push(Operand::Const(U256::from(instruction.pc))),
+            _ => {
+                let operands: Vec<Operand> = (0..inputs)
+                    .map(|_| stack.pop().expect("stack padded"))
+                    .collect();
+                if outputs > 0
+                    && let Some(value) = fold(op, &operands)
+                {
+                    stack.push(Operand::Const(value));
+                    return;
+                }
+
+                let output = (outputs > 0).then(|| self.new_value(Provisional::Instruction));
+                if let Some(output) = output {
+                    stack.push(Operand::Value(output));
+                }
+                out.push(SsaInstruction {
+                    pc: instruction.pc,
+                    opcode: op,
+                    inputs: operands,
+                    output,
+                });
+            }
+        }
+    }
+
+    /// Sets the incoming values of every phi from the exit stacks of its predecessors, matched
+    /// from the top of the stack.
+    fn fill_phis(&mut self) {
+        let starts: Vec<usize> = self.blocks.keys().copied().collect();
+        for start in starts {
+            let Some(&height) = self.entry_heights.get(&start) else {
+                continue;
+            };
+            let predecessors = self.blocks[&start].predecessors.clone();
+            for predecessor in predecessors {
+                let exit_stack = self.blocks[&predecessor].exit_stack.clone();
+                for position in 0..height {
+                    let from_top = height - position;
+                    let incoming = match exit_stack.len().checked_sub(from_top) {
+                        Some(index) => exit_stack[index],
+                        None => Operand::Value(self.new_value(Provisional::Input)),
+                    };
+                    self.blocks.get_mut(&start).expect("block exists").phis[position]
+                        .incoming
+                        .insert(predecessor, incoming);
+                }
+            }
+        }
+    }
+
+    fn resolve(&self, operand: Operand) -> Operand {
+        let mut operand = operand;
+        while let Operand::Value(value) = operand
+            && let Some(replacement) = self.replaced.get(&value.0)
+        {
+            operand = *replacement;
+        }
+        operand
+    }
+
+    /// Removes phis whose incoming values are all the same (ignoring the phi itself) and folds
+    /// instructions whose inputs became constant, until nothing changes.
+    fn simplify(&mut self) {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block in self.blocks.values() {
+                for phi in &block.phis {
+                    if self.replaced.contains_key(&phi.output.0) {
+                        continue;
+                    }
+                    let sources: BTreeSet<OperandKey> = phi
+                        .incoming
+                        .values()
+                        .map(|incoming| OperandKey(self.resolve(*incoming)))
+                        .filter(|incoming| incoming.0 != Operand::Value(phi.output))
+                        .collect();
+                    if let [only] = sources.iter().collect::<Vec<_>>()[..] {
+                        self.replaced.insert(phi.output.0, only.0);
+                        changed = true;
+                    }
+                }
+                for instruction in &block.instructions {
+                    let Some(output) = instruction.output else {
+                        continue;
+                    };
+                    if self.replaced.contains_key(&output.0) {
+                        continue;
+                    }
+                    let inputs: Vec<Operand> = instruction
+                        .inputs
+                        .iter()
+                        .map(|input| self.resolve(*input))
+                        .collect();
+                    if let Some(value) = fold(instruction.opcode, &inputs) {
+                        self.replaced.insert(output.0, Operand::Const(value));
+                        changed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Applies replacements, drops removed definitions and numbers the remaining values in
+    /// order: inputs first, then phis and instructions by block.
+    fn finish(mut self) -> Ssa {
+        let mut blocks = std::mem::take(&mut self.blocks);
+        for block in blocks.values_mut() {
+            block
+                .phis
+                .retain(|phi| !self.replaced.contains_key(&phi.output.0));
+            block.instructions.retain(|instruction| {
+                instruction
+                    .output
+                    .is_none_or(|output| !self.replaced.contains_key(&output.0))
+            });
+            for phi in &mut block.phis {
+                for incoming in phi.incoming.values_mut() {
+                    *incoming = self.resolve(*incoming);
+                }
+            }
+            for instruction in &mut block.instructions {
+                for input in &mut instruction.inputs {
+                    *input = self.resolve(*input);
+                }
+            }
+            for operand in &mut block.exit_stack {
+                *operand = self.resolve(*operand);
+            }
+        }
+
+        // Inputs are only kept if something still refers to them
+        let mut referenced = BTreeSet::new();
+        for block in blocks.values() {
+            let operands = block
+                .phis
+                .iter()
+                .flat_map(|phi| phi.incoming.values())
+                .chain(
+                    block
+                        .instructions
+                        .iter()
+                        .flat_map(|instruction| &instruction.inputs),
+                )
+                .chain(&block.exit_stack);
+            referenced.extend(operands.filter_map(Operand::as_value));
+        }
+
+        let mut numbering: HashMap<usize, ValueId> = HashMap::new();
+        let mut definitions = Vec::new();
+        let mut inputs = Vec::new();
+        for (value, kind) in self.kinds.iter().enumerate() {
+            if *kind == Provisional::Input && referenced.contains(&ValueId(value)) {
+                let id = ValueId(definitions.len());
+                numbering.insert(value, id);
+                definitions.push(None);
+                inputs.push(id);
+            }
+        }
+        for block in blocks.values() {
+            for (index, phi) in block.phis.iter().enumerate() {
+                numbering.insert(phi.output.0, ValueId(definitions.len()));
+                definitions.push(Some(Location::Phi {
+                    block: block.start,
+                    index,
+                }));
+            }
+            for (index, instruction) in block.instructions.iter().enumerate() {
+                if let Some(output) = instruction.output {
+                    numbering.insert(output.0, ValueId(definitions.len()));
+                    definitions.push(Some(Location::Instruction {
+                        block: block.start,
+                        index,
+                    }));
+                }
+            }
+        }
+
+        let renumber = |operand: &mut Operand| {
+            if let Operand::Value(value) = operand {
+                *value = numbering[&value.0];
+            }
+        };
+        for block in blocks.values_mut() {
+            for phi in &mut block.phis {
+                phi.output = numbering[&phi.output.0];
+                phi.incoming.values_mut().for_each(renumber);
+            }
+            for instruction in &mut block.instructions {
+                if let Some(output) = &mut instruction.output {
+                    *output = numbering[&output.0];
+                }
+                instruction.inputs.iter_mut().for_each(renumber);
+            }
+            block.exit_stack.iter_mut().for_each(renumber);
+        }
+
+        Ssa {
+            blocks,
+            inputs,
+            definitions,
+        }
+    }
+}
+
+/// Orders operands so they can be collected into a set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct OperandKey(Operand);
+
+impl Ord for OperandKey {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let key = |operand: &Operand| match operand {
+            Operand::Value(value) => (0, U256::from(value.0)),
+            Operand::Const(value) => (1, *value),
+        };
+        key(&self.0).cmp(&key(&other.0))
+    }
+}
+
+impl PartialOrd for OperandKey {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn fold(op: u8, operands: &[Operand]) -> Option<U256> {
+    let constants: Vec<U256> = operands
+        .iter()
+        .map(Operand::as_constant)
+        .collect::<Option<_>>()?;
+    fold_constants(op, &constants)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ssa(code: &str) -> Ssa {
+        Ssa::new(&hex::decode(code).unwrap())
+    }
+
+    fn constant(value: u64) -> Operand {
+        Operand::Const(U256::from(value))
+    }
+
+    #[test]
+    fn test_stack_shuffles_become_operands() {
+        // CALLER, CALLVALUE, SWAP1, DUP2, SSTORE, POP, STOP
+        let ssa = ssa("33349081555000");
+        let block = ssa.block(0).unwrap();
+
+        assert_eq!(block.instructions.len(), 4);
+        let caller = block.instructions[0].output.unwrap();
+        let value = block.instructions[1].output.unwrap();
+        // After SWAP1 the stack is [value, caller]; DUP2 copies value to the top
+        assert_eq!(
+            block.instructions[2].inputs,
+            vec![Operand::Value(value), Operand::Value(caller)]
+        );
+        assert!(block.exit_stack.is_empty());
+    }
+
+    #[test]
+    fn test_constants_are_folded() {
+        // PUSH1 0x02, PUSH1 0x03, ADD, PUSH0, SSTORE, STOP
+        let ssa = ssa("600260030160005500");
+        let block = ssa.block(0).unwrap();
+
+        assert_eq!(block.instructions[0].name(), "sstore");
+        assert_eq!(block.instructions[0].inputs, vec![constant(0), constant(5)]);
+        assert_eq!(ssa.value_count(), 0);
+    }
+
+    #[test]
+    fn test_phi_at_join() {
+        // 0x00: CALLDATASIZE, PUSH1 0x09, JUMPI
+        // 0x04: CALLER, PUSH1 0x0b, JUMP, STOP
+        // 0x09: JUMPDEST, CALLVALUE
+        // 0x0b: JUMPDEST, PUSH0, SSTORE, STOP
+        let ssa = ssa("3660095733600b56005b345b5f5500");
+        let join = ssa.block(0x0b).unwrap();
+
+        assert_eq!(join.phis.len(), 1);
+        let phi = &join.phis[0];
+        assert_eq!(
+            phi.incoming.keys().copied().collect::<Vec<_>>(),
+            vec![0x04, 0x09]
+        );
+        assert_eq!(join.instructions[0].inputs[1], Operand::Value(phi.output));
+        assert_eq!(
+            ssa.definition(phi.output),
+            Some(Location::Phi {
+                block: 0x0b,
+                index: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_trivial_phi_is_removed() {
+        // Both paths into 0x08 leave 0x2a on the stack
+        // 0x00: PUSH1 0x2a, CALLDATASIZE, PUSH1 0x08, JUMPI
+        // 0x06: PUSH0, POP
+        // 0x08: JUMPDEST, PUSH0, SSTORE, STOP
+        let ssa = ssa("602a366008575f505b5f5500");
+        let join = ssa.block(0x08).unwrap();
+
+        assert!(join.phis.is_empty());
+        assert_eq!(
+            join.instructions[0].inputs,
+            vec![constant(0), constant(0x2a)]
+        );
+    }
+
+    #[test]
+    fn test_loop_counter_phi() {
+        // 0x00: PUSH0
+        // 0x01: JUMPDEST, DUP1, PUSH1 0x0a, GT, ISZERO, PUSH1 0x10, JUMPI
+        // 0x0a: PUSH1 0x01, ADD, PUSH1 0x01, JUMP
+        // 0x10: JUMPDEST, STOP
+        let ssa = ssa("5f5b80600a11156010576001016001565b00");
+        let header = ssa.block(0x01).unwrap();
+
+        assert_eq!(header.phis.len(), 1);
+        let counter = header.phis[0].output;
+        assert_eq!(header.phis[0].incoming[&0x00], constant(0));
+        let increment =
+            ssa.defining_instruction(header.phis[0].incoming[&0x0a].as_value().unwrap());
+        assert_eq!(
+            increment.unwrap().inputs,
+            vec![constant(1), Operand::Value(counter)]
+        );
+        assert_eq!(ssa.uses(counter).len(), 2);
+    }
+
+    #[test]
+    fn test_underflow_reads_inputs() {
+        // POP, STOP
+        assert!(ssa("5000").inputs.is_empty(), "popped input is never read");
+
+        // PUSH0, SSTORE, STOP: the stored value comes from below the entry stack
+        let ssa = ssa("5f5500");
+        assert_eq!(ssa.inputs, vec![ValueId(0)]);
+        assert_eq!(ssa.definition(ValueId(0)), None);
+        assert_eq!(
+            ssa.block(0).unwrap().instructions[0].inputs,
+            vec![constant(0), Operand::Value(ValueId(0))]
+        );
+    }
+
+    #[test]
+    fn test_dump() {
+        // CALLVALUE, ISZERO, PUSH1 0x06, JUMPI, STOP, JUMPDEST, STOP
+        let dump = ssa("3415600657005b00").to_string();
+        assert_eq!(
+            dump,
+            "block_0000:\n    %0 = callvalue\n    %1 = iszero %0\n    jumpi 0x6, %1\n    ; -> 0005, 0006\n\
+             \n\
+             block_0005: ; preds 0000\n    stop\n\
+             \n\
+             block_0006: ; preds 0000\n    stop\n"
+        );
+    }
+}
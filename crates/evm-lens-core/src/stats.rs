@@ -1,10 +1,79 @@
-use revm::bytecode::{Bytecode, opcode::OPCODE_INFO};
+use std::collections::BTreeMap;
 
-#[derive(Debug)]
+use revm::bytecode::{Bytecode, OpCode, opcode::OPCODE_INFO};
+
+use crate::category::{OpcodeCategory, categorize};
+use crate::limits::{LimitUsage, SizeLimits};
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stats {
     pub byte_len: usize,
     pub opcode_count: usize,
     pub max_stack_depth: usize,
+    /// Number of occurrences of each opcode.
+    #[cfg_attr(feature = "serde", serde(with = "histogram"))]
+    pub opcode_histogram: BTreeMap<OpCode, usize>,
+    /// Number of opcodes in each category; categories that never occur are omitted.
+    pub category_counts: BTreeMap<OpcodeCategory, usize>,
+    pub jumpdest_count: usize,
+    pub unique_opcodes: usize,
+    /// Number of bytes that are PUSH immediates rather than opcodes.
+    pub push_data_bytes: usize,
+    /// Share of `byte_len` taken up by PUSH immediates, between 0.0 and 1.0.
+    pub push_data_ratio: f64,
+    /// Usage of the runtime code size limit (EIP-170 by default).
+    pub code_size_limit: LimitUsage,
+    /// Usage of the initcode size limit (EIP-3860 by default).
+    pub initcode_size_limit: LimitUsage,
+}
+
+impl Stats {
+    /// Returns the opcode histogram sorted by descending count, ties broken by opcode value.
+    pub fn sorted_histogram(&self) -> Vec<(OpCode, usize)> {
+        let mut entries: Vec<(OpCode, usize)> = self
+            .opcode_histogram
+            .iter()
+            .map(|(opcode, count)| (*opcode, *count))
+            .collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        entries
+    }
+}
+
+/// The opcode histogram keyed by mnemonic, since `OpCode` has no string form of its own.
+#[cfg(feature = "serde")]
+mod histogram {
+    use std::collections::BTreeMap;
+
+    use revm::bytecode::OpCode;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub(super) fn serialize<S: Serializer>(
+        histogram: &BTreeMap<OpCode, usize>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            histogram
+                .iter()
+                .map(|(opcode, count)| (opcode.as_str(), count)),
+        )
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<OpCode, usize>, D::Error> {
+        BTreeMap::<String, usize>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(name, count)| {
+                (0..=u8::MAX)
+                    .filter_map(OpCode::new)
+                    .find(|opcode| opcode.as_str() == name)
+                    .map(|opcode| (opcode, count))
+                    .ok_or_else(|| D::Error::custom(format!("unknown opcode {}", name)))
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug)]
@@ -25,6 +94,13 @@ impl std::fmt::Display for StatsError {
 impl std::error::Error for StatsError {}
 
 pub fn compute_stats(bytecode: &Bytecode) -> Result<Stats, StatsError> {
+    compute_stats_with_limits(bytecode, &SizeLimits::default())
+}
+
+pub fn compute_stats_with_limits(
+    bytecode: &Bytecode,
+    limits: &SizeLimits,
+) -> Result<Stats, StatsError> {
     // Count the number of opcodes
     let opcode_count = compute_opcode_count(bytecode);
 
@@ -34,10 +110,40 @@ pub fn compute_stats(bytecode: &Bytecode) -> Result<Stats, StatsError> {
     // Track PUSH / POP depth
     let max_stack_depth = compute_max_stack_depth(bytecode)?;
 
+    // Frequency of each opcode and the metrics derived from it
+    let opcode_histogram = compute_opcode_histogram(bytecode)?;
+    let category_counts = compute_category_counts(&opcode_histogram);
+    let jumpdest_count = opcode_histogram
+        .get(&OpCode::JUMPDEST)
+        .copied()
+        .unwrap_or(0);
+    let unique_opcodes = opcode_histogram.len();
+
+    // Bytes consumed by PUSH immediates
+    let push_data_bytes = compute_push_data_bytes(bytecode);
+    let push_data_ratio = if byte_len == 0 {
+        0.0
+    } else {
+        push_data_bytes as f64 / byte_len as f64
+    };
+
+    // Size limits are checked against the original code, without analysis padding
+    let code_size = bytecode.original_byte_slice().len();
+    let code_size_limit = LimitUsage::new(code_size, limits.max_code_size);
+    let initcode_size_limit = LimitUsage::new(code_size, limits.max_initcode_size);
+
     Ok(Stats {
         byte_len,
         opcode_count,
         max_stack_depth,
+        opcode_histogram,
+        category_counts,
+        jumpdest_count,
+        unique_opcodes,
+        push_data_bytes,
+        push_data_ratio,
+        code_size_limit,
+        initcode_size_limit,
     })
 }
 
@@ -50,6 +156,39 @@ fn get_byte_len(bytecode: &Bytecode) -> usize {
     bytecode.bytecode().as_ref().len()
 }
 
+fn compute_opcode_histogram(bytecode: &Bytecode) -> Result<BTreeMap<OpCode, usize>, StatsError> {
+    let mut iter = bytecode.iter_opcodes();
+    let mut histogram = BTreeMap::new();
+
+    while let Some(byte) = iter.peek() {
+        let opcode = OpCode::new(byte).ok_or(StatsError::UnknownOpcode(byte))?;
+        *histogram.entry(opcode).or_insert(0) += 1;
+        iter.next();
+    }
+
+    Ok(histogram)
+}
+
+fn compute_category_counts(histogram: &BTreeMap<OpCode, usize>) -> BTreeMap<OpcodeCategory, usize> {
+    let mut counts = BTreeMap::new();
+    for (opcode, count) in histogram {
+        *counts.entry(categorize(*opcode)).or_insert(0) += count;
+    }
+    counts
+}
+
+fn compute_push_data_bytes(bytecode: &Bytecode) -> usize {
+    let mut iter = bytecode.iter_opcodes();
+    let mut total = 0;
+
+    while let Some(opcode) = iter.peek_opcode() {
+        total += opcode.info().immediate_size() as usize;
+        iter.next();
+    }
+
+    total
+}
+
 fn compute_max_stack_depth(bytecode: &Bytecode) -> Result<usize, StatsError> {
     let mut iter = bytecode.iter_opcodes();
     let mut max_depth: i32 = 0;
@@ -237,10 +376,81 @@ mod tests {
             byte_len: 10,
             opcode_count: 5,
             max_stack_depth: 3,
+            ..Default::default()
         };
 
         assert_eq!(stats.byte_len, 10);
         assert_eq!(stats.opcode_count, 5);
         assert_eq!(stats.max_stack_depth, 3);
     }
+
+    #[test]
+    fn test_opcode_histogram() {
+        // PUSH1 0x01, PUSH1 0x02, ADD, PUSH1 0x03, MUL, STOP
+        let bytes = hex::decode("600160020160030200").unwrap();
+        let bytecode = Bytecode::new_raw_checked(Bytes::from(bytes)).unwrap();
+
+        let stats = compute_stats(&bytecode).unwrap();
+        assert_eq!(stats.opcode_histogram[&OpCode::PUSH1], 3);
+        assert_eq!(stats.opcode_histogram[&OpCode::ADD], 1);
+        assert_eq!(stats.opcode_histogram[&OpCode::MUL], 1);
+        assert_eq!(stats.opcode_histogram[&OpCode::STOP], 1);
+        assert_eq!(stats.unique_opcodes, 4);
+
+        let sorted = stats.sorted_histogram();
+        assert_eq!(sorted[0], (OpCode::PUSH1, 3));
+        assert_eq!(sorted[1], (OpCode::STOP, 1)); // Ties ordered by opcode value
+    }
+
+    #[test]
+    fn test_category_counts() {
+        // PUSH1 0x00, SLOAD, PUSH1 0x00, MSTORE, JUMPDEST, LOG0, STOP
+        let bytes = hex::decode("6000546000525ba000").unwrap();
+        let bytecode = Bytecode::new_raw_checked(Bytes::from(bytes)).unwrap();
+
+        let stats = compute_stats(&bytecode).unwrap();
+        assert_eq!(stats.category_counts[&OpcodeCategory::Stack], 2);
+        assert_eq!(stats.category_counts[&OpcodeCategory::Storage], 1);
+        assert_eq!(stats.category_counts[&OpcodeCategory::Memory], 1);
+        assert_eq!(stats.category_counts[&OpcodeCategory::ControlFlow], 2);
+        assert_eq!(stats.category_counts[&OpcodeCategory::Logging], 1);
+        assert!(!stats.category_counts.contains_key(&OpcodeCategory::Calls));
+        assert_eq!(stats.jumpdest_count, 1);
+    }
+
+    #[test]
+    fn test_push_data_ratio() {
+        // PUSH2 0xABCD, STOP
+        let bytes = hex::decode("61ABCD00").unwrap();
+        let bytecode = Bytecode::new_raw_checked(Bytes::from(bytes)).unwrap();
+
+        let stats = compute_stats(&bytecode).unwrap();
+        assert_eq!(stats.push_data_bytes, 2);
+        assert_eq!(stats.push_data_ratio, 0.5);
+    }
+
+    #[test]
+    fn test_size_limit_usage() {
+        // PUSH1 0xFF, PUSH2 0xABCD, STOP
+        let bytes = hex::decode("60FF61ABCD00").unwrap();
+        let bytecode = Bytecode::new_raw_checked(Bytes::from(bytes)).unwrap();
+
+        let stats = compute_stats(&bytecode).unwrap();
+        assert_eq!(stats.code_size_limit.size, 6);
+        assert_eq!(stats.code_size_limit.limit, 24_576);
+        assert_eq!(stats.code_size_limit.remaining(), 24_570);
+        assert_eq!(stats.initcode_size_limit.limit, 49_152);
+    }
+
+    #[test]
+    fn test_custom_size_limits() {
+        // PUSH1 0xFF, PUSH2 0xABCD, STOP
+        let bytes = hex::decode("60FF61ABCD00").unwrap();
+        let bytecode = Bytecode::new_raw_checked(Bytes::from(bytes)).unwrap();
+
+        let stats = compute_stats_with_limits(&bytecode, &SizeLimits::new(4, 8)).unwrap();
+        assert!(stats.code_size_limit.is_exceeded());
+        assert_eq!(stats.code_size_limit.excess(), 2);
+        assert!(!stats.initcode_size_limit.is_exceeded());
+    }
 }
@@ -0,0 +1,441 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use revm::{bytecode::opcode, primitives::U256};
+
+use crate::cfg::{AbstractValue, Cfg, explore, fold_constants};
+use crate::dispatch::{FunctionEntry, function_entries};
+use crate::instruction::Instruction;
+
+/// How a storage variable's slots are derived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SlotKind {
+    /// Accessed directly at a constant slot.
+    Value,
+    /// Accessed at `keccak256(key . slot)`, possibly nested or offset for struct members.
+    Mapping,
+    /// Accessed at `keccak256(slot) + index`; the length lives at the slot itself.
+    DynamicArray,
+}
+
+impl SlotKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SlotKind::Value => "value",
+            SlotKind::Mapping => "mapping",
+            SlotKind::DynamicArray => "array",
+        }
+    }
+}
+
+impl std::fmt::Display for SlotKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A variable packed into part of a slot, found from shift and mask patterns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PackedField {
+    /// Offset in bytes from the least significant end of the slot.
+    pub offset: u8,
+    /// Width in bytes.
+    pub width: u8,
+}
+
+/// A storage slot, or the base slot of a mapping or array, and how the code uses it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StorageVariable {
+    pub slot: U256,
+    pub kind: SlotKind,
+    /// Packed fields accessed within the slot; empty when only whole-slot accesses were seen.
+    pub fields: Vec<PackedField>,
+    pub read_pcs: Vec<usize>,
+    pub write_pcs: Vec<usize>,
+    /// Selectors of the dispatcher functions that read the slot.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::selectors"))]
+    pub readers: Vec<[u8; 4]>,
+    /// Selectors of the dispatcher functions that write the slot.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex::selectors"))]
+    pub writers: Vec<[u8; 4]>,
+}
+
+impl StorageVariable {
+    /// Whether the slot holds more than one variable.
+    pub fn is_packed(&self) -> bool {
+        !self.fields.is_empty()
+    }
+}
+
+/// Approximate storage layout reconstructed from `SLOAD` and `SSTORE` patterns.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StorageLayout {
+    /// Variables sorted by slot, then kind.
+    pub variables: Vec<StorageVariable>,
+}
+
+impl StorageLayout {
+    pub fn variable(&self, kind: SlotKind, slot: U256) -> Option<&StorageVariable> {
+        self.variables
+            .iter()
+            .find(|variable| variable.kind == kind && variable.slot == slot)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct SlotRef {
+    kind: SlotKind,
+    base: U256,
+}
+
+/// Stack value for layout inference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Symbol {
+    Unknown,
+    Constant(U256),
+    /// A slot derived from a variable's base slot.
+    Slot(SlotRef),
+    /// A value read from a slot, shifted right by `shift` bytes.
+    Loaded {
+        slot: SlotRef,
+        shift: u8,
+    },
+}
+
+impl Symbol {
+    fn slot(&self) -> Option<SlotRef> {
+        match self {
+            Symbol::Constant(slot) => Some(SlotRef {
+                kind: SlotKind::Value,
+                base: *slot,
+            }),
+            Symbol::Slot(slot) => Some(*slot),
+            _ => None,
+        }
+    }
+}
+
+impl AbstractValue for Symbol {
+    fn unknown() -> Self {
+        Symbol::Unknown
+    }
+
+    fn constant(value: U256) -> Self {
+        Symbol::Constant(value)
+    }
+
+    fn as_constant(&self) -> Option<U256> {
+        match self {
+            Symbol::Constant(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn apply(instruction: &Instruction, inputs: &[Self]) -> Self {
+        match (instruction.opcode, inputs) {
+            (opcode::SLOAD, [slot]) => match slot.slot() {
+                Some(slot) => Symbol::Loaded { slot, shift: 0 },
+                None => Symbol::Unknown,
+            },
+            // Array elements and struct members sit at an offset from the derived slot
+            (opcode::ADD, [Symbol::Slot(slot), _] | [_, Symbol::Slot(slot)]) => Symbol::Slot(*slot),
+            (opcode::SHR, [Symbol::Constant(bits), Symbol::Loaded { slot, shift }]) => {
+                shifted(*slot, *shift, *bits)
+            }
+            (opcode::DIV, [Symbol::Loaded { slot, shift }, Symbol::Constant(divisor)]) => {
+                if divisor.is_power_of_two() {
+                    shifted(*slot, *shift, U256::from(divisor.trailing_zeros()))
+                } else {
+                    Symbol::Unknown
+                }
+            }
+            _ => inputs
+                .iter()
+                .map(|input| input.as_constant())
+                .collect::<Option<Vec<_>>>()
+                .and_then(|constants| fold_constants(instruction.opcode, &constants))
+                .map_or(Symbol::Unknown, Symbol::Constant),
+        }
+    }
+
+    fn keccak(words: &[Option<Self>]) -> Self {
+        match words {
+            // Solidity hashes key . slot; nested mappings hash key . keccak(...)
+            [_, Some(Symbol::Constant(base))] => Symbol::Slot(SlotRef {
+                kind: SlotKind::Mapping,
+                base: *base,
+            }),
+            [_, Some(Symbol::Slot(slot))] => Symbol::Slot(SlotRef {
+                kind: SlotKind::Mapping,
+                base: slot.base,
+            }),
+            // Vyper hashes slot . key
+            [Some(Symbol::Constant(base)), _] => Symbol::Slot(SlotRef {
+                kind: SlotKind::Mapping,
+                base: *base,
+            }),
+            [Some(Symbol::Constant(base))] => Symbol::Slot(SlotRef {
+                kind: SlotKind::DynamicArray,
+                base: *base,
+            }),
+            // An array stored in a mapping value
+            [Some(Symbol::Slot(slot))] => Symbol::Slot(*slot),
+            _ => Symbol::Unknown,
+        }
+    }
+}
+
+/// A loaded value shifted right by `bits` more; only whole-byte shifts are tracked.
+fn shifted(slot: SlotRef, shift: u8, bits: U256) -> Symbol {
+    match usize::try_from(bits) {
+        Ok(bits) if bits.is_multiple_of(8) && shift as usize + bits / 8 < 32 => Symbol::Loaded {
+            slot,
+            shift: shift + (bits / 8) as u8,
+        },
+        _ => Symbol::Unknown,
+    }
+}
+
+/// Width in bytes of a mask covering the low bytes of a word, e.g. `0xffff` is 2.
+fn low_bytes_mask_width(mask: U256) -> Option<u8> {
+    let bits = mask.bit_len();
+    let is_low_mask = bits > 0
+        && bits < 256
+        && bits.is_multiple_of(8)
+        && (mask + U256::from(1)).is_power_of_two();
+    is_low_mask.then_some((bits / 8) as u8)
+}
+
+/// Byte range cleared by a mask such as `not(0xff << 160)`, used before writing a packed field.
+fn cleared_range(mask: U256) -> Option<PackedField> {
+    let cleared = !mask;
+    if cleared.is_zero() || mask.is_zero() {
+        return None;
+    }
+
+    let low = cleared.trailing_zeros();
+    let high = cleared.bit_len();
+    let contiguous = (cleared >> low) + U256::from(1);
+    if !low.is_multiple_of(8) || !high.is_multiple_of(8) || !contiguous.is_power_of_two() {
+        return None;
+    }
+    Some(PackedField {
+        offset: (low / 8) as u8,
+        width: ((high - low) / 8) as u8,
+    })
+}
+
+#[derive(Default)]
+struct Accesses {
+    reads: BTreeSet<usize>,
+    writes: BTreeSet<usize>,
+    fields: BTreeSet<PackedField>,
+}
+
+/// Reconstructs an approximate storage layout.
+///
+/// Constant slots are taken from `SLOAD` / `SSTORE` operands. Mapping and array slots are
+/// recognised by tracking `MSTORE`s that feed a `KECCAK256` in the same basic block: hashing
+/// `(key, slot)` marks a mapping and hashing `slot` alone marks a dynamic array. Packed
+/// variables show up as shifts and masks applied to loaded values. Accesses are attributed to
+/// dispatcher functions through the blocks reachable from each function's entry.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{SlotKind, infer_storage_layout};
+/// use revm::primitives::U256;
+///
+/// // PUSH1 0x2a, PUSH1 0x01, SSTORE, STOP
+/// let bytes = hex::decode("602a60015500").unwrap();
+/// let layout = infer_storage_layout(&bytes);
+/// let variable = layout.variable(SlotKind::Value, U256::from(1)).unwrap();
+/// assert_eq!(variable.write_pcs, vec![4]);
+/// ```
+pub fn infer_storage_layout(bytes: &[u8]) -> StorageLayout {
+    let mut accesses: BTreeMap<SlotRef, Accesses> = BTreeMap::new();
+
+    let cfg = explore::<Symbol>(bytes, |instruction, inputs| match instruction.opcode {
+        opcode::SLOAD => {
+            if let Some(slot) = inputs[0].slot() {
+                accesses
+                    .entry(slot)
+                    .or_default()
+                    .reads
+                    .insert(instruction.pc);
+            }
+        }
+        opcode::SSTORE => {
+            if let Some(slot) = inputs[0].slot() {
+                accesses
+                    .entry(slot)
+                    .or_default()
+                    .writes
+                    .insert(instruction.pc);
+            }
+        }
+        opcode::AND => {
+            let (loaded, mask) = match (inputs[0], inputs[1]) {
+                (Symbol::Loaded { slot, shift }, Symbol::Constant(mask))
+                | (Symbol::Constant(mask), Symbol::Loaded { slot, shift }) => ((slot, shift), mask),
+                _ => return,
+            };
+            let (slot, shift) = loaded;
+            let field = match low_bytes_mask_width(mask) {
+                Some(width) if shift as usize + width as usize <= 32 => Some(PackedField {
+                    offset: shift,
+                    width,
+                }),
+                _ if shift == 0 => cleared_range(mask),
+                _ => None,
+            };
+            if let Some(field) = field {
+                accesses.entry(slot).or_default().fields.insert(field);
+            }
+        }
+        _ => {}
+    });
+
+    let functions = function_pcs(&cfg, &function_entries(bytes));
+    let callers = |pcs: &BTreeSet<usize>| -> Vec<[u8; 4]> {
+        functions
+            .iter()
+            .filter(|(_, reachable)| pcs.iter().any(|pc| reachable.contains(pc)))
+            .map(|(function, _)| function.selector)
+            .collect()
+    };
+
+    let variables = accesses
+        .into_iter()
+        .filter(|(_, access)| !access.reads.is_empty() || !access.writes.is_empty())
+        .map(|(slot, access)| StorageVariable {
+            slot: slot.base,
+            kind: slot.kind,
+            fields: access.fields.iter().copied().collect(),
+            readers: callers(&access.reads),
+            writers: callers(&access.writes),
+            read_pcs: access.reads.into_iter().collect(),
+            write_pcs: access.writes.into_iter().collect(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut layout = StorageLayout { variables };
+    layout
+        .variables
+        .sort_by_key(|variable| (variable.slot, variable.kind));
+    layout
+}
+
+/// Instruction positions reachable from each function entry.
+fn function_pcs(cfg: &Cfg, functions: &[FunctionEntry]) -> Vec<(FunctionEntry, BTreeSet<usize>)> {
+    functions
+        .iter()
+        .map(|function| {
+            let mut seen = BTreeSet::new();
+            let mut pcs = BTreeSet::new();
+            let mut queue = VecDeque::from([function.entry]);
+            while let Some(start) = queue.pop_front() {
+                let Some(block) = cfg.block(start) else {
+                    continue;
+                };
+                if !seen.insert(start) {
+                    continue;
+                }
+                pcs.extend(block.instructions.iter().map(|instruction| instruction.pc));
+                queue.extend(block.successors.iter().copied());
+            }
+            (*function, pcs)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(code: &str) -> StorageLayout {
+        infer_storage_layout(&hex::decode(code).unwrap())
+    }
+
+    #[test]
+    fn test_constant_slots() {
+        // PUSH1 0x01, SLOAD, PUSH1 0x02, SSTORE, STOP
+        let layout = layout("60015460025500");
+
+        let one = layout.variable(SlotKind::Value, U256::from(1)).unwrap();
+        assert_eq!(one.read_pcs, vec![2]);
+        assert!(one.write_pcs.is_empty());
+        let two = layout.variable(SlotKind::Value, U256::from(2)).unwrap();
+        assert_eq!(two.write_pcs, vec![5]);
+        assert!(!two.is_packed());
+    }
+
+    #[test]
+    fn test_mapping_slot() {
+        // PUSH0, CALLDATALOAD, PUSH0, MSTORE, PUSH1 0x03, PUSH1 0x20, MSTORE,
+        // PUSH1 0x40, PUSH0, KECCAK256, SLOAD, STOP
+        let layout = layout("5f355f52600360205260405f205400");
+
+        assert_eq!(layout.variables.len(), 1);
+        let mapping = layout.variable(SlotKind::Mapping, U256::from(3)).unwrap();
+        assert_eq!(mapping.read_pcs, vec![13]);
+    }
+
+    #[test]
+    fn test_dynamic_array_slot() {
+        // PUSH1 0x02, PUSH0, MSTORE, PUSH1 0x20, PUSH0, KECCAK256, PUSH1 0x05, ADD, SLOAD, STOP
+        let layout = layout("60025f5260205f206005015400");
+
+        let array = layout
+            .variable(SlotKind::DynamicArray, U256::from(2))
+            .unwrap();
+        assert_eq!(array.read_pcs, vec![11]);
+    }
+
+    #[test]
+    fn test_packed_read_and_write() {
+        // PUSH0, SLOAD, PUSH1 0xa0, SHR, PUSH1 0xff, AND, POP,
+        // PUSH0, SLOAD, PUSH32 not(0xff << 160), AND, PUSH0, SSTORE, STOP
+        let mask = format!("{}00{}", "ff".repeat(11), "ff".repeat(20));
+        let layout = layout(&format!("5f5460a01c60ff16505f547f{mask}165f5500"));
+
+        let slot = layout.variable(SlotKind::Value, U256::ZERO).unwrap();
+        assert_eq!(
+            slot.fields,
+            vec![PackedField {
+                offset: 20,
+                width: 1
+            }]
+        );
+        assert!(slot.is_packed());
+    }
+
+    #[test]
+    fn test_accesses_attributed_to_functions() {
+        // DUP1, PUSH4 0xaabbccdd, EQ, PUSH1 0x0b, JUMPI, STOP,
+        // JUMPDEST, PUSH1 0x2a, PUSH1 0x01, SSTORE, STOP
+        let layout = layout("8063aabbccdd14600b57005b602a60015500");
+
+        let variable = layout.variable(SlotKind::Value, U256::from(1)).unwrap();
+        assert_eq!(variable.writers, vec![[0xaa, 0xbb, 0xcc, 0xdd]]);
+        assert!(variable.readers.is_empty());
+    }
+
+    #[test]
+    fn test_masks() {
+        assert_eq!(low_bytes_mask_width(U256::from(0xffff)), Some(2));
+        assert_eq!(low_bytes_mask_width(U256::from(0xfff)), None);
+        assert_eq!(
+            cleared_range(!(U256::from(0xffff) << 8usize)),
+            Some(PackedField {
+                offset: 1,
+                width: 2
+            })
+        );
+        assert_eq!(cleared_range(U256::from(0xff00ff)), None);
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::BTreeSet;
+
+use revm::primitives::U256;
+
+use crate::storage::{PackedField, SlotKind, StorageLayout, StorageVariable};
+
+/// A difference between the storage usage of two implementations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageChange {
+    /// A slot the new code uses as a different kind of variable than the old code did, e.g. a
+    /// plain value turned into a mapping base. Existing data would be misinterpreted.
+    Collision {
+        slot: U256,
+        old: SlotKind,
+        new: SlotKind,
+    },
+    /// The packed fields accessed within a slot changed.
+    PackingChanged {
+        slot: U256,
+        kind: SlotKind,
+        old: Vec<PackedField>,
+        new: Vec<PackedField>,
+        /// Set when a new field overlaps an old one with a different width or position;
+        /// fields added in unused bytes are safe.
+        conflicting: bool,
+    },
+    /// A slot the old code used that the new code never touches; its data is left behind.
+    Removed { slot: U256, kind: SlotKind },
+    /// A slot only the new code uses.
+    Added { slot: U256, kind: SlotKind },
+}
+
+impl StorageChange {
+    /// Whether the change can corrupt existing state after an upgrade.
+    pub fn is_breaking(&self) -> bool {
+        matches!(
+            self,
+            StorageChange::Collision { .. }
+                | StorageChange::PackingChanged {
+                    conflicting: true,
+                    ..
+                }
+        )
+    }
+
+    pub fn slot(&self) -> U256 {
+        match self {
+            StorageChange::Collision { slot, .. }
+            | StorageChange::PackingChanged { slot, .. }
+            | StorageChange::Removed { slot, .. }
+            | StorageChange::Added { slot, .. } => *slot,
+        }
+    }
+}
+
+/// Storage differences between an old and a new implementation, sorted by slot.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StorageDiff {
+    pub changes: Vec<StorageChange>,
+}
+
+impl StorageDiff {
+    /// Whether no change can corrupt existing state.
+    pub fn is_compatible(&self) -> bool {
+        !self.changes.iter().any(StorageChange::is_breaking)
+    }
+}
+
+/// Compares two inferred storage layouts for upgrade safety.
+///
+/// Variables are matched by base slot. A slot used as a different [`SlotKind`] in the new
+/// layout is a collision; the same kind with different packed fields is a packing change, which
+/// is breaking when a new field overlaps an old one. Slots present on only one side are reported
+/// as removed or added.
+///
+/// Layouts are inferred from bytecode, so a slot missing from one side may just be accessed in
+/// a way the inference does not recognise.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{StorageChange, diff_storage_layouts, infer_storage_layout};
+///
+/// // Old: SSTORE to slot 0. New: SSTORE to slot 1.
+/// let old = infer_storage_layout(&hex::decode("602a5f5500").unwrap());
+/// let new = infer_storage_layout(&hex::decode("602a60015500").unwrap());
+/// let diff = diff_storage_layouts(&old, &new);
+/// assert!(diff.is_compatible());
+/// assert_eq!(diff.changes.len(), 2);
+/// ```
+pub fn diff_storage_layouts(old: &StorageLayout, new: &StorageLayout) -> StorageDiff {
+    let slots: BTreeSet<U256> = old
+        .variables
+        .iter()
+        .chain(&new.variables)
+        .map(|variable| variable.slot)
+        .collect();
+
+    let mut changes = Vec::new();
+    for slot in slots {
+        let old_variables = variables_at(old, slot);
+        let new_variables = variables_at(new, slot);
+
+        for new_variable in &new_variables {
+            match old_variables
+                .iter()
+                .find(|old_variable| old_variable.kind == new_variable.kind)
+            {
+                Some(old_variable) if old_variable.fields != new_variable.fields => {
+                    changes.push(StorageChange::PackingChanged {
+                        slot,
+                        kind: new_variable.kind,
+                        old: old_variable.fields.clone(),
+                        new: new_variable.fields.clone(),
+                        conflicting: fields_conflict(&old_variable.fields, &new_variable.fields),
+                    });
+                }
+                Some(_) => {}
+                None => match old_variables.first() {
+                    Some(old_variable) => changes.push(StorageChange::Collision {
+                        slot,
+                        old: old_variable.kind,
+                        new: new_variable.kind,
+                    }),
+                    None => changes.push(StorageChange::Added {
+                        slot,
+                        kind: new_variable.kind,
+                    }),
+                },
+            }
+        }
+
+        // A slot taken over by another kind is already reported as a collision
+        if new_variables.is_empty() {
+            for old_variable in &old_variables {
+                changes.push(StorageChange::Removed {
+                    slot,
+                    kind: old_variable.kind,
+                });
+            }
+        }
+    }
+
+    StorageDiff { changes }
+}
+
+/// Whether a new field overlaps an old field it is not identical to. A slot accessed only as a
+/// whole counts as a single 32-byte field.
+fn fields_conflict(old: &[PackedField], new: &[PackedField]) -> bool {
+    const WHOLE_SLOT: [PackedField; 1] = [PackedField {
+        offset: 0,
+        width: 32,
+    }];
+    let old = if old.is_empty() { &WHOLE_SLOT[..] } else { old };
+    let new = if new.is_empty() { &WHOLE_SLOT[..] } else { new };
+
+    new.iter().any(|new_field| {
+        old.iter().any(|old_field| {
+            old_field != new_field
+                && old_field.offset < new_field.offset + new_field.width
+                && new_field.offset < old_field.offset + old_field.width
+        })
+    })
+}
+
+fn variables_at(layout: &StorageLayout, slot: U256) -> Vec<&StorageVariable> {
+    layout
+        .variables
+        .iter()
+        .filter(|variable| variable.slot == slot)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::infer_storage_layout;
+
+    fn diff(old: &str, new: &str) -> StorageDiff {
+        diff_storage_layouts(
+            &infer_storage_layout(&hex::decode(old).unwrap()),
+            &infer_storage_layout(&hex::decode(new).unwrap()),
+        )
+    }
+
+    /// `PUSH1 0x2a, PUSH1 0x01, SSTORE, STOP`: writes slot 1.
+    const WRITE_SLOT_1: &str = "602a60015500";
+    /// Writes a mapping based at slot 1 keyed by the first calldata word.
+    const WRITE_MAPPING_1: &str = "5f355f52600160205260405f20602a905500";
+
+    #[test]
+    fn test_identical_layouts() {
+        assert!(diff(WRITE_SLOT_1, WRITE_SLOT_1).changes.is_empty());
+    }
+
+    #[test]
+    fn test_collision() {
+        let diff = diff(WRITE_SLOT_1, WRITE_MAPPING_1);
+
+        assert_eq!(
+            diff.changes,
+            vec![StorageChange::Collision {
+                slot: U256::from(1),
+                old: SlotKind::Value,
+                new: SlotKind::Mapping
+            }]
+        );
+        assert!(!diff.is_compatible());
+    }
+
+    #[test]
+    fn test_packing_changed() {
+        // PUSH0, SLOAD, PUSH1 0xa0, SHR, PUSH1 <mask>, AND, STOP
+        let diff = diff("5f5460a01c60ff1600", "5f5460a01c61ffff1600");
+
+        assert_eq!(
+            diff.changes,
+            vec![StorageChange::PackingChanged {
+                slot: U256::ZERO,
+                kind: SlotKind::Value,
+                old: vec![PackedField {
+                    offset: 20,
+                    width: 1
+                }],
+                new: vec![PackedField {
+                    offset: 20,
+                    width: 2
+                }],
+                conflicting: true,
+            }]
+        );
+        assert!(!diff.is_compatible());
+    }
+
+    #[test]
+    fn test_field_added_in_unused_bytes() {
+        // Old reads byte 20 of slot 0; new also reads byte 21
+        let diff = diff("5f5460a01c60ff1600", "5f5460a01c60ff16505f5460a81c60ff1600");
+
+        assert!(matches!(
+            diff.changes[..],
+            [StorageChange::PackingChanged {
+                conflicting: false,
+                ..
+            }]
+        ));
+        assert!(diff.is_compatible());
+    }
+
+    #[test]
+    fn test_removed_and_added() {
+        // Old writes slot 1, new writes slot 0
+        let diff = diff(WRITE_SLOT_1, "602a5f5500");
+
+        assert_eq!(
+            diff.changes,
+            vec![
+                StorageChange::Added {
+                    slot: U256::ZERO,
+                    kind: SlotKind::Value
+                },
+                StorageChange::Removed {
+                    slot: U256::from(1),
+                    kind: SlotKind::Value
+                },
+            ]
+        );
+        assert!(diff.is_compatible());
+    }
+}
@@ -0,0 +1,910 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::rc::Rc;
+
+use revm::{bytecode::opcode, primitives::U256};
+
+use crate::cfg::fold_constants;
+use crate::instruction::{Instruction, decode_instructions};
+
+/// Largest `KECCAK256` input, in words, kept as a symbolic hash of its memory contents.
+const MAX_HASHED_WORDS: usize = 4;
+
+/// Bounds on symbolic exploration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolicLimits {
+    /// Instructions executed on one path before it is abandoned.
+    pub max_depth: usize,
+    /// Times one path may pass the same symbolic branch, i.e. how far loops are unrolled.
+    pub max_loop_iterations: usize,
+    /// Paths reported before exploration stops.
+    pub max_paths: usize,
+}
+
+impl SymbolicLimits {
+    pub fn new(max_depth: usize, max_loop_iterations: usize, max_paths: usize) -> Self {
+        Self {
+            max_depth,
+            max_loop_iterations,
+            max_paths,
+        }
+    }
+}
+
+impl Default for SymbolicLimits {
+    fn default() -> Self {
+        Self::new(10_000, 3, 256)
+    }
+}
+
+/// A symbolic value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Term {
+    Const(U256),
+    /// The calldata word at an offset.
+    CallData(Rc<Term>),
+    CallDataSize,
+    CallValue,
+    Caller,
+    /// A storage slot's value before the transaction.
+    Storage(Rc<Term>),
+    /// A stack slot below the entry point's stack; `0` is the top.
+    Input(usize),
+    /// An instruction applied to its operands (`args[0]` was the top of the stack). Used for
+    /// arithmetic, comparisons, block values and hashes of known memory.
+    Op(u8, Vec<Rc<Term>>),
+    /// A value the executor does not model, such as a call's success flag. Each one is
+    /// distinct.
+    Opaque {
+        op: u8,
+        id: usize,
+    },
+}
+
+impl Term {
+    pub fn as_constant(&self) -> Option<U256> {
+        match self {
+            Term::Const(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Whether the caller can choose the value through calldata.
+    pub fn depends_on_calldata(&self) -> bool {
+        match self {
+            Term::CallData(_) | Term::CallDataSize => true,
+            Term::Storage(slot) => slot.depends_on_calldata(),
+            Term::Op(_, args) => args.iter().any(|arg| arg.depends_on_calldata()),
+            Term::Const(_)
+            | Term::CallValue
+            | Term::Caller
+            | Term::Input(_)
+            | Term::Opaque { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Const(value) => write!(f, "{value:#x}"),
+            Term::CallData(offset) => write!(f, "calldata[{offset}]"),
+            Term::CallDataSize => f.write_str("msg.data.length"),
+            Term::CallValue => f.write_str("msg.value"),
+            Term::Caller => f.write_str("msg.sender"),
+            Term::Storage(slot) => write!(f, "storage[{slot}]"),
+            Term::Input(depth) => write!(f, "input{depth}"),
+            Term::Op(op, args) => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{}({})", mnemonic(*op), args.join(", "))
+            }
+            Term::Opaque { op, id } => write!(f, "{}_{id}", mnemonic(*op)),
+        }
+    }
+}
+
+fn mnemonic(op: u8) -> String {
+    match crate::OpCode::new(op) {
+        Some(op) => op.as_str().to_lowercase(),
+        None => format!("unknown({op:#04x})"),
+    }
+}
+
+/// A branch decision on a path: `term` is non-zero when `nonzero` is set, zero otherwise.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PathCondition {
+    /// Position of the `JUMPI`.
+    pub pc: usize,
+    pub term: Rc<Term>,
+    pub nonzero: bool,
+}
+
+impl PathCondition {
+    /// Builds a condition, unwrapping `ISZERO` so equivalent conditions compare equal.
+    pub fn new(pc: usize, mut term: Rc<Term>, mut nonzero: bool) -> Self {
+        while let Term::Op(opcode::ISZERO, args) = term.as_ref() {
+            term = args[0].clone();
+            nonzero = !nonzero;
+        }
+        PathCondition { pc, term, nonzero }
+    }
+}
+
+/// Written as `{"pc", "condition"}` with the condition in its [`fmt::Display`] form, e.g.
+/// `calldata[0x4] == 0x1`. Terms share subterms through [`Rc`], so conditions serialize but
+/// do not deserialize.
+#[cfg(feature = "serde")]
+impl serde::Serialize for PathCondition {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("PathCondition", 2)?;
+        state.serialize_field("pc", &self.pc)?;
+        state.serialize_field("condition", &self.to_string())?;
+        state.end()
+    }
+}
+
+impl fmt::Display for PathCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let infix = match self.term.as_ref() {
+            Term::Op(op, args) if args.len() == 2 => match *op {
+                opcode::EQ if self.nonzero => Some("=="),
+                opcode::EQ => Some("!="),
+                opcode::LT if self.nonzero => Some("<"),
+                opcode::LT => Some(">="),
+                opcode::GT if self.nonzero => Some(">"),
+                opcode::GT => Some("<="),
+                _ => None,
+            }
+            .map(|symbol| (symbol, &args[0], &args[1])),
+            _ => None,
+        };
+        match infix {
+            Some((symbol, lhs, rhs)) => write!(f, "{lhs} {symbol} {rhs}"),
+            None if self.nonzero => write!(f, "{} != 0", self.term),
+            None => write!(f, "{} == 0", self.term),
+        }
+    }
+}
+
+/// Whether a set of path conditions can hold together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Feasibility {
+    Feasible,
+    Infeasible,
+    /// The solver could not decide; the path is kept.
+    Unknown,
+}
+
+/// Decides whether path conditions can be satisfied.
+///
+/// [`ConstantFolding`] works without any dependency; with the `smt` feature,
+/// `smt::SmtSolver` hands the conditions to an external SMT solver.
+pub trait Solver {
+    fn check(&mut self, conditions: &[PathCondition]) -> Feasibility;
+}
+
+/// Refutes paths using only folded constants and directly contradicting conditions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConstantFolding;
+
+impl Solver for ConstantFolding {
+    fn check(&mut self, conditions: &[PathCondition]) -> Feasibility {
+        let mut required: HashMap<&Term, bool> = HashMap::new();
+        let mut all_constant = true;
+        for condition in conditions {
+            if let Some(value) = condition.term.as_constant() {
+                if value.is_zero() == condition.nonzero {
+                    return Feasibility::Infeasible;
+                }
+                continue;
+            }
+            all_constant = false;
+            if let Some(previous) = required.insert(condition.term.as_ref(), condition.nonzero)
+                && previous != condition.nonzero
+            {
+                return Feasibility::Infeasible;
+            }
+        }
+        if all_constant {
+            Feasibility::Feasible
+        } else {
+            Feasibility::Unknown
+        }
+    }
+}
+
+/// How a path ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PathEnd {
+    Stop,
+    Return,
+    Revert,
+    /// `INVALID`, an unknown opcode, or a jump to a position that is not a `JUMPDEST`.
+    Invalid,
+    SelfDestruct,
+    /// A jump to a computed target.
+    UnresolvedJump,
+    /// Abandoned after [`SymbolicLimits::max_depth`] instructions.
+    DepthLimit,
+    /// Abandoned after [`SymbolicLimits::max_loop_iterations`] passes of one branch.
+    LoopLimit,
+}
+
+impl PathEnd {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PathEnd::Stop => "stop",
+            PathEnd::Return => "return",
+            PathEnd::Revert => "revert",
+            PathEnd::Invalid => "invalid",
+            PathEnd::SelfDestruct => "selfdestruct",
+            PathEnd::UnresolvedJump => "unresolved jump",
+            PathEnd::DepthLimit => "depth limit",
+            PathEnd::LoopLimit => "loop limit",
+        }
+    }
+}
+
+impl fmt::Display for PathEnd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One explored path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SymbolicPath {
+    pub end: PathEnd,
+    /// Position of the instruction the path ended at.
+    pub end_pc: usize,
+    /// Branch decisions in the order they were taken.
+    pub conditions: Vec<PathCondition>,
+    /// Positions of calls on the path whose target address comes from calldata.
+    pub arbitrary_calls: Vec<usize>,
+    /// The solver's verdict on `conditions`.
+    pub feasibility: Feasibility,
+}
+
+/// The paths explored from an entry point.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Exploration {
+    pub paths: Vec<SymbolicPath>,
+    /// Set when [`SymbolicLimits::max_paths`] stopped exploration with paths left.
+    pub path_limit_reached: bool,
+}
+
+impl Exploration {
+    /// Number of paths that ended with `end`.
+    pub fn count(&self, end: PathEnd) -> usize {
+        self.paths.iter().filter(|path| path.end == end).count()
+    }
+
+    /// Paths that make at least one call to a calldata-chosen address.
+    pub fn with_arbitrary_calls(&self) -> impl Iterator<Item = &SymbolicPath> {
+        self.paths
+            .iter()
+            .filter(|path| !path.arbitrary_calls.is_empty())
+    }
+}
+
+/// Explores paths from `entry` with the [`ConstantFolding`] solver.
+///
+/// Calldata, call value, caller and initial storage are symbolic; memory is tracked for
+/// constant offsets. At a `JUMPI` on a symbolic condition the path forks, each side records a
+/// [`PathCondition`], and sides the solver refutes are dropped. Jumps must resolve to constant
+/// targets. Calls are not followed: their results are opaque, and a call whose target address
+/// depends on calldata is recorded as arbitrary.
+///
+/// Start at `0` for the whole contract, or at a [`crate::FunctionEntry`] to skip the
+/// dispatcher; stack slots read below the entry are [`Term::Input`]s.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{PathEnd, SymbolicLimits, explore_paths};
+///
+/// // CALLVALUE, PUSH1 0x05, JUMPI, STOP, JUMPDEST, PUSH0, DUP1, REVERT
+/// let bytes = hex::decode("34600557005b5f80fd").unwrap();
+/// let exploration = explore_paths(&bytes, 0, &SymbolicLimits::default());
+/// assert_eq!(exploration.count(PathEnd::Revert), 1);
+/// assert_eq!(exploration.count(PathEnd::Stop), 1);
+/// ```
+pub fn explore_paths(bytes: &[u8], entry: usize, limits: &SymbolicLimits) -> Exploration {
+    explore_paths_with(bytes, entry, limits, &mut ConstantFolding)
+}
+
+/// Explores paths from `entry`, checking branch feasibility with `solver`.
+///
+/// See [`explore_paths`].
+pub fn explore_paths_with(
+    bytes: &[u8],
+    entry: usize,
+    limits: &SymbolicLimits,
+    solver: &mut dyn Solver,
+) -> Exploration {
+    let instructions = decode_instructions(bytes);
+    let mut executor = Executor {
+        instructions: instructions
+            .iter()
+            .map(|instruction| (instruction.pc, *instruction))
+            .collect(),
+        limits,
+        next_opaque: 0,
+    };
+
+    let mut exploration = Exploration::default();
+    let mut pending = vec![State::new(entry)];
+    while let Some(state) = pending.pop() {
+        if exploration.paths.len() >= limits.max_paths {
+            exploration.path_limit_reached = true;
+            break;
+        }
+        match executor.run(state) {
+            Step::End(path) => exploration.paths.push(path),
+            Step::Fork(sides) => {
+                for mut side in sides {
+                    side.feasibility = solver.check(&side.conditions);
+                    if side.feasibility != Feasibility::Infeasible {
+                        pending.push(side);
+                    }
+                }
+            }
+        }
+    }
+    exploration
+}
+
+/// Execution state of one path.
+#[derive(Debug, Clone)]
+struct State {
+    pc: usize,
+    stack: Vec<Rc<Term>>,
+    /// Words written at constant offsets.
+    memory: BTreeMap<usize, Rc<Term>>,
+    /// Set once memory was written at an unknown position.
+    memory_clobbered: bool,
+    /// Slots written on the path, most recent last.
+    storage: Vec<(Rc<Term>, Rc<Term>)>,
+    conditions: Vec<PathCondition>,
+    depth: usize,
+    /// Passes of each symbolic `JUMPI`.
+    branch_visits: HashMap<usize, usize>,
+    arbitrary_calls: Vec<usize>,
+    next_input: usize,
+    feasibility: Feasibility,
+    /// Set on the taken side of a fork whose jump target is unusable.
+    ended: Option<PathEnd>,
+}
+
+impl State {
+    fn new(entry: usize) -> Self {
+        State {
+            pc: entry,
+            stack: Vec::new(),
+            memory: BTreeMap::new(),
+            memory_clobbered: false,
+            storage: Vec::new(),
+            conditions: Vec::new(),
+            depth: 0,
+            branch_visits: HashMap::new(),
+            arbitrary_calls: Vec::new(),
+            next_input: 0,
+            feasibility: Feasibility::Feasible,
+            ended: None,
+        }
+    }
+
+    fn finish(self, end: PathEnd, end_pc: usize) -> SymbolicPath {
+        SymbolicPath {
+            end,
+            end_pc,
+            conditions: self.conditions,
+            arbitrary_calls: self.arbitrary_calls,
+            feasibility: self.feasibility,
+        }
+    }
+
+    fn pop(&mut self) -> Rc<Term> {
+        match self.stack.pop() {
+            Some(term) => term,
+            None => {
+                let input = Term::Input(self.next_input);
+                self.next_input += 1;
+                Rc::new(input)
+            }
+        }
+    }
+
+    /// Makes sure the stack is at least `depth` deep, filling from below with inputs.
+    ///
+    /// The inputs are numbered as [`State::pop`] would draw them: the slot right below the
+    /// current bottom is the next input, the one below that the input after it.
+    fn reserve(&mut self, depth: usize) {
+        while self.stack.len() < depth {
+            self.stack.insert(0, Rc::new(Term::Input(self.next_input)));
+            self.next_input += 1;
+        }
+    }
+
+    fn load(&self, slot: &Rc<Term>) -> Option<Rc<Term>> {
+        for (written, value) in self.storage.iter().rev() {
+            if written == slot {
+                return Some(value.clone());
+            }
+            let distinct = written.as_constant().is_some() && slot.as_constant().is_some();
+            if !distinct {
+                // The slots may alias
+                return None;
+            }
+        }
+        Some(Rc::new(Term::Storage(slot.clone())))
+    }
+
+    fn mload(&self, offset: &Term) -> Option<Rc<Term>> {
+        let offset = usize::try_from(offset.as_constant()?).ok()?;
+        if let Some(value) = self.memory.get(&offset) {
+            return Some(value.clone());
+        }
+        let overlaps = self
+            .memory
+            .range(offset.saturating_sub(31)..offset.saturating_add(32))
+            .next()
+            .is_some();
+        (!overlaps && !self.memory_clobbered).then(|| Rc::new(Term::Const(U256::ZERO)))
+    }
+
+    fn mstore(&mut self, offset: &Term, value: Rc<Term>) {
+        match offset
+            .as_constant()
+            .and_then(|offset| usize::try_from(offset).ok())
+        {
+            Some(offset) => {
+                let overlapping: Vec<usize> = self
+                    .memory
+                    .range(offset.saturating_sub(31)..offset.saturating_add(32))
+                    .map(|(known, _)| *known)
+                    .collect();
+                for known in overlapping {
+                    self.memory.remove(&known);
+                }
+                self.memory.insert(offset, value);
+            }
+            None => self.clobber_memory(),
+        }
+    }
+
+    fn clobber_memory(&mut self) {
+        self.memory.clear();
+        self.memory_clobbered = true;
+    }
+
+    fn keccak(&self, offset: &Term, size: &Term) -> Option<Rc<Term>> {
+        let offset = usize::try_from(offset.as_constant()?).ok()?;
+        let size = usize::try_from(size.as_constant()?).ok()?;
+        if size == 0 || !size.is_multiple_of(32) || size / 32 > MAX_HASHED_WORDS {
+            return None;
+        }
+        let words = (0..size / 32)
+            .map(|word| {
+                let address = word.checked_mul(32).and_then(|w| offset.checked_add(w))?;
+                self.mload(&Term::Const(U256::from(address)))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Rc::new(Term::Op(opcode::KECCAK256, words)))
+    }
+}
+
+enum Step {
+    End(SymbolicPath),
+    Fork(Vec<State>),
+}
+
+struct Executor<'a, 'l> {
+    instructions: HashMap<usize, Instruction<'a>>,
+    limits: &'l SymbolicLimits,
+    next_opaque: usize,
+}
+
+impl Executor<'_, '_> {
+    /// Runs a path until it ends or forks.
+    fn run(&mut self, mut state: State) -> Step {
+        if let Some(end) = state.ended.take() {
+            let pc = state.pc;
+            return Step::End(state.finish(end, pc));
+        }
+        loop {
+            let pc = state.pc;
+            let Some(instruction) = self.instructions.get(&pc).copied() else {
+                // Running off the end of the code stops execution
+                return Step::End(state.finish(PathEnd::Stop, pc));
+            };
+            state.depth += 1;
+            if state.depth > self.limits.max_depth {
+                return Step::End(state.finish(PathEnd::DepthLimit, pc));
+            }
+
+            let op = instruction.opcode;
+            let end = match op {
+                opcode::STOP => Some(PathEnd::Stop),
+                opcode::RETURN => Some(PathEnd::Return),
+                opcode::REVERT => Some(PathEnd::Revert),
+                opcode::SELFDESTRUCT => Some(PathEnd::SelfDestruct),
+                _ if instruction.op().is_none() || op == opcode::INVALID => Some(PathEnd::Invalid),
+                _ => None,
+            };
+            if let Some(end) = end {
+                return Step::End(state.finish(end, pc));
+            }
+
+            match op {
+                opcode::JUMP => {
+                    let target = state.pop();
+                    match self.jump_target(&target) {
+                        Ok(target) => state.pc = target,
+                        Err(end) => return Step::End(state.finish(end, pc)),
+                    }
+                }
+                opcode::JUMPI => {
+                    let target = state.pop();
+                    let condition = state.pop();
+                    let fall = instruction.next_pc();
+                    if let Some(value) = condition.as_constant() {
+                        if value.is_zero() {
+                            state.pc = fall;
+                            continue;
+                        }
+                        match self.jump_target(&target) {
+                            Ok(target) => state.pc = target,
+                            Err(end) => return Step::End(state.finish(end, pc)),
+                        }
+                        continue;
+                    }
+
+                    let visits = state.branch_visits.entry(pc).or_default();
+                    *visits += 1;
+                    if *visits > self.limits.max_loop_iterations {
+                        return Step::End(state.finish(PathEnd::LoopLimit, pc));
+                    }
+
+                    let mut not_taken = state.clone();
+                    not_taken
+                        .conditions
+                        .push(PathCondition::new(pc, condition.clone(), false));
+                    not_taken.pc = fall;
+
+                    state
+                        .conditions
+                        .push(PathCondition::new(pc, condition, true));
+                    match self.jump_target(&target) {
+                        Ok(target) => state.pc = target,
+                        // The taken side ends at the jump once the solver has checked it
+                        Err(end) => state.ended = Some(end),
+                    }
+                    return Step::Fork(vec![not_taken, state]);
+                }
+                _ => {
+                    self.step(&instruction, &mut state);
+                    state.pc = instruction.next_pc();
+                }
+            }
+        }
+    }
+
+    fn jump_target(&self, target: &Term) -> Result<usize, PathEnd> {
+        let Some(target) = target.as_constant() else {
+            return Err(PathEnd::UnresolvedJump);
+        };
+        usize::try_from(target)
+            .ok()
+            .filter(|target| {
+                self.instructions
+                    .get(target)
+                    .is_some_and(|instruction| instruction.opcode == opcode::JUMPDEST)
+            })
+            .ok_or(PathEnd::Invalid)
+    }
+
+    /// Applies an instruction that neither jumps nor halts.
+    fn step(&mut self, instruction: &Instruction, state: &mut State) {
+        let op = instruction.opcode;
+        match op {
+            opcode::PUSH0..=opcode::PUSH32 => {
+                let value = instruction.push_value().unwrap_or_default();
+                state.stack.push(Rc::new(Term::Const(value)));
+                return;
+            }
+            opcode::DUP1..=opcode::DUP16 => {
+                let depth = (op - opcode::DUP1 + 1) as usize;
+                state.reserve(depth);
+                let value = state.stack[state.stack.len() - depth].clone();
+                state.stack.push(value);
+                return;
+            }
+            opcode::SWAP1..=opcode::SWAP16 => {
+                let depth = (op - opcode::SWAP1 + 2) as usize;
+                state.reserve(depth);
+                let top = state.stack.len() - 1;
+                state.stack.swap(top, top + 1 - depth);
+                return;
+            }
+            opcode::JUMPDEST => return,
+            opcode::PC => {
+                state
+                    .stack
+                    .push(Rc::new(Term::Const(U256::from(instruction.pc))));
+                return;
+            }
+            _ => {}
+        }
+
+        let info = instruction.op().expect("unknown opcodes end the path");
+        let operands: Vec<Rc<Term>> = (0..info.inputs()).map(|_| state.pop()).collect();
+
+        let output = match op {
+            opcode::CALLDATALOAD => Some(Rc::new(Term::CallData(operands[0].clone()))),
+            opcode::CALLDATASIZE => Some(Rc::new(Term::CallDataSize)),
+            opcode::CALLVALUE => Some(Rc::new(Term::CallValue)),
+            opcode::CALLER => Some(Rc::new(Term::Caller)),
+            opcode::SLOAD => Some(state.load(&operands[0]).unwrap_or_else(|| self.opaque(op))),
+            opcode::SSTORE => {
+                let slot = operands[0].clone();
+                state.storage.retain(|(written, _)| *written != slot);
+                state.storage.push((slot, operands[1].clone()));
+                None
+            }
+            opcode::MLOAD => Some(state.mload(&operands[0]).unwrap_or_else(|| self.opaque(op))),
+            opcode::MSTORE => {
+                state.mstore(&operands[0], operands[1].clone());
+                None
+            }
+            opcode::KECCAK256 => Some(
+                state
+                    .keccak(&operands[0], &operands[1])
+                    .unwrap_or_else(|| self.opaque(op)),
+            ),
+            opcode::CALL | opcode::CALLCODE | opcode::DELEGATECALL | opcode::STATICCALL => {
+                if operands[1].depends_on_calldata() {
+                    state.arbitrary_calls.push(instruction.pc);
+                }
+                state.clobber_memory();
+                Some(self.opaque(op))
+            }
+            _ if is_deterministic(op) => {
+                let constants = operands
+                    .iter()
+                    .map(|operand| operand.as_constant())
+                    .collect::<Option<Vec<_>>>();
+                let folded = constants.and_then(|constants| fold_constants(op, &constants));
+                Some(Rc::new(match folded {
+                    Some(value) => Term::Const(value),
+                    None => Term::Op(op, operands),
+                }))
+            }
+            _ => {
+                if info.modifies_memory() {
+                    state.clobber_memory();
+                }
+                (info.outputs() > 0).then(|| self.opaque(op))
+            }
+        };
+
+        if let Some(output) = output {
+            state.stack.push(output);
+        }
+    }
+
+    fn opaque(&mut self, op: u8) -> Rc<Term> {
+        self.next_opaque += 1;
+        Rc::new(Term::Opaque {
+            op,
+            id: self.next_opaque,
+        })
+    }
+}
+
+/// Pure arithmetic and values fixed for the whole transaction.
+fn is_deterministic(op: u8) -> bool {
+    matches!(
+        op,
+        opcode::ADD..=opcode::SIGNEXTEND
+            | opcode::LT..=opcode::SAR
+            | opcode::ADDRESS
+            | opcode::ORIGIN
+            | opcode::GASPRICE
+            | opcode::CODESIZE
+            | opcode::BLOCKHASH..=opcode::CHAINID
+            | opcode::BASEFEE
+            | opcode::BLOBHASH
+            | opcode::BLOBBASEFEE
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explore(code: &str) -> Exploration {
+        explore_paths(&hex::decode(code).unwrap(), 0, &SymbolicLimits::default())
+    }
+
+    #[test]
+    fn test_contradicting_branch_is_pruned() {
+        // CALLVALUE, ISZERO, PUSH1 0x08, JUMPI, PUSH0, DUP1, REVERT,
+        // JUMPDEST, CALLVALUE, PUSH1 0x0e, JUMPI, STOP, JUMPDEST, CALLER, SELFDESTRUCT
+        let exploration = explore("34156008575f80fd5b34600e57005b33ff");
+        assert_eq!(exploration.paths.len(), 2);
+        assert_eq!(exploration.count(PathEnd::Revert), 1);
+        assert_eq!(exploration.count(PathEnd::Stop), 1);
+        assert_eq!(exploration.count(PathEnd::SelfDestruct), 0);
+
+        let stop = exploration
+            .paths
+            .iter()
+            .find(|path| path.end == PathEnd::Stop)
+            .unwrap();
+        let conditions: Vec<String> = stop.conditions.iter().map(|c| c.to_string()).collect();
+        assert_eq!(conditions, ["msg.value == 0", "msg.value == 0"]);
+        assert_eq!(stop.end_pc, 0x0d);
+    }
+
+    /// Conditions of the path ending in `STOP` at `end_pc`.
+    fn conditions_at(code: &str, end_pc: usize) -> Vec<String> {
+        let exploration = explore(code);
+        let path = exploration
+            .paths
+            .iter()
+            .find(|path| path.end_pc == end_pc)
+            .unwrap();
+        path.conditions.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn test_swap_on_short_stack_draws_next_input() {
+        // SWAP1, PUSH1 0x05, JUMPI, STOP, JUMPDEST, STOP: the condition is the second word
+        assert_eq!(conditions_at("90600557005b00", 6), ["input1 != 0"]);
+    }
+
+    #[test]
+    fn test_dup_on_short_stack_draws_next_input() {
+        // DUP2, PUSH1 0x05, JUMPI, STOP, JUMPDEST, STOP
+        assert_eq!(conditions_at("81600557005b00", 6), ["input1 != 0"]);
+    }
+
+    #[test]
+    fn test_inputs_drawn_before_dup_are_not_reused() {
+        // POP x3 (inputs 0-2), DUP1 (input 3), POP x2, PUSH1 0x0a, JUMPI (input 4), STOP,
+        // JUMPDEST, STOP
+        assert_eq!(
+            conditions_at("505050805050600a57005b00", 11),
+            ["input4 != 0"]
+        );
+    }
+
+    #[test]
+    fn test_arbitrary_call() {
+        // PUSH0 x5, PUSH1 0x04, CALLDATALOAD, GAS, CALL, STOP
+        let exploration = explore("5f5f5f5f5f6004355af100");
+        assert_eq!(exploration.paths.len(), 1);
+        assert_eq!(exploration.paths[0].arbitrary_calls, [9]);
+        assert_eq!(exploration.with_arbitrary_calls().count(), 1);
+
+        // The same call to a constant address
+        let exploration = explore("5f5f5f5f5f60015af100");
+        assert!(exploration.paths[0].arbitrary_calls.is_empty());
+    }
+
+    #[test]
+    fn test_loop_unrolling_limit() {
+        // JUMPDEST, PUSH0, SLOAD, DUP1, PUSH1 0x01, ADD, PUSH0, SSTORE, PUSH1 0x00, JUMPI, STOP
+        // loops while the counter in slot 0, incremented on each pass, is non-zero
+        let code = hex::decode("5b5f54806001015f5560005700").unwrap();
+        let exploration = explore_paths(&code, 0, &SymbolicLimits::default());
+        assert_eq!(exploration.count(PathEnd::Stop), 3);
+        assert_eq!(exploration.count(PathEnd::LoopLimit), 1);
+
+        let limits = SymbolicLimits::new(10_000, 1, 256);
+        let exploration = explore_paths(&code, 0, &limits);
+        assert_eq!(exploration.count(PathEnd::Stop), 1);
+        assert_eq!(exploration.count(PathEnd::LoopLimit), 1);
+
+        // JUMPDEST, CALLDATASIZE, PUSH1 0x00, JUMPI, STOP: leaving the loop after passing
+        // the same condition contradicts it
+        let exploration = explore("5b3660005700");
+        assert_eq!(exploration.count(PathEnd::Stop), 1);
+        assert_eq!(exploration.count(PathEnd::LoopLimit), 1);
+    }
+
+    #[test]
+    fn test_storage_write_is_read_back() {
+        // PUSH1 0x01, PUSH0, SSTORE, PUSH0, SLOAD, PUSH1 0x0a, JUMPI, STOP,
+        // JUMPDEST, PUSH0, DUP1, REVERT
+        let exploration = explore("60015f555f54600a57005b5f80fd");
+        assert_eq!(exploration.paths.len(), 1);
+        assert_eq!(exploration.paths[0].end, PathEnd::Revert);
+        assert!(exploration.paths[0].conditions.is_empty());
+        assert_eq!(exploration.paths[0].feasibility, Feasibility::Feasible);
+
+        // Without the write the initial value is symbolic
+        let exploration = explore("5f54600657005b5f80fd");
+        assert_eq!(exploration.paths.len(), 2);
+        let revert = &exploration.paths[0];
+        assert_eq!(revert.end, PathEnd::Revert);
+        assert_eq!(revert.conditions[0].to_string(), "storage[0x0] != 0");
+        assert_eq!(revert.feasibility, Feasibility::Unknown);
+    }
+
+    #[test]
+    fn test_memory_and_hash_terms() {
+        // CALLER, PUSH0, MSTORE, PUSH1 0x20, PUSH0, KECCAK256, SLOAD, PUSH1 0x0c, JUMPI, STOP,
+        // JUMPDEST, STOP
+        let exploration = explore("335f5260205f2054600c57005b00");
+        let taken = exploration
+            .paths
+            .iter()
+            .find(|path| path.end_pc == 0x0d)
+            .unwrap();
+        assert_eq!(
+            taken.conditions[0].to_string(),
+            "storage[keccak256(msg.sender)] != 0"
+        );
+    }
+
+    #[test]
+    fn test_hash_past_address_space_is_opaque() {
+        // PUSH1 0x40, PUSH8 0xffffffffffffffff, KECCAK256, STOP
+        let exploration = explore("604067ffffffffffffffff2000");
+        assert_eq!(exploration.paths.len(), 1);
+        assert_eq!(exploration.paths[0].end, PathEnd::Stop);
+        assert_eq!(exploration.paths[0].end_pc, 0x0c);
+    }
+
+    #[test]
+    fn test_unresolved_jump_and_path_limit() {
+        // PUSH0, CALLDATALOAD, JUMP
+        let exploration = explore("5f3556");
+        assert_eq!(exploration.paths[0].end, PathEnd::UnresolvedJump);
+        assert_eq!(exploration.paths[0].end_pc, 2);
+
+        // CALLVALUE, PUSH1 0x05, JUMPI, STOP, JUMPDEST, STOP
+        let limits = SymbolicLimits::new(10_000, 3, 1);
+        let exploration = explore_paths(&hex::decode("34600557005b00").unwrap(), 0, &limits);
+        assert_eq!(exploration.paths.len(), 1);
+        assert!(exploration.path_limit_reached);
+    }
+
+    #[test]
+    fn test_condition_display() {
+        let selector = Rc::new(Term::Op(
+            opcode::SHR,
+            vec![
+                Rc::new(Term::Const(U256::from(0xe0))),
+                Rc::new(Term::CallData(Rc::new(Term::Const(U256::ZERO)))),
+            ],
+        ));
+        let matches = Rc::new(Term::Op(
+            opcode::EQ,
+            vec![Rc::new(Term::Const(U256::from(0xaabbccddu32))), selector],
+        ));
+        let negated = Rc::new(Term::Op(opcode::ISZERO, vec![matches.clone()]));
+
+        let condition = PathCondition::new(0, negated, false);
+        assert_eq!(condition.term, matches);
+        assert!(condition.nonzero);
+        assert_eq!(
+            condition.to_string(),
+            "0xaabbccdd == shr(0xe0, calldata[0x0])"
+        );
+        assert_eq!(
+            PathCondition::new(0, matches, false).to_string(),
+            "0xaabbccdd != shr(0xe0, calldata[0x0])"
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use revm::{
+    Context, ExecuteEvm, InspectEvm, Inspector, MainBuilder, MainContext,
+    bytecode::opcode,
+    context::{ContextTr, JournalTr},
+    inspector::inspectors::GasInspector,
+    interpreter::{
+        CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter,
+        interpreter::EthInterpreter,
+        interpreter_types::{InputsTr, Jumps, LoopControl, MemoryTr, ReturnData},
+    },
+    primitives::{Address, B256, Bytes, U256},
+};
+
+use crate::execute::{
+    CallParams, Execution, ExecutionError, database, execution_from, transaction,
+};
+
+/// One executed instruction, recorded before it ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    pub pc: usize,
+    pub opcode: u8,
+    /// Call depth, starting at 1 for the transaction's own frame.
+    pub depth: usize,
+    /// Account whose storage the frame reads and writes; the caller's for `DELEGATECALL`.
+    pub address: Address,
+    pub gas_remaining: u64,
+    /// Gas charged for the instruction, including gas forwarded to calls.
+    pub gas_cost: u64,
+    /// Gas refund accumulated in the frame.
+    pub refund: i64,
+    /// The stack, bottom first.
+    pub stack: Vec<U256>,
+    pub memory_size: usize,
+    /// Memory contents; only recorded when [`TraceOptions::memory`] is set.
+    pub memory: Option<Vec<u8>>,
+    /// 32-byte memory words the instruction changed, as (offset, new value); only recorded
+    /// when [`TraceOptions::memory`] is set.
+    pub memory_writes: Vec<(usize, B256)>,
+    /// Slot and value stored by an `SSTORE`.
+    pub storage_write: Option<(U256, U256)>,
+    /// Return data of the last call the frame made, empty before the first.
+    pub return_data: Bytes,
+    /// Why the instruction failed its frame, as named by revm (e.g. `Revert`, `OutOfGas`).
+    pub error: Option<String>,
+}
+
+impl TraceStep {
+    /// Top `count` stack items, top first.
+    pub fn stack_top(&self, count: usize) -> impl Iterator<Item = &U256> {
+        self.stack.iter().rev().take(count)
+    }
+}
+
+/// What a trace records beyond the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TraceOptions {
+    /// Record memory contents and memory changes per step. Costly for long traces.
+    pub memory: bool,
+}
+
+impl TraceOptions {
+    pub fn new(memory: bool) -> Self {
+        Self { memory }
+    }
+}
+
+/// A step-by-step record of an execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub steps: Vec<TraceStep>,
+    pub execution: Execution,
+}
+
+/// Executes like [`crate::execute()`] and records every step, including steps in called
+/// contracts.
+///
+/// Every step is kept, with its stack and, when [`TraceOptions::memory`] is set, its memory, so
+/// long executions take a lot of memory. Use [`trace_with`] to handle steps one at a time.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{CallParams, TraceOptions, trace};
+///
+/// // PUSH1 0x01, PUSH1 0x02, ADD, STOP
+/// let bytes = hex::decode("600160020100").unwrap();
+/// let trace = trace(&bytes, &CallParams::default(), &TraceOptions::default()).unwrap();
+/// assert_eq!(trace.steps.len(), 4);
+/// assert_eq!(trace.steps[3].stack, [revm::primitives::U256::from(3)]);
+/// ```
+pub fn trace(
+    bytes: &[u8],
+    params: &CallParams,
+    options: &TraceOptions,
+) -> Result<Trace, ExecutionError> {
+    let mut steps = Vec::new();
+    let execution = trace_with(bytes, params, options, |step| steps.push(step))?;
+    Ok(Trace { steps, execution })
+}
+
+/// Executes like [`trace`], handing each step to `on_step` once it has run instead of keeping
+/// them all.
+///
+/// # Example
+///
+/// ```
+/// use evm_lens_core::{CallParams, TraceOptions, trace_with};
+///
+/// // JUMPDEST, PUSH1 0x00, JUMP: loops until the gas runs out
+/// let bytes = hex::decode("5b600056").unwrap();
+/// let params = CallParams {
+///     gas_limit: 100_000,
+///     ..CallParams::default()
+/// };
+/// let mut steps = 0;
+/// let execution =
+///     trace_with(&bytes, &params, &TraceOptions::default(), |_| steps += 1).unwrap();
+/// assert!(steps > 1000);
+/// assert!(!execution.is_success());
+/// ```
+pub fn trace_with(
+    bytes: &[u8],
+    params: &CallParams,
+    options: &TraceOptions,
+    on_step: impl FnMut(TraceStep),
+) -> Result<Execution, ExecutionError> {
+    let mut evm = Context::mainnet()
+        .with_db(database(bytes, params))
+        .build_mainnet_with_inspector(Tracer::new(*options, on_step));
+    let result = evm.inspect_tx(transaction(params))?;
+    let state = evm.finalize();
+    evm.inspector.flush();
+    Ok(execution_from(result, &state))
+}
+
+struct Tracer<F> {
+    options: TraceOptions,
+    gas: GasInspector,
+    /// The step being executed, handed on once its cost is known.
+    pending: Option<TraceStep>,
+    on_step: F,
+}
+
+impl<F: FnMut(TraceStep)> Tracer<F> {
+    fn new(options: TraceOptions, on_step: F) -> Self {
+        Tracer {
+            options,
+            gas: GasInspector::new(),
+            pending: None,
+            on_step,
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(step) = self.pending.take() {
+            (self.on_step)(step);
+        }
+    }
+}
+
+impl<CTX: ContextTr, F: FnMut(TraceStep)> Inspector<CTX, EthInterpreter> for Tracer<F> {
+    fn initialize_interp(&mut self, interp: &mut Interpreter<EthInterpreter>, _: &mut CTX) {
+        self.gas.initialize_interp(&interp.gas);
+    }
+
+    fn step(&mut self, interp: &mut Interpreter<EthInterpreter>, context: &mut CTX) {
+        self.flush();
+        self.gas.step(&interp.gas);
+        let opcode = interp.bytecode.opcode();
+        let stack = interp.stack.data().clone();
+        let storage_write = (opcode == opcode::SSTORE && stack.len() >= 2)
+            .then(|| (stack[stack.len() - 1], stack[stack.len() - 2]));
+        let memory = self
+            .options
+            .memory
+            .then(|| interp.memory.slice(0..interp.memory.size()).to_vec());
+
+        self.pending = Some(TraceStep {
+            pc: interp.bytecode.pc(),
+            opcode,
+            depth: context.journal().depth(),
+            address: interp.input.target_address(),
+            gas_remaining: interp.gas.remaining(),
+            gas_cost: 0,
+            refund: interp.gas.refunded(),
+            stack,
+            memory_size: interp.memory.size(),
+            memory,
+            memory_writes: Vec::new(),
+            storage_write,
+            return_data: interp.return_data.buffer().clone(),
+            error: None,
+        });
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter<EthInterpreter>, _: &mut CTX) {
+        self.gas.step_end(&mut interp.gas);
+        let memory_after = self
+            .options
+            .memory
+            .then(|| interp.memory.slice(0..interp.memory.size()).to_vec());
+        let Some(step) = self.pending.as_mut() else {
+            return;
+        };
+        step.gas_cost = self.gas.last_gas_cost();
+        step.error = interp
+            .bytecode
+            .action()
+            .as_ref()
+            .and_then(|action| action.instruction_result())
+            .filter(|result| !result.is_ok())
+            .map(|result| format!("{result:?}"));
+        if let (Some(before), Some(after)) = (&step.memory, memory_after) {
+            step.memory_writes = changed_words(before, &after);
+        }
+        self.flush();
+    }
+
+    fn call_end(&mut self, _: &mut CTX, _: &CallInputs, outcome: &mut CallOutcome) {
+        self.gas.call_end(outcome);
+    }
+
+    fn create_end(&mut self, _: &mut CTX, _: &CreateInputs, outcome: &mut CreateOutcome) {
+        self.gas.create_end(outcome);
+    }
+}
+
+/// Words of `after` that differ from `before`, which may be shorter.
+fn changed_words(before: &[u8], after: &[u8]) -> Vec<(usize, B256)> {
+    after
+        .chunks(32)
+        .enumerate()
+        .filter_map(|(index, word)| {
+            let offset = index * 32;
+            let old = before.get(offset..offset + 32).unwrap_or(&[0; 32]);
+            (old != word).then(|| (offset, B256::from_slice(word)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ExitStatus;
+
+    fn run(code: &str, options: TraceOptions) -> Trace {
+        trace(
+            &hex::decode(code).unwrap(),
+            &CallParams::default(),
+            &options,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_steps_and_gas() {
+        // PUSH1 0x01, PUSH1 0x02, ADD, STOP
+        let trace = run("600160020100", TraceOptions::default());
+        let pcs: Vec<usize> = trace.steps.iter().map(|step| step.pc).collect();
+        assert_eq!(pcs, [0, 2, 4, 5]);
+        assert_eq!(trace.steps[0].gas_cost, 3);
+        assert_eq!(trace.steps[2].gas_cost, 3);
+        assert_eq!(
+            trace.steps[1].gas_remaining,
+            trace.steps[0].gas_remaining - 3
+        );
+        assert_eq!(trace.steps[2].stack, [U256::from(1), U256::from(2)]);
+        assert_eq!(
+            trace.steps[2].stack_top(1).collect::<Vec<_>>(),
+            [&U256::from(2)]
+        );
+        assert!(trace.steps.iter().all(|step| step.depth == 1));
+        assert!(trace.steps.iter().all(|step| step.error.is_none()));
+        assert_eq!(trace.execution.status, ExitStatus::Success);
+    }
+
+    #[test]
+    fn test_memory_and_storage_writes() {
+        // PUSH1 0x2a, PUSH1 0x20, MSTORE, PUSH1 0x07, PUSH1 0x01, SSTORE, STOP
+        let trace = run("602a60205260076001555f00", TraceOptions::new(true));
+        let mstore = &trace.steps[2];
+        assert_eq!(mstore.memory.as_deref(), Some(&[][..]));
+        assert_eq!(mstore.memory_writes, [(0x20, B256::from(U256::from(0x2a)))]);
+        let sstore = &trace.steps[5];
+        assert_eq!(sstore.storage_write, Some((U256::from(1), U256::from(7))));
+        assert_eq!(sstore.address, crate::CONTRACT_ADDRESS);
+        assert_eq!(sstore.memory_size, 64);
+
+        // Memory is left out unless asked for
+        let trace = run("602a60205260076001555f00", TraceOptions::default());
+        assert!(trace.steps.iter().all(|step| step.memory.is_none()));
+        assert!(trace.steps[2].memory_writes.is_empty());
+    }
+
+    #[test]
+    fn test_return_data_of_last_call() {
+        // PUSH0 x4, PUSH1 0x04 (identity precompile), GAS, STATICCALL, POP, PUSH0 x4,
+        // PUSH1 0x20, PUSH0, PUSH1 0x04, GAS, STATICCALL, STOP
+        let trace = run(
+            "5f5f5f5f60045afa505f5f5f5f60205f60045afa00",
+            TraceOptions::default(),
+        );
+        assert!(trace.steps.iter().all(|step| step.depth == 1));
+        let stop = trace.steps.last().unwrap();
+        assert_eq!(stop.return_data.len(), 32);
+        assert!(
+            trace.steps[..8]
+                .iter()
+                .all(|step| step.return_data.is_empty())
+        );
+    }
+
+    #[test]
+    fn test_steps_are_streamed_in_order() {
+        let mut pcs = Vec::new();
+        let execution = trace_with(
+            &hex::decode("600160020100").unwrap(),
+            &CallParams::default(),
+            &TraceOptions::default(),
+            |step| pcs.push((step.pc, step.gas_cost)),
+        )
+        .unwrap();
+        assert_eq!(pcs, [(0, 3), (2, 3), (4, 3), (5, 0)]);
+        assert!(execution.is_success());
+    }
+
+    #[test]
+    fn test_halting_step() {
+        // PUSH1 0x05, JUMP
+        let trace = run("600556", TraceOptions::default());
+        assert_eq!(trace.steps.len(), 2);
+        assert_eq!(trace.steps[0].error, None);
+        assert_eq!(trace.steps[1].error.as_deref(), Some("InvalidJump"));
+        assert!(matches!(trace.execution.status, ExitStatus::Halt(_)));
+    }
+}
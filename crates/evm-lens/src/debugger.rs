@@ -0,0 +1,719 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use colored::Colorize;
+use evm_lens_core::{Address, ExitStatus, OpCode, Trace, TraceStep, U256, decode_instructions};
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph, Wrap},
+};
+
+/// Bytes per memory hexdump row.
+const MEMORY_ROW: usize = 32;
+
+/// Where `continue` stops: at a position, or at every occurrence of an opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    Pc(usize),
+    Opcode(u8),
+}
+
+impl Breakpoint {
+    fn matches(&self, step: &TraceStep) -> bool {
+        match self {
+            Breakpoint::Pc(pc) => step.pc == *pc,
+            Breakpoint::Opcode(opcode) => step.opcode == *opcode,
+        }
+    }
+}
+
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    /// Parses an opcode name such as `SSTORE`, or a position in hex such as `0x1a`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let opcode = (0..=u8::MAX)
+            .find(|&byte| OpCode::new(byte).is_some_and(|op| op.as_str().eq_ignore_ascii_case(s)));
+        if let Some(opcode) = opcode {
+            return Ok(Breakpoint::Opcode(opcode));
+        }
+        let digits = s.strip_prefix("0x").unwrap_or(s);
+        usize::from_str_radix(digits, 16)
+            .map(Breakpoint::Pc)
+            .map_err(|_| format!("'{}' is neither an opcode nor a hex position", s))
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Pc(pc) => write!(f, "{:04x}", pc),
+            Breakpoint::Opcode(opcode) => match OpCode::new(*opcode) {
+                Some(op) => f.write_str(op.as_str()),
+                None => write!(f, "0x{:02x}", opcode),
+            },
+        }
+    }
+}
+
+/// A code pane row.
+struct CodeLine {
+    pc: usize,
+    name: String,
+    immediate: Option<String>,
+}
+
+/// Browses a recorded trace: stepping back is moving to an earlier step.
+///
+/// Steps hold memory changes rather than memory contents; the memory pane replays them up to
+/// the current step.
+pub struct Debugger {
+    code: Vec<CodeLine>,
+    trace: Trace,
+    /// Whether recording stopped before the execution ended.
+    truncated: bool,
+    position: usize,
+    breakpoints: Vec<Breakpoint>,
+    /// Breakpoint being typed, while the prompt is open.
+    prompt: Option<String>,
+    message: Option<String>,
+}
+
+impl Debugger {
+    /// `trace` must have at least one step. `truncated` tells that it holds only the first
+    /// steps of the execution.
+    pub fn new(bytes: &[u8], trace: Trace, truncated: bool, breakpoints: Vec<Breakpoint>) -> Self {
+        let code = decode_instructions(bytes)
+            .iter()
+            .map(|instruction| CodeLine {
+                pc: instruction.pc,
+                name: instruction.name(),
+                immediate: instruction
+                    .push_value()
+                    .filter(|_| !instruction.immediate.is_empty())
+                    .map(|value| format!("{:#x}", value)),
+            })
+            .collect();
+        Debugger {
+            code,
+            trace,
+            truncated,
+            position: 0,
+            breakpoints,
+            prompt: None,
+            message: None,
+        }
+    }
+
+    fn step(&self) -> &TraceStep {
+        &self.trace.steps[self.position]
+    }
+
+    fn is_last(&self) -> bool {
+        self.position + 1 == self.trace.steps.len()
+    }
+
+    fn step_forward(&mut self) {
+        if !self.is_last() {
+            self.position += 1;
+        }
+    }
+
+    fn step_back(&mut self) {
+        self.position = self.position.saturating_sub(1);
+    }
+
+    /// Moves to the next step that hits a breakpoint, or to the last step.
+    fn continue_forward(&mut self) {
+        let steps = &self.trace.steps;
+        self.position = (self.position + 1..steps.len())
+            .find(|&index| self.hits_breakpoint(&steps[index]))
+            .unwrap_or(steps.len() - 1);
+    }
+
+    /// Moves to the previous step that hits a breakpoint, or to the first step.
+    fn continue_back(&mut self) {
+        let steps = &self.trace.steps;
+        self.position = (0..self.position)
+            .rev()
+            .find(|&index| self.hits_breakpoint(&steps[index]))
+            .unwrap_or(0);
+    }
+
+    fn hits_breakpoint(&self, step: &TraceStep) -> bool {
+        self.breakpoints
+            .iter()
+            .any(|breakpoint| breakpoint.matches(step))
+    }
+
+    fn toggle_breakpoint(&mut self, breakpoint: Breakpoint) {
+        match self.breakpoints.iter().position(|b| *b == breakpoint) {
+            Some(index) => {
+                self.breakpoints.remove(index);
+                self.message = Some(format!("Removed breakpoint {}", breakpoint));
+            }
+            None => {
+                self.breakpoints.push(breakpoint);
+                self.message = Some(format!("Added breakpoint {}", breakpoint));
+            }
+        }
+    }
+
+    /// Storage written by the steps before the current one, by contract and slot. Writes
+    /// made by frames that have since reverted are left out.
+    fn storage(&self) -> BTreeMap<(Address, U256), U256> {
+        let steps = &self.trace.steps;
+        // Writes of each open frame, outermost first
+        let mut frames: Vec<Vec<(Address, U256, U256)>> = Vec::new();
+        for (index, step) in steps[..=self.position].iter().enumerate() {
+            while frames.len() > step.depth {
+                let writes = frames.pop().unwrap_or_default();
+                let reverted = steps[index - 1].error.is_some();
+                if let Some(parent) = frames.last_mut()
+                    && !reverted
+                {
+                    parent.extend(writes);
+                }
+            }
+            frames.resize_with(step.depth, Vec::new);
+
+            if index < self.position
+                && step.error.is_none()
+                && let Some((slot, value)) = step.storage_write
+                && let Some(frame) = frames.last_mut()
+            {
+                frame.push((step.address, slot, value));
+            }
+        }
+
+        frames
+            .into_iter()
+            .flatten()
+            .map(|(address, slot, value)| ((address, slot), value))
+            .collect()
+    }
+
+    /// Memory of the current frame before the current step, rebuilt from the changes of the
+    /// steps before it.
+    fn memory(&self) -> Vec<u8> {
+        let mut frames: Vec<Vec<u8>> = Vec::new();
+        for step in &self.trace.steps[..self.position] {
+            frames.truncate(step.depth);
+            frames.resize_with(step.depth, Vec::new);
+            let Some(memory) = frames.last_mut() else {
+                continue;
+            };
+            for (offset, word) in &step.memory_writes {
+                if memory.len() < offset + 32 {
+                    memory.resize(offset + 32, 0);
+                }
+                memory[*offset..offset + 32].copy_from_slice(word.as_slice());
+            }
+        }
+
+        let step = self.step();
+        frames.truncate(step.depth);
+        frames.resize_with(step.depth, Vec::new);
+        let mut memory = frames.pop().unwrap_or_default();
+        memory.resize(step.memory_size, 0);
+        memory
+    }
+
+    /// Applies a key press; returns `false` to quit.
+    fn handle_key(&mut self, key: KeyCode) -> bool {
+        if let Some(prompt) = &mut self.prompt {
+            match key {
+                KeyCode::Char(c) => prompt.push(c),
+                KeyCode::Backspace => {
+                    prompt.pop();
+                }
+                KeyCode::Enter => {
+                    let input = self.prompt.take().unwrap_or_default();
+                    match input.parse() {
+                        Ok(breakpoint) => self.toggle_breakpoint(breakpoint),
+                        Err(e) => self.message = Some(e),
+                    }
+                }
+                KeyCode::Esc => self.prompt = None,
+                _ => {}
+            }
+            return true;
+        }
+
+        self.message = None;
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('n') | KeyCode::Char('j') | KeyCode::Char(' ') | KeyCode::Right => {
+                self.step_forward()
+            }
+            KeyCode::Char('p') | KeyCode::Char('k') | KeyCode::Left => self.step_back(),
+            KeyCode::Char('c') => self.continue_forward(),
+            KeyCode::Char('r') => self.continue_back(),
+            KeyCode::Char('g') | KeyCode::Home => self.position = 0,
+            KeyCode::Char('G') | KeyCode::End => self.position = self.trace.steps.len() - 1,
+            KeyCode::Char('b') => self.prompt = Some(String::new()),
+            KeyCode::Char('B') => self.toggle_breakpoint(Breakpoint::Pc(self.step().pc)),
+            _ => {}
+        }
+        true
+    }
+
+    fn render(&self, frame: &mut Frame) {
+        let [main, memory, status] = Layout::vertical([
+            Constraint::Min(10),
+            Constraint::Length(10),
+            Constraint::Length(4),
+        ])
+        .areas(frame.area());
+        let [code, side] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main);
+        let [stack, storage, output] = Layout::vertical([
+            Constraint::Percentage(50),
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+        ])
+        .areas(side);
+
+        let (code_list, mut code_state) = self.code_pane();
+        frame.render_stateful_widget(code_list, code, &mut code_state);
+        frame.render_widget(self.stack_pane(), stack);
+        frame.render_widget(self.storage_pane(), storage);
+        frame.render_widget(self.output_pane(), output);
+        frame.render_widget(self.memory_pane(), memory);
+        frame.render_widget(self.status_bar(), status);
+    }
+
+    fn code_pane(&self) -> (List<'_>, ListState) {
+        let current = self.step().pc;
+        let items: Vec<ListItem> = self
+            .code
+            .iter()
+            .map(|line| {
+                let marker = if self.breakpoints.contains(&Breakpoint::Pc(line.pc)) {
+                    Span::styled("● ", Style::new().fg(Color::LightRed))
+                } else {
+                    Span::raw("  ")
+                };
+                let mut spans = vec![
+                    marker,
+                    Span::styled(
+                        format!("{:04x} │ ", line.pc),
+                        Style::new().fg(Color::DarkGray),
+                    ),
+                    Span::styled(line.name.clone(), opcode_style(&line.name)),
+                ];
+                if let Some(immediate) = &line.immediate {
+                    spans.push(Span::raw(format!(" {}", immediate)));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        let selected = self.code.iter().position(|line| line.pc == current);
+        let list = List::new(items)
+            .block(Block::bordered().title(" Code "))
+            .highlight_style(
+                Style::new()
+                    .bg(Color::DarkGray)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("▶");
+        (list, ListState::default().with_selected(selected))
+    }
+
+    fn stack_pane(&self) -> Paragraph<'_> {
+        let step = self.step();
+        let lines: Vec<Line> = if step.stack.is_empty() {
+            vec![Line::styled("empty", Style::new().fg(Color::DarkGray))]
+        } else {
+            step.stack_top(step.stack.len())
+                .enumerate()
+                .map(|(index, item)| {
+                    Line::from(vec![
+                        Span::styled(format!("{:>3} ", index), Style::new().fg(Color::DarkGray)),
+                        Span::raw(format!("{:#x}", item)),
+                    ])
+                })
+                .collect()
+        };
+        Paragraph::new(lines).block(Block::bordered().title(" Stack (top first) "))
+    }
+
+    fn storage_pane(&self) -> Paragraph<'_> {
+        let storage = self.storage();
+        let lines: Vec<Line> = if storage.is_empty() {
+            vec![Line::styled(
+                "no writes yet",
+                Style::new().fg(Color::DarkGray),
+            )]
+        } else {
+            let mut lines = Vec::new();
+            let mut contract = None;
+            for ((address, slot), value) in &storage {
+                if contract != Some(address) {
+                    contract = Some(address);
+                    lines.push(Line::styled(
+                        address.to_string(),
+                        Style::new().fg(Color::DarkGray),
+                    ));
+                }
+                lines.push(Line::from(vec![
+                    Span::styled(
+                        format!("  {:#x}", slot),
+                        Style::new().fg(Color::LightMagenta),
+                    ),
+                    Span::raw(format!(" → {:#x}", value)),
+                ]));
+            }
+            lines
+        };
+        Paragraph::new(lines).block(Block::bordered().title(" Storage "))
+    }
+
+    fn output_pane(&self) -> Paragraph<'_> {
+        let lines = if self.is_last() {
+            let execution = &self.trace.execution;
+            let status = match &execution.status {
+                ExitStatus::Success => Span::styled("success", Style::new().fg(Color::Green)),
+                ExitStatus::Revert => Span::styled("reverted", Style::new().fg(Color::LightRed)),
+                ExitStatus::Halt(reason) => Span::styled(
+                    format!("halted ({})", reason),
+                    Style::new().fg(Color::LightRed),
+                ),
+            };
+            let mut lines = vec![Line::from(vec![Span::raw("Status: "), status])];
+            if let Some(reason) = execution.revert_reason() {
+                lines.push(Line::raw(format!("Reason: {}", reason)));
+            }
+            lines.push(Line::raw(format!(
+                "Data: 0x{}",
+                hex::encode(&execution.output)
+            )));
+            lines
+        } else {
+            vec![Line::styled(
+                "available at the last step",
+                Style::new().fg(Color::DarkGray),
+            )]
+        };
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(" Return data "))
+    }
+
+    fn memory_pane(&self) -> Paragraph<'_> {
+        let memory = self.memory();
+        let lines: Vec<Line> = if memory.is_empty() {
+            vec![Line::styled("empty", Style::new().fg(Color::DarkGray))]
+        } else {
+            memory
+                .chunks(MEMORY_ROW)
+                .enumerate()
+                .map(|(row, bytes)| {
+                    Line::from(vec![
+                        Span::styled(
+                            format!("{:04x}  ", row * MEMORY_ROW),
+                            Style::new().fg(Color::DarkGray),
+                        ),
+                        Span::raw(hex::encode(bytes)),
+                    ])
+                })
+                .collect()
+        };
+        Paragraph::new(lines)
+            .block(Block::bordered().title(format!(" Memory ({} bytes) ", memory.len())))
+    }
+
+    fn status_bar(&self) -> Paragraph<'_> {
+        let step = self.step();
+        let name = OpCode::new(step.opcode)
+            .map(|op| op.as_str().to_string())
+            .unwrap_or_else(|| format!("0x{:02x}", step.opcode));
+        let mut spans = vec![
+            Span::raw(format!(
+                "step {}/{}{} │ pc {:04x} │ ",
+                self.position + 1,
+                self.trace.steps.len(),
+                if self.truncated {
+                    " (recording stopped)"
+                } else {
+                    ""
+                },
+                step.pc
+            )),
+            Span::styled(name.clone(), opcode_style(&name)),
+            Span::raw(format!(
+                " │ gas {} cost {} │ depth {}",
+                step.gas_remaining, step.gas_cost, step.depth
+            )),
+        ];
+        if let Some(error) = &step.error {
+            spans.push(Span::styled(
+                format!(" │ {}", error),
+                Style::new().fg(Color::LightRed),
+            ));
+        }
+
+        let hint = match (&self.prompt, &self.message) {
+            (Some(prompt), _) => Line::raw(format!(
+                "Toggle breakpoint (pc in hex or opcode): {}█",
+                prompt
+            )),
+            (None, Some(message)) => Line::styled(message.clone(), Style::new().fg(Color::Yellow)),
+            (None, None) => Line::styled(
+                "n/→ step  p/← back  c continue  r reverse  b breakpoint  B break here  g/G start/end  q quit",
+                Style::new().fg(Color::DarkGray),
+            ),
+        };
+        Paragraph::new(vec![Line::from(spans), hint]).block(Block::bordered())
+    }
+}
+
+/// Runs the debugger until the user quits.
+pub fn run(mut debugger: Debugger) -> std::io::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, &mut debugger);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, debugger: &mut Debugger) -> std::io::Result<()> {
+    loop {
+        terminal.draw(|frame| debugger.render(frame))?;
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && !debugger.handle_key(key.code)
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// The disassembly colors of [`crate::categorize_opcode`], as a terminal UI style.
+fn opcode_style(name: &str) -> Style {
+    let colored = crate::categorize_opcode(name);
+    let mut style = Style::new();
+    if let Some(color) = colored.fgcolor {
+        style = style.fg(ui_color(color));
+    }
+    if colored.style.contains(colored::Styles::Bold) {
+        style = style.add_modifier(Modifier::BOLD);
+    }
+    style
+}
+
+fn ui_color(color: colored::Color) -> Color {
+    use colored::Color as Ansi;
+    match color {
+        Ansi::Black => Color::Black,
+        Ansi::Red => Color::Red,
+        Ansi::Green => Color::Green,
+        Ansi::Yellow => Color::Yellow,
+        Ansi::Blue => Color::Blue,
+        Ansi::Magenta => Color::Magenta,
+        Ansi::Cyan => Color::Cyan,
+        Ansi::White => Color::Gray,
+        Ansi::BrightBlack => Color::DarkGray,
+        Ansi::BrightRed => Color::LightRed,
+        Ansi::BrightGreen => Color::LightGreen,
+        Ansi::BrightYellow => Color::LightYellow,
+        Ansi::BrightBlue => Color::LightBlue,
+        Ansi::BrightMagenta => Color::LightMagenta,
+        Ansi::BrightCyan => Color::LightCyan,
+        Ansi::BrightWhite => Color::White,
+        Ansi::TrueColor { r, g, b } => Color::Rgb(r, g, b),
+    }
+}
+
+/// Text shown when the debugger cannot take over the terminal.
+pub fn terminal_required() -> String {
+    format!(
+        "{} needs an interactive terminal; use {} for a non-interactive step listing",
+        "debug".bold(),
+        "trace".bold()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use evm_lens_core::{CONTRACT_ADDRESS, CallParams, TraceOptions, trace};
+
+    use super::*;
+
+    fn debugger(code: &str, breakpoints: Vec<Breakpoint>) -> Debugger {
+        let bytes = hex::decode(code).unwrap();
+        let mut trace = trace(&bytes, &CallParams::default(), &TraceOptions::new(true)).unwrap();
+        // As recorded by `debug`: changes only
+        for step in &mut trace.steps {
+            step.memory = None;
+        }
+        Debugger::new(&bytes, trace, false, breakpoints)
+    }
+
+    #[test]
+    fn test_parse_breakpoint() {
+        assert_eq!("SSTORE".parse(), Ok(Breakpoint::Opcode(0x55)));
+        assert_eq!("sstore".parse(), Ok(Breakpoint::Opcode(0x55)));
+        assert_eq!("0x1a".parse(), Ok(Breakpoint::Pc(0x1a)));
+        assert_eq!("1a".parse(), Ok(Breakpoint::Pc(0x1a)));
+        // Opcode names win over hex
+        assert_eq!("ADD".parse(), Ok(Breakpoint::Opcode(0x01)));
+        assert_eq!("0xadd".parse(), Ok(Breakpoint::Pc(0xadd)));
+        assert!("NOPE".parse::<Breakpoint>().is_err());
+        assert_eq!(Breakpoint::Pc(0x1a).to_string(), "001a");
+    }
+
+    #[test]
+    fn test_stepping_and_breakpoints() {
+        // PUSH1 0x01, PUSH0, SSTORE, PUSH1 0x02, PUSH1 0x01, SSTORE, STOP
+        let mut debugger = debugger("60015f55600260015500", vec![Breakpoint::Opcode(0x55)]);
+        debugger.step_back();
+        assert_eq!(debugger.position, 0);
+
+        debugger.continue_forward();
+        assert_eq!(debugger.step().pc, 0x03);
+        assert!(debugger.storage().is_empty());
+
+        debugger.continue_forward();
+        assert_eq!(debugger.step().pc, 0x08);
+        assert_eq!(
+            debugger.storage(),
+            BTreeMap::from([((CONTRACT_ADDRESS, U256::ZERO), U256::from(1))])
+        );
+
+        // No breakpoint left: run to the end
+        debugger.continue_forward();
+        assert!(debugger.is_last());
+        assert_eq!(debugger.storage().len(), 2);
+
+        debugger.continue_back();
+        assert_eq!(debugger.step().pc, 0x08);
+        debugger.handle_key(KeyCode::Char('g'));
+        assert_eq!(debugger.position, 0);
+    }
+
+    /// Index of the step at `pc` in a frame at `depth`.
+    fn position(debugger: &Debugger, pc: usize, depth: usize) -> usize {
+        debugger
+            .trace
+            .steps
+            .iter()
+            .position(|step| step.pc == pc && step.depth == depth)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_memory_is_rebuilt_per_frame() {
+        // 0x00: CALLDATASIZE, PUSH1 0x12, JUMPI
+        // 0x04: PUSH1 0x2a, PUSH0, MSTORE, then STATICCALL to itself with one byte of
+        //       calldata: PUSH0, PUSH0, PUSH1 0x01, PUSH0, ADDRESS, GAS, STATICCALL, POP, STOP
+        // 0x12: JUMPDEST, PUSH1 0x07, PUSH1 0x20, MSTORE, STOP
+        let mut debugger = debugger(
+            "36601257602a5f525f5f60015f305afa50005b600760205200",
+            Vec::new(),
+        );
+        let word = |value: u64| U256::from(value).to_be_bytes::<32>().to_vec();
+
+        debugger.position = position(&debugger, 0x08, 1);
+        assert_eq!(debugger.memory(), word(0x2a));
+
+        // The called frame starts with empty memory
+        debugger.position = position(&debugger, 0x00, 2);
+        assert!(debugger.memory().is_empty());
+        debugger.position = position(&debugger, 0x18, 2);
+        assert_eq!(debugger.memory(), [word(0), word(7)].concat());
+
+        // Back in the caller, its own memory is shown again
+        debugger.position = position(&debugger, 0x10, 1);
+        assert_eq!(debugger.memory(), word(0x2a));
+    }
+
+    #[test]
+    fn test_reverted_writes_are_dropped() {
+        // 0x00: CALLDATASIZE, PUSH1 0x13, JUMPI
+        // 0x04: CALL to itself with one byte of calldata: PUSH0, PUSH0, PUSH1 0x01, PUSH0,
+        //       PUSH0, ADDRESS, GAS, CALL, POP, then PUSH1 0x02, PUSH0, SSTORE, STOP
+        // 0x13: JUMPDEST, PUSH1 0x07, PUSH1 0x01, SSTORE, PUSH0, PUSH0, REVERT
+        let mut debugger = debugger(
+            "366013575f5f60015f5f305af15060025f55005b60076001555f5ffd",
+            Vec::new(),
+        );
+
+        debugger.position = position(&debugger, 0x1b, 2);
+        assert_eq!(
+            debugger.storage(),
+            BTreeMap::from([((CONTRACT_ADDRESS, U256::from(1)), U256::from(7))])
+        );
+
+        debugger.position = debugger.trace.steps.len() - 1;
+        assert_eq!(
+            debugger.storage(),
+            BTreeMap::from([((CONTRACT_ADDRESS, U256::ZERO), U256::from(2))])
+        );
+    }
+
+    #[test]
+    fn test_breakpoint_prompt() {
+        let mut debugger = debugger("60015f55600260015500", Vec::new());
+        for key in [
+            KeyCode::Char('b'),
+            KeyCode::Char('0'),
+            KeyCode::Char('6'),
+            KeyCode::Enter,
+        ] {
+            assert!(debugger.handle_key(key));
+        }
+        assert_eq!(debugger.breakpoints, [Breakpoint::Pc(6)]);
+
+        debugger.handle_key(KeyCode::Char('c'));
+        assert_eq!(debugger.step().pc, 6);
+        // Toggling the current position removes it again
+        debugger.handle_key(KeyCode::Char('B'));
+        assert!(debugger.breakpoints.is_empty());
+        assert!(!debugger.handle_key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn test_render_panes() {
+        let mut debugger = debugger("60015f55600260015500", vec![Breakpoint::Pc(3)]);
+        debugger.continue_forward();
+        debugger.step_forward();
+        debugger.truncated = true;
+
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(120, 40)).unwrap();
+        terminal.draw(|frame| debugger.render(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+
+        assert!(screen.contains("● 0003 │ SSTORE"));
+        assert!(screen.contains("Stack (top first)"));
+        assert!(screen.contains("0x0 → 0x1"));
+        assert!(screen.contains("step 4/7 (recording stopped)"));
+        assert!(screen.contains(&CONTRACT_ADDRESS.to_string()));
+        assert!(screen.contains("available at the last step"));
+    }
+
+    #[test]
+    fn test_opcode_style_matches_disassembly_colors() {
+        assert_eq!(
+            opcode_style("SSTORE"),
+            Style::new()
+                .fg(Color::LightMagenta)
+                .add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(opcode_style("POP"), Style::new().fg(Color::Green));
+    }
+}
@@ -1,16 +1,23 @@
 use color_eyre::{Result, eyre::eyre};
-use ethereum_types::Address;
+use ethereum_types::{Address, H256};
+use evm_lens_core::{Immutable, LibraryPlaceholder, find_library_placeholders};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::io::{self, Read};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use url::Url;
 
 #[derive(Debug, Clone)]
 pub enum Source {
     Stdin,
+    /// A file in any format [`detect_format`] tells apart.
     File(PathBuf),
-    OnChain { address: Address, rpc: Url },
+    /// A file of raw bytecode bytes, read without checking for hex.
+    Binary(PathBuf),
+    OnChain {
+        address: Address,
+        rpc: Url,
+    },
 }
 
 /// Fetches bytecode from the specified source and returns it as a vector of bytes.
@@ -18,8 +25,9 @@ pub enum Source {
 /// # Arguments
 ///
 /// * `source` - The source to fetch bytecode from, which can be:
-///   * `Source::Stdin` - Read hex-encoded bytecode from standard input
-///   * `Source::File` - Read hex-encoded bytecode from a file
+///   * `Source::Stdin` - Read bytecode from standard input in any detected format
+///   * `Source::File` - Read bytecode from a file in any detected format
+///   * `Source::Binary` - Read raw bytecode from a file
 ///   * `Source::OnChain` - Fetch bytecode from an Ethereum contract address via RPC
 ///
 /// # Returns
@@ -36,37 +44,557 @@ pub enum Source {
 /// * The hex decoding fails
 /// * The RPC request fails when fetching on-chain bytecode
 pub async fn fetch_bytes(source: Source) -> Result<Vec<u8>> {
+    fetch_bytecode(source).await.map(|(bytes, _)| bytes)
+}
+
+/// Fetches bytecode like [`fetch_bytes`], also returning the library placeholders of unlinked
+/// bytecode, whose bytes are left as zeros.
+pub async fn fetch_bytecode(source: Source) -> Result<(Vec<u8>, Vec<LibraryPlaceholder>)> {
+    fetch_input(source)
+        .await
+        .map(|input| (input.bytes, input.placeholders))
+}
+
+/// Fetches bytecode like [`fetch_bytecode`], also returning the format a file or stdin payload
+/// was detected in.
+pub async fn fetch_input(source: Source) -> Result<Input> {
     match source {
         Source::Stdin => {
-            let mut buffer = String::new();
+            let mut buffer = Vec::new();
             io::stdin()
-                .read_to_string(&mut buffer)
+                .read_to_end(&mut buffer)
                 .map_err(|e| eyre!("Failed to read from stdin: {}", e))?;
 
-            let trimmed = buffer.trim();
-            if trimmed.is_empty() {
-                return Err(eyre!("No input provided via stdin"));
+            decode_input(&buffer, "stdin").ok_or_else(|| eyre!("No input provided via stdin"))?
+        }
+
+        Source::File(path) => read_code_file(&path, false),
+
+        Source::Binary(path) => read_code_file(&path, true),
+
+        Source::OnChain { address, rpc } => fetch_on_chain_bytecode(address, rpc)
+            .await
+            .map(|bytes| Input::from((bytes, Vec::new()))),
+    }
+}
+
+/// The format of a file or stdin payload, as told apart by [`detect_format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// Hex bytecode, optionally `0x`-prefixed.
+    Hex,
+    /// Hex split by whitespace or newlines, or annotated with `//` or `#` comments.
+    FormattedHex,
+    /// Raw bytecode bytes.
+    Binary,
+    /// A JSON-RPC response, such as from `eth_getCode` or `eth_getTransactionByHash`.
+    RpcResponse,
+    /// A Foundry, Hardhat or solc standard JSON artifact.
+    Artifact,
+    /// A transaction whose `input` or `data` holds the code.
+    Transaction,
+    /// An EIP-3540 EOF container, starting with `0xEF00`, in hex or raw.
+    Eof,
+}
+
+impl InputFormat {
+    /// Stable identifier, as written in JSON output.
+    pub fn id(&self) -> &'static str {
+        match self {
+            InputFormat::Hex => "hex",
+            InputFormat::FormattedHex => "formatted_hex",
+            InputFormat::Binary => "binary",
+            InputFormat::RpcResponse => "rpc_response",
+            InputFormat::Artifact => "artifact",
+            InputFormat::Transaction => "transaction",
+            InputFormat::Eof => "eof",
+        }
+    }
+}
+
+impl std::fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            InputFormat::Hex => "hex",
+            InputFormat::FormattedHex => "hex with whitespace or comments",
+            InputFormat::Binary => "raw binary",
+            InputFormat::RpcResponse => "JSON-RPC response",
+            InputFormat::Artifact => "compiler artifact",
+            InputFormat::Transaction => "transaction JSON",
+            InputFormat::Eof => "EOF container",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Bytecode read from a source.
+#[derive(Debug, Clone)]
+pub struct Input {
+    pub bytes: Vec<u8>,
+    pub placeholders: Vec<LibraryPlaceholder>,
+    /// The detected format of a file or stdin payload; `None` when a flag named the format.
+    pub format: Option<InputFormat>,
+}
+
+impl From<(Vec<u8>, Vec<LibraryPlaceholder>)> for Input {
+    fn from((bytes, placeholders): (Vec<u8>, Vec<LibraryPlaceholder>)) -> Self {
+        Self {
+            bytes,
+            placeholders,
+            format: None,
+        }
+    }
+}
+
+/// One contract's code and metadata, read from a compiler artifact.
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    /// Contract name, when the artifact records it.
+    pub name: Option<String>,
+    pub bytes: Vec<u8>,
+    pub placeholders: Vec<LibraryPlaceholder>,
+    pub source_map: Option<String>,
+    pub abi: Option<serde_json::Value>,
+    /// Immutable references; only recorded for runtime code.
+    pub immutables: Vec<Immutable>,
+}
+
+/// Reads a contract's runtime or creation code from a Foundry (`out/**/*.json`), Hardhat
+/// (`artifacts/**/*.json`) or solc `--standard-json` output file.
+///
+/// Standard JSON output can hold many contracts; `contract` picks one by name or by
+/// `path:Name`, and may be left out when only one contract has code.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// * The file cannot be read or is not JSON
+/// * The file is not a recognised artifact
+/// * The contract is not found, or several match
+/// * The selected code is empty or not valid hex
+pub fn read_artifact(path: &Path, contract: Option<&str>, creation: bool) -> Result<Artifact> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| eyre!("Failed to read artifact {:?}: {}", path, e))?;
+    let json: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| eyre!("Failed to parse artifact {:?}: {}", path, e))?;
+    parse_artifact(&json, &format!("{:?}", path), contract, creation)
+}
+
+/// Lists the contracts with runtime code in a solc standard JSON output file, as `path:Name`.
+/// Other files, including Foundry and Hardhat artifacts, list none.
+pub fn artifact_contracts(path: &Path) -> Vec<String> {
+    if path.extension().is_none_or(|extension| extension != "json") {
+        return Vec::new();
+    }
+    let Some(json) = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+    else {
+        return Vec::new();
+    };
+    let Some(files) = json.get("contracts").and_then(serde_json::Value::as_object) else {
+        return Vec::new();
+    };
+
+    files
+        .iter()
+        .filter_map(|(file, contracts)| Some((file, contracts.as_object()?)))
+        .flat_map(|(file, contracts)| {
+            contracts
+                .iter()
+                .filter(|(_, output)| {
+                    output
+                        .pointer("/evm/deployedBytecode/object")
+                        .and_then(serde_json::Value::as_str)
+                        .is_some_and(|object| !object.trim_start_matches("0x").is_empty())
+                })
+                .map(move |(name, _)| format!("{}:{}", file, name))
+        })
+        .collect()
+}
+
+/// Picks a contract's code from parsed artifact JSON as [`read_artifact`] does; `origin` names
+/// where the JSON came from in errors.
+fn parse_artifact(
+    json: &serde_json::Value,
+    origin: &str,
+    contract: Option<&str>,
+    creation: bool,
+) -> Result<Artifact> {
+    // solc standard JSON output: contracts -> file -> name -> { abi, evm }
+    if let Some(files) = json.get("contracts").and_then(serde_json::Value::as_object) {
+        let section = if creation {
+            "bytecode"
+        } else {
+            "deployedBytecode"
+        };
+        let candidates: Vec<(String, &serde_json::Value)> = files
+            .iter()
+            .filter_map(|(file, contracts)| Some((file, contracts.as_object()?)))
+            .flat_map(|(file, contracts)| {
+                contracts
+                    .iter()
+                    .map(move |(name, output)| (format!("{}:{}", file, name), output))
+            })
+            .filter(|(qualified, _)| match contract {
+                Some(contract) => {
+                    qualified == contract || qualified.rsplit(':').next() == Some(contract)
+                }
+                None => true,
+            })
+            .collect();
+        let with_code: Vec<&(String, &serde_json::Value)> = candidates
+            .iter()
+            .filter(|(_, output)| {
+                output
+                    .pointer(&format!("/evm/{}/object", section))
+                    .and_then(serde_json::Value::as_str)
+                    .is_some_and(|object| !object.trim_start_matches("0x").is_empty())
+            })
+            .collect();
+        let (qualified, output) = match (contract, candidates.as_slice(), with_code.as_slice()) {
+            (Some(contract), [], _) => {
+                return Err(eyre!("Contract {} not found in {}", contract, origin));
+            }
+            (Some(_), [only], _) => only,
+            (None, _, [only]) => *only,
+            (None, _, []) => return Err(eyre!("No contract with code in {}", origin)),
+            _ => {
+                let names: Vec<&str> = match contract {
+                    Some(_) => candidates.iter().map(|(name, _)| name.as_str()).collect(),
+                    None => with_code.iter().map(|(name, _)| name.as_str()).collect(),
+                };
+                return Err(eyre!(
+                    "Several contracts match in {}, pick one with --contract: {}",
+                    origin,
+                    names.join(", ")
+                ));
             }
+        };
+        let name = qualified.rsplit(':').next().map(str::to_string);
+        let evm = output.get("evm").unwrap_or(&serde_json::Value::Null);
+        return artifact_code(name, evm, output.get("abi"), creation);
+    }
 
-            decode_hex(trimmed)
+    // Foundry and Hardhat: one contract per file with bytecode and deployedBytecode at the top
+    if json.get("bytecode").is_some() || json.get("deployedBytecode").is_some() {
+        let name = json
+            .get("contractName")
+            .and_then(serde_json::Value::as_str)
+            .map(str::to_string)
+            .or_else(|| {
+                json.pointer("/metadata/settings/compilationTarget")
+                    .and_then(serde_json::Value::as_object)
+                    .and_then(|target| target.values().next()?.as_str().map(str::to_string))
+            });
+        if let (Some(contract), Some(name)) = (contract, &name)
+            && contract.rsplit(':').next() != Some(name.as_str())
+        {
+            return Err(eyre!(
+                "Artifact {} holds {}, not {}",
+                origin,
+                name,
+                contract
+            ));
         }
+        return artifact_code(name, json, json.get("abi"), creation);
+    }
+
+    Err(eyre!(
+        "{} is not a Foundry, Hardhat or solc standard JSON artifact",
+        origin
+    ))
+}
+
+/// Reads `bytecode` or `deployedBytecode` from `container`, which holds either hex strings
+/// (Hardhat) or objects with `object`, `sourceMap` and `immutableReferences` (Foundry, solc).
+fn artifact_code(
+    name: Option<String>,
+    container: &serde_json::Value,
+    abi: Option<&serde_json::Value>,
+    creation: bool,
+) -> Result<Artifact> {
+    let (section, kind) = if creation {
+        ("bytecode", "creation")
+    } else {
+        ("deployedBytecode", "runtime")
+    };
+    let code = container.get(section);
+    let object = code
+        .and_then(|code| code.as_str().or_else(|| code.get("object")?.as_str()))
+        .unwrap_or_default();
+    let label = name.as_deref().unwrap_or("The contract");
+    if object.trim_start_matches("0x").is_empty() {
+        return Err(eyre!(
+            "{} has no {} code; abstract contracts and interfaces have none",
+            label,
+            kind
+        ));
+    }
+    let (bytes, placeholders) = decode_bytecode(object)?;
+
+    let source_map = code
+        .and_then(|code| code.get("sourceMap")?.as_str())
+        .filter(|source_map| !source_map.is_empty())
+        .map(str::to_string);
+    let immutables = match code
+        .and_then(|code| code.get("immutableReferences")?.as_object())
+        .filter(|_| !creation)
+    {
+        Some(references) => immutables_from_references(references)?,
+        None => Vec::new(),
+    };
+
+    Ok(Artifact {
+        name,
+        bytes,
+        placeholders,
+        source_map,
+        abi: abi.cloned(),
+        immutables,
+    })
+}
+
+/// Files at least this large are memory-mapped instead of read into a buffer.
+const MMAP_THRESHOLD: u64 = 1 << 20;
+
+/// Reads a bytecode file in any format [`detect_format`] tells apart, or as raw bytes when
+/// `binary` is set.
+///
+/// Large files are memory-mapped and decoded straight from the map, so hex never needs a
+/// second buffer beside the decoded bytes. Raw bytes are read into their buffer directly.
+fn read_code_file(path: &Path, binary: bool) -> Result<Input> {
+    let read_error = |e: std::io::Error| eyre!("Failed to read file {:?}: {}", path, e);
+    if binary {
+        let data = std::fs::read(path).map_err(read_error)?;
+        return (!data.is_empty())
+            .then(|| Input::from((data, Vec::new())))
+            .ok_or_else(|| eyre!("File {:?} is empty", path));
+    }
+
+    let file = std::fs::File::open(path).map_err(read_error)?;
+    let len = file.metadata().map_err(read_error)?.len();
+    let origin = format!("{:?}", path);
+    let input = if len >= MMAP_THRESHOLD {
+        // SAFETY: the map is only borrowed for the decode below and dropped on return. Another
+        // process truncating or writing the file meanwhile is undefined behaviour that cannot
+        // be ruled out for a shared file; like other command-line tools reading large inputs,
+        // we accept that risk for files the user points us at.
+        let map = unsafe { memmap2::Mmap::map(&file) }
+            .map_err(|e| eyre!("Failed to map file {:?}: {}", path, e))?;
+        decode_input(&map, &origin)
+    } else {
+        let mut buffer = Vec::with_capacity(len as usize);
+        (&file).read_to_end(&mut buffer).map_err(read_error)?;
+        decode_input(&buffer, &origin)
+    };
+    input.ok_or_else(|| eyre!("File {:?} is empty", path))?
+}
+
+/// The two bytes every EOF container starts with.
+const EOF_MAGIC: [u8; 2] = [0xef, 0x00];
+
+/// Whether `bytes` is an EOF container (EIP-3540) rather than legacy code, which cannot start
+/// with its magic since EIP-3541.
+pub fn is_eof(bytes: &[u8]) -> bool {
+    bytes.starts_with(&EOF_MAGIC)
+}
 
-        Source::File(path) => {
-            let content = std::fs::read_to_string(&path)
-                .map_err(|e| eyre!("Failed to read file {:?}: {}", path, e))?;
+/// What the header of an EOF container says about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EofHeader {
+    pub version: u8,
+    /// Number of code sections, or `None` when the header is cut short or out of order.
+    pub code_sections: Option<u16>,
+}
+
+/// Reads the version and code section count of an EOF container: the magic and version, then
+/// the type section header (`0x01` and a 2-byte size) and the code section header (`0x02` and
+/// a 2-byte count). Returns `None` for anything but an EOF container.
+pub fn eof_header(bytes: &[u8]) -> Option<EofHeader> {
+    if !is_eof(bytes) {
+        return None;
+    }
+    let version = *bytes.get(2)?;
+    let code_sections = match bytes.get(3..9) {
+        Some(&[0x01, _, _, 0x02, high, low]) => Some(u16::from_be_bytes([high, low])),
+        _ => None,
+    };
+    Some(EofHeader {
+        version,
+        code_sections,
+    })
+}
+
+/// The byte order mark some editors start UTF-8 text with.
+const UTF8_BOM: [u8; 3] = [0xef, 0xbb, 0xbf];
+
+/// Tells apart the formats a file or stdin payload may hold bytecode in. Returns `None` when
+/// there is no code at all, such as for whitespace or comments only.
+///
+/// Text is taken as hex unless it is JSON of a known shape; anything else is raw bytes.
+pub fn detect_format(data: &[u8]) -> Option<InputFormat> {
+    let data = data.strip_prefix(&UTF8_BOM).unwrap_or(data);
+    if let Ok(text) = std::str::from_utf8(data)
+        && text.trim_start().starts_with('{')
+        && let Some(format) = serde_json::from_str(text)
+            .ok()
+            .as_ref()
+            .and_then(json_format)
+    {
+        return Some(format);
+    }
+
+    if !is_text(data) {
+        return Some(if is_eof(data) {
+            InputFormat::Eof
+        } else {
+            InputFormat::Binary
+        });
+    }
+
+    let text = std::str::from_utf8(data).ok()?.trim();
+    let hex = strip_hex_formatting(text);
+    if hex.is_empty() {
+        return None;
+    }
+    Some(if hex.to_ascii_lowercase().starts_with("ef00") {
+        InputFormat::Eof
+    } else if hex == text.strip_prefix("0x").unwrap_or(text) {
+        InputFormat::Hex
+    } else {
+        InputFormat::FormattedHex
+    })
+}
+
+/// The format of JSON holding bytecode, if it has a known shape.
+fn json_format(json: &serde_json::Value) -> Option<InputFormat> {
+    let object = json.as_object()?;
+    let has = |key: &str| object.contains_key(key);
+
+    if has("jsonrpc") || (has("id") && (has("result") || has("error"))) {
+        Some(InputFormat::RpcResponse)
+    } else if has("contracts") || has("bytecode") || has("deployedBytecode") {
+        Some(InputFormat::Artifact)
+    } else if transaction_input(json).is_some()
+        && ["to", "from", "nonce", "hash", "gas"]
+            .iter()
+            .any(|key| has(key))
+    {
+        Some(InputFormat::Transaction)
+    } else {
+        None
+    }
+}
+
+/// Decodes a file or stdin payload in whichever format [`detect_format`] finds. Returns `None`
+/// when there is no code at all.
+fn decode_input(data: &[u8], origin: &str) -> Option<Result<Input>> {
+    let format = detect_format(data)?;
+    let data = match format {
+        InputFormat::Binary => data,
+        _ => data.strip_prefix(&UTF8_BOM).unwrap_or(data),
+    };
+    let json = || serde_json::from_slice::<serde_json::Value>(data).unwrap_or_default();
 
-            let trimmed = content.trim();
-            if trimmed.is_empty() {
-                return Err(eyre!("File {:?} is empty", path));
+    let decoded = match format {
+        InputFormat::Binary => Ok((data.to_vec(), Vec::new())),
+        InputFormat::Eof if !is_text(data) => Ok((data.to_vec(), Vec::new())),
+        InputFormat::Hex | InputFormat::FormattedHex | InputFormat::Eof => {
+            let text = std::str::from_utf8(data).unwrap_or_default().trim();
+            if text.starts_with('{') {
+                Err(eyre!(
+                    "{} holds JSON that is not a JSON-RPC response, compiler artifact or transaction",
+                    origin
+                ))
+            } else {
+                decode_bytecode(&strip_hex_formatting(text))
             }
+        }
+        InputFormat::RpcResponse => rpc_response_code(&json(), origin),
+        InputFormat::Artifact => parse_artifact(&json(), origin, None, false)
+            .map(|artifact| (artifact.bytes, artifact.placeholders)),
+        InputFormat::Transaction => transaction_code(&json(), origin),
+    };
+    Some(decoded.map(|(bytes, placeholders)| Input {
+        bytes,
+        placeholders,
+        format: Some(format),
+    }))
+}
 
-            decode_hex(trimmed)
+/// Reads the code from an `eth_getCode` result, or the input of an
+/// `eth_getTransactionByHash` result.
+fn rpc_response_code(
+    json: &serde_json::Value,
+    origin: &str,
+) -> Result<(Vec<u8>, Vec<LibraryPlaceholder>)> {
+    if let Some(error) = json.get("error") {
+        let message = error
+            .get("message")
+            .and_then(serde_json::Value::as_str)
+            .map_or_else(|| error.to_string(), str::to_string);
+        return Err(eyre!("JSON-RPC error in {}: {}", origin, message));
+    }
+    match json.get("result") {
+        Some(serde_json::Value::String(code)) if code.trim_start_matches("0x").is_empty() => {
+            Err(eyre!(
+                "JSON-RPC response in {} holds no code; is it an EOA?",
+                origin
+            ))
         }
+        Some(serde_json::Value::String(code)) => decode_bytecode(code),
+        Some(result) if transaction_input(result).is_some() => transaction_code(result, origin),
+        _ => Err(eyre!(
+            "JSON-RPC response in {} holds neither code nor a transaction",
+            origin
+        )),
+    }
+}
 
-        Source::OnChain { address, rpc } => fetch_on_chain_bytecode(address, rpc).await,
+/// The `input` (or `data`, as ethers names it) of a transaction.
+fn transaction_input(transaction: &serde_json::Value) -> Option<&str> {
+    transaction
+        .get("input")
+        .or_else(|| transaction.get("data"))?
+        .as_str()
+}
+
+fn transaction_code(
+    transaction: &serde_json::Value,
+    origin: &str,
+) -> Result<(Vec<u8>, Vec<LibraryPlaceholder>)> {
+    match transaction_input(transaction) {
+        Some(input) if !input.trim_start_matches("0x").is_empty() => decode_bytecode(input),
+        _ => Err(eyre!("Transaction in {} has no input", origin)),
     }
 }
 
+/// Joins hex split across whitespace and lines, dropping `//` and `#` comments and the `0x`
+/// prefix of each piece.
+fn strip_hex_formatting(text: &str) -> String {
+    text.lines()
+        .map(|line| line.split("//").next().unwrap_or_default())
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(str::split_whitespace)
+        .map(|piece| piece.strip_prefix("0x").unwrap_or(piece))
+        .collect()
+}
+
+/// Whether `data` is text, as hex bytecode files are: UTF-8 without control characters other
+/// than whitespace.
+fn is_text(data: &[u8]) -> bool {
+    std::str::from_utf8(data)
+        .is_ok_and(|text| text.chars().all(|c| !c.is_control() || c.is_whitespace()))
+}
+
+/// Decodes hex bytecode that may be unlinked, zeroing its `__$<hash>$__` library placeholders
+/// and returning them alongside.
+pub fn decode_bytecode(s: &str) -> Result<(Vec<u8>, Vec<LibraryPlaceholder>)> {
+    let (cleaned, placeholders) = find_library_placeholders(s.trim());
+    Ok((decode_hex(&cleaned)?, placeholders))
+}
+
 pub fn decode_hex(s: &str) -> Result<Vec<u8>> {
     let cleaned = s.trim().trim_start_matches("0x");
 
@@ -74,7 +602,7 @@ pub fn decode_hex(s: &str) -> Result<Vec<u8>> {
         return Err(eyre!("Empty hex string provided"));
     }
 
-    if cleaned.len() % 2 != 0 {
+    if !cleaned.len().is_multiple_of(2) {
         return Err(eyre!(
             "Invalid hex string length ({}). Hex strings must have an even number of characters",
             cleaned.len()
@@ -94,7 +622,7 @@ pub fn decode_hex(s: &str) -> Result<Vec<u8>> {
 struct JsonRpcRequest {
     jsonrpc: &'static str,
     method: &'static str,
-    params: Vec<String>,
+    params: Vec<serde_json::Value>,
     id: u32,
 }
 
@@ -104,34 +632,25 @@ struct JsonRpcResponse {
     error: Option<serde_json::Value>,
 }
 
-/// Fetches contract bytecode from an Ethereum node via JSON-RPC.
-///
-/// # Arguments
-///
-/// * `address` - The Ethereum address of the contract to fetch bytecode from
-/// * `rpc_url` - The URL of the Ethereum JSON-RPC endpoint
-///
-/// # Returns
-///
-/// Returns a `Result` containing either:
-/// * `Ok(Vec<u8>)` - The contract bytecode as a byte vector
-/// * `Err` - If the RPC request fails, the address has no code, or the response is invalid
+/// Sends a single JSON-RPC request and returns its `result` string.
 ///
 /// # Errors
 ///
 /// This function will return an error if:
 /// * The RPC request fails to send
 /// * The RPC response cannot be parsed
-/// * The RPC response contains an error
-/// * The address has no contract code (is an EOA or empty contract)
-/// * The returned bytecode cannot be hex decoded
-async fn fetch_on_chain_bytecode(address: Address, rpc_url: Url) -> Result<Vec<u8>> {
+/// * The RPC response contains an error or no result
+async fn rpc_request(
+    rpc_url: &Url,
+    method: &'static str,
+    params: Vec<serde_json::Value>,
+) -> Result<String> {
     let client = Client::new();
 
     let request = JsonRpcRequest {
         jsonrpc: "2.0",
-        method: "eth_getCode",
-        params: vec![format!("{:#x}", address), "latest".to_string()],
+        method,
+        params,
         id: 1,
     };
 
@@ -151,20 +670,214 @@ async fn fetch_on_chain_bytecode(address: Address, rpc_url: Url) -> Result<Vec<u
         return Err(eyre!("RPC error: {}", error));
     }
 
-    let hex_code = rpc_response
+    rpc_response
         .result
-        .ok_or_else(|| eyre!("Missing result in RPC response"))?;
+        .ok_or_else(|| eyre!("Missing result in RPC response"))
+}
 
-    if hex_code == "0x" {
+/// Fetches contract bytecode from an Ethereum node via JSON-RPC.
+///
+/// # Arguments
+///
+/// * `address` - The Ethereum address of the contract to fetch bytecode from
+/// * `rpc_url` - The URL of the Ethereum JSON-RPC endpoint
+///
+/// # Returns
+///
+/// Returns a `Result` containing either:
+/// * `Ok(Vec<u8>)` - The contract bytecode as a byte vector
+/// * `Err` - If the RPC request fails, the address has no code, or the response is invalid
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// * The RPC request fails to send
+/// * The RPC response cannot be parsed
+/// * The RPC response contains an error
+/// * The address has no contract code (is an EOA or empty contract)
+/// * The returned bytecode cannot be hex decoded
+async fn fetch_on_chain_bytecode(address: Address, rpc_url: Url) -> Result<Vec<u8>> {
+    let code = fetch_code_at(address, rpc_url).await?;
+
+    if code.is_empty() {
         return Err(eyre!(
             "Address {:#x} has no contract code (might be an EOA or empty contract)",
             address
         ));
     }
 
+    Ok(code)
+}
+
+/// Fetches the code at an address via `eth_getCode`; empty for accounts without code.
+///
+/// # Errors
+///
+/// This function will return an error if the RPC request fails or the returned code is not
+/// valid hex.
+pub async fn fetch_code_at(address: Address, rpc_url: Url) -> Result<Vec<u8>> {
+    let hex_code = rpc_request(
+        &rpc_url,
+        "eth_getCode",
+        vec![format!("{:#x}", address).into(), "latest".into()],
+    )
+    .await?;
+
+    if hex_code == "0x" {
+        return Ok(Vec::new());
+    }
+
     decode_hex(&hex_code)
 }
 
+/// Reads a storage slot of a contract via `eth_getStorageAt`.
+///
+/// # Arguments
+///
+/// * `address` - The contract whose storage is read
+/// * `slot` - The storage slot to read
+/// * `rpc_url` - The URL of the Ethereum JSON-RPC endpoint
+///
+/// # Errors
+///
+/// This function will return an error if the RPC request fails or the returned value is not a
+/// valid 32-byte hex word.
+pub async fn fetch_storage_at(address: Address, slot: H256, rpc_url: Url) -> Result<H256> {
+    let value = rpc_request(
+        &rpc_url,
+        "eth_getStorageAt",
+        vec![
+            format!("{:#x}", address).into(),
+            format!("{:#x}", slot).into(),
+            "latest".into(),
+        ],
+    )
+    .await?;
+
+    decode_word(&value)
+}
+
+/// Executes a read-only call against a contract via `eth_call` and returns the raw return data.
+///
+/// # Arguments
+///
+/// * `address` - The contract to call
+/// * `data` - ABI-encoded calldata, starting with the function selector
+/// * `rpc_url` - The URL of the Ethereum JSON-RPC endpoint
+///
+/// # Errors
+///
+/// This function will return an error if the RPC request fails or the call reverts.
+pub async fn call_contract(address: Address, data: &[u8], rpc_url: Url) -> Result<Vec<u8>> {
+    let result = rpc_request(
+        &rpc_url,
+        "eth_call",
+        vec![
+            serde_json::json!({
+                "to": format!("{:#x}", address),
+                "data": format!("0x{}", hex::encode(data)),
+            }),
+            "latest".into(),
+        ],
+    )
+    .await?;
+
+    if result == "0x" {
+        return Ok(Vec::new());
+    }
+
+    decode_hex(&result)
+}
+
+/// Decodes a hex quantity of up to 32 bytes, such as a storage value, into a left-padded word.
+fn decode_word(s: &str) -> Result<H256> {
+    let cleaned = s.trim().trim_start_matches("0x");
+
+    if cleaned.len() > 64 {
+        return Err(eyre!(
+            "Invalid storage value ({} hex characters, expected at most 64)",
+            cleaned.len()
+        ));
+    }
+
+    let padded = format!("{:0>64}", cleaned);
+    let bytes = hex::decode(&padded).map_err(|e| eyre!("Failed to decode storage value: {}", e))?;
+
+    Ok(H256::from_slice(&bytes))
+}
+
+/// Parses solc's `immutableReferences`, either on its own or from the runtime code of an
+/// artifact or standard JSON output.
+///
+/// Standard JSON output is read as by [`read_artifact`], so `contract` picks one of its
+/// contracts and may be left out when only one has code.
+///
+/// # Errors
+///
+/// This function will return an error if:
+/// * The input is not JSON
+/// * The contract is not found, or several match
+/// * No `immutableReferences` object is found
+/// * An AST id or a reference entry is malformed
+pub fn parse_immutable_references(json: &str, contract: Option<&str>) -> Result<Vec<Immutable>> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| eyre!("Failed to parse JSON: {}", e))?;
+    if value.get("contracts").is_some() {
+        return Ok(parse_artifact(&value, "the JSON", contract, false)?.immutables);
+    }
+    let references = find_immutable_references(&value)
+        .ok_or_else(|| eyre!("No immutableReferences found in the JSON"))?;
+    immutables_from_references(references)
+}
+
+fn immutables_from_references(
+    references: &serde_json::Map<String, serde_json::Value>,
+) -> Result<Vec<Immutable>> {
+    let mut immutables = Vec::new();
+    for (id, entries) in references {
+        let ast_id = id
+            .parse()
+            .map_err(|_| eyre!("Invalid AST id {:?} in immutableReferences", id))?;
+        let ranges = entries
+            .as_array()
+            .ok_or_else(|| eyre!("Expected a list of references for AST id {}", id))?
+            .iter()
+            .map(|entry| {
+                let field = |name: &str| entry.get(name).and_then(serde_json::Value::as_u64);
+                match (field("start"), field("length")) {
+                    (Some(start), Some(length)) => match start.checked_add(length) {
+                        Some(end) => Ok(start as usize..end as usize),
+                        None => Err(eyre!("Reference {} for AST id {} overflows", entry, id)),
+                    },
+                    _ => Err(eyre!("Invalid reference {} for AST id {}", entry, id)),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        immutables.push(Immutable::new(Some(ast_id), ranges));
+    }
+    immutables.sort_by_key(|immutable| immutable.ranges.first().map(|range| range.start));
+    Ok(immutables)
+}
+
+/// The first `immutableReferences` object in `value`, or `value` itself when it is one.
+/// Standard JSON output is handled by [`parse_artifact`] instead, as it can hold many.
+fn find_immutable_references(
+    value: &serde_json::Value,
+) -> Option<&serde_json::Map<String, serde_json::Value>> {
+    let object = value.as_object()?;
+    if let Some(references) = object.get("immutableReferences") {
+        return references.as_object();
+    }
+    let is_references = !object.is_empty()
+        && object
+            .iter()
+            .all(|(key, entries)| key.parse::<u64>().is_ok() && entries.is_array());
+    if is_references {
+        return Some(object);
+    }
+    object.values().find_map(find_immutable_references)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -190,4 +903,338 @@ mod tests {
         assert_eq!(decode_hex("60FF").unwrap(), vec![0x60, 0xFF]);
         assert_eq!(decode_hex("60Ff").unwrap(), vec![0x60, 0xFF]);
     }
+
+    #[test]
+    fn test_decode_word_pads_short_values() {
+        let word = decode_word("0x1").unwrap();
+        assert_eq!(word.0[31], 0x01);
+        assert!(word.0[..31].iter().all(|b| *b == 0));
+
+        let full = format!("0x{}", "ab".repeat(32));
+        assert_eq!(decode_word(&full).unwrap().0, [0xab; 32]);
+    }
+
+    #[test]
+    fn test_decode_word_invalid() {
+        assert!(decode_word(&"ab".repeat(33)).is_err());
+        assert!(decode_word("0xzz").is_err());
+    }
+
+    #[test]
+    fn test_parse_immutable_references_from_artifact() {
+        let json = r#"{
+            "abi": [],
+            "deployedBytecode": {
+                "object": "0x00",
+                "immutableReferences": {
+                    "12": [{ "start": 200, "length": 32 }, { "start": 400, "length": 32 }],
+                    "5": [{ "start": 100, "length": 32 }]
+                }
+            }
+        }"#;
+        let immutables = parse_immutable_references(json, None).unwrap();
+        assert_eq!(immutables.len(), 2);
+        assert_eq!(immutables[0].ast_id, Some(5));
+        assert_eq!(immutables[0].ranges.len(), 1);
+        assert_eq!(immutables[0].ranges[0], 100..132);
+        assert_eq!(immutables[1].ast_id, Some(12));
+        assert_eq!(immutables[1].ranges, [200..232, 400..432]);
+
+        let bare = r#"{ "7": [{ "start": 1, "length": 32 }] }"#;
+        let immutables = parse_immutable_references(bare, None).unwrap();
+        assert_eq!(immutables[0].ast_id, Some(7));
+        assert_eq!(immutables[0].ranges.len(), 1);
+        assert_eq!(immutables[0].ranges[0], 1..33);
+    }
+
+    #[test]
+    fn test_parse_immutable_references_picks_contract() {
+        let json = r#"{
+            "contracts": {
+                "src/A.sol": {
+                    "A": {
+                        "evm": {
+                            "deployedBytecode": {
+                                "object": "00",
+                                "immutableReferences": { "3": [{ "start": 1, "length": 32 }] }
+                            }
+                        }
+                    },
+                    "B": {
+                        "evm": {
+                            "deployedBytecode": {
+                                "object": "00",
+                                "immutableReferences": { "9": [{ "start": 8, "length": 32 }] }
+                            }
+                        }
+                    }
+                }
+            }
+        }"#;
+        let immutables = parse_immutable_references(json, Some("B")).unwrap();
+        assert_eq!(immutables[0].ast_id, Some(9));
+        let immutables = parse_immutable_references(json, Some("src/A.sol:A")).unwrap();
+        assert_eq!(immutables[0].ast_id, Some(3));
+
+        let error = parse_immutable_references(json, None).unwrap_err();
+        assert!(error.to_string().contains("pick one with --contract"));
+        assert!(parse_immutable_references(json, Some("C")).is_err());
+    }
+
+    #[test]
+    fn test_parse_immutable_references_invalid() {
+        let parse = |json: &str| parse_immutable_references(json, None);
+        assert!(parse("not json").is_err());
+        assert!(parse(r#"{ "abi": [] }"#).is_err());
+        assert!(parse(r#"{ "immutableReferences": { "x": [] } }"#).is_err());
+        assert!(parse(r#"{ "immutableReferences": { "1": [{ "start": 1 }] } }"#).is_err());
+        let overflow = format!(
+            r#"{{ "1": [{{ "start": {}, "length": 32 }}] }}"#,
+            u64::MAX - 1
+        );
+        assert!(parse(&overflow).is_err());
+    }
+
+    #[test]
+    fn test_decode_bytecode_with_placeholders() {
+        let hash = "0123456789abcdef0123456789abcdef01";
+        let (bytes, placeholders) = decode_bytecode(&format!("0x73__${hash}$__3b")).unwrap();
+        assert_eq!(bytes.len(), 22);
+        assert!(bytes[1..21].iter().all(|b| *b == 0));
+        assert_eq!(placeholders[0].offset, 1);
+        assert_eq!(placeholders[0].hash, hash);
+
+        // Anything else that isn't hex is still rejected
+        assert!(decode_bytecode("73__$nothex$__3b").is_err());
+    }
+
+    fn write_artifact(json: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, json.as_bytes()).unwrap();
+        file
+    }
+
+    fn decode(data: &[u8]) -> Result<Input> {
+        decode_input(data, "test").unwrap()
+    }
+
+    #[test]
+    fn test_detect_format() {
+        let detect = |data: &str| detect_format(data.as_bytes());
+        assert_eq!(detect(" 0x6001\n"), Some(InputFormat::Hex));
+        assert_eq!(detect("60zz"), Some(InputFormat::Hex));
+        assert_eq!(
+            detect("0x6001 6002\n// STOP\n00 # end"),
+            Some(InputFormat::FormattedHex)
+        );
+        assert_eq!(detect("EF0001"), Some(InputFormat::Eof));
+        assert_eq!(detect_format(&[0xef, 0x00, 0x01]), Some(InputFormat::Eof));
+        assert_eq!(detect_format(&[0x60, 0x80]), Some(InputFormat::Binary));
+        assert_eq!(
+            detect(r#"{"jsonrpc": "2.0", "id": 1, "result": "0x00"}"#),
+            Some(InputFormat::RpcResponse)
+        );
+        assert_eq!(
+            detect(r#"{"abi": [], "bytecode": "0x00"}"#),
+            Some(InputFormat::Artifact)
+        );
+        assert_eq!(
+            detect(r#"{"to": null, "input": "0x00"}"#),
+            Some(InputFormat::Transaction)
+        );
+        assert_eq!(detect(" \n# nothing\n"), None);
+        assert_eq!(detect(""), None);
+        // Text with a byte order mark or non-ASCII comments is still hex
+        assert_eq!(detect("\u{feff}0x6001\n"), Some(InputFormat::Hex));
+        assert_eq!(
+            detect("# déployé — 部署\n6001"),
+            Some(InputFormat::FormattedHex)
+        );
+    }
+
+    #[test]
+    fn test_eof_header() {
+        let container = hex::decode("ef000101000402000200030400000000800001600000").unwrap();
+        let header = eof_header(&container).unwrap();
+        assert_eq!(header.version, 1);
+        assert_eq!(header.code_sections, Some(2));
+
+        assert_eq!(
+            eof_header(&[0xef, 0x00, 0x01, 0x02]),
+            Some(EofHeader {
+                version: 1,
+                code_sections: None
+            })
+        );
+        assert_eq!(eof_header(&[0xef, 0x00]), None);
+        assert_eq!(eof_header(&[0x60, 0x00]), None);
+    }
+
+    #[test]
+    fn test_decode_input_formats() {
+        let input = decode(b"0x6001 // PUSH1 1\n0x00").unwrap();
+        assert_eq!(input.bytes, [0x60, 0x01, 0x00]);
+        assert_eq!(input.format, Some(InputFormat::FormattedHex));
+        assert!(decode(b"60zz").is_err());
+
+        // Not text, so taken as bytecode as-is
+        let input = decode(&[0x60, 0x80, 0x00]).unwrap();
+        assert_eq!(input.bytes, [0x60, 0x80, 0x00]);
+        assert_eq!(input.format, Some(InputFormat::Binary));
+
+        let rpc = br#"{"jsonrpc": "2.0", "id": 1, "result": "0x6002"}"#;
+        assert_eq!(decode(rpc).unwrap().bytes, [0x60, 0x02]);
+        let with_bom = [&UTF8_BOM[..], rpc].concat();
+        assert_eq!(decode(&with_bom).unwrap().bytes, [0x60, 0x02]);
+        let with_bom = [&UTF8_BOM[..], "# déployé\n6003".as_bytes()].concat();
+        assert_eq!(decode(&with_bom).unwrap().bytes, [0x60, 0x03]);
+        let transaction =
+            br#"{"jsonrpc": "2.0", "id": 1, "result": {"hash": "0x01", "input": "0x6003"}}"#;
+        assert_eq!(decode(transaction).unwrap().bytes, [0x60, 0x03]);
+        let transaction = br#"{"from": "0x01", "data": "0x6004"}"#;
+        let input = decode(transaction).unwrap();
+        assert_eq!(input.bytes, [0x60, 0x04]);
+        assert_eq!(input.format, Some(InputFormat::Transaction));
+
+        assert!(decode_input(b" \n", "test").is_none());
+    }
+
+    #[test]
+    fn test_decode_input_errors() {
+        let error = |data: &[u8]| decode(data).unwrap_err().to_string();
+        assert!(
+            error(br#"{"jsonrpc": "2.0", "id": 1, "error": {"message": "rate limited"}}"#)
+                .contains("JSON-RPC error in test: rate limited")
+        );
+        assert!(error(br#"{"jsonrpc": "2.0", "id": 1, "result": "0x"}"#).contains("holds no code"));
+        assert!(error(br#"{"to": "0x01", "input": "0x"}"#).contains("has no input"));
+        assert!(error(br#"{"name": "x"}"#).contains("holds JSON that is not"));
+    }
+
+    #[test]
+    fn test_read_large_files_are_mapped() {
+        let code: Vec<u8> = (0..MMAP_THRESHOLD + 1).map(|i| (i % 251) as u8).collect();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, &code).unwrap();
+
+        let input = read_code_file(file.path(), false).unwrap();
+        assert_eq!(input.bytes, code);
+        assert_eq!(input.format, Some(InputFormat::Binary));
+
+        // Hex is decoded from the map too
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, hex::encode(&code).as_bytes()).unwrap();
+        let input = read_code_file(file.path(), false).unwrap();
+        assert_eq!(input.bytes, code);
+        assert_eq!(input.format, Some(InputFormat::Hex));
+    }
+
+    #[test]
+    fn test_binary_overrides_detection() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"6001").unwrap();
+
+        let input = read_code_file(file.path(), true).unwrap();
+        assert_eq!(input.bytes, b"6001");
+        assert_eq!(input.format, None);
+        assert_eq!(
+            read_code_file(file.path(), false).unwrap().bytes,
+            [0x60, 0x01]
+        );
+    }
+
+    #[test]
+    fn test_read_foundry_artifact() {
+        let file = write_artifact(
+            r#"{
+                "abi": [{ "type": "function", "name": "f", "inputs": [] }],
+                "bytecode": { "object": "0x6080", "sourceMap": "" },
+                "deployedBytecode": {
+                    "object": "0x7f0000000000000000000000000000000000000000000000000000000000000000",
+                    "sourceMap": "0:1:0:-:0",
+                    "immutableReferences": { "3": [{ "start": 1, "length": 32 }] }
+                },
+                "metadata": { "settings": { "compilationTarget": { "src/A.sol": "A" } } }
+            }"#,
+        );
+
+        let runtime = read_artifact(file.path(), None, false).unwrap();
+        assert_eq!(runtime.name.as_deref(), Some("A"));
+        assert_eq!(runtime.bytes.len(), 33);
+        assert_eq!(runtime.source_map.as_deref(), Some("0:1:0:-:0"));
+        assert_eq!(runtime.immutables[0].ast_id, Some(3));
+        assert!(runtime.abi.is_some());
+
+        let creation = read_artifact(file.path(), Some("src/A.sol:A"), true).unwrap();
+        assert_eq!(creation.bytes, [0x60, 0x80]);
+        assert_eq!(creation.source_map, None);
+        assert!(creation.immutables.is_empty());
+
+        assert!(read_artifact(file.path(), Some("B"), false).is_err());
+    }
+
+    #[test]
+    fn test_read_hardhat_artifact() {
+        let file = write_artifact(
+            r#"{
+                "_format": "hh-sol-artifact-1",
+                "contractName": "Lib",
+                "sourceName": "contracts/Lib.sol",
+                "abi": [],
+                "bytecode": "0x6001",
+                "deployedBytecode": "0x"
+            }"#,
+        );
+
+        let creation = read_artifact(file.path(), None, true).unwrap();
+        assert_eq!(creation.name.as_deref(), Some("Lib"));
+        assert_eq!(creation.bytes, [0x60, 0x01]);
+
+        let error = read_artifact(file.path(), None, false).unwrap_err();
+        assert!(error.to_string().contains("Lib has no runtime code"));
+    }
+
+    #[test]
+    fn test_read_standard_json_output() {
+        let file = write_artifact(
+            r#"{
+                "contracts": {
+                    "a.sol": {
+                        "A": { "abi": [], "evm": { "deployedBytecode": { "object": "600100" } } },
+                        "I": { "abi": [], "evm": { "deployedBytecode": { "object": "" } } }
+                    },
+                    "b.sol": {
+                        "B": { "abi": [], "evm": { "deployedBytecode": { "object": "600200" } } }
+                    }
+                }
+            }"#,
+        );
+
+        let error = read_artifact(file.path(), None, false).unwrap_err();
+        assert!(error.to_string().contains("a.sol:A, b.sol:B"));
+        assert_eq!(
+            read_artifact(file.path(), Some("B"), false).unwrap().bytes,
+            [0x60, 0x02, 0x00]
+        );
+        assert_eq!(
+            read_artifact(file.path(), Some("a.sol:A"), false)
+                .unwrap()
+                .name
+                .as_deref(),
+            Some("A")
+        );
+        assert!(read_artifact(file.path(), Some("C"), false).is_err());
+        assert!(read_artifact(file.path(), Some("I"), false).is_err());
+    }
+
+    #[test]
+    fn test_read_unrecognised_artifact() {
+        let file = write_artifact(r#"{ "abi": [] }"#);
+        let error = read_artifact(file.path(), None, false).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("is not a Foundry, Hardhat or solc")
+        );
+    }
 }
@@ -1,8 +1,28 @@
-use clap::Parser;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+
+use clap::{ArgGroup, Parser, Subcommand};
+use color_eyre::eyre::eyre;
 use colored::*;
-use evm_lens_core::{Stats, disassemble, get_stats};
+use debugger::{Breakpoint, Debugger};
+use evm_lens_core::{
+    Address, B256, CallParams, Cluster, CodeDiff, CodeDiffOptions, CodeKind, DEFAULT_CALLER,
+    DEFAULT_GAS_LIMIT, DecompiledFunction, DiffEntry, EIP170_MAX_CODE_SIZE,
+    EIP3860_MAX_INITCODE_SIZE, Execution, ExitStatus, Exploration, Finding, Fingerprint,
+    FunctionEntry, IMPLEMENTATION_SELECTOR, Immutable, JumpKind, LibraryPlaceholder, LimitUsage,
+    MinHash, MinimalProxy, OpCode, OpcodeCategory, PackedField, PathEnd, ProxyKind, ReentrancyRisk,
+    Severity, SizeLimits, Stats, StorageChange, StorageDiff, StorageLayout, SymbolicLimits,
+    SymbolicPath, Trace, TraceOptions, TraceStep, U256, UpgradeableProxy, address_from_word,
+    cluster, decode_instructions, decompile, detect_code_kind, detect_immutables,
+    detect_minimal_proxy, detect_upgradeable_proxy, diff_bytecode, diff_storage_layouts,
+    disassemble, execute, explore_paths, find_reentrancy, fingerprint, function_entries,
+    function_selector, get_stats, get_stats_with_limits, immutable_pushes, infer_storage_layout,
+    link_libraries, lint, parse_source_map, trace_with,
+};
 use io::Source;
+use url::Url;
 
+mod debugger;
 mod io;
 
 #[derive(Parser)]
@@ -14,12 +34,39 @@ mod io;
     evm-lens 60FF                              # Simple PUSH1 instruction from arg
     echo '0x60FF61ABCD00' | evm-lens --stdin   # From stdin
     evm-lens --file bytecode.txt               # From file
+    evm-lens --binary code.bin                 # From a raw binary file
     evm-lens --address 0x... --rpc http://...  # From blockchain
     evm-lens 60FF61ABCD00 --stats              # Show disassembly + statistics
+    evm-lens --file bytecode.txt --lint        # Flag risky patterns
+    evm-lens --file bytecode.txt --decompile   # Print pseudo-code per function
+    evm-lens --file bytecode.txt --format jsonl  # One JSON record per instruction
+    evm-lens --file bytecode.txt --symbolic    # Find paths to reverts and risky calls
+    evm-lens --address 0x... --storage         # Infer the storage layout
+    evm-lens diff-storage old.txt new.txt      # Check an upgrade for storage collisions
+    evm-lens diff old.txt new.txt              # Align two bytecodes instruction by instruction
+    evm-lens cluster contracts/                # Group near-duplicate contracts
+    evm-lens batch out/ --details              # Summarise every contract in a directory
+    evm-lens run code.txt --calldata 0x...     # Execute locally and show the result
+    evm-lens trace code.txt --json             # Print an EIP-3155 step trace
+    evm-lens debug code.txt --break SSTORE     # Step through execution interactively
+    evm-lens --file init.txt --creation        # Check initcode against EIP-3860
+    evm-lens --artifact out/Token.sol/Token.json  # Read a Foundry or Hardhat artifact
+    evm-lens --file a.txt --immutable-refs a.json  # Label immutables from solc output
+    evm-lens --file lib.txt --libraries a.sol:A=0x... --link  # Link library placeholders
+    evm-lens --address 0x... --raw             # Disassemble even if it is a minimal proxy
+    evm-lens --address 0x... --follow-proxy    # Disassemble the proxy's implementation too
 
 For more information, visit: https://github.com/andyrobert3/evm-lens"
 )]
+#[command(group(
+    ArgGroup::new("contract_files")
+        .args(["artifact", "immutable_refs"])
+        .multiple(true)
+))]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     #[arg(
         help = "Hexadecimal EVM bytecode to disassemble (if no other source specified)",
         value_name = "BYTECODE",
@@ -42,6 +89,14 @@ struct Args {
     )]
     file: Option<String>,
 
+    #[arg(
+        long,
+        help = "Read raw bytecode bytes from a file instead of hex (files that are not text are detected anyway)",
+        value_name = "FILE",
+        conflicts_with_all = ["hex", "stdin", "file", "address", "artifact"]
+    )]
+    binary: Option<String>,
+
     #[arg(
         long,
         help = "Ethereum address to fetch bytecode from",
@@ -50,6 +105,22 @@ struct Args {
     )]
     address: Option<String>,
 
+    #[arg(
+        long,
+        help = "Read bytecode from a Foundry, Hardhat or solc standard JSON artifact",
+        value_name = "FILE",
+        conflicts_with_all = ["hex", "stdin", "file", "address"]
+    )]
+    artifact: Option<String>,
+
+    #[arg(
+        long,
+        help = "Contract to read from the artifact or --immutable-refs file, as Name or path:Name",
+        value_name = "NAME",
+        requires = "contract_files"
+    )]
+    contract: Option<String>,
+
     #[arg(
         long,
         help = "RPC endpoint URL for fetching on-chain bytecode",
@@ -58,8 +129,299 @@ struct Args {
     )]
     rpc: Option<String>,
 
+    #[arg(
+        long,
+        value_enum,
+        help = "Output format: colored text, or a JSON document or JSON Lines for scripts",
+        default_value_t = OutputFormat::Text
+    )]
+    format: OutputFormat,
+
     #[arg(long, help = "Show bytecode statistics after disassembly")]
     stats: bool,
+
+    #[arg(long, help = "Flag risky bytecode patterns after disassembly")]
+    lint: bool,
+
+    #[arg(
+        long,
+        help = "Explore each function's paths symbolically and report reverts, self-destructs and calls to calldata-chosen addresses"
+    )]
+    symbolic: bool,
+
+    #[arg(
+        long,
+        help = "Print Solidity-like pseudo-code per function instead of the disassembly"
+    )]
+    decompile: bool,
+
+    #[arg(long, help = "Show the inferred storage layout after disassembly")]
+    storage: bool,
+
+    #[arg(
+        long,
+        help = "Print only the inferred storage layout, as JSON",
+        conflicts_with = "storage"
+    )]
+    storage_json: bool,
+
+    #[arg(
+        long,
+        help = "Print the full disassembly even when the bytecode is a recognised minimal proxy"
+    )]
+    raw: bool,
+
+    #[arg(
+        long,
+        help = "Also disassemble the implementation behind a detected proxy",
+        requires = "address",
+        conflicts_with_all = ["hex", "stdin", "file", "binary", "artifact"]
+    )]
+    follow_proxy: bool,
+
+    #[arg(
+        long,
+        help = "Treat the input as creation code (detected automatically otherwise)"
+    )]
+    creation: bool,
+
+    #[arg(
+        long,
+        help = "Runtime code size limit in bytes, for chains that differ from EIP-170",
+        value_name = "BYTES",
+        default_value_t = EIP170_MAX_CODE_SIZE
+    )]
+    max_code_size: usize,
+
+    #[arg(
+        long,
+        help = "Initcode size limit in bytes, for chains that differ from EIP-3860",
+        value_name = "BYTES",
+        default_value_t = EIP3860_MAX_INITCODE_SIZE
+    )]
+    max_initcode_size: usize,
+
+    #[arg(
+        long,
+        help = "Mark immutables from solc's immutableReferences in an artifact or standard JSON output",
+        value_name = "FILE"
+    )]
+    immutable_refs: Option<String>,
+
+    #[arg(
+        long,
+        help = "Mark likely immutables by comparing with another deployment of the same code",
+        value_name = "BYTECODE",
+        conflicts_with = "immutable_refs"
+    )]
+    immutables_from: Option<String>,
+
+    #[arg(
+        long,
+        help = "Library addresses for unlinked bytecode, by fully qualified name or placeholder hash",
+        value_name = "NAME=ADDRESS",
+        value_delimiter = ','
+    )]
+    libraries: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Print the bytecode linked with --libraries instead of the disassembly",
+        requires = "libraries"
+    )]
+    link: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compare the storage layouts of two implementations before a proxy upgrade
+    DiffStorage {
+        #[arg(
+            help = "Old implementation: hex bytecode, a file or artifact containing it, or an address"
+        )]
+        old: String,
+
+        #[arg(
+            help = "New implementation: hex bytecode, a file or artifact containing it, or an address"
+        )]
+        new: String,
+
+        #[arg(
+            long,
+            help = "RPC endpoint URL for addresses",
+            value_name = "URL",
+            default_value = "https://eth.llamarpc.com"
+        )]
+        rpc: String,
+    },
+
+    /// Compare two bytecodes instruction by instruction, ignoring metadata trailers
+    Diff {
+        #[arg(help = "Old bytecode: hex, a file or artifact containing it, or an address")]
+        old: String,
+
+        #[arg(help = "New bytecode: hex, a file or artifact containing it, or an address")]
+        new: String,
+
+        #[arg(
+            long,
+            help = "Match PUSH32 instructions by opcode alone, as immutables fill them at deployment"
+        )]
+        ignore_immutables: bool,
+
+        #[arg(
+            long,
+            help = "Unchanged instructions to show around each change",
+            value_name = "N",
+            default_value_t = 3
+        )]
+        context: usize,
+
+        #[arg(
+            long,
+            help = "RPC endpoint URL for addresses",
+            value_name = "URL",
+            default_value = "https://eth.llamarpc.com"
+        )]
+        rpc: String,
+    },
+
+    /// Group a directory of bytecode files into clusters of near-duplicates
+    Cluster {
+        #[arg(help = "Directory of files containing hex bytecode, searched recursively")]
+        dir: std::path::PathBuf,
+
+        #[arg(
+            long,
+            help = "Minimum similarity, from 0 to 1, for two contracts to share a cluster",
+            value_name = "SCORE",
+            default_value_t = 0.8
+        )]
+        threshold: f64,
+    },
+
+    /// Summarise many contracts at once, continuing past inputs that fail
+    Batch {
+        #[arg(
+            help = "Files and directories to read, directories recursively; without any, a newline-separated list of hex bytecodes, addresses or paths is read from stdin"
+        )]
+        inputs: Vec<String>,
+
+        #[arg(long, help = "Print each contract's statistics after the summary")]
+        details: bool,
+
+        #[arg(
+            long,
+            help = "Contracts to load and analyse at once",
+            value_name = "N",
+            default_value_t = 8
+        )]
+        jobs: usize,
+
+        #[arg(
+            long,
+            help = "RPC endpoint URL for addresses",
+            value_name = "URL",
+            default_value = "https://eth.llamarpc.com"
+        )]
+        rpc: String,
+    },
+
+    /// Execute bytecode locally in an empty in-memory EVM, without network access
+    Run {
+        #[command(flatten)]
+        call: CallArgs,
+    },
+
+    /// Execute bytecode locally and print every executed step
+    Trace {
+        #[command(flatten)]
+        call: CallArgs,
+
+        #[arg(
+            long,
+            help = "Stack items to show per step, top first",
+            value_name = "N",
+            default_value_t = 4
+        )]
+        stack_items: usize,
+
+        #[arg(long, help = "Show the memory words each step changes")]
+        memory: bool,
+
+        #[arg(long, help = "Show the storage slot each SSTORE writes")]
+        storage: bool,
+
+        #[arg(
+            long,
+            help = "Print EIP-3155 JSON lines, for comparing with other EVM tools"
+        )]
+        json: bool,
+    },
+
+    /// Step forward and back through a local execution in an interactive terminal UI
+    Debug {
+        #[command(flatten)]
+        call: CallArgs,
+
+        #[arg(
+            long = "break",
+            help = "Stop `continue` at a hex position or an opcode (repeatable)",
+            value_name = "PC|OPCODE"
+        )]
+        breakpoints: Vec<Breakpoint>,
+
+        #[arg(
+            long,
+            help = "Stop recording after this many steps; the execution still runs to the end",
+            value_name = "N",
+            default_value_t = 1_000_000
+        )]
+        max_steps: usize,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    Text,
+    /// One JSON document
+    Json,
+    /// One JSON object per line: a header, then each instruction, function and finding
+    Jsonl,
+}
+
+/// The call made by `run`, `trace` and `debug`.
+#[derive(clap::Args)]
+struct CallArgs {
+    #[arg(help = "Runtime bytecode: hex, or a file or artifact containing it")]
+    bytecode: String,
+
+    #[arg(long, help = "Calldata as hex", value_name = "HEX", default_value = "")]
+    calldata: String,
+
+    #[arg(
+        long,
+        help = "Wei to send, in decimal or 0x-prefixed hex",
+        value_name = "WEI",
+        default_value = "0"
+    )]
+    value: String,
+
+    #[arg(
+        long,
+        help = "Address of the caller",
+        value_name = "ADDRESS",
+        default_value_t = DEFAULT_CALLER.to_string()
+    )]
+    caller: String,
+
+    #[arg(
+        long,
+        help = "Transaction gas limit",
+        value_name = "GAS",
+        default_value_t = DEFAULT_GAS_LIMIT
+    )]
+    gas_limit: u64,
 }
 
 fn categorize_opcode(opcode_str: &str) -> ColoredString {
@@ -112,134 +474,2158 @@ fn print_footer(total_opcodes: usize) {
     );
 }
 
-fn print_opcode(position: usize, opcode: &str) {
+/// Prints the listing; `labels` names the immediates of instructions by position, such as
+/// immutables.
+fn print_disassembly(ops: &[(usize, OpCode)], labels: &BTreeMap<usize, String>) {
+    print_header();
+
+    for (position, opcode) in ops.iter() {
+        print_opcode(*position, opcode.as_str(), labels.get(position));
+    }
+
+    print_footer(ops.len());
+}
+
+fn print_opcode(position: usize, opcode: &str, label: Option<&String>) {
     let colored_opcode = categorize_opcode(opcode);
+    let label = label
+        .map(|label| format!(" <{}>", label).magenta().to_string())
+        .unwrap_or_default();
 
     println!(
-        "{} {} {}",
+        "{} {} {}{}",
         format!("{:04x}", position).bright_black(),
         "│".bright_black(),
-        colored_opcode
+        colored_opcode,
+        label
     );
 }
 
-fn print_error(message: &str) {
-    eprintln!("{} {}", "Error:".bright_red().bold(), message);
-}
+fn print_decompiled(functions: &[DecompiledFunction]) {
+    println!("{}", "PSEUDO-CODE".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
 
-fn print_usage_hint() {
-    eprintln!();
-    eprintln!("{}", "Usage examples:".bright_blue().bold());
-    eprintln!("  {} 60FF61ABCD00", "evm-lens".bright_green());
-    eprintln!("  {} 0x60FF61ABCD00", "evm-lens".bright_green());
-    eprintln!("  {} --stdin", "evm-lens".bright_green());
-    eprintln!("  {} --file bytecode.txt", "evm-lens".bright_green());
-    eprintln!(
-        "  {} --address 0x123... --rpc https://eth.llamarpc.com",
-        "evm-lens".bright_green()
-    );
-    eprintln!("  {} 60FF61ABCD00 --stats", "evm-lens".bright_green());
-    eprintln!();
-    eprintln!(
+    for (index, function) in functions.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        println!(
+            "{} {} {}",
+            function.signature().bold(),
+            "{".bold(),
+            format!("// entry {:04x}", function.entry).bright_black()
+        );
+        for line in &function.lines {
+            println!("    {}", color_statement(line));
+        }
+        println!("{}", "}".bold());
+    }
+
+    println!("{}", "=".repeat(50).bright_black());
+    println!(
         "{}",
-        "The input should be valid hexadecimal EVM bytecode.".bright_black()
+        format!("{} functions total", functions.len()).bright_black()
     );
 }
 
-async fn get_bytes_from_args(args: &Args) -> color_eyre::Result<Vec<u8>> {
-    match (&args.hex, &args.address, &args.file, args.stdin) {
-        (Some(hex_string), None, None, false) => io::decode_hex(hex_string),
-        (None, Some(address_str), None, false) => {
-            let rpc_url = args.rpc.as_deref().unwrap_or("https://eth.llamarpc.com");
+/// Colors a pseudo-code line by the statement it starts with.
+fn color_statement(line: &str) -> ColoredString {
+    let statement = line.trim_start();
+    let keyword = statement
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or_default();
+    match keyword {
+        "revert" | "invalid" | "selfdestruct" => line.red(),
+        "return" | "stop" => line.green(),
+        "if" | "while" | "break" | "continue" | "goto" | "" => line.cyan(),
+        _ if statement.starts_with("label_") => line.bright_black(),
+        _ => line.normal(),
+    }
+}
 
-            let address = address_str
-                .parse()
-                .map_err(|_| color_eyre::eyre::eyre!("Invalid address: {}", address_str))?;
+fn print_symbolic_execution(bytes: &[u8]) {
+    println!("{}", "SYMBOLIC EXECUTION".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
 
-            let rpc = rpc_url
-                .parse()
-                .map_err(|_| color_eyre::eyre::eyre!("Invalid RPC URL: {}", rpc_url))?;
+    let mut total = 0;
+    for (function, exploration) in explore_functions(bytes) {
+        total += exploration.paths.len();
+        let name = function.map_or("main".to_string(), |function| function.selector_hex());
+        print_exploration(&name, &exploration);
+    }
 
-            let source = Source::OnChain { address, rpc };
-            io::fetch_bytes(source).await
-        }
-        (None, None, Some(file_path), false) => {
-            let source = Source::File(file_path.into());
-            io::fetch_bytes(source).await
-        }
-        (None, None, None, true) => {
-            let source = Source::Stdin;
-            io::fetch_bytes(source).await
+    println!("{}", "=".repeat(50).bright_black());
+    println!("{}", format!("{} paths total", total).bright_black());
+}
+
+/// Explores the paths of each dispatcher entry, or of the whole contract as one function when
+/// there is no dispatcher.
+fn explore_functions(bytes: &[u8]) -> Vec<(Option<FunctionEntry>, Exploration)> {
+    let limits = SymbolicLimits::default();
+    let functions = function_entries(bytes);
+    if functions.is_empty() {
+        return vec![(None, explore_paths(bytes, 0, &limits))];
+    }
+    functions
+        .into_iter()
+        .map(|function| {
+            let exploration = explore_paths(bytes, function.entry, &limits);
+            (Some(function), exploration)
+        })
+        .collect()
+}
+
+fn print_exploration(name: &str, exploration: &Exploration) {
+    let arbitrary_calls = exploration.with_arbitrary_calls().count();
+    println!(
+        "{} │ {} paths{}: {} revert, {} selfdestruct, {} arbitrary call",
+        name.cyan(),
+        exploration.paths.len(),
+        if exploration.path_limit_reached {
+            " (limit reached)"
+        } else {
+            ""
+        },
+        exploration.count(PathEnd::Revert),
+        exploration.count(PathEnd::SelfDestruct),
+        arbitrary_calls
+    );
+
+    for path in &exploration.paths {
+        if path.end == PathEnd::SelfDestruct {
+            print_path("SELFDESTRUCT".bright_red().bold(), path.end_pc, path);
         }
-        _ => {
-            let source = Source::Stdin;
-            io::fetch_bytes(source).await
+        for &call in &path.arbitrary_calls {
+            print_path("CALL".bright_red().bold(), call, path);
         }
     }
 }
 
-#[tokio::main]
-async fn main() -> color_eyre::Result<()> {
-    color_eyre::install()?;
+fn print_path(label: ColoredString, pc: usize, path: &SymbolicPath) {
+    let conditions = if path.conditions.is_empty() {
+        "always".to_string()
+    } else {
+        let conditions: Vec<String> = path.conditions.iter().map(|c| c.to_string()).collect();
+        format!("when {}", conditions.join(" && "))
+    };
+    println!(
+        "       {} at {} {}",
+        label,
+        format!("{:04x}", pc).bold(),
+        conditions.bright_black()
+    );
+}
 
-    let args = Args::parse();
+fn print_lint_findings(findings: &[Finding]) {
+    println!("{}", "LINT FINDINGS".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
 
-    let bytes = match get_bytes_from_args(&args).await {
-        Ok(bytes) => bytes,
-        Err(e) => {
-            print_error(&format!("{}", e));
-            print_usage_hint();
-            std::process::exit(1);
-        }
-    };
+    if findings.is_empty() {
+        println!("{}", "No findings".green());
+        return;
+    }
 
-    let ops = match disassemble(&bytes) {
-        Ok(ops) => ops,
-        Err(e) => {
-            print_error(&format!("Failed to disassemble bytecode: {}", e));
-            eprintln!();
-            eprintln!("{}", "This could happen if:".bright_blue().bold());
-            eprintln!("  • The bytecode is malformed or incomplete");
-            eprintln!("  • The bytecode contains invalid opcodes");
-            eprintln!("  • The bytecode structure is corrupted");
-            print_usage_hint();
-            std::process::exit(1);
+    for finding in findings {
+        let severity = format!("{:<6}", finding.severity.as_str().to_uppercase());
+        let severity = match finding.severity {
+            Severity::High => severity.bright_red().bold(),
+            Severity::Medium => severity.yellow().bold(),
+            Severity::Low => severity.cyan(),
+            Severity::Info => severity.bright_black(),
+        };
+        println!(
+            "{} │ {} {} {}",
+            format!("{:04x}", finding.pc).bright_black(),
+            severity,
+            finding.rule.id().bold(),
+            finding.rule.name()
+        );
+        println!("       {}", finding.message);
+    }
+
+    println!("{}", "=".repeat(50).bright_black());
+    println!(
+        "{}",
+        format!("{} findings total", findings.len()).bright_black()
+    );
+}
+
+fn print_reentrancy_risks(risks: &[ReentrancyRisk]) {
+    println!(
+        "{}",
+        "STATE WRITES AFTER EXTERNAL CALLS".bright_blue().bold()
+    );
+    println!("{}", "=".repeat(50).bright_black());
+
+    if risks.is_empty() {
+        println!("{}", "No unguarded calls followed by SSTORE".green());
+        return;
+    }
+
+    for risk in risks {
+        let stores = risk
+            .sstore_pcs
+            .iter()
+            .map(|pc| format!("{:04x}", pc))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "{} │ {} at {} may be followed by {} at {}",
+            risk.selector_hex().cyan(),
+            "CALL".bright_red().bold(),
+            format!("{:04x}", risk.call_pc).bold(),
+            "SSTORE".magenta(),
+            stores
+        );
+    }
+
+    println!("{}", "=".repeat(50).bright_black());
+    println!(
+        "{}",
+        format!("{} call sites total", risks.len()).bright_black()
+    );
+}
+
+fn format_selectors(selectors: &[[u8; 4]]) -> String {
+    if selectors.is_empty() {
+        return "-".to_string();
+    }
+    selectors
+        .iter()
+        .map(|selector| format!("0x{}", hex::encode(selector)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn print_storage_layout(layout: &StorageLayout) {
+    println!("{}", "STORAGE LAYOUT".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
+
+    if layout.variables.is_empty() {
+        println!("{}", "No storage accesses with known slots".bright_black());
+        return;
+    }
+
+    println!(
+        "{}",
+        format!(
+            "{:<12} {:<8} {:<18} {:>5} {:>6}",
+            "SLOT", "KIND", "PACKING", "READS", "WRITES"
+        )
+        .bold()
+    );
+    for variable in &layout.variables {
+        let packing = format_fields(&variable.fields);
+        println!(
+            "{:<12} {:<8} {:<18} {:>5} {:>6}",
+            format!("{:#x}", variable.slot).magenta(),
+            variable.kind.as_str(),
+            packing,
+            variable.read_pcs.len(),
+            variable.write_pcs.len()
+        );
+        if !variable.readers.is_empty() || !variable.writers.is_empty() {
+            println!(
+                "{}",
+                format!(
+                    "             read by {}; written by {}",
+                    format_selectors(&variable.readers),
+                    format_selectors(&variable.writers)
+                )
+                .bright_black()
+            );
         }
+    }
+
+    println!("{}", "=".repeat(50).bright_black());
+    println!(
+        "{}",
+        "Packing is offset+width in bytes from the low end of the slot".bright_black()
+    );
+}
+
+fn storage_layout_json(layout: &StorageLayout) -> serde_json::Value {
+    let selectors = |selectors: &[[u8; 4]]| -> Vec<String> {
+        selectors
+            .iter()
+            .map(|selector| format!("0x{}", hex::encode(selector)))
+            .collect()
     };
 
-    if ops.is_empty() {
-        print_error("No opcodes found in the provided bytecode");
-        print_usage_hint();
-        std::process::exit(1);
-    }
+    let variables: Vec<serde_json::Value> = layout
+        .variables
+        .iter()
+        .map(|variable| {
+            serde_json::json!({
+                "slot": format!("{:#x}", variable.slot),
+                "kind": variable.kind.as_str(),
+                "fields": variable
+                    .fields
+                    .iter()
+                    .map(|field| serde_json::json!({ "offset": field.offset, "width": field.width }))
+                    .collect::<Vec<_>>(),
+                "readPcs": variable.read_pcs,
+                "writePcs": variable.write_pcs,
+                "readers": selectors(&variable.readers),
+                "writers": selectors(&variable.writers),
+            })
+        })
+        .collect();
 
-    print_header();
+    serde_json::json!({ "variables": variables })
+}
 
-    for (position, opcode) in ops.iter() {
-        print_opcode(*position, opcode.as_str());
+/// Whether the code is runtime or creation code, from `--creation` or the code itself.
+fn code_kind(args: &Args, bytes: &[u8]) -> CodeKind {
+    // On-chain code is always runtime code
+    if args.creation {
+        CodeKind::Creation
+    } else if args.address.is_some() {
+        CodeKind::Runtime
+    } else {
+        detect_code_kind(bytes)
     }
+}
 
-    print_footer(ops.len());
+/// Version of the `--format json` and `jsonl` schema, raised on incompatible changes.
+const JSON_SCHEMA_VERSION: u32 = 1;
 
-    if args.stats {
-        println!();
-        match get_stats(&bytes) {
-            Ok(Stats {
-                byte_len,
-                opcode_count,
-                max_stack_depth,
-            }) => {
-                println!("{}", "BYTECODE STATISTICS".bright_blue().bold());
-                println!("{}", "=".repeat(50).bright_black());
-                println!("Byte length: {}", byte_len);
-                println!("Number of opcodes: {}", opcode_count);
-                println!("Max stack depth: {}", max_stack_depth);
+/// Top-level arrays that `--format jsonl` writes one record per line, with their record type.
+const JSONL_RECORDS: [(&str, &str); 7] = [
+    ("instructions", "instruction"),
+    ("functions", "function"),
+    ("findings", "finding"),
+    ("reentrancy_risks", "reentrancy_risk"),
+    ("storage_variables", "storage_variable"),
+    ("decompiled_functions", "decompiled_function"),
+    ("explorations", "exploration"),
+];
+
+/// Builds the `--format json` document, described under "JSON output" in the README.
+///
+/// `labels` are the instruction labels of the text listing, and `signatures` the ABI function
+/// signatures that name dispatcher entries.
+fn json_report(
+    args: &Args,
+    bytes: &[u8],
+    input_format: Option<io::InputFormat>,
+    contract: Option<&str>,
+    labels: &BTreeMap<usize, String>,
+    signatures: &[String],
+) -> serde_json::Value {
+    let code_kind = code_kind(args, bytes);
+    let limits = SizeLimits::new(args.max_code_size, args.max_initcode_size);
+    let (size_limit, _) = size_limit_usage(bytes.len(), code_kind, &limits);
+    let (stats, stats_error) = match get_stats_with_limits(bytes, &limits) {
+        Ok(stats) => (Some(stats), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    let instructions: Vec<serde_json::Value> = decode_instructions(bytes)
+        .iter()
+        .map(|instruction| {
+            let mut value = serde_json::json!(instruction);
+            if let Some(label) = labels.get(&instruction.pc) {
+                value["label"] = label.as_str().into();
             }
+            value
+        })
+        .collect();
+    let functions: Vec<serde_json::Value> = function_entries(bytes)
+        .iter()
+        .map(|entry| {
+            let mut value = serde_json::json!(entry);
+            if let Some(signature) = signatures
+                .iter()
+                .find(|signature| function_selector(signature) == entry.selector)
+            {
+                value["signature"] = signature.as_str().into();
+            }
+            value
+        })
+        .collect();
+
+    let mut report = serde_json::json!({
+        "schema_version": JSON_SCHEMA_VERSION,
+        "input_format": input_format.map(|format| format.id()),
+        "contract": contract,
+        "code_kind": code_kind,
+        "byte_len": bytes.len(),
+        "size_limit": size_limit,
+        "fingerprint": fingerprint(bytes),
+        "stats": stats,
+        "stats_error": stats_error,
+        "minimal_proxy": detect_minimal_proxy(bytes),
+        "upgradeable_proxy": detect_upgradeable_proxy(bytes),
+        "instructions": instructions,
+        "functions": functions,
+    });
+    if args.lint {
+        report["findings"] = serde_json::json!(lint(bytes));
+        report["reentrancy_risks"] = serde_json::json!(find_reentrancy(bytes));
+    }
+    if args.storage {
+        report["storage_variables"] = serde_json::json!(infer_storage_layout(bytes).variables);
+    }
+    if args.decompile {
+        report["decompiled_functions"] = serde_json::json!(decompile(bytes));
+    }
+    if args.symbolic {
+        let explorations: Vec<serde_json::Value> = explore_functions(bytes)
+            .iter()
+            .map(|(function, exploration)| {
+                let mut value = serde_json::json!(exploration);
+                value["selector"] = serde_json::json!(function.map(|f| f.selector_hex()));
+                value["entry"] = function.map_or(0, |f| f.entry).into();
+                value
+            })
+            .collect();
+        report["explorations"] = explorations.into();
+    }
+    report
+}
+
+/// Prints a report as JSON Lines: a header with the scalar fields, then one typed record per
+/// element of each array in [`JSONL_RECORDS`].
+fn print_jsonl(report: serde_json::Value) -> color_eyre::Result<()> {
+    let serde_json::Value::Object(mut header) = report else {
+        return Ok(());
+    };
+    let records: Vec<(&str, serde_json::Value)> = JSONL_RECORDS
+        .iter()
+        .filter_map(|(key, kind)| Some((*kind, header.remove(*key)?)))
+        .collect();
+
+    let typed = |kind: &str, fields: serde_json::Map<String, serde_json::Value>| {
+        let mut record = serde_json::Map::new();
+        record.insert("type".into(), kind.into());
+        record.extend(fields);
+        serde_json::to_string(&record)
+    };
+    println!("{}", typed("header", header)?);
+    for (kind, values) in records {
+        for value in values.as_array().into_iter().flatten() {
+            let fields = value.as_object().cloned().unwrap_or_default();
+            println!("{}", typed(kind, fields)?);
+        }
+    }
+    Ok(())
+}
+
+/// Loads bytecode given as a file path, a contract address or hex.
+///
+/// A `0x`-prefixed 20-byte value is looked up as an address first, and read as hex bytecode
+/// when there is no code at that address. Leave out the `0x` to skip the lookup.
+async fn load_bytecode(input: &str, rpc: &str) -> color_eyre::Result<Vec<u8>> {
+    if std::path::Path::new(input).is_file() {
+        return io::fetch_bytes(Source::File(input.into())).await;
+    }
+
+    let digits = input.strip_prefix("0x").unwrap_or(input);
+    if input.starts_with("0x") && digits.len() == 40 {
+        let (address, rpc) = parse_on_chain_args(input, Some(rpc))?;
+        let code = io::fetch_code_at(address, rpc).await?;
+        if !code.is_empty() {
+            return Ok(code);
+        }
+    }
+
+    io::decode_bytecode(input).map(|(bytes, _)| bytes)
+}
+
+async fn diff_storage(old: &str, new: &str, rpc: &str) -> color_eyre::Result<()> {
+    let mut layouts = Vec::new();
+    for (label, input) in [("old", old), ("new", new)] {
+        match load_bytecode(input, rpc).await {
+            Ok(bytes) => layouts.push(infer_storage_layout(&bytes)),
+            Err(e) => {
+                print_error(&format!("Failed to load {} implementation: {}", label, e));
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let diff = diff_storage_layouts(&layouts[0], &layouts[1]);
+    print_storage_diff(&diff);
+
+    if !diff.is_compatible() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn diff_code(
+    old: &str,
+    new: &str,
+    options: &CodeDiffOptions,
+    context: usize,
+    rpc: &str,
+) -> color_eyre::Result<()> {
+    let mut codes = Vec::new();
+    for (label, input) in [("old", old), ("new", new)] {
+        match load_bytecode(input, rpc).await {
+            Ok(bytes) => codes.push(bytes),
+            Err(e) => {
+                print_error(&format!("Failed to load {} bytecode: {}", label, e));
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let diff = diff_bytecode(&codes[0], &codes[1], options);
+    print_code_diff(&diff, context);
+
+    if !diff.is_identical() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn cluster_dir(dir: &std::path::Path, threshold: f64) -> color_eyre::Result<()> {
+    if !(0.0..=1.0).contains(&threshold) {
+        print_error(&format!(
+            "Threshold must be between 0 and 1, got {}",
+            threshold
+        ));
+        std::process::exit(1);
+    }
+    let mut paths = Vec::new();
+    if let Err(e) = collect_cluster_files(dir, &mut paths) {
+        print_error(&format!("Failed to read {}: {}", dir.display(), e));
+        std::process::exit(1);
+    }
+
+    let mut names = Vec::new();
+    let mut signatures = Vec::new();
+    for path in paths {
+        match io::fetch_bytes(Source::File(path.clone())).await {
+            Ok(bytes) => {
+                // Files in subdirectories are named by their path below `dir`
+                let name = path.strip_prefix(dir).unwrap_or(&path);
+                names.push(name.display().to_string());
+                signatures.push(MinHash::new(&bytes));
+            }
+            Err(e) => print_warning(&format!("Skipping {}: {}", path.display(), e)),
+        }
+    }
+    if signatures.is_empty() {
+        print_error(&format!("No bytecode files found in {}", dir.display()));
+        std::process::exit(1);
+    }
+
+    let clusters = cluster(&signatures, threshold);
+    print_clusters(&clusters, &names, &signatures);
+    Ok(())
+}
+
+/// Lists the files below `dir` in path order, as `batch` walks directories: hidden entries are
+/// skipped, and so are symlinked directories, which could loop.
+fn collect_cluster_files(
+    dir: &std::path::Path,
+    files: &mut Vec<std::path::PathBuf>,
+) -> std::io::Result<()> {
+    let mut entries: Vec<std::path::PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            !path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    entries.sort();
+    for entry in entries {
+        let is_link = std::fs::symlink_metadata(&entry).is_ok_and(|meta| meta.is_symlink());
+        if entry.is_dir() {
+            if !is_link && let Err(e) = collect_cluster_files(&entry, files) {
+                print_warning(&format!("Skipping {}: {}", entry.display(), e));
+            }
+        } else if entry.is_file() {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Where `batch` reads one contract from.
+enum BatchSource {
+    /// Hex, a file or an address, as for [`load_bytecode`].
+    Bytecode(String),
+    /// One contract of a standard JSON output file.
+    Contract {
+        path: std::path::PathBuf,
+        contract: String,
+    },
+    /// An input that could not be listed, such as an unreadable directory.
+    Unreadable(String),
+}
+
+/// One row of the `batch` summary.
+struct BatchRow {
+    name: String,
+    result: Result<(Vec<u8>, Stats, usize), String>,
+}
+
+async fn batch(inputs: &[String], details: bool, jobs: usize, rpc: &str) -> color_eyre::Result<()> {
+    let inputs = if inputs.is_empty() {
+        let mut list = String::new();
+        if let Err(e) = std::io::Read::read_to_string(&mut std::io::stdin(), &mut list) {
+            print_error(&format!("Failed to read from stdin: {}", e));
+            std::process::exit(1);
+        }
+        list.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect()
+    } else {
+        inputs.to_vec()
+    };
+
+    let mut sources = Vec::new();
+    for input in &inputs {
+        collect_batch_sources(input, &mut sources);
+    }
+    if sources.is_empty() {
+        print_error("No inputs to analyse");
+        std::process::exit(1);
+    }
+
+    let permits = std::sync::Arc::new(tokio::sync::Semaphore::new(jobs.max(1)));
+    let tasks: Vec<_> = sources
+        .into_iter()
+        .map(|(name, source)| {
+            let permits = permits.clone();
+            let rpc = rpc.to_string();
+            tokio::spawn(async move {
+                let _permit = permits.acquire().await;
+                BatchRow {
+                    name,
+                    result: analyse_batch_source(source, &rpc).await,
+                }
+            })
+        })
+        .collect();
+    let mut rows = Vec::new();
+    for task in tasks {
+        rows.push(task.await?);
+    }
+
+    print_batch_summary(&rows);
+    if details {
+        for row in &rows {
+            if let Ok((bytes, stats, _)) = &row.result {
+                println!();
+                println!(
+                    "{} {}",
+                    "CONTRACT".bright_blue().bold(),
+                    row.name.bright_green().bold()
+                );
+                println!();
+                print_stats(stats, detect_code_kind(bytes), &fingerprint(bytes));
+            }
+        }
+    }
+
+    if rows.iter().all(|row| row.result.is_err()) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Expands a batch input into the contracts it holds: every file under a directory, every
+/// contract with code in a standard JSON output, or the input itself. Hidden entries and
+/// symlinked directories below a directory are skipped; the latter could loop.
+fn collect_batch_sources(input: &str, sources: &mut Vec<(String, BatchSource)>) {
+    let path = std::path::Path::new(input);
+    if path.is_dir() {
+        let mut entries: Vec<std::path::PathBuf> = match std::fs::read_dir(path) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    !path
+                        .file_name()
+                        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+                })
+                .filter(|path| {
+                    !(path.is_dir()
+                        && std::fs::symlink_metadata(path).is_ok_and(|meta| meta.is_symlink()))
+                })
+                .collect(),
+            Err(e) => {
+                let error = format!("Failed to read {}: {}", path.display(), e);
+                sources.push((input.to_string(), BatchSource::Unreadable(error)));
+                return;
+            }
+        };
+        entries.sort();
+        for entry in entries {
+            collect_batch_sources(&entry.to_string_lossy(), sources);
+        }
+        return;
+    }
+
+    let contracts = if path.is_file() {
+        io::artifact_contracts(path)
+    } else {
+        Vec::new()
+    };
+    if contracts.len() > 1 {
+        for contract in contracts {
+            let name = format!("{}:{}", input, contract);
+            let path = path.to_path_buf();
+            sources.push((name, BatchSource::Contract { path, contract }));
+        }
+        return;
+    }
+
+    // Long hex blobs are named by their start
+    let name = if path.is_file() || input.len() <= 20 {
+        input.to_string()
+    } else {
+        format!("{}…", input.chars().take(18).collect::<String>())
+    };
+    sources.push((name, BatchSource::Bytecode(input.to_string())));
+}
+
+/// Loads one batch contract and computes its summary: bytes, statistics and selector count.
+/// The analysis runs on the blocking pool, so the caller's `--jobs` permit bounds it too.
+async fn analyse_batch_source(
+    source: BatchSource,
+    rpc: &str,
+) -> Result<(Vec<u8>, Stats, usize), String> {
+    let bytes = match source {
+        BatchSource::Bytecode(input) => load_bytecode(&input, rpc).await,
+        BatchSource::Contract { path, contract } => {
+            io::read_artifact(&path, Some(&contract), false).map(|artifact| artifact.bytes)
+        }
+        BatchSource::Unreadable(error) => return Err(error),
+    }
+    .map_err(|e| e.to_string())?;
+
+    tokio::task::spawn_blocking(move || {
+        let stats = get_stats(&bytes).map_err(|e| e.to_string())?;
+        let selectors: std::collections::BTreeSet<[u8; 4]> = function_entries(&bytes)
+            .iter()
+            .map(|entry| entry.selector)
+            .collect();
+        Ok((bytes, stats, selectors.len()))
+    })
+    .await
+    .map_err(|e| format!("Analysis failed: {}", e))?
+}
+
+fn print_batch_summary(rows: &[BatchRow]) {
+    println!("{}", "BATCH SUMMARY".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
+
+    let width = rows
+        .iter()
+        .map(|row| row.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("NAME".len());
+    println!(
+        "{}",
+        format!(
+            "{:<width$} {:>8} {:>8} {:>6} {:>10} {}",
+            "NAME", "SIZE", "OPCODES", "STACK", "SELECTORS", "ERROR"
+        )
+        .bright_white()
+        .bold()
+    );
+    for row in rows {
+        match &row.result {
+            Ok((_, stats, selectors)) => println!(
+                "{:<width$} {:>8} {:>8} {:>6} {:>10}",
+                row.name.bright_cyan(),
+                stats.byte_len,
+                stats.opcode_count,
+                stats.max_stack_depth,
+                selectors
+            ),
+            Err(e) => println!(
+                "{:<width$} {:>8} {:>8} {:>6} {:>10} {}",
+                row.name.bright_cyan(),
+                "-",
+                "-",
+                "-",
+                "-",
+                e.red()
+            ),
+        }
+    }
+
+    let failed = rows.iter().filter(|row| row.result.is_err()).count();
+    println!("{}", "=".repeat(50).bright_black());
+    println!(
+        "{}",
+        format!("{} contracts, {} failed", rows.len(), failed).bright_black()
+    );
+}
+
+/// Loads the bytecode and builds the call for `run`, `trace` and `debug`, exiting on invalid
+/// input.
+async fn load_call(call: &CallArgs) -> (Vec<u8>, CallParams) {
+    let bytecode = &call.bytecode;
+    let bytes = if std::path::Path::new(bytecode).is_file() {
+        io::fetch_bytes(Source::File(bytecode.into())).await
+    } else {
+        io::decode_bytecode(bytecode).map(|(bytes, _)| bytes)
+    };
+    let bytes = match bytes {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            print_error(&format!("Failed to load bytecode: {}", e));
+            std::process::exit(1);
+        }
+    };
+
+    let calldata = if call.calldata.is_empty() {
+        Ok(Vec::new())
+    } else {
+        io::decode_hex(&call.calldata)
+    };
+    let params = match (
+        calldata,
+        call.value.parse::<U256>(),
+        call.caller.parse::<Address>(),
+    ) {
+        (Ok(calldata), Ok(value), Ok(caller)) => {
+            CallParams::new(calldata, value, caller, call.gas_limit)
+        }
+        (Err(e), _, _) => {
+            print_error(&format!("Invalid calldata: {}", e));
+            std::process::exit(1);
+        }
+        (_, Err(_), _) => {
+            print_error(&format!("Invalid value: {}", call.value));
+            std::process::exit(1);
+        }
+        (_, _, Err(_)) => {
+            print_error(&format!("Invalid caller address: {}", call.caller));
+            std::process::exit(1);
+        }
+    };
+    (bytes, params)
+}
+
+async fn run(call: &CallArgs) -> color_eyre::Result<()> {
+    let (bytes, params) = load_call(call).await;
+    let execution = match execute(&bytes, &params) {
+        Ok(execution) => execution,
+        Err(e) => {
+            print_error(&e.to_string());
+            std::process::exit(1);
+        }
+    };
+    print_execution(&execution);
+
+    if !execution.is_success() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// What `trace` shows per step besides pc, opcode and gas.
+struct TraceDisplay {
+    stack_items: usize,
+    memory: bool,
+    storage: bool,
+}
+
+async fn run_trace(call: &CallArgs, display: &TraceDisplay, json: bool) -> color_eyre::Result<()> {
+    let (bytes, params) = load_call(call).await;
+    // EIP-3155 lines carry the full memory when it is asked for
+    let options = TraceOptions::new(display.memory);
+    if !json {
+        println!("{}", "EXECUTION TRACE".bright_blue().bold());
+        println!("{}", "=".repeat(50).bright_black());
+    }
+
+    // Steps are printed as they run rather than kept, so long traces don't pile up in memory
+    let mut steps = 0;
+    let execution = match trace_with(&bytes, &params, &options, |step| {
+        steps += 1;
+        if json {
+            println!("{}", eip3155_step(&step, display.memory));
+        } else {
+            print_trace_step(&step, display);
+        }
+    }) {
+        Ok(execution) => execution,
+        Err(e) => {
+            print_error(&e.to_string());
+            std::process::exit(1);
+        }
+    };
+
+    if json {
+        println!("{}", eip3155_summary(&execution));
+    } else {
+        print_trace_summary(steps, &execution);
+    }
+
+    if !execution.is_success() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn debug(
+    call: &CallArgs,
+    breakpoints: &[Breakpoint],
+    max_steps: usize,
+) -> color_eyre::Result<()> {
+    if !std::io::stdout().is_terminal() {
+        print_error(&debugger::terminal_required());
+        std::process::exit(1);
+    }
+
+    let (bytes, params) = load_call(call).await;
+    // Memory changes are kept and the contents dropped; the debugger rebuilds memory from them
+    let mut steps = Vec::new();
+    let mut truncated = false;
+    let execution = match trace_with(&bytes, &params, &TraceOptions::new(true), |mut step| {
+        if steps.len() == max_steps {
+            truncated = true;
+            return;
+        }
+        step.memory = None;
+        steps.push(step);
+    }) {
+        Ok(execution) => execution,
+        Err(e) => {
+            print_error(&e.to_string());
+            std::process::exit(1);
+        }
+    };
+    if steps.is_empty() {
+        print_error("The call executed no instructions");
+        std::process::exit(1);
+    }
+
+    let trace = Trace { steps, execution };
+    debugger::run(Debugger::new(
+        &bytes,
+        trace,
+        truncated,
+        breakpoints.to_vec(),
+    ))?;
+    Ok(())
+}
+
+fn print_trace_step(step: &TraceStep, display: &TraceDisplay) {
+    let indent = "  ".repeat(step.depth.saturating_sub(1));
+    let name = OpCode::new(step.opcode)
+        .map(|op| op.as_str().to_string())
+        .unwrap_or_else(|| format!("0x{:02x}", step.opcode));
+    let padding = " ".repeat(14usize.saturating_sub(name.len()));
+    let stack = step
+        .stack_top(display.stack_items)
+        .map(|item| format!("{:#x}", item))
+        .collect::<Vec<_>>()
+        .join(" ");
+    println!(
+        "{}{} {} {}{} {} {} {}",
+        indent,
+        format!("{:04x}", step.pc).bright_black(),
+        "│".bright_black(),
+        categorize_opcode(&name),
+        padding,
+        format!("gas {:>10} cost {:>6}", step.gas_remaining, step.gas_cost).bright_black(),
+        "│".bright_black(),
+        stack
+    );
+
+    let detail_indent = format!("{}       ", indent);
+    if display.memory {
+        for (offset, word) in &step.memory_writes {
+            println!(
+                "{}{} {}",
+                detail_indent,
+                format!("mem[{:#x}] ←", offset).blue(),
+                word
+            );
+        }
+    }
+    if display.storage
+        && let Some((slot, value)) = step.storage_write
+    {
+        println!(
+            "{}{} {:#x}",
+            detail_indent,
+            format!("storage[{:#x}] ←", slot).magenta(),
+            value
+        );
+    }
+    if let Some(error) = &step.error {
+        println!("{}{}", detail_indent, error.bright_red());
+    }
+}
+
+fn print_trace_summary(steps: usize, execution: &Execution) {
+    println!("{}", "=".repeat(50).bright_black());
+    let status = match &execution.status {
+        ExitStatus::Success => "success".to_string(),
+        ExitStatus::Revert => "reverted".to_string(),
+        ExitStatus::Halt(reason) => format!("halted ({})", reason),
+    };
+    println!(
+        "{}",
+        format!(
+            "{} steps, {}, {} gas used",
+            steps, status, execution.gas_used
+        )
+        .bright_black()
+    );
+}
+
+/// One step as an EIP-3155 JSON line.
+fn eip3155_step(step: &TraceStep, memory: bool) -> String {
+    let mut line = serde_json::json!({
+        "pc": step.pc,
+        "op": step.opcode,
+        "gas": format!("{:#x}", step.gas_remaining),
+        "gasCost": format!("{:#x}", step.gas_cost),
+        "memSize": step.memory_size,
+        "stack": step.stack.iter().map(|item| format!("{:#x}", item)).collect::<Vec<_>>(),
+        "depth": step.depth,
+        "returnData": format!("0x{}", hex::encode(&step.return_data)),
+        "refund": format!("{:#x}", step.refund.max(0)),
+    });
+    if let Some(op) = OpCode::new(step.opcode) {
+        line["opName"] = op.as_str().into();
+    }
+    if let Some(error) = &step.error {
+        line["error"] = error.as_str().into();
+    }
+    if memory && let Some(contents) = &step.memory {
+        line["memory"] = format!("0x{}", hex::encode(contents)).into();
+    }
+    line.to_string()
+}
+
+/// The EIP-3155 summary line that ends a trace.
+///
+/// `stateRoot` is left out: the execution runs against an in-memory state with no trie to
+/// take a root of.
+fn eip3155_summary(execution: &Execution) -> String {
+    serde_json::json!({
+        "output": format!("0x{}", hex::encode(&execution.output)),
+        "gasUsed": format!("{:#x}", execution.gas_used),
+        "pass": execution.is_success(),
+    })
+    .to_string()
+}
+
+fn print_execution(execution: &Execution) {
+    println!("{}", "EXECUTION".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
+
+    let status = match &execution.status {
+        ExitStatus::Success => "success".green().bold(),
+        ExitStatus::Revert => "reverted".bright_red().bold(),
+        ExitStatus::Halt(reason) => format!("halted ({})", reason).bright_red().bold(),
+    };
+    println!("Status: {}", status);
+    println!("Gas used: {}", execution.gas_used);
+    if let Some(reason) = execution.revert_reason() {
+        println!("Revert reason: {}", reason.to_string().yellow());
+    }
+    if execution.output.is_empty() {
+        println!("Output: {}", "none".bright_black());
+    } else {
+        println!("Output: 0x{}", hex::encode(&execution.output));
+    }
+
+    println!();
+    println!("{}", "LOGS".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
+    if execution.logs.is_empty() {
+        println!("{}", "No logs".bright_black());
+    }
+    for (index, log) in execution.logs.iter().enumerate() {
+        println!(
+            "{} │ {} {}",
+            format!("{:>4}", index).bright_black(),
+            format!("LOG{}", log.topics.len()).cyan().bold(),
+            log.address
+        );
+        for (position, topic) in log.topics.iter().enumerate() {
+            println!("       topic{}: {}", position, topic);
+        }
+        println!("       data: 0x{}", hex::encode(&log.data));
+    }
+
+    println!();
+    println!("{}", "STORAGE WRITES".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
+    if execution.storage_writes.is_empty() {
+        println!("{}", "No storage writes".bright_black());
+    }
+    for write in &execution.storage_writes {
+        println!(
+            "{} │ {:#x} → {:#x}",
+            format!("{:#x}", write.slot).magenta(),
+            write.original,
+            write.present
+        );
+    }
+
+    println!("{}", "=".repeat(50).bright_black());
+    println!(
+        "{}",
+        format!(
+            "{} logs, {} storage writes",
+            execution.logs.len(),
+            execution.storage_writes.len()
+        )
+        .bright_black()
+    );
+}
+
+fn format_fields(fields: &[PackedField]) -> String {
+    if fields.is_empty() {
+        return "whole slot".to_string();
+    }
+    fields
+        .iter()
+        .map(|field| format!("{}+{}", field.offset, field.width))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Prints the entries around changes as a unified diff, with old and new pcs side by side.
+fn print_code_diff(diff: &CodeDiff, context: usize) {
+    println!("{}", "BYTECODE DIFF".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
+
+    let shown: Vec<bool> = {
+        let mut shown = vec![false; diff.entries.len()];
+        for (index, entry) in diff.entries.iter().enumerate() {
+            if !matches!(entry, DiffEntry::Same { .. }) {
+                let end = (index + context + 1).min(shown.len());
+                shown[index.saturating_sub(context)..end].fill(true);
+            }
+        }
+        shown
+    };
+
+    if !shown.contains(&true) {
+        println!("{}", "No instruction changes".green());
+    }
+
+    let pc = |pc: Option<usize>| {
+        pc.map(|pc| format!("{:04x}", pc))
+            .unwrap_or_else(|| " ".repeat(4))
+    };
+    for (index, entry) in diff.entries.iter().enumerate() {
+        if !shown[index] {
+            continue;
+        }
+        if index == 0 || !shown[index - 1] {
+            let (old_pc, new_pc) = diff.entries[index..]
+                .iter()
+                .zip(&shown[index..])
+                .take_while(|(_, shown)| **shown)
+                .fold((None, None), |(old, new), (entry, _)| {
+                    let (entry_old, entry_new) = diff_entry_pcs(entry);
+                    (old.or(entry_old), new.or(entry_new))
+                });
+            println!(
+                "{}",
+                format!("@@ -{} +{} @@", pc(old_pc).trim(), pc(new_pc).trim()).cyan()
+            );
+        }
+
+        let (old_pc, new_pc) = diff_entry_pcs(entry);
+        let pcs = format!("{} {} │", pc(old_pc), pc(new_pc));
+        match entry {
+            DiffEntry::Same { instruction, .. } => {
+                let (name, immediate) = instruction
+                    .split_once(' ')
+                    .map_or((instruction.as_str(), String::new()), |(name, rest)| {
+                        (name, format!(" {}", rest))
+                    });
+                println!(
+                    "  {} {}{}",
+                    pcs.bright_black(),
+                    categorize_opcode(name),
+                    immediate.bright_white()
+                );
+            }
+            DiffEntry::ImmutableDiffers { old, new, .. } => {
+                let new_immediate = new.split_once(' ').map_or("", |(_, rest)| rest);
+                println!(
+                    "{}",
+                    format!("~ {} {} → {}", pcs, old, new_immediate).yellow()
+                );
+            }
+            DiffEntry::Removed { instruction, .. } => {
+                println!("{}", format!("- {} {}", pcs, instruction).red())
+            }
+            DiffEntry::Added { instruction, .. } => {
+                println!("{}", format!("+ {} {}", pcs, instruction).green())
+            }
+        }
+    }
+
+    let (removed, added) = diff.change_counts();
+    println!("{}", "=".repeat(50).bright_black());
+    let similarity = format!("Similarity: {:.1}%", diff.similarity * 100.0);
+    if diff.is_identical() {
+        println!("{}", similarity.green().bold());
+    } else {
+        println!(
+            "{} {}",
+            similarity.bright_yellow().bold(),
+            format!("({} removed, {} added)", removed, added).bright_black()
+        );
+    }
+    if diff.metadata_differs {
+        println!("{}", "Metadata trailers differ (ignored)".bright_black());
+    }
+}
+
+/// Old and new pc of a diff entry, for the sides it appears on.
+fn diff_entry_pcs(entry: &DiffEntry) -> (Option<usize>, Option<usize>) {
+    match entry {
+        DiffEntry::Same { old_pc, new_pc, .. }
+        | DiffEntry::ImmutableDiffers { old_pc, new_pc, .. } => (Some(*old_pc), Some(*new_pc)),
+        DiffEntry::Removed { old_pc, .. } => (Some(*old_pc), None),
+        DiffEntry::Added { new_pc, .. } => (None, Some(*new_pc)),
+    }
+}
+
+/// Prints clusters with more than one member, each with its members' similarity to the
+/// representative.
+fn print_clusters(clusters: &[Cluster], names: &[String], signatures: &[MinHash]) {
+    println!("{}", "CLUSTERS".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
+
+    let groups: Vec<&Cluster> = clusters
+        .iter()
+        .filter(|cluster| cluster.len() > 1)
+        .collect();
+    if groups.is_empty() {
+        println!("{}", "No near-duplicates found".green());
+    }
+
+    for (number, group) in groups.iter().enumerate() {
+        if number > 0 {
+            println!();
+        }
+        println!(
+            "{} {}",
+            format!("Cluster {}", number + 1).bright_white().bold(),
+            format!("({} contracts)", group.len()).bright_black()
+        );
+        let representative = &signatures[group.representative];
+        println!(
+            "  {} {}",
+            names[group.representative].bright_cyan(),
+            "representative".bright_black()
+        );
+        for &member in &group.members {
+            if member != group.representative {
+                let score = representative.similarity(&signatures[member]);
+                println!(
+                    "  {} {}",
+                    names[member],
+                    format!("{:.1}%", score * 100.0).yellow()
+                );
+            }
+        }
+    }
+
+    let singletons = clusters.len() - groups.len();
+    println!("{}", "=".repeat(50).bright_black());
+    println!(
+        "{}",
+        format!(
+            "{} contracts in {} clusters, {} without near-duplicates",
+            names.len(),
+            groups.len(),
+            singletons
+        )
+        .bright_black()
+    );
+}
+
+fn print_storage_diff(diff: &StorageDiff) {
+    println!("{}", "STORAGE LAYOUT DIFF".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
+
+    if diff.changes.is_empty() {
+        println!("{}", "No storage changes".green());
+    }
+
+    for change in &diff.changes {
+        let slot = format!("{:#x}", change.slot()).magenta();
+        match change {
+            StorageChange::Collision { old, new, .. } => println!(
+                "{:<10} {} was a {}, now used as a {}",
+                "COLLISION".bright_red().bold(),
+                slot,
+                old,
+                new
+            ),
+            StorageChange::PackingChanged {
+                kind,
+                old,
+                new,
+                conflicting,
+                ..
+            } => {
+                let label = if *conflicting {
+                    "PACKING".bright_red().bold()
+                } else {
+                    "PACKING".yellow()
+                };
+                println!(
+                    "{:<10} {} {} fields {} -> {}",
+                    label,
+                    slot,
+                    kind,
+                    format_fields(old),
+                    format_fields(new)
+                )
+            }
+            StorageChange::Removed { kind, .. } => println!(
+                "{:<10} {} {} is no longer accessed",
+                "REMOVED".yellow(),
+                slot,
+                kind
+            ),
+            StorageChange::Added { kind, .. } => {
+                println!("{:<10} {} {}", "ADDED".green(), slot, kind)
+            }
+        }
+    }
+
+    println!("{}", "=".repeat(50).bright_black());
+    if diff.is_compatible() {
+        println!("{}", "No storage collisions found".green().bold());
+    } else {
+        let breaking = diff
+            .changes
+            .iter()
+            .filter(|change| change.is_breaking())
+            .count();
+        println!(
+            "{}",
+            format!("{} breaking storage changes", breaking)
+                .bright_red()
+                .bold()
+        );
+    }
+}
+
+fn print_limit_usage(label: &str, usage: &LimitUsage) {
+    let summary = format!(
+        "{} / {} bytes ({:.1}%)",
+        usage.size,
+        usage.limit,
+        usage.percent_used()
+    );
+
+    if usage.is_exceeded() {
+        println!(
+            "{}: {}, {}",
+            label,
+            summary.bright_red().bold(),
+            format!("{} bytes over", usage.excess()).bright_red()
+        );
+    } else {
+        println!(
+            "{}: {}, {} bytes remaining",
+            label,
+            summary,
+            usage.remaining()
+        );
+    }
+}
+
+/// Usage of the size limit that applies to the code, and the EIP setting it.
+fn size_limit_usage(
+    byte_len: usize,
+    code_kind: CodeKind,
+    limits: &SizeLimits,
+) -> (LimitUsage, &'static str) {
+    let (limit, default, rule) = match code_kind {
+        CodeKind::Runtime => (limits.max_code_size, EIP170_MAX_CODE_SIZE, "EIP-170"),
+        CodeKind::Creation => (
+            limits.max_initcode_size,
+            EIP3860_MAX_INITCODE_SIZE,
+            "EIP-3860",
+        ),
+    };
+    let rule = if limit == default {
+        rule
+    } else {
+        "custom limit"
+    };
+    (LimitUsage::new(byte_len, limit), rule)
+}
+
+fn check_size_limits(byte_len: usize, code_kind: CodeKind, limits: &SizeLimits) {
+    let (usage, rule) = size_limit_usage(byte_len, code_kind, limits);
+
+    if usage.is_exceeded() {
+        print_warning(&format!(
+            "{} code is {} bytes, exceeding the {} byte limit ({}) by {} bytes",
+            code_kind,
+            usage.size,
+            usage.limit,
+            rule,
+            usage.excess()
+        ));
+    }
+}
+
+const HISTOGRAM_BAR_WIDTH: usize = 20;
+
+fn print_minimal_proxy(proxy: &MinimalProxy) {
+    println!("{}", "MINIMAL PROXY".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
+    println!(
+        "{} {}",
+        "Minimal proxy →".bright_white().bold(),
+        proxy.implementation.to_string().bright_green().bold()
+    );
+    println!("Variant: {}", proxy);
+    if !proxy.immutable_args.is_empty() {
+        println!(
+            "Immutable args: {} bytes {}",
+            proxy.immutable_args.len(),
+            format!("0x{}", hex::encode(&proxy.immutable_args)).bright_black()
+        );
+    }
+}
+
+fn print_upgradeable_proxy(proxy: &UpgradeableProxy) {
+    println!("{}", "UPGRADEABLE PROXY".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
+    println!("Proxy type: {}", proxy.kind);
+    if let Some(slot) = proxy.slot {
+        let label = match proxy.kind {
+            ProxyKind::Beacon => "Beacon slot",
+            _ => "Implementation slot",
+        };
+        println!("{}: {}", label, slot.to_string().bright_black());
+    }
+    let delegatecalls: Vec<String> = proxy
+        .delegatecall_pcs
+        .iter()
+        .map(|pc| format!("{:04x}", pc))
+        .collect();
+    println!("DELEGATECALL at: {}", delegatecalls.join(", "));
+}
+
+fn print_resolved_address(label: &str, address: Option<Address>) {
+    match address {
+        Some(address) => println!(
+            "{} {}",
+            format!("{} →", label).bright_white().bold(),
+            address.to_string().bright_green().bold()
+        ),
+        None => println!("{} → {}", label, "not set".bright_black()),
+    }
+}
+
+/// Prints the compiler guess, e.g. `Compiler: solc 0.8.24 (legacy, 95% confidence)`
+fn print_fingerprint(fingerprint: &Fingerprint) {
+    let mut compiler = fingerprint.toolchain.to_string();
+    if let Some(version) = &fingerprint.version {
+        compiler.push_str(&format!(" {}", version));
+    }
+
+    let mut details = Vec::new();
+    if let Some(pipeline) = fingerprint.pipeline {
+        details.push(pipeline.to_string());
+    }
+    details.push(format!("{:.0}% confidence", fingerprint.confidence * 100.0));
+
+    println!("Compiler: {} ({})", compiler.cyan(), details.join(", "));
+    if !fingerprint.evidence.is_empty() {
+        println!(
+            "{}",
+            format!("  based on: {}", fingerprint.evidence.join("; ")).bright_black()
+        );
+    }
+}
+
+fn print_stats(stats: &Stats, code_kind: CodeKind, fingerprint: &Fingerprint) {
+    println!("{}", "BYTECODE STATISTICS".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
+    println!("Code kind: {}", code_kind);
+    print_fingerprint(fingerprint);
+    println!("Byte length: {}", stats.byte_len);
+    println!("Number of opcodes: {}", stats.opcode_count);
+    println!("Max stack depth: {}", stats.max_stack_depth);
+    println!("Unique opcodes: {}", stats.unique_opcodes);
+    println!("Jump destinations: {}", stats.jumpdest_count);
+    println!(
+        "Push data: {} bytes ({:.1}%)",
+        stats.push_data_bytes,
+        stats.push_data_ratio * 100.0
+    );
+    print_limit_usage("Runtime size limit", &stats.code_size_limit);
+    print_limit_usage("Initcode size limit", &stats.initcode_size_limit);
+
+    println!();
+    println!("{}", "CATEGORY BREAKDOWN".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
+    for category in OpcodeCategory::ALL {
+        let count = stats.category_counts.get(&category).copied().unwrap_or(0);
+        println!(
+            "{:<14} {:>6} {}",
+            category.as_str(),
+            count,
+            format!("{:5.1}%", percentage(count, stats.opcode_count)).bright_black()
+        );
+    }
+
+    println!();
+    println!("{}", "OPCODE HISTOGRAM".bright_blue().bold());
+    println!("{}", "=".repeat(50).bright_black());
+    let max_count = stats.opcode_histogram.values().copied().max().unwrap_or(0);
+    for (opcode, count) in stats.sorted_histogram() {
+        let bar_len = if max_count == 0 {
+            0
+        } else {
+            (count * HISTOGRAM_BAR_WIDTH).div_ceil(max_count)
+        };
+        println!(
+            "{:<14} {:>6} {} {}",
+            categorize_opcode(opcode.as_str()),
+            count,
+            format!("{:5.1}%", percentage(count, stats.opcode_count)).bright_black(),
+            "█".repeat(bar_len).bright_black()
+        );
+    }
+}
+
+fn percentage(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
+
+fn print_error(message: &str) {
+    eprintln!("{} {}", "Error:".bright_red().bold(), message);
+}
+
+fn print_warning(message: &str) {
+    eprintln!("{} {}", "Warning:".bright_yellow().bold(), message);
+}
+
+fn print_usage_hint() {
+    eprintln!();
+    eprintln!("{}", "Usage examples:".bright_blue().bold());
+    eprintln!("  {} 60FF61ABCD00", "evm-lens".bright_green());
+    eprintln!("  {} 0x60FF61ABCD00", "evm-lens".bright_green());
+    eprintln!("  {} --stdin", "evm-lens".bright_green());
+    eprintln!("  {} --file bytecode.txt", "evm-lens".bright_green());
+    eprintln!(
+        "  {} --address 0x123... --rpc https://eth.llamarpc.com",
+        "evm-lens".bright_green()
+    );
+    eprintln!("  {} 60FF61ABCD00 --stats", "evm-lens".bright_green());
+    eprintln!();
+    eprintln!(
+        "{}",
+        "The input should be valid hexadecimal EVM bytecode.".bright_black()
+    );
+}
+
+fn parse_on_chain_args(
+    address_str: &str,
+    rpc: Option<&str>,
+) -> color_eyre::Result<(ethereum_types::Address, Url)> {
+    let rpc_url = rpc.unwrap_or("https://eth.llamarpc.com");
+
+    let address = address_str
+        .parse()
+        .map_err(|_| color_eyre::eyre::eyre!("Invalid address: {}", address_str))?;
+
+    let rpc = rpc_url
+        .parse()
+        .map_err(|_| color_eyre::eyre::eyre!("Invalid RPC URL: {}", rpc_url))?;
+
+    Ok((address, rpc))
+}
+
+async fn get_bytes_from_args(args: &Args) -> color_eyre::Result<io::Input> {
+    if let Some(path) = &args.binary {
+        return io::fetch_input(Source::Binary(path.into())).await;
+    }
+
+    match (&args.hex, &args.address, &args.file, args.stdin) {
+        (Some(hex_string), None, None, false) => io::decode_bytecode(hex_string).map(Into::into),
+        (None, Some(address_str), None, false) => {
+            let (address, rpc) = parse_on_chain_args(address_str, args.rpc.as_deref())?;
+
+            let source = Source::OnChain { address, rpc };
+            io::fetch_input(source).await
+        }
+        (None, None, Some(file_path), false) => {
+            let source = Source::File(file_path.into());
+            io::fetch_input(source).await
+        }
+        (None, None, None, true) => {
+            let source = Source::Stdin;
+            io::fetch_input(source).await
+        }
+        _ => {
+            let source = Source::Stdin;
+            io::fetch_input(source).await
+        }
+    }
+}
+
+/// Parses `--libraries` entries of the form `name=0x...`.
+fn parse_libraries(entries: &[String]) -> color_eyre::Result<Vec<(String, Address)>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let (name, address) = entry
+                .split_once('=')
+                .ok_or_else(|| eyre!("Invalid library {:?}, expected NAME=ADDRESS", entry))?;
+            let address = address
+                .trim()
+                .parse()
+                .map_err(|e| eyre!("Invalid address for library {}: {}", name, e))?;
+            Ok((name.trim().to_string(), address))
+        })
+        .collect()
+}
+
+/// Labels the `PUSH20` of each library placeholder with the library's name when `--libraries`
+/// names it, or its placeholder hash otherwise.
+fn library_labels(
+    placeholders: &[LibraryPlaceholder],
+    libraries: &[(String, Address)],
+) -> BTreeMap<usize, String> {
+    placeholders
+        .iter()
+        .filter(|placeholder| placeholder.offset > 0)
+        .map(|placeholder| {
+            let name = libraries
+                .iter()
+                .find(|(name, _)| placeholder.matches(name))
+                .map(|(name, _)| name.rsplit(':').next().unwrap_or(name).to_string())
+                .unwrap_or_else(|| format!("${}$", placeholder.hash));
+            (placeholder.offset - 1, format!("lib:{}", name))
+        })
+        .collect()
+}
+
+/// Labels the pushes of immutables given by `--immutable-refs`, detected with
+/// `--immutables-from` or recorded in the artifact, exiting when either flag's input cannot be
+/// loaded.
+async fn immutable_labels(
+    args: &Args,
+    bytes: &[u8],
+    artifact_immutables: &[Immutable],
+) -> BTreeMap<usize, String> {
+    let immutables = if let Some(path) = &args.immutable_refs {
+        let json =
+            std::fs::read_to_string(path).map_err(|e| eyre!("Failed to read {}: {}", path, e));
+        match json.and_then(|json| io::parse_immutable_references(&json, args.contract.as_deref()))
+        {
+            Ok(immutables) => immutables,
+            Err(e) => {
+                print_error(&format!("Failed to load immutable references: {}", e));
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(other) = &args.immutables_from {
+        let rpc = args.rpc.as_deref().unwrap_or_default();
+        match load_bytecode(other, rpc).await {
+            Ok(other) => detect_immutables(bytes, &other),
+            Err(e) => {
+                print_error(&format!("Failed to load the other deployment: {}", e));
+                std::process::exit(1);
+            }
+        }
+    } else {
+        artifact_immutables.to_vec()
+    };
+
+    immutable_pushes(&immutables)
+        .into_iter()
+        .map(|(position, number)| (position, format!("immutable #{}", number)))
+        .collect()
+}
+
+fn print_input_header(format: io::InputFormat) {
+    println!(
+        "{} {} {}",
+        "INPUT".bright_blue().bold(),
+        format.to_string().bright_green().bold(),
+        "(detected)".bright_black()
+    );
+    println!();
+}
+
+fn print_eof_summary(header: &io::EofHeader) {
+    let sections = match header.code_sections {
+        Some(1) => "1 code section".to_string(),
+        Some(count) => format!("{} code sections", count),
+        None => "malformed section headers".to_string(),
+    };
+    println!(
+        "{} {}",
+        "Format:".bright_white().bold(),
+        format!("EOF v{}, {}", header.version, sections).bright_green()
+    );
+    println!(
+        "{}",
+        "EOF code is not disassembled or analysed yet; only its header is shown".bright_black()
+    );
+}
+
+fn print_artifact_header(artifact: &io::Artifact, creation: bool) {
+    let kind = if creation {
+        "creation code"
+    } else {
+        "runtime code"
+    };
+    println!(
+        "{} {} {}",
+        "CONTRACT".bright_blue().bold(),
+        artifact
+            .name
+            .as_deref()
+            .unwrap_or("(unnamed)")
+            .bright_green()
+            .bold(),
+        format!("({})", kind).bright_black()
+    );
+    println!();
+}
+
+/// Labels function entries with their ABI signatures and jumps into and out of functions from
+/// the source map.
+fn artifact_labels(
+    artifact: &io::Artifact,
+    bytes: &[u8],
+    ops: &[(usize, OpCode)],
+) -> BTreeMap<usize, String> {
+    let mut labels = BTreeMap::new();
+
+    let signatures = abi_signatures(artifact);
+    for entry in function_entries(bytes) {
+        if let Some(signature) = signatures
+            .iter()
+            .find(|signature| function_selector(signature) == entry.selector)
+        {
+            labels.insert(entry.entry, signature.clone());
+        }
+    }
+
+    if let Some(source_map) = &artifact.source_map {
+        for ((position, _), entry) in ops.iter().zip(parse_source_map(source_map)) {
+            match entry.jump {
+                JumpKind::Into => labels.insert(*position, "into function".to_string()),
+                JumpKind::Out => labels.insert(*position, "out of function".to_string()),
+                JumpKind::Regular => None,
+            };
+        }
+    }
+
+    labels
+}
+
+/// Canonical signatures of the functions in the artifact's ABI.
+fn abi_signatures(artifact: &io::Artifact) -> Vec<String> {
+    artifact
+        .abi
+        .as_ref()
+        .and_then(serde_json::Value::as_array)
+        .map(|items| items.iter().filter_map(abi_signature).collect())
+        .unwrap_or_default()
+}
+
+/// Canonical signature of an ABI function entry, such as `transfer(address,uint256)`.
+fn abi_signature(item: &serde_json::Value) -> Option<String> {
+    if item.get("type")?.as_str()? != "function" {
+        return None;
+    }
+    let name = item.get("name")?.as_str()?;
+    Some(format!("{}({})", name, abi_types(item.get("inputs")?)?))
+}
+
+/// Comma-separated canonical types of ABI parameters, expanding tuples into their components.
+fn abi_types(params: &serde_json::Value) -> Option<String> {
+    let types = params
+        .as_array()?
+        .iter()
+        .map(|param| {
+            let kind = param.get("type")?.as_str()?;
+            match kind.strip_prefix("tuple") {
+                Some(suffix) => Some(format!(
+                    "({}){}",
+                    abi_types(param.get("components")?)?,
+                    suffix
+                )),
+                None => Some(kind.to_string()),
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(types.join(","))
+}
+
+/// Converts the core library's address type to the one used by the RPC layer.
+fn to_rpc_address(address: Address) -> ethereum_types::Address {
+    ethereum_types::Address::from(address.0.0)
+}
+
+/// Reads the address stored in a proxy slot; `None` when the slot is empty.
+async fn read_address_slot(
+    address: ethereum_types::Address,
+    slot: B256,
+    rpc: &Url,
+) -> color_eyre::Result<Option<Address>> {
+    let word = io::fetch_storage_at(address, ethereum_types::H256(slot.0), rpc.clone()).await?;
+    let stored = address_from_word(B256::from(word.0));
+    Ok((!stored.is_zero()).then_some(stored))
+}
+
+/// Calls `implementation()` on a proxy or beacon; `None` when it returns nothing usable.
+async fn call_implementation(
+    address: ethereum_types::Address,
+    rpc: &Url,
+) -> color_eyre::Result<Option<Address>> {
+    let output = io::call_contract(address, &IMPLEMENTATION_SELECTOR, rpc.clone()).await?;
+    if output.len() < 32 {
+        return Ok(None);
+    }
+    let implementation = address_from_word(B256::from_slice(&output[..32]));
+    Ok((!implementation.is_zero()).then_some(implementation))
+}
+
+/// Looks up the current implementation of an upgradeable proxy and prints what was found.
+async fn resolve_implementation(
+    proxy: &UpgradeableProxy,
+    address: ethereum_types::Address,
+    rpc: &Url,
+) -> color_eyre::Result<Option<Address>> {
+    let implementation = match (proxy.kind, proxy.slot) {
+        (ProxyKind::Beacon, Some(slot)) => {
+            let beacon = read_address_slot(address, slot, rpc).await?;
+            print_resolved_address("Beacon", beacon);
+            match beacon {
+                Some(beacon) => call_implementation(to_rpc_address(beacon), rpc).await?,
+                None => None,
+            }
+        }
+        (_, Some(slot)) => read_address_slot(address, slot, rpc).await?,
+        (_, None) => call_implementation(address, rpc).await?,
+    };
+
+    print_resolved_address("Implementation", implementation);
+    Ok(implementation)
+}
+
+async fn print_implementation(implementation: Address, rpc: &Url) {
+    println!();
+    println!(
+        "{} {}",
+        "IMPLEMENTATION".bright_blue().bold(),
+        implementation.to_string().bright_green().bold()
+    );
+
+    let source = Source::OnChain {
+        address: to_rpc_address(implementation),
+        rpc: rpc.clone(),
+    };
+    let bytes = match io::fetch_bytes(source).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            print_error(&format!("Failed to fetch implementation bytecode: {}", e));
+            return;
+        }
+    };
+
+    match disassemble(&bytes) {
+        Ok(ops) => print_disassembly(&ops, &BTreeMap::new()),
+        Err(e) => print_error(&format!("Failed to disassemble implementation: {}", e)),
+    }
+}
+
+#[tokio::main]
+async fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
+
+    let args = Args::parse();
+
+    // Subcommands print text of their own, which a JSON consumer would fail to parse
+    if args.command.is_some() && args.format != OutputFormat::Text {
+        print_error("--format applies to the disassembly only; trace --json prints JSON lines");
+        std::process::exit(1);
+    }
+
+    match &args.command {
+        Some(Command::DiffStorage { old, new, rpc }) => return diff_storage(old, new, rpc).await,
+        Some(Command::Diff {
+            old,
+            new,
+            ignore_immutables,
+            context,
+            rpc,
+        }) => {
+            let options = CodeDiffOptions::new(*ignore_immutables);
+            return diff_code(old, new, &options, *context, rpc).await;
+        }
+        Some(Command::Cluster { dir, threshold }) => return cluster_dir(dir, *threshold).await,
+        Some(Command::Batch {
+            inputs,
+            details,
+            jobs,
+            rpc,
+        }) => return batch(inputs, *details, *jobs, rpc).await,
+        Some(Command::Run { call }) => return run(call).await,
+        Some(Command::Trace {
+            call,
+            stack_items,
+            memory,
+            storage,
+            json,
+        }) => {
+            let options = TraceDisplay {
+                stack_items: *stack_items,
+                memory: *memory,
+                storage: *storage,
+            };
+            return run_trace(call, &options, *json).await;
+        }
+        Some(Command::Debug {
+            call,
+            breakpoints,
+            max_steps,
+        }) => return debug(call, breakpoints, *max_steps).await,
+        None => {}
+    }
+
+    if args.follow_proxy && args.format != OutputFormat::Text {
+        print_error("--follow-proxy has no JSON output; use --format text");
+        std::process::exit(1);
+    }
+
+    let artifact = args.artifact.as_ref().map(|path| {
+        match io::read_artifact(path.as_ref(), args.contract.as_deref(), args.creation) {
+            Ok(artifact) => artifact,
+            Err(e) => {
+                print_error(&format!("{}", e));
+                std::process::exit(1);
+            }
+        }
+    });
+    let input = match &artifact {
+        Some(artifact) => Ok(io::Input::from((
+            artifact.bytes.clone(),
+            artifact.placeholders.clone(),
+        ))),
+        None => get_bytes_from_args(&args).await,
+    };
+    let io::Input {
+        bytes,
+        placeholders,
+        format,
+    } = match input {
+        Ok(input) => input,
+        Err(e) => {
+            print_error(&format!("{}", e));
+            print_usage_hint();
+            std::process::exit(1);
+        }
+    };
+
+    // Reading an EOF container as legacy code would list its header as instructions
+    if let Some(header) = io::eof_header(&bytes) {
+        if args.format == OutputFormat::Text {
+            print_input_header(io::InputFormat::Eof);
+            print_eof_summary(&header);
+        } else {
+            let report = serde_json::json!({
+                "schema_version": JSON_SCHEMA_VERSION,
+                "input_format": io::InputFormat::Eof.id(),
+                "byte_len": bytes.len(),
+                "eof": {
+                    "version": header.version,
+                    "code_sections": header.code_sections,
+                },
+            });
+            if args.format == OutputFormat::Jsonl {
+                print_jsonl(report)?;
+            } else {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        }
+        return Ok(());
+    }
+
+    let libraries = match parse_libraries(&args.libraries) {
+        Ok(libraries) => libraries,
+        Err(e) => {
+            print_error(&format!("{}", e));
+            std::process::exit(1);
+        }
+    };
+    let bytes = if libraries.is_empty() {
+        bytes
+    } else {
+        match link_libraries(&bytes, &placeholders, &libraries) {
+            Ok(linked) => linked,
+            Err(e) => {
+                print_error(&format!("Failed to link libraries: {}", e));
+                std::process::exit(1);
+            }
+        }
+    };
+    if args.format != OutputFormat::Text {
+        let own_output = [(args.link, "--link"), (args.storage_json, "--storage-json")];
+        if let Some((_, flag)) = own_output.iter().find(|(set, _)| *set) {
+            print_error(&format!(
+                "{} prints its own output; leave out --format",
+                flag
+            ));
+            std::process::exit(1);
+        }
+    }
+
+    if args.link {
+        println!("0x{}", hex::encode(&bytes));
+        return Ok(());
+    }
+
+    let ops = match disassemble(&bytes) {
+        Ok(ops) => ops,
+        Err(e) => {
+            print_error(&format!("Failed to disassemble bytecode: {}", e));
+            eprintln!();
+            eprintln!("{}", "This could happen if:".bright_blue().bold());
+            eprintln!("  • The bytecode is malformed or incomplete");
+            eprintln!("  • The bytecode contains invalid opcodes");
+            eprintln!("  • The bytecode structure is corrupted");
+            print_usage_hint();
+            std::process::exit(1);
+        }
+    };
+
+    if ops.is_empty() {
+        print_error("No opcodes found in the provided bytecode");
+        print_usage_hint();
+        std::process::exit(1);
+    }
+
+    if args.storage_json {
+        let layout = storage_layout_json(&infer_storage_layout(&bytes));
+        println!("{}", serde_json::to_string_pretty(&layout)?);
+        return Ok(());
+    }
+
+    let artifact_immutables = artifact.as_ref().map_or(&[][..], |a| &a.immutables[..]);
+    let mut labels = immutable_labels(&args, &bytes, artifact_immutables).await;
+    labels.extend(library_labels(&placeholders, &libraries));
+    if let Some(artifact) = &artifact {
+        labels.extend(artifact_labels(artifact, &bytes, &ops));
+    }
+
+    if args.format != OutputFormat::Text {
+        let contract = artifact
+            .as_ref()
+            .and_then(|artifact| artifact.name.as_deref());
+        let signatures = artifact.as_ref().map(abi_signatures).unwrap_or_default();
+        let report = json_report(&args, &bytes, format, contract, &labels, &signatures);
+        if args.format == OutputFormat::Jsonl {
+            print_jsonl(report)?;
+        } else {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+        return Ok(());
+    }
+
+    if let Some(format) = format {
+        print_input_header(format);
+    }
+
+    // A clone's listing says nothing beyond where it delegates to
+    let minimal_proxy = detect_minimal_proxy(&bytes);
+    match minimal_proxy.as_ref().filter(|_| !args.raw) {
+        Some(proxy) => print_minimal_proxy(proxy),
+        None if args.decompile => print_decompiled(&decompile(&bytes)),
+        None => {
+            if let Some(artifact) = &artifact {
+                print_artifact_header(artifact, args.creation);
+            }
+            print_disassembly(&ops, &labels)
+        }
+    }
+
+    let upgradeable_proxy = detect_upgradeable_proxy(&bytes);
+    if let Some(proxy) = &upgradeable_proxy {
+        println!();
+        print_upgradeable_proxy(proxy);
+    }
+
+    if let Some(address_str) = &args.address {
+        let (address, rpc) = parse_on_chain_args(address_str, args.rpc.as_deref())?;
+
+        let implementation = match (&minimal_proxy, &upgradeable_proxy) {
+            (Some(proxy), _) => Some(proxy.implementation),
+            (None, Some(proxy)) => match resolve_implementation(proxy, address, &rpc).await {
+                Ok(implementation) => implementation,
+                Err(e) => {
+                    print_warning(&format!("Could not resolve proxy implementation: {}", e));
+                    None
+                }
+            },
+            (None, None) => None,
+        };
+
+        if args.follow_proxy {
+            match implementation {
+                Some(implementation) => print_implementation(implementation, &rpc).await,
+                None => print_warning("No proxy implementation to follow"),
+            }
+        }
+    }
+
+    let code_kind = code_kind(&args, &bytes);
+    let limits = SizeLimits::new(args.max_code_size, args.max_initcode_size);
+    check_size_limits(bytes.len(), code_kind, &limits);
+
+    if args.stats {
+        println!();
+        match get_stats_with_limits(&bytes, &limits) {
+            Ok(stats) => print_stats(&stats, code_kind, &fingerprint(&bytes)),
             Err(e) => {
                 print_error(&format!("Failed to compute bytecode statistics: {}", e));
             }
         }
     }
 
+    if args.lint {
+        println!();
+        print_lint_findings(&lint(&bytes));
+        println!();
+        print_reentrancy_risks(&find_reentrancy(&bytes));
+    }
+
+    if args.storage {
+        println!();
+        print_storage_layout(&infer_storage_layout(&bytes));
+    }
+
+    if args.symbolic {
+        println!();
+        print_symbolic_execution(&bytes);
+    }
+
     Ok(())
 }
@@ -4,7 +4,7 @@ use std::io::Write;
 use tempfile::NamedTempFile;
 use wiremock::{
     Mock, MockServer, ResponseTemplate,
-    matchers::{method, path},
+    matchers::{body_partial_json, method, path},
 };
 
 /// Helper to get the evm-lens binary command
@@ -91,6 +91,124 @@ fn test_file_input_valid_bytecode() {
         .stdout(predicate::str::contains("PUSH1"));
 }
 
+#[test]
+fn test_binary_file_input() {
+    // PUSH1 0x80, PUSH1 0x40, MSTORE, STOP as raw bytes
+    let mut temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    temp_file
+        .write_all(&[0x60, 0x80, 0x60, 0x40, 0x52, 0x00])
+        .expect("Failed to write to temp file");
+
+    // Detected without --binary, since the file is not text
+    for flag in ["--binary", "--file"] {
+        let mut cmd = evm_lens_cmd();
+        cmd.arg(flag).arg(temp_file.path());
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("PUSH1"))
+            .stdout(predicate::str::contains("MSTORE"))
+            .stdout(predicate::str::contains("4 opcodes total"));
+    }
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--stdin")
+        .write_stdin(vec![0x60u8, 0x80, 0x60, 0x40, 0x52, 0x00]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("PUSH1"));
+}
+
+#[test]
+fn test_binary_flag_reads_hex_text_as_bytes() {
+    let mut temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    write!(temp_file, "60").expect("Failed to write to temp file");
+
+    // '6' and '0' are 0x36 (CALLDATASIZE) and 0x30 (ADDRESS)
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--binary").arg(temp_file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("CALLDATASIZE"))
+        .stdout(predicate::str::contains("ADDRESS"));
+}
+
+#[test]
+fn test_detected_input_format_in_header() {
+    let mut temp_file = NamedTempFile::new().expect("Failed to create temp file");
+    writeln!(
+        temp_file,
+        "// PUSH1 0x80, PUSH1 0x40\n0x6080 6040\n52 # MSTORE"
+    )
+    .expect("Failed to write to temp file");
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--file").arg(temp_file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "INPUT hex with whitespace or comments (detected)",
+        ))
+        .stdout(predicate::str::contains("4 opcodes total"));
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--stdin")
+        .write_stdin(r#"{"jsonrpc":"2.0","id":1,"result":"0x6080604052"}"#);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "INPUT JSON-RPC response (detected)",
+        ))
+        .stdout(predicate::str::contains("MSTORE"));
+
+    let mut cmd = evm_lens_cmd();
+    cmd.write_stdin(r#"{"hash":"0x01","to":null,"input":"0x6080604052"}"#);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "INPUT transaction JSON (detected)",
+    ));
+
+    // A positional argument is hex by definition, so nothing is detected
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("6080604052");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("INPUT").not());
+
+    // The header is shown whatever the rest of the output is
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--stdin")
+        .arg("--decompile")
+        .write_stdin("0x6080604052 // MSTORE\n");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "INPUT hex with whitespace or comments (detected)",
+    ));
+}
+
+#[test]
+fn test_eof_container_header() {
+    let container = "ef000101000402000100030400000000800001600000";
+    let mut from_stdin = evm_lens_cmd();
+    from_stdin.arg("--stdin").write_stdin(container);
+    let mut from_arg = evm_lens_cmd();
+    from_arg.arg(container);
+
+    for mut cmd in [from_stdin, from_arg] {
+        cmd.assert()
+            .success()
+            .stdout(predicate::str::contains("INPUT EOF container (detected)"))
+            .stdout(predicate::str::contains("Format: EOF v1, 1 code section"))
+            .stdout(predicate::str::contains("only its header is shown"))
+            .stdout(predicate::str::contains("PUSH1").not());
+    }
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(container).arg("--format").arg("json");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(report["input_format"], "eof");
+    assert_eq!(report["eof"]["code_sections"], 1);
+    assert!(report.get("instructions").is_none());
+}
+
 #[test]
 fn test_file_input_nonexistent_file() {
     let mut cmd = evm_lens_cmd();
@@ -207,3 +325,1145 @@ fn test_help_output() {
         .stdout(predicate::str::contains("--address"))
         .stdout(predicate::str::contains("--rpc"));
 }
+
+#[test]
+fn test_stats_output_includes_histogram() {
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("6000546000525ba000").arg("--stats");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("BYTECODE STATISTICS"))
+        .stdout(predicate::str::contains("Jump destinations: 1"))
+        .stdout(predicate::str::contains("CATEGORY BREAKDOWN"))
+        .stdout(predicate::str::contains("OPCODE HISTOGRAM"))
+        .stdout(predicate::str::contains("SLOAD"));
+}
+
+#[test]
+fn test_stats_output_includes_compiler_fingerprint() {
+    let mut cmd = evm_lens_cmd();
+    // PUSH1 0x80, PUSH1 0x40, MSTORE, STOP, then {"solc": 0.8.24}
+    cmd.arg("608060405200a164736f6c6343000818000a")
+        .arg("--stats");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Compiler: solc 0.8.24"))
+        .stdout(predicate::str::contains("95% confidence"));
+}
+
+#[test]
+fn test_lint_reports_findings() {
+    let mut cmd = evm_lens_cmd();
+    // PUSH0 x4, PUSH0, CALLDATALOAD, GAS, DELEGATECALL, CALLER, SELFDESTRUCT
+    cmd.arg("5f5f5f5f5f355af433ff").arg("--lint");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("LINT FINDINGS"))
+        .stdout(predicate::str::contains("EL001 reachable-selfdestruct"))
+        .stdout(predicate::str::contains("EL002 dynamic-delegatecall"))
+        .stdout(predicate::str::contains("2 findings total"));
+}
+
+#[test]
+fn test_lint_reports_state_write_after_call() {
+    let mut cmd = evm_lens_cmd();
+    // PUSH0 x5, CALLER, GAS, CALL, POP, PUSH1 0x01, PUSH0, SSTORE, STOP
+    cmd.arg("5f5f5f5f5f335af15060015f5500").arg("--lint");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "STATE WRITES AFTER EXTERNAL CALLS",
+        ))
+        .stdout(predicate::str::contains(
+            "CALL at 0007 may be followed by SSTORE at 000c",
+        ));
+}
+
+#[test]
+fn test_lint_without_findings() {
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(SAMPLE_BYTECODE).arg("--lint");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("No findings"));
+}
+
+#[test]
+fn test_decompile() {
+    let mut cmd = evm_lens_cmd();
+    // Legacy solc prologue, non-payable check, calldata length check and a dispatcher for
+    // 0xaabbccdd, whose body stores the first argument in slot 1
+    cmd.arg("6080604052348015600e575f80fd5b50600436106026575f3560e01c8063aabbccdd14602a575b5f80fd5b60043560015500")
+        .arg("--decompile");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("PSEUDO-CODE"))
+        .stdout(predicate::str::contains("function 0xaabbccdd()"))
+        .stdout(predicate::str::contains("sstore(1, calldataload(4));"))
+        .stdout(predicate::str::contains("1 functions total"))
+        .stdout(predicate::str::contains("EVM BYTECODE DISASSEMBLY").not());
+}
+
+#[test]
+fn test_symbolic_execution() {
+    let mut cmd = evm_lens_cmd();
+    // Dispatcher for 0xaabbccdd, whose body self-destructs when the caller matches slot 0
+    // and reverts otherwise
+    cmd.arg("5f3560e01c8063aabbccdd14601057005b335f5414601b575f80fd5b33ff")
+        .arg("--symbolic");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("SYMBOLIC EXECUTION"))
+        .stdout(predicate::str::contains(
+            "2 paths: 1 revert, 1 selfdestruct, 0 arbitrary call",
+        ))
+        .stdout(predicate::str::contains(
+            "at 001d when storage[0x0] == msg.sender",
+        ));
+}
+
+#[test]
+fn test_run_success() {
+    let mut cmd = evm_lens_cmd();
+    // CALLVALUE, PUSH1 0x01, SSTORE, PUSH0, CALLDATALOAD, PUSH0, MSTORE, PUSH1 0x20, PUSH0,
+    // RETURN
+    cmd.args([
+        "run",
+        "346001555f355f5260205ff3",
+        "--value",
+        "5",
+        "--calldata",
+    ])
+    .arg(format!("0x{:064x}", 42));
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Status: success"))
+        .stdout(predicate::str::contains(format!("Output: 0x{:064x}", 42)))
+        .stdout(predicate::str::contains("0x1 │ 0x0 → 0x5"))
+        .stdout(predicate::str::contains("0 logs, 1 storage writes"));
+}
+
+#[test]
+fn test_run_revert_reason() {
+    let mut cmd = evm_lens_cmd();
+    // Reverts with Error("no")
+    cmd.args([
+        "run",
+        "6308c379a060e01b5f5260206004526002602452616e6f60f01b60445260645ffd",
+    ]);
+
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("Status: reverted"))
+        .stdout(predicate::str::contains("Revert reason: Error(\"no\")"));
+}
+
+#[test]
+fn test_run_invalid_caller() {
+    let mut cmd = evm_lens_cmd();
+    cmd.args(["run", "00", "--caller", "0x1234"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid caller address"));
+}
+
+#[test]
+fn test_trace() {
+    let mut cmd = evm_lens_cmd();
+    // PUSH1 0x2a, PUSH1 0x20, MSTORE, PUSH1 0x07, PUSH1 0x01, SSTORE, STOP
+    cmd.args(["trace", "602a602052600760015500", "--memory", "--storage"]);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("EXECUTION TRACE"))
+        .stdout(predicate::str::contains("0x20 0x2a"))
+        .stdout(predicate::str::contains(format!(
+            "mem[0x20] ← 0x{:064x}",
+            0x2a
+        )))
+        .stdout(predicate::str::contains("storage[0x1] ← 0x7"))
+        .stdout(predicate::str::contains("7 steps, success"));
+}
+
+#[test]
+fn test_trace_eip3155_json() {
+    let mut cmd = evm_lens_cmd();
+    // PUSH1 0x01, PUSH1 0x02, ADD, STOP
+    cmd.args(["trace", "600160020100", "--json"]);
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let lines: Vec<serde_json::Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[2]["pc"], 4);
+    assert_eq!(lines[2]["op"], 1);
+    assert_eq!(lines[2]["opName"], "ADD");
+    assert_eq!(lines[2]["gasCost"], "0x3");
+    assert_eq!(lines[2]["depth"], 1);
+    assert_eq!(lines[2]["stack"], serde_json::json!(["0x1", "0x2"]));
+    assert_eq!(lines[2]["returnData"], "0x");
+    assert_eq!(lines[4]["pass"], true);
+    assert_eq!(lines[4]["output"], "0x");
+    assert!(lines[4].get("stateRoot").is_none());
+}
+
+#[test]
+fn test_trace_eip3155_return_data() {
+    let mut cmd = evm_lens_cmd();
+    // PUSH1 0x2a, PUSH0, MSTORE, PUSH0 x2, PUSH1 0x20, PUSH0, PUSH1 0x04 (identity), GAS,
+    // STATICCALL, STOP
+    cmd.args(["trace", "602a5f525f5f60205f60045afa00", "--json"]);
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let stdout = String::from_utf8(output).unwrap();
+    let stop: serde_json::Value =
+        serde_json::from_str(stdout.lines().rev().nth(1).unwrap()).unwrap();
+    assert_eq!(stop["opName"], "STOP");
+    assert_eq!(stop["returnData"], format!("0x{:064x}", 0x2a));
+}
+
+#[test]
+fn test_debug_requires_terminal() {
+    let mut cmd = evm_lens_cmd();
+    cmd.args(["debug", "600160020100", "--break", "ADD"]);
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("needs an interactive terminal"));
+}
+
+#[test]
+fn test_debug_rejects_bad_breakpoint() {
+    let mut cmd = evm_lens_cmd();
+    cmd.args(["debug", "600160020100", "--break", "NOPE"]);
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "neither an opcode nor a hex position",
+    ));
+}
+
+#[test]
+fn test_storage_layout_table() {
+    let mut cmd = evm_lens_cmd();
+    // DUP1, PUSH4 0xaabbccdd, EQ, PUSH1 0x0b, JUMPI, STOP, JUMPDEST, PUSH1 0x2a, PUSH1 0x01,
+    // SSTORE, STOP
+    cmd.arg("8063aabbccdd14600b57005b602a60015500")
+        .arg("--storage");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("STORAGE LAYOUT"))
+        .stdout(predicate::str::contains("0x1"))
+        .stdout(predicate::str::contains("written by 0xaabbccdd"));
+}
+
+#[test]
+fn test_storage_layout_json() {
+    let mut cmd = evm_lens_cmd();
+    // Reads a mapping at slot 3 keyed by the first calldata word
+    cmd.arg("5f355f52600360205260405f205400")
+        .arg("--storage-json");
+
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(json["variables"][0]["slot"], "0x3");
+    assert_eq!(json["variables"][0]["kind"], "mapping");
+    assert_eq!(json["variables"][0]["readPcs"][0], 13);
+}
+
+#[test]
+fn test_diff_storage_reports_collision() {
+    let mut cmd = evm_lens_cmd();
+    // Old writes slot 1 directly, new uses slot 1 as a mapping base
+    cmd.arg("diff-storage")
+        .arg("602a60015500")
+        .arg("5f355f52600160205260405f20602a905500");
+
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("STORAGE LAYOUT DIFF"))
+        .stdout(predicate::str::contains(
+            "0x1 was a value, now used as a mapping",
+        ))
+        .stdout(predicate::str::contains("1 breaking storage changes"));
+}
+
+#[test]
+fn test_diff_storage_compatible_from_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let old = dir.path().join("old.txt");
+    let new = dir.path().join("new.txt");
+    std::fs::write(&old, "602a60015500").unwrap();
+    // Keeps slot 1 and adds slot 2
+    std::fs::write(&new, "602a600155602a60025500").unwrap();
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("diff-storage").arg(&old).arg(&new);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("ADDED"))
+        .stdout(predicate::str::contains("No storage collisions found"));
+}
+
+#[test]
+fn test_diff_aligns_inserted_instruction() {
+    let mut cmd = evm_lens_cmd();
+    // PUSH1 0x01, PUSH1 0x02, ADD, STOP vs. the same with a POP inserted after ADD
+    cmd.arg("diff").arg("600160020100").arg("60016002015000");
+
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("BYTECODE DIFF"))
+        .stdout(predicate::str::contains("+      0005 │ POP"))
+        .stdout(predicate::str::contains("0005 0006 │"))
+        .stdout(predicate::str::contains("Similarity: 88.9%"))
+        .stdout(predicate::str::contains("(0 removed, 1 added)"));
+}
+
+#[test]
+fn test_diff_ignores_immutables_and_metadata() {
+    let old = format!(
+        "7f{}00a264697066735822{}64736f6c63430008130033",
+        "11".repeat(32),
+        "ab".repeat(34)
+    );
+    let new = format!("7f{}00", "22".repeat(32));
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("diff").arg(&old).arg(&new);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("(1 removed, 1 added)"));
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("diff")
+        .arg(&old)
+        .arg(&new)
+        .arg("--ignore-immutables");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("~ 0000 0000 │ PUSH32 0x1111"))
+        .stdout(predicate::str::contains("Similarity: 100.0%"))
+        .stdout(predicate::str::contains("Metadata trailers differ"));
+}
+
+#[test]
+fn test_cluster_groups_near_duplicates() {
+    let dir = tempfile::tempdir().unwrap();
+    // The first two differ only in push values
+    std::fs::write(dir.path().join("a.txt"), "6080604052600160020160005500").unwrap();
+    std::fs::write(dir.path().join("b.txt"), "6080604052600560070160015500").unwrap();
+    std::fs::write(dir.path().join("c.txt"), "3360005460e01c1415").unwrap();
+    std::fs::write(dir.path().join("notes.txt"), "not bytecode").unwrap();
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("cluster")
+        .arg(dir.path())
+        .arg("--threshold")
+        .arg("0.9");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Cluster 1 (2 contracts)"))
+        .stdout(predicate::str::contains("a.txt representative"))
+        .stdout(predicate::str::contains("b.txt 100.0%"))
+        .stdout(predicate::str::contains(
+            "3 contracts in 1 clusters, 1 without near-duplicates",
+        ))
+        .stderr(predicate::str::contains("Skipping"));
+}
+
+#[test]
+fn test_cluster_walks_subdirectories() {
+    let dir = tempfile::tempdir().unwrap();
+    let nested = dir.path().join("v2");
+    std::fs::create_dir(&nested).unwrap();
+    std::fs::write(dir.path().join("a.txt"), "6080604052600160020160005500").unwrap();
+    std::fs::write(nested.join("a.txt"), "6080604052600560070160015500").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(dir.path(), nested.join("loop")).unwrap();
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("cluster").arg(dir.path());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Cluster 1 (2 contracts)"))
+        .stdout(predicate::str::contains("v2/a.txt 100.0%"))
+        .stdout(predicate::str::contains(
+            "2 contracts in 1 clusters, 0 without near-duplicates",
+        ));
+}
+
+#[test]
+fn test_cluster_rejects_bad_threshold() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("cluster")
+        .arg(dir.path())
+        .arg("--threshold")
+        .arg("1.5");
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Threshold must be between 0 and 1",
+    ));
+}
+
+#[test]
+fn test_batch_summarises_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("nested")).unwrap();
+    std::fs::write(dir.path().join("a.txt"), "6080604052").unwrap();
+    std::fs::write(dir.path().join("nested").join("bad.txt"), "60zz").unwrap();
+    std::fs::write(
+        dir.path().join("nested").join("out.json"),
+        r#"{"contracts": {"a.sol": {
+            "A": {"evm": {"deployedBytecode": {"object": "600100"}}},
+            "B": {"evm": {"deployedBytecode": {"object": "60026003"}}}
+        }}}"#,
+    )
+    .unwrap();
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("batch").arg(dir.path()).arg("--details");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("BATCH SUMMARY"))
+        .stdout(predicate::str::is_match(r"a\.txt +6 +4 +2 +0").unwrap())
+        .stdout(predicate::str::is_match(r"out\.json:a\.sol:A +3 +2 +1 +0").unwrap())
+        .stdout(predicate::str::contains("out.json:a.sol:B"))
+        .stdout(predicate::str::is_match(r"NAME +SIZE +OPCODES +STACK +SELECTORS ERROR").unwrap())
+        .stdout(predicate::str::is_match(r"bad\.txt +- +- +- +- Invalid hex characters").unwrap())
+        .stdout(predicate::str::contains("4 contracts, 1 failed"))
+        .stdout(predicate::str::contains("BYTECODE STATISTICS"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_batch_skips_symlinked_directories() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.txt"), "6080604052").unwrap();
+    std::os::unix::fs::symlink(dir.path(), dir.path().join("loop")).unwrap();
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("batch").arg(dir.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("1 contracts, 0 failed"));
+}
+
+#[test]
+fn test_batch_reads_list_from_stdin() {
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("batch")
+        .write_stdin("# one per line\n6001600201\n\n0x60zz\n");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::is_match(r"6001600201 +6 +4 +2 +0").unwrap())
+        .stdout(predicate::str::contains("2 contracts, 1 failed"));
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("batch").write_stdin("zz\n");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("1 contracts, 1 failed"));
+}
+
+#[test]
+fn test_json_output() {
+    let mut cmd = evm_lens_cmd();
+    // PUSH1 0x80, CALLER, SELFDESTRUCT
+    cmd.arg("608033ff")
+        .arg("--format")
+        .arg("json")
+        .arg("--lint");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+    assert_eq!(report["schema_version"], 1);
+    assert_eq!(report["code_kind"], "runtime");
+    assert_eq!(report["byte_len"], 4);
+    assert_eq!(
+        report["instructions"][0],
+        serde_json::json!({ "pc": 0, "opcode": "PUSH1", "immediate": "0x80", "size": 2 })
+    );
+    assert_eq!(report["stats"]["opcode_histogram"]["SELFDESTRUCT"], 1);
+    assert_eq!(report["findings"][0]["rule"], "EL001");
+    assert!(report.get("storage_variables").is_none());
+}
+
+#[test]
+fn test_jsonl_output() {
+    let mut file = NamedTempFile::new().unwrap();
+    write!(file, "0x{SAMPLE_BYTECODE}").unwrap();
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--file")
+        .arg(file.path())
+        .arg("--format")
+        .arg("jsonl");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let records: Vec<serde_json::Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    assert_eq!(records[0]["type"], "header");
+    assert_eq!(records[0]["schema_version"], 1);
+    assert_eq!(records[0]["input_format"], "hex");
+    assert!(records[0].get("instructions").is_none());
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[2]["type"], "instruction");
+    assert_eq!(records[2]["opcode"], "PUSH2");
+    assert_eq!(records[2]["immediate"], "0xabcd");
+}
+
+#[test]
+fn test_json_output_rejects_text_only_views() {
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--address")
+        .arg("0x1111111111111111111111111111111111111111")
+        .arg("--follow-proxy")
+        .arg("--format")
+        .arg("json");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--follow-proxy has no JSON output; use --format text",
+        ))
+        .stdout(predicate::str::is_empty());
+
+    let library = "a.sol:A=0x1111111111111111111111111111111111111111";
+    for flags in [&["--storage-json"][..], &["--link", "--libraries", library]] {
+        let flag = flags[0];
+        let mut cmd = evm_lens_cmd();
+        cmd.arg(SAMPLE_BYTECODE)
+            .arg("--format")
+            .arg("jsonl")
+            .args(flags);
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains(format!(
+                "{flag} prints its own output; leave out --format"
+            )))
+            .stdout(predicate::str::is_empty());
+    }
+}
+
+#[test]
+fn test_json_output_with_decompile_and_symbolic() {
+    // CALLVALUE, PUSH1 0x05, JUMPI, STOP, JUMPDEST, PUSH0, DUP1, REVERT
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("34600557005b5f80fd")
+        .arg("--format")
+        .arg("json")
+        .arg("--decompile")
+        .arg("--symbolic");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+    let decompiled = &report["decompiled_functions"][0];
+    assert_eq!(decompiled["selector"], serde_json::Value::Null);
+    assert_eq!(decompiled["entry"], 0);
+    assert!(!decompiled["lines"].as_array().unwrap().is_empty());
+
+    let exploration = &report["explorations"][0];
+    assert_eq!(exploration["selector"], serde_json::Value::Null);
+    assert_eq!(exploration["path_limit_reached"], false);
+    let ends: Vec<&str> = exploration["paths"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|path| path["end"].as_str().unwrap())
+        .collect();
+    assert!(ends.contains(&"stop") && ends.contains(&"revert"));
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("34600557005b5f80fd")
+        .arg("--format")
+        .arg("jsonl")
+        .arg("--symbolic");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let records: Vec<serde_json::Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let explorations: Vec<_> = records
+        .iter()
+        .filter(|record| record["type"] == "exploration")
+        .collect();
+    assert_eq!(explorations.len(), 1);
+    assert_eq!(explorations[0]["paths"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_format_rejected_with_subcommand() {
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--format")
+        .arg("json")
+        .arg("diff")
+        .arg("6001")
+        .arg("6002");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--format applies to the disassembly only",
+        ))
+        .stdout(predicate::str::is_empty());
+}
+
+#[test]
+fn test_json_output_reports_stats_error() {
+    let mut cmd = evm_lens_cmd();
+    // PUSH1 0x0c, then the undefined opcode 0x0c
+    cmd.arg("600c0c00").arg("--format").arg("json");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+    assert!(report["stats"].is_null());
+    assert!(
+        report["stats_error"]
+            .as_str()
+            .unwrap()
+            .contains("invalid opcode 0x0c")
+    );
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(SAMPLE_BYTECODE).arg("--format").arg("json");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert!(report["stats"].is_object());
+    assert!(report["stats_error"].is_null());
+}
+
+#[test]
+fn test_json_output_carries_labels() {
+    let dir = tempfile::tempdir().unwrap();
+    let artifact = dir.path().join("Token.json");
+    // DUP1, PUSH4 0xa9059cbb, EQ, PUSH1 0x0b, JUMPI, STOP, JUMPDEST, STOP
+    std::fs::write(
+        &artifact,
+        r#"{
+            "abi": [{
+                "type": "function",
+                "name": "transfer",
+                "inputs": [{ "name": "to", "type": "address" }, { "name": "amount", "type": "uint256" }]
+            }],
+            "deployedBytecode": { "object": "0x8063a9059cbb14600b57005b00" }
+        }"#,
+    )
+    .unwrap();
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--artifact")
+        .arg(&artifact)
+        .arg("--format")
+        .arg("json");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(
+        report["functions"][0]["signature"],
+        "transfer(address,uint256)"
+    );
+    let jumpdest = &report["instructions"][6];
+    assert_eq!(jumpdest["pc"], 0x0b);
+    assert_eq!(jumpdest["label"], "transfer(address,uint256)");
+    assert!(report["instructions"][0].get("label").is_none());
+
+    // Immutables and libraries are labelled as in the listing
+    let library = "contracts/Math.sol:SafeMath";
+    let code = format!(
+        "73__${}$__3b7f{}00",
+        evm_lens_core::library_hash(library),
+        "11".repeat(32)
+    );
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(&code)
+        .arg("--libraries")
+        .arg(format!(
+            "{library}=0x1111111111111111111111111111111111111111"
+        ))
+        .arg("--immutables-from")
+        .arg(format!(
+            "7300000000000000000000000000000000000000003b7f{}00",
+            "22".repeat(32)
+        ))
+        .arg("--format")
+        .arg("jsonl");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let records: Vec<serde_json::Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records[1]["label"], "lib:SafeMath");
+    assert_eq!(records[3]["pc"], 22);
+    assert_eq!(records[3]["label"], "immutable #1");
+}
+
+#[test]
+fn test_immutables_from_other_deployment() {
+    // PUSH32 <immutable>, SLOAD, PUSH32 <immutable>, STOP
+    let deploy = |fill: &str| format!("7f{0}547f{0}00", fill.repeat(32));
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(deploy("11"))
+        .arg("--immutables-from")
+        .arg(deploy("22"));
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("0000 │ PUSH32 <immutable #1>"))
+        .stdout(predicate::str::contains("0022 │ PUSH32 <immutable #1>"));
+}
+
+#[test]
+fn test_immutable_refs_from_artifact() {
+    let dir = tempfile::tempdir().unwrap();
+    let artifact = dir.path().join("Token.json");
+    std::fs::write(
+        &artifact,
+        r#"{"deployedBytecode": {"immutableReferences": {"42": [{"start": 35, "length": 32}]}}}"#,
+    )
+    .unwrap();
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(format!("7f{0}547f{0}00", "11".repeat(32)))
+        .arg("--immutable-refs")
+        .arg(&artifact);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("0000 │ PUSH32\n"))
+        .stdout(predicate::str::contains("0022 │ PUSH32 <immutable #1>"));
+
+    std::fs::write(&artifact, r#"{"abi": []}"#).unwrap();
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("6000").arg("--immutable-refs").arg(&artifact);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No immutableReferences found"));
+}
+
+#[test]
+fn test_immutable_refs_contract_from_standard_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("output.json");
+    let contract = |start: u32| {
+        serde_json::json!({
+            "evm": { "deployedBytecode": {
+                "object": "00",
+                "immutableReferences": { "1": [{ "start": start, "length": 32 }] }
+            } }
+        })
+    };
+    let json = serde_json::json!({
+        "contracts": { "src/A.sol": { "A": contract(0), "B": contract(35) } }
+    });
+    std::fs::write(&output, json.to_string()).unwrap();
+    let code = format!("7f{0}547f{0}00", "11".repeat(32));
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(&code)
+        .arg("--immutable-refs")
+        .arg(&output)
+        .arg("--contract")
+        .arg("B");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("0000 │ PUSH32\n"))
+        .stdout(predicate::str::contains("0022 │ PUSH32 <immutable #1>"));
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(&code).arg("--immutable-refs").arg(&output);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Several contracts match in the JSON, pick one with --contract: src/A.sol:A, src/A.sol:B",
+    ));
+}
+
+#[test]
+fn test_unlinked_bytecode_placeholders() {
+    let library = "contracts/Math.sol:SafeMath";
+    // PUSH20 <SafeMath>, EXTCODESIZE, STOP
+    let unlinked = format!("73__${}$__3b00", evm_lens_core::library_hash(library));
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(&unlinked);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("0000 │ PUSH20 <lib:$"))
+        .stdout(predicate::str::contains("0015 │ EXTCODESIZE"));
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(&unlinked).arg("--libraries").arg(format!(
+        "{library}=0x1111111111111111111111111111111111111111"
+    ));
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("0000 │ PUSH20 <lib:SafeMath>"));
+}
+
+#[test]
+fn test_link_libraries() {
+    let library = "contracts/Math.sol:SafeMath";
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(
+        file,
+        "0x73__${}$__3b00",
+        evm_lens_core::library_hash(library)
+    )
+    .unwrap();
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--file")
+        .arg(file.path())
+        .arg("--libraries")
+        .arg(format!(
+            "{library}=0x1111111111111111111111111111111111111111"
+        ))
+        .arg("--link");
+    cmd.assert()
+        .success()
+        .stdout("0x7311111111111111111111111111111111111111113b00\n");
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--file")
+        .arg(file.path())
+        .arg("--libraries")
+        .arg("contracts/Other.sol:Other=0x1111111111111111111111111111111111111111")
+        .arg("--link");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "No library address for placeholder",
+    ));
+}
+
+#[test]
+fn test_artifact_listing_uses_abi_and_source_map() {
+    let dir = tempfile::tempdir().unwrap();
+    let artifact = dir.path().join("Token.json");
+    // DUP1, PUSH4 0xa9059cbb, EQ, PUSH1 0x0b, JUMPI, STOP, JUMPDEST, STOP
+    std::fs::write(
+        &artifact,
+        r#"{
+            "abi": [{
+                "type": "function",
+                "name": "transfer",
+                "inputs": [{ "name": "to", "type": "address" }, { "name": "amount", "type": "uint256" }]
+            }],
+            "bytecode": { "object": "0x6080" },
+            "deployedBytecode": {
+                "object": "0x8063a9059cbb14600b57005b00",
+                "sourceMap": "0:10:0:-:0;;;;;;;1:2::o"
+            },
+            "metadata": { "settings": { "compilationTarget": { "src/Token.sol": "Token" } } }
+        }"#,
+    )
+    .unwrap();
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--artifact").arg(&artifact);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("CONTRACT Token (runtime code)"))
+        .stdout(predicate::str::contains(
+            "000b │ JUMPDEST <transfer(address,uint256)>",
+        ))
+        .stdout(predicate::str::contains("000c │ STOP <out of function>"));
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--artifact").arg(&artifact).arg("--creation");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("CONTRACT Token (creation code)"))
+        .stdout(predicate::str::contains("2 opcodes total"));
+
+    // Artifacts are read wherever a bytecode file is
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("diff")
+        .arg(&artifact)
+        .arg("8063a9059cbb14600b57005b00");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Similarity: 100.0%"));
+}
+
+#[test]
+fn test_artifact_contract_not_found() {
+    let dir = tempfile::tempdir().unwrap();
+    let artifact = dir.path().join("out.json");
+    std::fs::write(
+        &artifact,
+        r#"{"contracts": {"a.sol": {"A": {"evm": {"deployedBytecode": {"object": "00"}}}}}}"#,
+    )
+    .unwrap();
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--artifact")
+        .arg(&artifact)
+        .arg("--contract")
+        .arg("B");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Contract B not found"));
+}
+
+#[test]
+fn test_runtime_code_over_size_limit_warns() {
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(SAMPLE_BYTECODE).arg("--max-code-size").arg("4");
+
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Warning:"))
+        .stderr(predicate::str::contains("runtime code is 6 bytes"))
+        .stderr(predicate::str::contains("4 byte limit (custom limit)"));
+}
+
+#[test]
+fn test_runtime_code_over_eip170_warns() {
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("00".repeat(24_577));
+
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("24576 byte limit (EIP-170)"));
+}
+
+#[test]
+fn test_creation_code_over_size_limit_warns() {
+    let mut cmd = evm_lens_cmd();
+    // PUSH1 0x0a, DUP1, PUSH1 0x0c, PUSH1 0x00, CODECOPY, PUSH1 0x00, RETURN
+    cmd.arg("600a80600c6000396000f3")
+        .arg("--max-initcode-size")
+        .arg("8");
+
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("creation code is 11 bytes"))
+        .stderr(predicate::str::contains("8 byte limit (custom limit)"));
+}
+
+#[test]
+fn test_stats_output_includes_size_limits() {
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(SAMPLE_BYTECODE).arg("--stats");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Code kind: runtime"))
+        .stdout(predicate::str::contains(
+            "Runtime size limit: 6 / 24576 bytes (0.0%), 24570 bytes remaining",
+        ))
+        .stderr(predicate::str::contains("Warning:").not());
+}
+
+const MINIMAL_PROXY_BYTECODE: &str =
+    "363d3d373d3d3d363d73bebebebebebebebebebebebebebebebebebebebe5af43d82803e903d91602b57fd5bf3";
+
+#[test]
+fn test_minimal_proxy_summary() {
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(MINIMAL_PROXY_BYTECODE);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Minimal proxy →"))
+        .stdout(predicate::str::contains(
+            "0xBEbeBeBEbeBebeBeBEBEbebEBeBeBebeBeBebebe",
+        ))
+        .stdout(predicate::str::contains("Variant: EIP-1167\n"))
+        .stdout(predicate::str::contains("DELEGATECALL").not());
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(format!("{MINIMAL_PROXY_BYTECODE}aabb"));
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Variant: EIP-1167 with immutable args",
+        ))
+        .stdout(predicate::str::contains("Immutable args: 2 bytes 0xaabb"));
+}
+
+#[test]
+fn test_minimal_proxy_raw_disassembly() {
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(MINIMAL_PROXY_BYTECODE).arg("--raw");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("EVM BYTECODE DISASSEMBLY"))
+        .stdout(predicate::str::contains("DELEGATECALL"))
+        .stdout(predicate::str::contains("Minimal proxy →").not());
+}
+
+/// PUSH32 <EIP-1967 implementation slot>, SLOAD, GAS, DELEGATECALL, STOP
+const EIP1967_PROXY_BYTECODE: &str =
+    "7f360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc545af400";
+const PROXY_ADDRESS: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+const IMPLEMENTATION_ADDRESS: &str = "0xbebebebebebebebebebebebebebebebebebebebe";
+
+#[test]
+fn test_upgradeable_proxy_detected() {
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(EIP1967_PROXY_BYTECODE);
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("EVM BYTECODE DISASSEMBLY"))
+        .stdout(predicate::str::contains("UPGRADEABLE PROXY"))
+        .stdout(predicate::str::contains("Proxy type: EIP-1967"))
+        .stdout(predicate::str::contains(
+            "Implementation slot: 0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc",
+        ));
+}
+
+async fn mount_eip1967_proxy(mock_server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({
+            "method": "eth_getCode",
+            "params": [IMPLEMENTATION_ADDRESS]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": format!("0x{}", SAMPLE_BYTECODE),
+            "id": 1
+        })))
+        .mount(mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({
+            "method": "eth_getCode",
+            "params": [PROXY_ADDRESS]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": format!("0x{}", EIP1967_PROXY_BYTECODE),
+            "id": 1
+        })))
+        .mount(mock_server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(body_partial_json(serde_json::json!({
+            "method": "eth_getStorageAt",
+            "params": [
+                PROXY_ADDRESS,
+                "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc"
+            ]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": format!("0x000000000000000000000000{}", &IMPLEMENTATION_ADDRESS[2..]),
+            "id": 1
+        })))
+        .mount(mock_server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_address_input_resolves_proxy_implementation() {
+    let mock_server = MockServer::start().await;
+    mount_eip1967_proxy(&mock_server).await;
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--address")
+        .arg(PROXY_ADDRESS)
+        .arg("--rpc")
+        .arg(mock_server.uri());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Implementation →"))
+        .stdout(predicate::str::contains(
+            "0xBEbeBeBEbeBebeBeBEBEbebEBeBeBebeBeBebebe",
+        ))
+        .stdout(predicate::str::contains("IMPLEMENTATION").not());
+}
+
+#[tokio::test]
+async fn test_follow_proxy_disassembles_implementation() {
+    let mock_server = MockServer::start().await;
+    mount_eip1967_proxy(&mock_server).await;
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--address")
+        .arg(PROXY_ADDRESS)
+        .arg("--rpc")
+        .arg(mock_server.uri())
+        .arg("--follow-proxy");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("IMPLEMENTATION"))
+        .stdout(predicate::str::contains("PUSH2"))
+        .stdout(predicate::str::contains("3 opcodes total"));
+}
+
+#[test]
+fn test_follow_proxy_requires_address() {
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("--follow-proxy");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the following required arguments were not provided",
+        ))
+        .stderr(predicate::str::contains("--address"));
+
+    // Bytecode from anywhere but an address has no proxy to look up
+    let mut cmd = evm_lens_cmd();
+    cmd.arg(EIP1967_PROXY_BYTECODE).arg("--follow-proxy");
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "cannot be used with '--follow-proxy'",
+    ));
+}
+
+#[tokio::test]
+async fn test_twenty_byte_bytecode_without_code_on_chain() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(body_partial_json(
+            serde_json::json!({ "method": "eth_getCode" }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "result": "0x",
+            "id": 1
+        })))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    // PUSH1 0x01, PUSH1 0x02, ADD, then STOP padding to 20 bytes
+    let code = format!("6001600201{}", "00".repeat(15));
+
+    let mut cmd = evm_lens_cmd();
+    cmd.arg("diff")
+        .arg(format!("0x{code}"))
+        .arg(&code)
+        .arg("--rpc")
+        .arg(mock_server.uri());
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Similarity: 100.0%"));
+}